@@ -0,0 +1,70 @@
+use crate::config::Backend;
+use wgpu::{Adapter, AdapterInfo, Backends, DeviceType, Features, Instance, Surface};
+
+/// Features the renderer can't do without.
+pub const REQUIRED_FEATURES: Features =
+    Features::CONSERVATIVE_RASTERIZATION.union(Features::PUSH_CONSTANTS);
+
+/// Picks the adapter to render with: the most powerful one of `backend` that supports the
+/// required features and can present to `surface`. If `backend` has none, any other
+/// backend is tried, with a warning. The error lists all adapters and why they don't fit.
+pub fn select_adapter(
+    instance: &Instance,
+    surface: &Surface,
+    backend: Backend,
+) -> Result<Adapter, String> {
+    let usable = |backends: Backends| {
+        instance
+            .enumerate_adapters(backends)
+            .filter(|adapter| unusable(adapter, surface).is_none())
+            .max_by_key(|adapter| preference(adapter.get_info().device_type))
+    };
+
+    if let Some(adapter) = usable(backend.backends()) {
+        return Ok(adapter);
+    }
+    if let Some(adapter) = usable(Backends::all()) {
+        eprintln!(
+            "no usable {:?} adapter, falling back to {}",
+            backend,
+            describe(&adapter.get_info())
+        );
+        return Ok(adapter);
+    }
+
+    let adapters: Vec<_> = instance
+        .enumerate_adapters(Backends::all())
+        .map(|adapter| {
+            let reason = unusable(&adapter, surface).unwrap_or_default();
+            format!("  {}: {}", describe(&adapter.get_info()), reason)
+        })
+        .collect();
+    Err(match adapters.is_empty() {
+        true => "no graphics adapters found, are the gpu drivers installed?".to_string(),
+        false => format!("no usable graphics adapter:\n{}", adapters.join("\n")),
+    })
+}
+
+/// Why `adapter` can't be rendered with, `None` if it can.
+fn unusable(adapter: &Adapter, surface: &Surface) -> Option<String> {
+    let missing = REQUIRED_FEATURES - adapter.features();
+    if !missing.is_empty() {
+        return Some(format!("missing features {:?}", missing));
+    }
+    (!adapter.is_surface_supported(surface)).then(|| "can't present to the window".to_string())
+}
+
+/// Higher is more powerful, like `PowerPreference::HighPerformance`.
+fn preference(device_type: DeviceType) -> u8 {
+    match device_type {
+        DeviceType::DiscreteGpu => 4,
+        DeviceType::IntegratedGpu => 3,
+        DeviceType::VirtualGpu => 2,
+        DeviceType::Other => 1,
+        DeviceType::Cpu => 0,
+    }
+}
+
+fn describe(info: &AdapterInfo) -> String {
+    format!("{} ({:?}, {:?})", info.name, info.backend, info.device_type)
+}
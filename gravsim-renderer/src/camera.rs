@@ -0,0 +1,244 @@
+use gravsim_simulation::{Star, StarId};
+use nalgebra::{Matrix3, Point2, Vector2};
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+
+/// Which part of the world a target shows. All mappings between the world and a target go
+/// through `view`, which combines translation, zoom and aspect ratio, so stars stay round
+/// on any target, whether an ultrawide window, a split screen half or an offscreen capture.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Camera {
+    /// world position in the middle of the target
+    pub center: Vector2<f32>,
+    /// clip space units per world unit, clip space is 2 high
+    pub render_scale: f32,
+    /// width over height of the target
+    pub aspect: f32,
+}
+
+impl Camera {
+    /// Looking at the origin of a target of `size`.
+    pub fn new(size: PhysicalSize<u32>) -> Self {
+        Self {
+            center: Vector2::zeros(),
+            render_scale: 1.0,
+            aspect: aspect_of(size),
+        }
+    }
+
+    /// World units per half target height.
+    pub fn view_extent(&self) -> f32 {
+        1.0 / self.render_scale
+    }
+
+    /// The same view for a target of another size.
+    pub fn with_aspect(&self, size: PhysicalSize<u32>) -> Self {
+        Self {
+            aspect: aspect_of(size),
+            ..*self
+        }
+    }
+
+    /// Affine transform from world to clip space.
+    pub fn view(&self) -> Matrix3<f32> {
+        let scale = Vector2::new(self.render_scale / self.aspect, self.render_scale);
+        Matrix3::new_nonuniform_scaling(&scale) * Matrix3::new_translation(&-self.center)
+    }
+
+    pub fn world_to_clip(&self, world: Vector2<f32>) -> Vector2<f32> {
+        self.view().transform_point(&Point2::from(world)).coords
+    }
+
+    pub fn clip_to_world(&self, clip: Vector2<f32>) -> Vector2<f32> {
+        let inverse = self.view().try_inverse().unwrap_or_else(Matrix3::identity);
+        inverse.transform_point(&Point2::from(clip)).coords
+    }
+
+    /// World position of the pixel `position` of a target of `size`.
+    pub fn screen_to_world(
+        &self,
+        position: PhysicalPosition<f64>,
+        size: PhysicalSize<u32>,
+    ) -> Vector2<f32> {
+        let clip = Vector2::new(
+            2.0 * position.x as f32 / size.width as f32 - 1.0,
+            1.0 - 2.0 * position.y as f32 / size.height as f32,
+        );
+        self.clip_to_world(clip)
+    }
+
+    /// Pixel of a target of `size` that shows `world`.
+    pub fn world_to_screen(
+        &self,
+        world: Vector2<f32>,
+        size: PhysicalSize<u32>,
+    ) -> PhysicalPosition<f64> {
+        let clip = self.world_to_clip(world);
+        PhysicalPosition::new(
+            ((clip.x + 1.0) / 2.0 * size.width as f32) as f64,
+            ((1.0 - clip.y) / 2.0 * size.height as f32) as f64,
+        )
+    }
+
+    /// Moves the view by `offset` half target heights, so panning feels the same at any zoom.
+    pub fn pan(&mut self, offset: Vector2<f32>) {
+        self.center += offset / self.render_scale;
+    }
+
+    /// Centers the view on the bounding box of `stars` and zooms so all of them are visible.
+    pub fn fit(&mut self, stars: &[Star]) {
+        let (min, max) = stars
+            .iter()
+            .map(|star| star.pos().cast::<f32>())
+            .filter(|pos| pos.iter().all(|x| x.is_finite()))
+            .fold(
+                (Vector2::repeat(f32::MAX), Vector2::repeat(f32::MIN)),
+                |(min, max), pos| (min.inf(&pos), max.sup(&pos)),
+            );
+        if min.x > max.x {
+            return;
+        }
+
+        // a small margin, so stars on the edge aren't cut off
+        let half_extent = ((max - min) * 0.55).sup(&Vector2::repeat(1e-3));
+        self.center = (min + max) / 2.0;
+        self.render_scale = (self.aspect / half_extent.x).min(1.0 / half_extent.y);
+    }
+
+    /// Centers the view on what `follow` points at, unless it left the simulation.
+    pub fn follow(&mut self, follow: Follow, stars: &[Star]) {
+        let target = match follow {
+            Follow::Star(id) => stars.get(id).map(|star| star.pos().cast::<f32>()),
+            Follow::CenterOfMass => center_of_mass(stars),
+        };
+        if let Some(target) = target.filter(|target| target.iter().all(|x| x.is_finite())) {
+            self.center = target;
+        }
+    }
+
+    pub fn push_constants(&self) -> PushConstants {
+        let view = self.view();
+        PushConstants {
+            view: [0, 1, 2].map(|i| {
+                let column = view.column(i);
+                [column[0], column[1], column[2], 0.0]
+            }),
+        }
+    }
+}
+
+fn aspect_of(size: PhysicalSize<u32>) -> f32 {
+    size.width as f32 / size.height as f32
+}
+
+/// What the camera keeps in the middle of the view every frame, see `Camera::follow`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Follow {
+    Star(StarId),
+    /// the center of mass of all stars, so e.g. merging galaxies don't drift out of view
+    CenterOfMass,
+}
+
+/// Mass weighted mean position of the stars still in the simulation.
+fn center_of_mass(stars: &[Star]) -> Option<Vector2<f32>> {
+    let (weighted, mass) = stars
+        .iter()
+        .filter(|star| star.pos().iter().all(|x| x.is_finite()))
+        .fold((Vector2::<f64>::zeros(), 0.0), |(weighted, mass), star| {
+            let star_mass = star.mass() as f64;
+            (
+                weighted + star.pos().cast::<f64>() * star_mass,
+                mass + star_mass,
+            )
+        });
+    (mass > 0.0).then(|| (weighted / mass).cast())
+}
+
+/// `Camera::view` as the shaders get it, a `mat3x3<f32>` whose columns are padded to 16 bytes.
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Zeroable, bytemuck::Pod)]
+pub struct PushConstants {
+    view: [[f32; 4]; 3],
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gravsim_simulation::Real;
+
+    fn camera(size: PhysicalSize<u32>) -> Camera {
+        Camera {
+            center: Vector2::new(-250.0, 1e4),
+            render_scale: 3e-3,
+            ..Camera::new(size)
+        }
+    }
+
+    #[test]
+    fn screen_and_world_positions_round_trip() {
+        for size in [
+            PhysicalSize::new(800, 600),
+            PhysicalSize::new(5120, 1440),
+            PhysicalSize::new(600, 1800),
+        ] {
+            let camera = camera(size);
+            for (x, y) in [(0.0, 0.0), (17.5, 400.25), (size.width as f64, 3.0)] {
+                let position = PhysicalPosition::new(x, y);
+                let back = camera.world_to_screen(camera.screen_to_world(position, size), size);
+                assert!(
+                    (back.x - x).abs() < 1e-2 && (back.y - y).abs() < 1e-2,
+                    "{:?}",
+                    back
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn circles_stay_round_on_any_target() {
+        for size in [PhysicalSize::new(800, 600), PhysicalSize::new(5120, 1440)] {
+            let camera = camera(size);
+            let center = camera.center + Vector2::new(300.0, -120.0);
+            let pixels = |offset: Vector2<f32>| {
+                let a = camera.world_to_screen(center, size);
+                let b = camera.world_to_screen(center + offset, size);
+                Vector2::new(b.x - a.x, b.y - a.y).norm()
+            };
+            let (x, y) = (
+                pixels(Vector2::new(50.0, 0.0)),
+                pixels(Vector2::new(0.0, 50.0)),
+            );
+            assert!((x - y).abs() < 1e-3 * y, "{} {}", x, y);
+        }
+    }
+
+    #[test]
+    fn the_center_is_in_the_middle() {
+        let size = PhysicalSize::new(3440, 1440);
+        let mut camera = camera(size);
+        camera.pan(Vector2::new(0.5, -2.0));
+        let middle = camera.world_to_screen(camera.center, size);
+        assert!((middle.x - 1720.0).abs() < 1e-2 && (middle.y - 720.0).abs() < 1e-2);
+    }
+
+    #[test]
+    fn following_centers_the_view() {
+        let mut camera = camera(PhysicalSize::new(800, 600));
+        let mut stars = vec![
+            Star::new(Vector2::new(100.0, 0.0), Vector2::zeros(), 3.0),
+            Star::new(Vector2::new(-300.0, 40.0), Vector2::zeros(), 1.0),
+        ];
+
+        camera.follow(Follow::Star(1), &stars);
+        assert_eq!(camera.center, Vector2::new(-300.0, 40.0));
+        camera.follow(Follow::CenterOfMass, &stars);
+        assert!((camera.center - Vector2::new(0.0, 10.0)).norm() < 1e-3);
+
+        // stars that left don't move the view
+        stars[1] = Star::new(Vector2::repeat(Real::NAN), Vector2::zeros(), 1.0);
+        camera.follow(Follow::Star(1), &stars);
+        camera.follow(Follow::Star(7), &stars);
+        assert!((camera.center - Vector2::new(0.0, 10.0)).norm() < 1e-3);
+        camera.follow(Follow::CenterOfMass, &stars);
+        assert_eq!(camera.center, Vector2::new(100.0, 0.0));
+    }
+}
@@ -0,0 +1,306 @@
+use crate::camera::Camera;
+use crate::hdr::HdrTargets;
+use crate::state::State;
+use crate::trails::Trails;
+use gravsim_simulation::Star;
+use nalgebra::Vector2;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::BufWriter;
+use std::num::NonZeroU32;
+use wgpu::{
+    Buffer, BufferDescriptor, BufferUsages, CommandEncoderDescriptor, Extent3d, ImageCopyBuffer,
+    ImageCopyTexture, ImageDataLayout, Maintain, MapMode, Origin3d, Texture, TextureAspect,
+    TextureDescriptor, TextureDimension, TextureFormat, TextureUsages, TextureView,
+    TextureViewDescriptor, COPY_BYTES_PER_ROW_ALIGNMENT,
+};
+use winit::dpi::PhysicalSize;
+
+/// Linear supersampling factor used for beauty shots.
+pub const SUPERSAMPLING: u32 = 4;
+
+/// Like `supersampled`, with trails of `Trails::LONG_LENGTH` frames reconstructed from the
+/// replay being recorded, see `--record_replay=`, in place of the interactive ones.
+pub fn beauty_shot(state: &mut State, camera: &Camera, path: &str) -> Result<(), String> {
+    let (Some(recorder), Some(replay)) = (&mut state.replay_recorder, &state.replay_path) else {
+        return Err(
+            "beauty shots need a replay for their trails, record one with --record_replay="
+                .to_string(),
+        );
+    };
+    recorder.flush().map_err(|e| e.to_string())?;
+    let mut trails = Trails::from_replay(&state.device, state.target, replay, Trails::LONG_LENGTH)?;
+    trails.write(&state.queue, &state.colors);
+
+    let interactive = state.trails.replace(trails);
+    let result = supersampled(state, camera, path);
+    state.trails = interactive;
+    result
+}
+
+/// Renders the current frame with `camera` offscreen at `SUPERSAMPLING` times the window
+/// resolution, downsamples it and writes it to `path` as a png.
+pub fn supersampled(state: &State, camera: &Camera, path: &str) -> Result<(), String> {
+    let max_dimension = state.device.limits().max_texture_dimension_2d;
+    let factor = SUPERSAMPLING
+        .min(max_dimension / state.size.width.max(1))
+        .min(max_dimension / state.size.height.max(1))
+        .max(1);
+
+    let width = state.size.width * factor;
+    let height = state.size.height * factor;
+    let pixels = Offscreen::new(state, width, height).render_with(state, camera)?;
+    let pixels = downsample(&pixels, width, height, factor);
+
+    write_png(path, &pixels, state.size.width, state.size.height)
+}
+
+/// An offscreen target of a fixed size, with a buffer to read it back, so sequences of
+/// frames don't recreate them for every frame.
+pub struct Offscreen {
+    texture: Texture,
+    view: TextureView,
+    targets: HdrTargets,
+    readback: Buffer,
+    size: PhysicalSize<u32>,
+    /// bytes per row of `readback`, rows of a texture copy have to be aligned
+    padded_row: u32,
+}
+
+impl Offscreen {
+    pub fn new(state: &State, width: u32, height: u32) -> Self {
+        let texture = state.device.create_texture(&TextureDescriptor {
+            label: Some("offscreen target"),
+            size: Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: state.config.format,
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+        });
+        let view = texture.create_view(&TextureViewDescriptor::default());
+        let size = PhysicalSize::new(width, height);
+        let targets = HdrTargets::new(&state.device, &state.post, state.target, size);
+
+        let padded_row =
+            (width * 4).div_ceil(COPY_BYTES_PER_ROW_ALIGNMENT) * COPY_BYTES_PER_ROW_ALIGNMENT;
+        let readback = state.device.create_buffer(&BufferDescriptor {
+            label: Some("offscreen readback"),
+            size: padded_row as u64 * height as u64,
+            usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            texture,
+            view,
+            targets,
+            readback,
+            size,
+            padded_row,
+        }
+    }
+
+    pub fn size(&self) -> PhysicalSize<u32> {
+        self.size
+    }
+
+    /// Renders the current frame, with the camera of the window stretched to the aspect
+    /// ratio of the target, and reads back the pixels as tightly packed rgba8.
+    pub fn render(&self, state: &State) -> Result<Vec<u8>, String> {
+        self.render_with(state, &state.camera)
+    }
+
+    /// Like `render`, with another camera.
+    pub fn render_with(&self, state: &State, camera: &Camera) -> Result<Vec<u8>, String> {
+        let PhysicalSize { width, height } = self.size;
+        let camera = camera.with_aspect(self.size);
+
+        let mut command_encoder = state
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor::default());
+        state.draw(
+            &mut command_encoder,
+            &self.view,
+            &self.targets,
+            self.size,
+            &camera,
+        );
+        command_encoder.copy_texture_to_buffer(
+            ImageCopyTexture {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: Origin3d::ZERO,
+                aspect: TextureAspect::All,
+            },
+            ImageCopyBuffer {
+                buffer: &self.readback,
+                layout: ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: NonZeroU32::new(self.padded_row),
+                    rows_per_image: None,
+                },
+            },
+            Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+
+        state.write_stars();
+        if let Some(comparison) = &state.comparison {
+            comparison.write_stars(&state.queue);
+        }
+        state.queue.submit(Some(command_encoder.finish()));
+
+        let slice = self.readback.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        state.device.poll(Maintain::Wait);
+        receiver
+            .recv()
+            .map_err(|e| e.to_string())?
+            .map_err(|e| e.to_string())?;
+
+        let swizzle = matches!(
+            state.config.format,
+            TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb
+        );
+        let unpadded_row = width as usize * 4;
+        let mut pixels = Vec::with_capacity(unpadded_row * height as usize);
+        for row in slice.get_mapped_range().chunks(self.padded_row as usize) {
+            for pixel in row[..unpadded_row].chunks(4) {
+                if swizzle {
+                    pixels.extend_from_slice(&[pixel[2], pixel[1], pixel[0], pixel[3]]);
+                } else {
+                    pixels.extend_from_slice(pixel);
+                }
+            }
+        }
+        self.readback.unmap();
+
+        Ok(pixels)
+    }
+}
+
+/// A view of a figure panel. All panels are captured at once by `State::capture_panels`, so
+/// figures of several time points of a run show the same views.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Panel {
+    /// part of the file name
+    pub name: String,
+    /// magnification relative to the view that fits all stars
+    pub zoom: f32,
+    #[serde(default)]
+    pub center: PanelCenter,
+}
+
+/// What is in the middle of a `Panel`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PanelCenter {
+    /// the middle of the bounding box of all stars
+    #[default]
+    Fit,
+    /// the center of mass of all stars, e.g. the core of a galaxy
+    CenterOfMass,
+    /// a fixed world position, e.g. a region of interest
+    At([f32; 2]),
+}
+
+impl Panel {
+    /// The whole system, and two close-ups of the center of mass.
+    pub fn defaults() -> Vec<Self> {
+        [
+            ("full", 1.0, PanelCenter::Fit),
+            ("mid", 4.0, PanelCenter::CenterOfMass),
+            ("core", 16.0, PanelCenter::CenterOfMass),
+        ]
+        .into_iter()
+        .map(|(name, zoom, center)| Self {
+            name: name.to_string(),
+            zoom,
+            center,
+        })
+        .collect()
+    }
+
+    /// The camera of the panel for `stars`, on a target of the aspect ratio of `camera`.
+    pub fn camera(&self, camera: &Camera, stars: &[Star]) -> Camera {
+        let mut camera = *camera;
+        camera.fit(stars);
+        camera.render_scale *= self.zoom;
+        match self.center {
+            PanelCenter::Fit => {}
+            PanelCenter::CenterOfMass => {
+                camera.center = center_of_mass(stars).unwrap_or(camera.center);
+            }
+            PanelCenter::At(center) => camera.center = center.into(),
+        }
+        camera
+    }
+}
+
+fn center_of_mass(stars: &[Star]) -> Option<Vector2<f32>> {
+    let (weighted, mass) = stars
+        .iter()
+        .filter(|star| star.pos().iter().all(|x| x.is_finite()))
+        .fold((Vector2::zeros(), 0.0), |(weighted, mass), star| {
+            let star_mass = star.mass() as f64;
+            (
+                weighted + star.pos().cast::<f64>() * star_mass,
+                mass + star_mass,
+            )
+        });
+    (mass > 0.0).then(|| (weighted / mass).cast())
+}
+
+/// Box-filters an srgb image down by `factor`, averaging in linear space.
+fn downsample(pixels: &[u8], width: u32, height: u32, factor: u32) -> Vec<u8> {
+    let to_linear = |c: u8| (c as f32 / 255.0).powf(2.2);
+    let to_srgb = |c: f32| (c.powf(1.0 / 2.2) * 255.0).round() as u8;
+
+    let (out_width, out_height) = (width / factor, height / factor);
+    let samples = (factor * factor) as f32;
+
+    let mut out = Vec::with_capacity((out_width * out_height * 4) as usize);
+    for y in 0..out_height {
+        for x in 0..out_width {
+            let mut sum = [0.0f32; 4];
+            for sy in 0..factor {
+                for sx in 0..factor {
+                    let i = (((y * factor + sy) * width + x * factor + sx) * 4) as usize;
+                    for c in 0..3 {
+                        sum[c] += to_linear(pixels[i + c]);
+                    }
+                    sum[3] += pixels[i + 3] as f32;
+                }
+            }
+            out.extend_from_slice(&[
+                to_srgb(sum[0] / samples),
+                to_srgb(sum[1] / samples),
+                to_srgb(sum[2] / samples),
+                (sum[3] / samples).round() as u8,
+            ]);
+        }
+    }
+    out
+}
+
+pub fn write_png(path: &str, pixels: &[u8], width: u32, height: u32) -> Result<(), String> {
+    let file = File::create(path).map_err(|e| e.to_string())?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header().map_err(|e| e.to_string())?;
+    writer.write_image_data(pixels).map_err(|e| e.to_string())
+}
@@ -0,0 +1,358 @@
+use crate::config::Config;
+use gravsim_simulation::output::{Cadence, OutputPolicy};
+use gravsim_simulation::scenario::{GalaxySpec, Scenario};
+use gravsim_simulation::script::Script;
+use gravsim_simulation::solver::Solver;
+use gravsim_simulation::Simulation;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+/// What the `gravsim` binary does, named by its first argument:
+///
+/// `gravsim [view] [script]` opens the window, see `main` for its flags. Without `script`, the
+/// script of the first scenario runs before every update.
+///
+/// `gravsim headless [--steps=<n>] [--every=<n>] [--stars=<n>] [--scenario=<path>] [--seed=<n>] [--out=<dir>] [snapshot]`
+/// continues the given snapshot, or starts the scenario or a galaxy of `stars` stars, without
+/// a window. Snapshots are written as the `output` policy of the scenario says, or every
+/// `every` steps, 100 by default. With `seed`, the stars are the same in every run, see
+/// `Scenario::seed`. The script of the scenario runs before every update.
+///
+/// `gravsim bench [--steps=<n>] [--stars=<n>] [--scenario=<path>] [--seed=<n>] [snapshot]`
+/// times the updates of the same simulations without writing anything.
+///
+/// `gravsim render-replay <replay>` plays back a recording of `--record_replay=` and captures
+/// every frame as `--record=` does, then exits.
+///
+/// `gravsim validate [--samples=<n>] <snapshot>` checks the tree built for the snapshot and
+/// prints a report, see `Validation`.
+///
+/// `gravsim diff [--top=<n>] <a> <b>` compares the stars of two snapshots and lists the `top`
+/// stars that moved apart the most, see `Diff`.
+///
+/// `gravsim datagen [--runs=<n>] [--stars=<n>] [--scenario=<path>] [--seed=<n>] [--out=<dir>]`
+/// writes the initial conditions of `runs` runs as snapshots, for datasets of many runs.
+///
+/// All of them read the config layers of `Config`, e.g. `--threads=`, and the star count of
+/// generated galaxies defaults to the one of its quality preset. `--solver=<name>` replaces
+/// the solver of the scenario, see `Solver`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Subcommand {
+    View,
+    Headless,
+    Bench,
+    RenderReplay,
+    Validate,
+    Diff,
+    Datagen,
+}
+
+impl Subcommand {
+    /// The subcommand named by the first of `args`, which is then removed. Anything else
+    /// opens the window, so `gravsim <script>` keeps working.
+    pub fn take(args: &mut Vec<String>) -> Self {
+        match args.first().and_then(|name| name.parse().ok()) {
+            Some(subcommand) => {
+                args.remove(0);
+                subcommand
+            }
+            None => Subcommand::View,
+        }
+    }
+
+    /// Whether the subcommand opens a window, the others are handled by `run`.
+    pub fn windowed(&self) -> bool {
+        matches!(self, Subcommand::View | Subcommand::RenderReplay)
+    }
+}
+
+impl FromStr for Subcommand {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Ok(match name {
+            "view" => Subcommand::View,
+            "headless" => Subcommand::Headless,
+            "bench" => Subcommand::Bench,
+            "render-replay" => Subcommand::RenderReplay,
+            "validate" => Subcommand::Validate,
+            "diff" => Subcommand::Diff,
+            "datagen" => Subcommand::Datagen,
+            _ => return Err(format!("unknown subcommand: {}", name)),
+        })
+    }
+}
+
+/// Flags of the subcommands without a window, see `Subcommand`.
+pub struct Options {
+    pub samples: usize,
+    pub top: usize,
+    pub steps: u64,
+    pub every: Option<u64>,
+    pub stars: usize,
+    pub scenario: Option<PathBuf>,
+    pub seed: Option<u64>,
+    pub solver: Option<Solver>,
+    /// `snapshots` for `headless` and `initial_conditions` for `datagen` by default
+    pub out: Option<PathBuf>,
+    pub runs: u64,
+}
+
+impl Options {
+    /// Parses `flags` of the form `--<key>=<value>`. Flags of the config are left to
+    /// `Config::load`, any other flag is an error.
+    pub fn parse(flags: &[String], config: &Config) -> Result<Self, String> {
+        let mut options = Self {
+            samples: 100,
+            top: 10,
+            steps: 1000,
+            every: None,
+            stars: config.quality.stars(),
+            scenario: None,
+            seed: None,
+            solver: None,
+            out: None,
+            runs: 1,
+        };
+        for flag in flags {
+            match flag.trim_start_matches("--").split_once('=') {
+                Some(("steps", value)) => options.steps = parse(value)?,
+                Some(("every", value)) => options.every = Some(parse::<u64>(value)?.max(1)),
+                Some(("stars", value)) => options.stars = parse(value)?,
+                Some(("scenario", value)) => options.scenario = Some(value.into()),
+                Some(("seed", value)) => options.seed = Some(parse(value)?),
+                Some(("solver", value)) => options.solver = Some(value.parse()?),
+                Some(("out", value)) => options.out = Some(value.into()),
+                Some(("samples", value)) => options.samples = parse(value)?,
+                Some(("top", value)) => options.top = parse(value)?,
+                Some(("runs", value)) => options.runs = parse(value)?,
+                Some((key, _)) if key == "config" || Config::FLAGS.contains(&key) => {}
+                _ => return Err(format!("unknown flag: {}", flag)),
+            }
+        }
+        Ok(options)
+    }
+
+    /// The scenario of `--scenario=`, or a galaxy of `stars` stars, seeded with `seed` and
+    /// simulated with `solver`.
+    fn scenario(&self) -> Scenario {
+        let mut scenario = match &self.scenario {
+            Some(path) => Scenario::from_path(path).unwrap_or_else(|e| panic!("{}", e)),
+            None => Scenario {
+                galaxies: vec![GalaxySpec {
+                    stars: self.stars,
+                    ..GalaxySpec::default()
+                }],
+                ..Scenario::default()
+            },
+        };
+        scenario.seed = self.seed.or(scenario.seed);
+        scenario.config.solver = self.solver.unwrap_or(scenario.config.solver);
+        scenario
+    }
+}
+
+fn parse<T: FromStr>(value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid number: {}", value))
+}
+
+/// Runs a subcommand without a window on the snapshots given as `paths`.
+pub fn run(subcommand: Subcommand, options: &Options, paths: &[String]) {
+    match subcommand {
+        Subcommand::Headless => headless(options, paths.first()),
+        Subcommand::Bench => bench(options, paths.first()),
+        Subcommand::Validate => {
+            validate(options, paths.first().expect("validate needs a snapshot"))
+        }
+        Subcommand::Diff => match paths {
+            [a, b] => diff(options, a, b),
+            _ => panic!("diff needs two snapshots"),
+        },
+        Subcommand::Datagen => datagen(options),
+        Subcommand::View | Subcommand::RenderReplay => {
+            unreachable!("{:?} opens a window", subcommand)
+        }
+    }
+}
+
+/// The simulation of a snapshot, with the solver of `--solver=` if given.
+fn load(path: &str, options: &Options) -> Simulation {
+    let mut simulation =
+        Simulation::load(path).unwrap_or_else(|e| panic!("failed to load {}: {}", path, e));
+    simulation.config.solver = options.solver.unwrap_or(simulation.config.solver);
+    simulation
+}
+
+fn headless(options: &Options, snapshot: Option<&String>) {
+    let (mut output, mut script) = (None, None);
+    let mut simulation = match snapshot {
+        Some(path) => load(path, options),
+        None => {
+            let mut scenario = options.scenario();
+            output = scenario.output.take();
+            script = scenario.script.take().map(|spec| {
+                let source = spec.source().unwrap_or_else(|e| panic!("{}", e));
+                Script::new(&source)
+                    .unwrap_or_else(|e| panic!("failed to compile the script: {}", e))
+            });
+            scenario.to_simulation()
+        }
+    };
+    let out = options
+        .out
+        .clone()
+        .unwrap_or_else(|| PathBuf::from("snapshots"));
+    std::fs::create_dir_all(&out)
+        .unwrap_or_else(|e| panic!("failed to create {}: {}", out.display(), e));
+
+    let policy = match (options.every, output) {
+        (Some(every), _) => OutputPolicy::every_steps(every),
+        (None, Some(output)) => output,
+        (None, None) => OutputPolicy::every_steps(100),
+    };
+    let mut cadence = Cadence::new(policy, &simulation);
+
+    let start = Instant::now();
+    for _ in 0..options.steps {
+        if let Some(script) = &script {
+            script
+                .run(&mut simulation)
+                .unwrap_or_else(|e| panic!("script failed at step {}: {}", simulation.step, e));
+        }
+        simulation.update();
+        if let Some(monitor) = &simulation.pericenter_monitor {
+            for pericenter in &simulation.pericenters {
+                let [a, b] = pericenter.groups.map(|group| &monitor.groups[group].name);
+                println!(
+                    "pericenter of {} and {} at time {:.1}, distance {:.1}",
+                    a, b, pericenter.time, pericenter.distance
+                );
+            }
+        }
+        if cadence.due(&simulation) {
+            let path = out.join(format!("step_{}.snapshot", simulation.step));
+            simulation
+                .save(&path)
+                .unwrap_or_else(|e| panic!("failed to write {}: {}", path.display(), e));
+            println!(
+                "step {}: {} stars, {:.1?} elapsed, saved {}",
+                simulation.step,
+                simulation.stars.len(),
+                start.elapsed(),
+                path.display()
+            );
+        }
+    }
+}
+
+/// Prints how long the updates took, the first ones included, as they are part of any run.
+fn bench(options: &Options, snapshot: Option<&String>) {
+    let mut simulation = match snapshot {
+        Some(path) => load(path, options),
+        None => options.scenario().to_simulation(),
+    };
+    let stars = simulation.stars.len();
+
+    let mut times: Vec<Duration> = (0..options.steps.max(1))
+        .map(|_| {
+            let start = Instant::now();
+            simulation.update();
+            start.elapsed()
+        })
+        .collect();
+    times.sort();
+    let total: Duration = times.iter().sum();
+    println!("{} steps of {} stars in {:.2?}", times.len(), stars, total);
+    println!(
+        "per step: mean {:.2?}, median {:.2?}, max {:.2?}",
+        total / times.len() as u32,
+        times[times.len() / 2],
+        times[times.len() - 1]
+    );
+    println!(
+        "{:.0} star updates per second",
+        (stars * times.len()) as f64 / total.as_secs_f64()
+    );
+}
+
+fn validate(options: &Options, path: &str) {
+    let simulation = load(path, options);
+
+    let start = Instant::now();
+    let validation = simulation.validate(options.samples);
+    println!("{} at step {}", path, simulation.step);
+    println!("{}", validation);
+    println!("validated in {:.1?}", start.elapsed());
+    if !validation.is_consistent() {
+        std::process::exit(1);
+    }
+}
+
+fn diff(options: &Options, a: &str, b: &str) {
+    let (a_simulation, b_simulation) = (load(a, options), load(b, options));
+
+    let diff = a_simulation.diff(&b_simulation);
+    println!(
+        "a: {} at step {}, time {}",
+        a, a_simulation.step, a_simulation.time
+    );
+    println!(
+        "b: {} at step {}, time {}",
+        b, b_simulation.step, b_simulation.time
+    );
+    println!("{}", diff);
+
+    let largest: Vec<_> = diff
+        .largest(options.top)
+        .into_iter()
+        .filter(|delta| delta.position > 0.0)
+        .collect();
+    if !largest.is_empty() {
+        println!("largest differences:");
+    }
+    for delta in largest {
+        println!(
+            "  star {}: position {:.3e}, velocity {:.3e}, mass {:.3e}",
+            delta.id, delta.position, delta.velocity, delta.mass
+        );
+    }
+    for (name, ids) in [("a", &diff.only_in_a), ("b", &diff.only_in_b)] {
+        if !ids.is_empty() {
+            println!("only in {}: {:?}", name, ids);
+        }
+    }
+    if !diff.is_identical() {
+        std::process::exit(1);
+    }
+}
+
+/// Writes run `i` to `run_<i>.snapshot`. With a seed, run `i` is seeded with `seed + i`, so
+/// the same dataset can be generated again, otherwise every run is random.
+fn datagen(options: &Options) {
+    let out = options
+        .out
+        .clone()
+        .unwrap_or_else(|| PathBuf::from("initial_conditions"));
+    std::fs::create_dir_all(&out)
+        .unwrap_or_else(|e| panic!("failed to create {}: {}", out.display(), e));
+
+    let base = options.scenario();
+    for run in 0..options.runs {
+        let mut scenario = base.clone();
+        scenario.seed = base.seed.map(|seed| seed.wrapping_add(run));
+        let simulation = scenario.to_simulation();
+        let path = out.join(format!("run_{}.snapshot", run));
+        simulation
+            .save(&path)
+            .unwrap_or_else(|e| panic!("failed to write {}: {}", path.display(), e));
+        println!(
+            "run {}: {} stars, saved {}",
+            run,
+            simulation.stars.len(),
+            path.display()
+        );
+    }
+}
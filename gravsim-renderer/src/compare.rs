@@ -0,0 +1,84 @@
+use crate::state::star_bytes;
+use gravsim_simulation::{Real, Simulation, Star};
+use wgpu::util::{BufferInitDescriptor, DeviceExt};
+use wgpu::{Buffer, BufferUsages, Device, Queue};
+
+/// A second simulation stepped alongside the main one from identical initial conditions,
+/// but with a different solver configuration. Both are rendered in split screen.
+pub struct Comparison {
+    pub simulation: Simulation,
+    pub star_buffer: Buffer,
+
+    /// root mean square distance between corresponding stars of both simulations
+    pub divergence: f32,
+
+    /// number of stars `star_buffer` can hold
+    capacity: usize,
+}
+
+impl Comparison {
+    /// Opening angle of the comparison simulation until one is set with `compare theta`.
+    pub const DEFAULT_THETA: Real = 1.25;
+
+    /// Clones `reference`, stepping the clone with the opening angle `theta`.
+    pub fn new(device: &Device, reference: &Simulation, theta: Real) -> Self {
+        let mut simulation = reference.clone();
+        simulation.config.theta = theta;
+        simulation.record_stats = false;
+        // sorted along with the reference, so stars keep corresponding by id
+        simulation.config.sort_every = None;
+
+        let star_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("comparison stars"),
+            contents: &star_bytes(&simulation.stars),
+            usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+        });
+
+        Self {
+            simulation,
+            star_buffer,
+            divergence: 0.0,
+            capacity: reference.stars.len(),
+        }
+    }
+
+    pub fn update(&mut self, reference: &[Star]) {
+        self.simulation.update();
+        self.divergence = divergence(reference, &self.simulation.stars);
+    }
+
+    /// Recreates the star buffer if it is too small for the current stars.
+    pub fn reserve(&mut self, device: &Device) {
+        if self.capacity < self.simulation.stars.len() {
+            self.star_buffer = device.create_buffer_init(&BufferInitDescriptor {
+                label: Some("comparison stars"),
+                contents: &star_bytes(&self.simulation.stars),
+                usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+            });
+            self.capacity = self.simulation.stars.len();
+        }
+    }
+
+    pub fn write_stars(&self, queue: &Queue) {
+        queue.write_buffer(&self.star_buffer, 0, &star_bytes(&self.simulation.stars));
+    }
+}
+
+/// Root mean square distance between corresponding stars of `a` and `b`.
+/// Stars that left either simulation are ignored.
+pub fn divergence(a: &[Star], b: &[Star]) -> f32 {
+    let (sum, count) = a
+        .iter()
+        .zip(b)
+        .map(|(a, b)| (a.pos() - b.pos()).norm_squared())
+        .filter(|dist_sq| dist_sq.is_finite())
+        .fold((0.0, 0), |(sum, count), dist_sq| {
+            (sum + dist_sq as f64, count + 1)
+        });
+
+    if count == 0 {
+        0.0
+    } else {
+        (sum / count as f64).sqrt() as f32
+    }
+}
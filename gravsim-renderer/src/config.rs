@@ -0,0 +1,380 @@
+use crate::capture::Panel;
+use crate::record::Recording;
+use crate::state::RenderPath;
+use gravsim_simulation::fits::Units;
+use gravsim_simulation::Simulation;
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use toml::Value;
+use winit::event::VirtualKeyCode;
+
+/// Settings of the viewer, merged from several layers where later ones win:
+/// defaults, the user config (`~/.config/gravsim.toml`), the workspace config
+/// (`gravsim.toml` in the working directory, or `--config=<path>`), environment variables
+/// and command line flags.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub backend: Backend,
+    /// worker threads of the simulation, 0 uses all cores
+    pub threads: usize,
+    pub quality: Quality,
+    /// overrides the render path of `quality`, e.g. `impostor` for very many stars
+    pub render_path: Option<RenderPath>,
+    /// rebuilds the star pipelines whenever `src/shaders/stars.wgsl` of the checkout the
+    /// viewer was built from changes
+    pub hot_reload_shaders: bool,
+    /// if set, every update is captured to png frames or a video
+    pub record: Option<Recording>,
+    /// if set, the stars of every update are written to this file, to be played back with
+    /// `--replay=<path>`
+    pub record_replay: Option<PathBuf>,
+    /// physical units of the simulation, used by exports
+    pub units: Units,
+    /// views captured together by the panels key, `panels` command or marker, e.g.
+    /// `[[panels]]` tables with `name = "arm"`, `zoom = 8.0` and `center = { at = [x, y] }`
+    pub panels: Vec<Panel>,
+    /// if set, the viewer can be driven remotely from this address, see `Remote`
+    pub remote: Option<SocketAddr>,
+    pub keybindings: Keybindings,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            backend: Backend::Auto,
+            threads: 0,
+            quality: Quality::Medium,
+            render_path: None,
+            hot_reload_shaders: false,
+            record: None,
+            record_replay: None,
+            units: Units::default(),
+            panels: Panel::defaults(),
+            remote: None,
+            keybindings: Keybindings::default(),
+        }
+    }
+}
+
+impl Config {
+    pub const FILE_NAME: &'static str = "gravsim.toml";
+    /// Keys of the command line flags read by `flags`.
+    pub const FLAGS: [&'static str; 9] = [
+        "backend",
+        "threads",
+        "quality",
+        "render_path",
+        "hot_reload_shaders",
+        "record",
+        "record_every",
+        "record_replay",
+        "remote",
+    ];
+
+    pub fn user_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join(Self::FILE_NAME))
+    }
+
+    /// Merges all layers. `workspace` replaces the workspace config in the working directory,
+    /// `flags` are command line flags of the form `--<key>=<value>`, others are ignored.
+    pub fn load(workspace: Option<&Path>, flags: &[String]) -> Result<Self, String> {
+        let mut merged = Value::try_from(Self::default()).unwrap();
+
+        let workspace = workspace.unwrap_or_else(|| Path::new(Self::FILE_NAME));
+        if let Some(user) = Self::user_path().filter(|path| path.exists()) {
+            merge(&mut merged, read(&user)?);
+        }
+        if workspace.exists() {
+            merge(&mut merged, read(workspace)?);
+        }
+        merge(&mut merged, Self::env());
+        merge(&mut merged, Self::flags(flags)?);
+
+        merged.try_into().map_err(|e| e.to_string())
+    }
+
+    /// The layer set by environment variables, `GRAVSIM_BACKEND` overrides the backend, e.g.
+    /// when a machine's config asks for one the current session can't use.
+    fn env() -> Value {
+        let mut layer = toml::value::Table::new();
+        if let Ok(backend) = std::env::var("GRAVSIM_BACKEND") {
+            layer.insert("backend".to_string(), Value::String(backend.to_lowercase()));
+        }
+        Value::Table(layer)
+    }
+
+    /// The layer set by `--backend=`, `--threads=`, `--quality=`, `--render_path=`,
+    /// `--hot_reload_shaders=`, `--record=`, `--record_every=`, `--record_replay=` and
+    /// `--remote=` flags.
+    fn flags(flags: &[String]) -> Result<Value, String> {
+        let mut layer = toml::value::Table::new();
+        let mut record = toml::value::Table::new();
+        for flag in flags {
+            let Some((key, value)) = flag.trim_start_matches("--").split_once('=') else {
+                continue;
+            };
+            let value = match key {
+                "record" => {
+                    record.insert("path".to_string(), Value::String(value.to_string()));
+                    continue;
+                }
+                "record_every" => {
+                    let every = value
+                        .parse()
+                        .map_err(|_| format!("invalid record_every: {}", value))?;
+                    record.insert("every".to_string(), Value::Integer(every));
+                    continue;
+                }
+                "backend" | "quality" | "render_path" | "record_replay" | "remote" => {
+                    Value::String(value.to_string())
+                }
+                "threads" => Value::Integer(
+                    value
+                        .parse()
+                        .map_err(|_| format!("invalid thread count: {}", value))?,
+                ),
+                "hot_reload_shaders" => Value::Boolean(
+                    value
+                        .parse()
+                        .map_err(|_| format!("invalid hot_reload_shaders: {}", value))?,
+                ),
+                _ => continue,
+            };
+            layer.insert(key.to_string(), value);
+        }
+        if !record.is_empty() {
+            layer.insert("record".to_string(), Value::Table(record));
+        }
+        Ok(Value::Table(layer))
+    }
+
+    /// The effective config, as it would be written to a config file.
+    pub fn show(&self) -> String {
+        toml::to_string_pretty(self).unwrap()
+    }
+}
+
+fn read(path: &Path) -> Result<Value, String> {
+    std::fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|source| source.parse().map_err(|e| format!("{}", e)))
+        .map_err(|e| format!("invalid config {}: {}", path.display(), e))
+}
+
+/// Merges `layer` into `base`, tables are merged key by key, everything else is replaced.
+fn merge(base: &mut Value, layer: Value) {
+    match (base, layer) {
+        (Value::Table(base), Value::Table(layer)) => {
+            for (key, value) in layer {
+                match base.get_mut(&key) {
+                    Some(base) => merge(base, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, layer) => *base = layer,
+    }
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    /// the most powerful adapter of any backend
+    Auto,
+    Vulkan,
+    Metal,
+    Dx12,
+    Gl,
+    /// whichever of Vulkan, Metal and DX12 is available
+    Primary,
+}
+
+impl Backend {
+    pub fn backends(&self) -> wgpu::Backends {
+        match self {
+            Backend::Auto => wgpu::Backends::all(),
+            Backend::Vulkan => wgpu::Backends::VULKAN,
+            Backend::Metal => wgpu::Backends::METAL,
+            Backend::Dx12 => wgpu::Backends::DX12,
+            Backend::Gl => wgpu::Backends::GL,
+            Backend::Primary => wgpu::Backends::PRIMARY,
+        }
+    }
+}
+
+/// Presets bundling the settings whose cost depends on the machine,
+/// from laptops (`low`) to workstations (`ultra`).
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Quality {
+    Low,
+    Medium,
+    High,
+    Ultra,
+}
+
+impl Quality {
+    /// Number of stars of the generated galaxy.
+    pub fn stars(&self) -> usize {
+        match self {
+            Quality::Low => Simulation::N_STARS / 4,
+            Quality::Medium => Simulation::N_STARS,
+            Quality::High => Simulation::N_STARS * 4,
+            Quality::Ultra => Simulation::N_STARS * 8,
+        }
+    }
+
+    /// Simulation steps per frame.
+    pub fn substeps(&self) -> u32 {
+        match self {
+            Quality::Low => 2,
+            Quality::Medium => 4,
+            Quality::High | Quality::Ultra => 8,
+        }
+    }
+
+    /// How star instances are fed to the gpu, culling pays off with many stars.
+    pub fn render_path(&self) -> RenderPath {
+        match self {
+            Quality::Low | Quality::Medium => RenderPath::VertexBuffer,
+            Quality::High | Quality::Ultra => RenderPath::Culled,
+        }
+    }
+
+    pub fn msaa_samples(&self) -> u32 {
+        match self {
+            Quality::Low | Quality::Medium => 1,
+            Quality::High | Quality::Ultra => 4,
+        }
+    }
+}
+
+/// Something the user can do with a key press.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Action {
+    Up,
+    Left,
+    Down,
+    Right,
+    Pause,
+    CycleRenderPath,
+    PrintStats,
+    ToggleComparison,
+    ToggleErrorColors,
+    ToggleTrails,
+    CycleQuiver,
+    Screenshot,
+    CapturePanels,
+    SaveSnapshot,
+    LoadSnapshot,
+    AddDilationZone,
+    ClearDilationZones,
+    ResetView,
+    FitView,
+    ToggleFollow,
+    ToggleConsole,
+    ToggleOverlay,
+    NextTab,
+    PreviousTab,
+}
+
+/// Keys of every action, by their winit names (e.g. `"Space"`, `"F2"`, `"Grave"`).
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Keybindings {
+    pub up: Vec<VirtualKeyCode>,
+    pub left: Vec<VirtualKeyCode>,
+    pub down: Vec<VirtualKeyCode>,
+    pub right: Vec<VirtualKeyCode>,
+    pub pause: Vec<VirtualKeyCode>,
+    pub cycle_render_path: Vec<VirtualKeyCode>,
+    pub print_stats: Vec<VirtualKeyCode>,
+    pub toggle_comparison: Vec<VirtualKeyCode>,
+    pub toggle_error_colors: Vec<VirtualKeyCode>,
+    pub toggle_trails: Vec<VirtualKeyCode>,
+    pub cycle_quiver: Vec<VirtualKeyCode>,
+    pub screenshot: Vec<VirtualKeyCode>,
+    pub capture_panels: Vec<VirtualKeyCode>,
+    pub save_snapshot: Vec<VirtualKeyCode>,
+    pub load_snapshot: Vec<VirtualKeyCode>,
+    pub add_dilation_zone: Vec<VirtualKeyCode>,
+    pub clear_dilation_zones: Vec<VirtualKeyCode>,
+    pub reset_view: Vec<VirtualKeyCode>,
+    pub fit_view: Vec<VirtualKeyCode>,
+    pub toggle_follow: Vec<VirtualKeyCode>,
+    pub toggle_console: Vec<VirtualKeyCode>,
+    pub toggle_overlay: Vec<VirtualKeyCode>,
+    pub next_tab: Vec<VirtualKeyCode>,
+    pub previous_tab: Vec<VirtualKeyCode>,
+}
+
+impl Default for Keybindings {
+    fn default() -> Self {
+        use VirtualKeyCode::*;
+
+        Self {
+            up: vec![W, Up],
+            left: vec![A, Left],
+            down: vec![S, Down],
+            right: vec![D, Right],
+            pause: vec![Space],
+            cycle_render_path: vec![F2],
+            print_stats: vec![F3],
+            toggle_comparison: vec![F4],
+            toggle_error_colors: vec![F5],
+            toggle_trails: vec![T],
+            cycle_quiver: vec![V],
+            screenshot: vec![F12],
+            capture_panels: vec![F8],
+            save_snapshot: vec![F6],
+            load_snapshot: vec![F9],
+            add_dilation_zone: vec![Z],
+            clear_dilation_zones: vec![X],
+            reset_view: vec![Return],
+            fit_view: vec![F],
+            toggle_follow: vec![C],
+            toggle_console: vec![Grave],
+            toggle_overlay: vec![F1],
+            next_tab: vec![Tab, PageDown],
+            previous_tab: vec![PageUp],
+        }
+    }
+}
+
+impl Keybindings {
+    pub fn action(&self, key: VirtualKeyCode) -> Option<Action> {
+        [
+            (Action::Up, &self.up),
+            (Action::Left, &self.left),
+            (Action::Down, &self.down),
+            (Action::Right, &self.right),
+            (Action::Pause, &self.pause),
+            (Action::CycleRenderPath, &self.cycle_render_path),
+            (Action::PrintStats, &self.print_stats),
+            (Action::ToggleComparison, &self.toggle_comparison),
+            (Action::ToggleErrorColors, &self.toggle_error_colors),
+            (Action::ToggleTrails, &self.toggle_trails),
+            (Action::CycleQuiver, &self.cycle_quiver),
+            (Action::Screenshot, &self.screenshot),
+            (Action::CapturePanels, &self.capture_panels),
+            (Action::SaveSnapshot, &self.save_snapshot),
+            (Action::LoadSnapshot, &self.load_snapshot),
+            (Action::AddDilationZone, &self.add_dilation_zone),
+            (Action::ClearDilationZones, &self.clear_dilation_zones),
+            (Action::ResetView, &self.reset_view),
+            (Action::FitView, &self.fit_view),
+            (Action::ToggleFollow, &self.toggle_follow),
+            (Action::ToggleConsole, &self.toggle_console),
+            (Action::ToggleOverlay, &self.toggle_overlay),
+            (Action::NextTab, &self.next_tab),
+            (Action::PreviousTab, &self.previous_tab),
+        ]
+        .into_iter()
+        .find(|(_, keys)| keys.contains(&key))
+        .map(|(action, _)| action)
+    }
+}
@@ -0,0 +1,170 @@
+use crate::camera::Follow;
+use gravsim_simulation::map::Quantity;
+use gravsim_simulation::{Real, StarId};
+use nalgebra::Vector2;
+use serde::Deserialize;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// A single line command prompt, toggled with the grave key. While it is open it captures
+/// all keyboard input, the line being typed is shown in the window title.
+#[derive(Default)]
+pub struct Console {
+    pub open: bool,
+    pub line: String,
+    /// result of the last command
+    pub output: String,
+}
+
+impl Console {
+    /// Adds a typed character to the line, returning the line once it is submitted.
+    pub fn type_char(&mut self, c: char) -> Option<String> {
+        match c {
+            '\r' | '\n' => return Some(std::mem::take(&mut self.line)),
+            '\u{8}' => {
+                self.line.pop();
+            }
+            // the toggle key itself
+            '`' => {}
+            c if !c.is_control() => self.line.push(c),
+            _ => {}
+        }
+        None
+    }
+}
+
+/// Where a command places something.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Location {
+    Cursor,
+    World(Vector2<Real>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    /// `select id <id>` or `select none`, shows the star in the window title
+    Select(Option<StarId>),
+    /// `follow id <id>`, `follow center` or `follow none`, keeps a star or the center of mass
+    /// in the middle of the view
+    Follow(Option<Follow>),
+    /// `select pair <id> <id>`, shows the Keplerian orbit of the two stars in the window title
+    SelectPair(StarId, StarId),
+    /// `set theta <value>`, `set gravity <value>`, `set softening <value>`, `set dt <value>` or
+    /// `set temperature <value>`, a temperature of 0 turns thermal noise off
+    Set(Parameter, Real),
+    /// `compare theta <value>`, sets the opening angle of the comparison, starting one if
+    /// there is none
+    CompareTheta(Real),
+    /// `spawn galaxy <stars> at cursor` or `spawn galaxy <stars> at <x> <y>`
+    SpawnGalaxy(usize, Location),
+    /// `delete id <id>`
+    Delete(StarId),
+    /// `impulse id <id> <vx> <vy>`, adds to the velocity of a star
+    Impulse(StarId, Vector2<Real>),
+    /// `probe at cursor <vx> <vy>` or `probe at <x> <y> <vx> <vy>`, launches a probe with
+    /// the given velocity
+    SpawnProbe(Location, Vector2<Real>),
+    /// `probe clear`, removes all probes
+    ClearProbes,
+    /// `export csv <path>`, writes all stars
+    ExportCsv(PathBuf),
+    /// `export fits density <path>` or `export fits potential <path>`, writes a map of the
+    /// view at the resolution of the window
+    ExportFits(Quantity, PathBuf),
+    /// `undo`, reverts the last spawn, delete, impulse or parameter change
+    Undo,
+    /// `redo`
+    Redo,
+    /// `tab <n>`, switches to the simulation of another tab, counted from 1
+    SwitchTab(usize),
+    /// `tab next` or `tab previous`
+    CycleTab(isize),
+    /// `tab open <path>`, opens a scenario in a new tab
+    OpenTab(PathBuf),
+    /// `panels`, captures a screenshot of every panel of the config
+    CapturePanels,
+    /// `pause` or `resume`
+    Pause(bool),
+    /// `screenshot`, saves a beauty shot and pauses
+    Screenshot,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Parameter {
+    Theta,
+    Gravity,
+    Softening,
+    #[serde(rename = "dt")]
+    TimeStep,
+    Temperature,
+}
+
+impl FromStr for Command {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let words: Vec<_> = line.split_whitespace().collect();
+        match words[..] {
+            ["select", "none"] => Ok(Self::Select(None)),
+            ["select", "id", id] => Ok(Self::Select(Some(parse(id)?))),
+            ["follow", "id", id] => Ok(Self::Follow(Some(Follow::Star(parse(id)?)))),
+            ["follow", "center"] => Ok(Self::Follow(Some(Follow::CenterOfMass))),
+            ["follow", "none"] => Ok(Self::Follow(None)),
+            ["select", "pair", a, b] => Ok(Self::SelectPair(parse(a)?, parse(b)?)),
+            ["set", "theta", value] => Ok(Self::Set(Parameter::Theta, parse(value)?)),
+            ["set", "gravity", value] => Ok(Self::Set(Parameter::Gravity, parse(value)?)),
+            ["set", "softening", value] => Ok(Self::Set(Parameter::Softening, parse(value)?)),
+            ["set", "dt", value] => Ok(Self::Set(Parameter::TimeStep, parse(value)?)),
+            ["set", "temperature", value] => Ok(Self::Set(Parameter::Temperature, parse(value)?)),
+            ["compare", "theta", value] => Ok(Self::CompareTheta(parse(value)?)),
+            ["spawn", "galaxy", stars, "at", "cursor"] => {
+                Ok(Self::SpawnGalaxy(parse(stars)?, Location::Cursor))
+            }
+            ["spawn", "galaxy", stars, "at", x, y] => Ok(Self::SpawnGalaxy(
+                parse(stars)?,
+                Location::World(Vector2::new(parse(x)?, parse(y)?)),
+            )),
+            ["delete", "id", id] => Ok(Self::Delete(parse(id)?)),
+            ["impulse", "id", id, x, y] => Ok(Self::Impulse(
+                parse(id)?,
+                Vector2::new(parse(x)?, parse(y)?),
+            )),
+            ["probe", "at", "cursor", vx, vy] => Ok(Self::SpawnProbe(
+                Location::Cursor,
+                Vector2::new(parse(vx)?, parse(vy)?),
+            )),
+            ["probe", "at", x, y, vx, vy] => Ok(Self::SpawnProbe(
+                Location::World(Vector2::new(parse(x)?, parse(y)?)),
+                Vector2::new(parse(vx)?, parse(vy)?),
+            )),
+            ["probe", "clear"] => Ok(Self::ClearProbes),
+            ["export", "csv", path] => Ok(Self::ExportCsv(path.into())),
+            ["export", "fits", "density", path] => {
+                Ok(Self::ExportFits(Quantity::SurfaceDensity, path.into()))
+            }
+            ["export", "fits", "potential", path] => {
+                Ok(Self::ExportFits(Quantity::Potential, path.into()))
+            }
+            ["undo"] => Ok(Self::Undo),
+            ["redo"] => Ok(Self::Redo),
+            ["tab", "next"] => Ok(Self::CycleTab(1)),
+            ["tab", "previous"] => Ok(Self::CycleTab(-1)),
+            ["tab", "open", path] => Ok(Self::OpenTab(path.into())),
+            ["panels"] => Ok(Self::CapturePanels),
+            ["pause"] => Ok(Self::Pause(true)),
+            ["resume"] => Ok(Self::Pause(false)),
+            ["screenshot"] => Ok(Self::Screenshot),
+            ["tab", n] => parse::<usize>(n)?
+                .checked_sub(1)
+                .map(Self::SwitchTab)
+                .ok_or_else(|| "tabs are counted from 1".to_string()),
+            _ => Err(format!("unknown command: {}", line)),
+        }
+    }
+}
+
+fn parse<T: FromStr>(word: &str) -> Result<T, String> {
+    word.parse()
+        .map_err(|_| format!("invalid number: {}", word))
+}
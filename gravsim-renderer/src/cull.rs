@@ -0,0 +1,370 @@
+use crate::camera::PushConstants;
+use crate::state::{create_star_pipeline, TargetFormat, Vertex};
+use bytemuck::{Pod, Zeroable};
+use std::cell::Cell;
+use std::mem::size_of;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use wgpu::util::{BufferInitDescriptor, DeviceExt};
+use wgpu::{
+    include_wgsl, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingType, Buffer, BufferBindingType, BufferDescriptor, BufferUsages,
+    CommandEncoder, ComputePassDescriptor, ComputePipeline, ComputePipelineDescriptor, Device,
+    IndexFormat, Maintain, MapMode, PipelineLayoutDescriptor, PushConstantRange, Queue, RenderPass,
+    RenderPipeline, ShaderModule, ShaderStages, VertexBufferLayout, VertexStepMode,
+};
+
+/// Push constants of the culling pass, the camera followed by the number of stars and the
+/// height of the target in pixels.
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+pub struct CullConstants {
+    camera: PushConstants,
+    star_count: u32,
+    viewport_height: f32,
+    /// the struct is 16 byte aligned in wgsl, like the view matrix
+    _padding: [u32; 2],
+}
+
+/// What the culling pass did with the stars of a frame.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct CullStats {
+    /// stars drawn, including `sub_pixel` ones
+    pub drawn: u32,
+    /// stars outside of the view, or removed from the simulation
+    pub culled: u32,
+    /// drawn stars less than a pixel across, which may be too faint to see
+    pub sub_pixel: u32,
+}
+
+/// Counters the culling pass increments besides the instance count, in `stats_buffer`.
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+struct Counters {
+    culled: u32,
+    sub_pixel: u32,
+}
+
+/// Layout of the arguments of `draw_indexed_indirect`.
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+pub struct DrawIndexedIndirectArgs {
+    index_count: u32,
+    instance_count: u32,
+    first_index: u32,
+    base_vertex: i32,
+    first_instance: u32,
+}
+
+/// Culls stars outside of the view on the gpu. A compute pass writes the indices of all
+/// visible stars and their count into the arguments of an indirect draw,
+/// so the cpu never needs to know how many instances are actually drawn.
+/// How many there were is read back a few frames later for the stats, see `stats`.
+pub struct Culling {
+    pub compute_pipeline: ComputePipeline,
+    pub compute_bind_group: BindGroup,
+    pub render_pipeline: RenderPipeline,
+    pub render_bind_group: BindGroup,
+
+    pub visible_buffer: Buffer,
+    pub draw_args_buffer: Buffer,
+    pub stats_buffer: Buffer,
+
+    /// instance count and `Counters` of a frame, copied out of the buffers above
+    readback: Buffer,
+    /// whether a copy into `readback` was encoded and still has to be mapped
+    copied: Cell<bool>,
+    /// whether `readback` is being mapped
+    mapping: bool,
+    mapped: Arc<AtomicBool>,
+    /// of the last frame that was read back, `None` until the first one is
+    pub stats: Option<CullStats>,
+
+    index_count: u32,
+}
+
+impl Culling {
+    const WORKGROUP_SIZE: u32 = 64;
+    const READBACK_SIZE: u64 = (size_of::<u32>() + size_of::<Counters>()) as u64;
+
+    pub fn new(
+        device: &Device,
+        star_buffer: &Buffer,
+        attribute_buffer: &Buffer,
+        star_count: usize,
+        index_count: u32,
+        star_shader: &ShaderModule,
+        target: TargetFormat,
+    ) -> Self {
+        let visible_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("visible stars"),
+            contents: bytemuck::cast_slice(&vec![0u32; star_count.max(1)]),
+            usage: BufferUsages::STORAGE,
+        });
+        let draw_args_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("draw args"),
+            contents: bytemuck::bytes_of(&DrawIndexedIndirectArgs::zeroed()),
+            usage: BufferUsages::STORAGE
+                | BufferUsages::INDIRECT
+                | BufferUsages::COPY_DST
+                | BufferUsages::COPY_SRC,
+        });
+        let stats_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("cull stats"),
+            contents: bytemuck::bytes_of(&Counters::zeroed()),
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_DST | BufferUsages::COPY_SRC,
+        });
+        let readback = device.create_buffer(&BufferDescriptor {
+            label: Some("cull stats readback"),
+            size: Self::READBACK_SIZE,
+            usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let storage_entry = |binding, visibility, read_only| BindGroupLayoutEntry {
+            binding,
+            visibility,
+            ty: BindingType::Buffer {
+                ty: BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+
+        let compute_bind_group_layout =
+            device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: None,
+                entries: &[
+                    storage_entry(0, ShaderStages::COMPUTE, true),
+                    storage_entry(1, ShaderStages::COMPUTE, true),
+                    storage_entry(2, ShaderStages::COMPUTE, false),
+                    storage_entry(3, ShaderStages::COMPUTE, false),
+                    storage_entry(4, ShaderStages::COMPUTE, false),
+                ],
+            });
+        let compute_bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: None,
+            layout: &compute_bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: star_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: attribute_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: visible_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 3,
+                    resource: draw_args_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 4,
+                    resource: stats_buffer.as_entire_binding(),
+                },
+            ],
+        });
+        let compute_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&compute_bind_group_layout],
+            push_constant_ranges: &[PushConstantRange {
+                stages: ShaderStages::COMPUTE,
+                range: 0..size_of::<CullConstants>() as u32,
+            }],
+        });
+        let cull_shader = device.create_shader_module(include_wgsl!("shaders/cull.wgsl"));
+        let compute_pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+            label: Some("cull"),
+            layout: Some(&compute_layout),
+            module: &cull_shader,
+            entry_point: "main",
+        });
+
+        let render_bind_group_layout =
+            device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: None,
+                entries: &[0, 1, 2]
+                    .map(|binding| storage_entry(binding, ShaderStages::VERTEX, true)),
+            });
+        let render_bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: None,
+            layout: &render_bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: star_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: attribute_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: visible_buffer.as_entire_binding(),
+                },
+            ],
+        });
+        let render_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&render_bind_group_layout],
+            push_constant_ranges: &[PushConstantRange {
+                stages: ShaderStages::VERTEX,
+                range: 0..size_of::<PushConstants>() as u32,
+            }],
+        });
+        let render_pipeline = create_star_pipeline(
+            device,
+            &render_layout,
+            star_shader,
+            "vs_culled",
+            &[VertexBufferLayout {
+                array_stride: size_of::<Vertex>() as u64,
+                step_mode: VertexStepMode::Vertex,
+                attributes: Vertex::ATTRIBS,
+            }],
+            target,
+        );
+
+        Self {
+            compute_pipeline,
+            compute_bind_group,
+            render_pipeline,
+            render_bind_group,
+
+            visible_buffer,
+            draw_args_buffer,
+            stats_buffer,
+
+            readback,
+            copied: Cell::new(false),
+            mapping: false,
+            mapped: Arc::new(AtomicBool::new(false)),
+            stats: None,
+
+            index_count,
+        }
+    }
+
+    /// Resets the draw arguments and the counters and encodes the culling pass, on a
+    /// target `viewport_height` pixels high.
+    pub fn cull(
+        &self,
+        queue: &Queue,
+        command_encoder: &mut CommandEncoder,
+        camera: &PushConstants,
+        star_count: usize,
+        viewport_height: u32,
+    ) {
+        queue.write_buffer(
+            &self.draw_args_buffer,
+            0,
+            bytemuck::bytes_of(&DrawIndexedIndirectArgs {
+                index_count: self.index_count,
+                ..Zeroable::zeroed()
+            }),
+        );
+        queue.write_buffer(
+            &self.stats_buffer,
+            0,
+            bytemuck::bytes_of(&Counters::zeroed()),
+        );
+
+        let constants = CullConstants {
+            camera: *camera,
+            star_count: star_count as u32,
+            viewport_height: viewport_height as f32,
+            _padding: [0; 2],
+        };
+
+        let mut compute_pass = command_encoder.begin_compute_pass(&ComputePassDescriptor {
+            label: Some("cull"),
+        });
+        compute_pass.set_pipeline(&self.compute_pipeline);
+        compute_pass.set_bind_group(0, &self.compute_bind_group, &[]);
+        compute_pass.set_push_constants(0, bytemuck::bytes_of(&constants));
+        compute_pass.dispatch_workgroups((star_count as u32).div_ceil(Self::WORKGROUP_SIZE), 1, 1);
+        drop(compute_pass);
+
+        // unless the readback of an earlier frame is still in flight
+        if !self.copied.get() && !self.mapping {
+            let instance_count = size_of::<u32>() as u64;
+            command_encoder.copy_buffer_to_buffer(
+                &self.draw_args_buffer,
+                instance_count,
+                &self.readback,
+                0,
+                instance_count,
+            );
+            command_encoder.copy_buffer_to_buffer(
+                &self.stats_buffer,
+                0,
+                &self.readback,
+                instance_count,
+                size_of::<Counters>() as u64,
+            );
+            self.copied.set(true);
+        }
+    }
+
+    /// Maps the readback of the last `cull`, call after submitting its commands. Like
+    /// `StagingRing`, this doesn't wait for the gpu, the stats show up in a later frame.
+    pub fn submitted(&mut self) {
+        if !self.copied.replace(false) {
+            return;
+        }
+        self.mapping = true;
+        let mapped = self.mapped.clone();
+        self.readback
+            .slice(..)
+            .map_async(MapMode::Read, move |result| {
+                mapped.store(result.is_ok(), Ordering::Release)
+            });
+    }
+
+    /// Updates `stats` if the readback finished mapping, without blocking.
+    pub fn read_stats(&mut self, device: &Device) {
+        if !self.mapping {
+            return;
+        }
+        device.poll(Maintain::Poll);
+        if !self.mapped.swap(false, Ordering::Acquire) {
+            return;
+        }
+
+        let words: Vec<u32> = self
+            .readback
+            .slice(..)
+            .get_mapped_range()
+            .chunks_exact(4)
+            .map(|word| u32::from_ne_bytes(word.try_into().unwrap()))
+            .collect();
+        self.readback.unmap();
+        self.mapping = false;
+        self.stats = Some(CullStats {
+            drawn: words[0],
+            culled: words[1],
+            sub_pixel: words[2],
+        });
+    }
+
+    /// Draws the stars that survived the last `cull`.
+    pub fn draw<'a>(
+        &'a self,
+        render_pass: &mut RenderPass<'a>,
+        vertex_buffer: &'a Buffer,
+        index_buffer: &'a Buffer,
+        camera: &PushConstants,
+    ) {
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_bind_group(0, &self.render_bind_group, &[]);
+        render_pass.set_push_constants(ShaderStages::VERTEX, 0, bytemuck::bytes_of(camera));
+        render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        render_pass.set_index_buffer(index_buffer.slice(..), IndexFormat::Uint16);
+        render_pass.draw_indexed_indirect(&self.draw_args_buffer, 0);
+    }
+}
@@ -0,0 +1,304 @@
+use crate::state::TargetFormat;
+use bytemuck::{Pod, Zeroable};
+use std::mem::size_of;
+use wgpu::{
+    include_wgsl, AddressMode, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
+    BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource, BindingType, BlendComponent,
+    BlendFactor, BlendOperation, BlendState, Color, ColorTargetState, ColorWrites, CommandEncoder,
+    Device, Extent3d, FilterMode, FragmentState, LoadOp, MultisampleState, Operations,
+    PipelineLayoutDescriptor, PrimitiveState, PushConstantRange, RenderPassColorAttachment,
+    RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor, Sampler, SamplerBindingType,
+    SamplerDescriptor, ShaderStages, TextureDescriptor, TextureDimension, TextureFormat,
+    TextureSampleType, TextureUsages, TextureView, TextureViewDescriptor, TextureViewDimension,
+    VertexState,
+};
+use winit::dpi::PhysicalSize;
+
+/// Format stars are accumulated in, so overlapping stars can get brighter than white.
+pub const HDR_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+
+/// Stars add up, so dense regions brighten instead of the last star covering the others.
+pub const ADDITIVE: BlendState = BlendState {
+    color: BlendComponent {
+        src_factor: BlendFactor::One,
+        dst_factor: BlendFactor::One,
+        operation: BlendOperation::Add,
+    },
+    alpha: BlendComponent {
+        src_factor: BlendFactor::One,
+        dst_factor: BlendFactor::One,
+        operation: BlendOperation::Add,
+    },
+};
+
+/// Push constants of the post pass, see `shaders/post.wgsl`.
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+struct PostConstants {
+    direction: [f32; 2],
+    amount: f32,
+    exposure: f32,
+}
+
+/// The pass turning the hdr image of the stars into the final one. Parts brighter than
+/// `bloom_threshold` are blurred at half resolution and added back, then everything is
+/// tonemapped into the output format.
+pub struct PostProcess {
+    pub bright_pipeline: RenderPipeline,
+    pub blur_pipeline: RenderPipeline,
+    pub composite_pipeline: RenderPipeline,
+    pub bind_group_layout: BindGroupLayout,
+    pub sampler: Sampler,
+
+    pub exposure: f32,
+    pub bloom_threshold: f32,
+    pub bloom_strength: f32,
+}
+
+impl PostProcess {
+    /// `output` is the format of the final image, e.g. of the surface.
+    pub fn new(device: &Device, output: TextureFormat) -> Self {
+        let texture_entry = |binding| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::FRAGMENT,
+            ty: BindingType::Texture {
+                sample_type: TextureSampleType::Float { filterable: true },
+                view_dimension: TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[
+                texture_entry(0),
+                texture_entry(1),
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[PushConstantRange {
+                stages: ShaderStages::FRAGMENT,
+                range: 0..size_of::<PostConstants>() as u32,
+            }],
+        });
+
+        let shader = device.create_shader_module(include_wgsl!("shaders/post.wgsl"));
+        let pipeline = |entry_point, format| {
+            device.create_render_pipeline(&RenderPipelineDescriptor {
+                label: Some(entry_point),
+                layout: Some(&layout),
+                vertex: VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[],
+                },
+                primitive: PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: MultisampleState::default(),
+                fragment: Some(FragmentState {
+                    module: &shader,
+                    entry_point,
+                    targets: &[Some(ColorTargetState {
+                        format,
+                        blend: Some(BlendState::REPLACE),
+                        write_mask: ColorWrites::ALL,
+                    })],
+                }),
+                multiview: None,
+            })
+        };
+
+        let sampler = device.create_sampler(&SamplerDescriptor {
+            label: None,
+            address_mode_u: AddressMode::ClampToEdge,
+            address_mode_v: AddressMode::ClampToEdge,
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            ..Default::default()
+        });
+
+        Self {
+            bright_pipeline: pipeline("fs_bright", HDR_FORMAT),
+            blur_pipeline: pipeline("fs_blur", HDR_FORMAT),
+            composite_pipeline: pipeline("fs_composite", output),
+            bind_group_layout,
+            sampler,
+
+            exposure: 1.0,
+            bloom_threshold: 1.0,
+            bloom_strength: 0.6,
+        }
+    }
+
+    /// Encodes the post pass from `targets` into `view`, after the stars were drawn into them.
+    pub fn apply(
+        &self,
+        command_encoder: &mut CommandEncoder,
+        targets: &HdrTargets,
+        view: &TextureView,
+    ) {
+        let [bloom, blurred] = &targets.bloom_views;
+        let texel = [
+            1.0 / targets.bloom_size.width as f32,
+            1.0 / targets.bloom_size.height as f32,
+        ];
+        let passes = [
+            (
+                &self.bright_pipeline,
+                &targets.bind_groups[0],
+                bloom,
+                [0.0; 2],
+                self.bloom_threshold,
+            ),
+            (
+                &self.blur_pipeline,
+                &targets.bind_groups[1],
+                blurred,
+                [texel[0], 0.0],
+                0.0,
+            ),
+            (
+                &self.blur_pipeline,
+                &targets.bind_groups[2],
+                bloom,
+                [0.0, texel[1]],
+                0.0,
+            ),
+            (
+                &self.composite_pipeline,
+                &targets.bind_groups[3],
+                view,
+                [0.0; 2],
+                self.bloom_strength,
+            ),
+        ];
+
+        for (pipeline, bind_group, view, direction, amount) in passes {
+            let mut render_pass = command_encoder.begin_render_pass(&RenderPassDescriptor {
+                label: Some("post"),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Clear(Color::BLACK),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            render_pass.set_pipeline(pipeline);
+            render_pass.set_bind_group(0, bind_group, &[]);
+            render_pass.set_push_constants(
+                ShaderStages::FRAGMENT,
+                0,
+                bytemuck::bytes_of(&PostConstants {
+                    direction,
+                    amount,
+                    exposure: self.exposure,
+                }),
+            );
+            render_pass.draw(0..3, 0..1);
+        }
+    }
+}
+
+/// The textures stars are drawn into for one output size, and the bloom textures of the
+/// post pass. Recreated whenever the output size changes.
+pub struct HdrTargets {
+    /// stars are drawn into this, or resolved into it with msaa
+    pub hdr_view: TextureView,
+    /// multisampled target resolved into `hdr_view`, if msaa is enabled
+    pub msaa_view: Option<TextureView>,
+
+    /// the bright parts of `hdr_view` and their blurred copy, at half resolution
+    bloom_views: [TextureView; 2],
+    bloom_size: PhysicalSize<u32>,
+    /// bright pass, horizontal blur, vertical blur and composite
+    bind_groups: [BindGroup; 4],
+}
+
+impl HdrTargets {
+    pub fn new(
+        device: &Device,
+        post: &PostProcess,
+        target: TargetFormat,
+        size: PhysicalSize<u32>,
+    ) -> Self {
+        let texture = |label, size: PhysicalSize<u32>, samples, usage| {
+            device
+                .create_texture(&TextureDescriptor {
+                    label: Some(label),
+                    size: Extent3d {
+                        width: size.width.max(1),
+                        height: size.height.max(1),
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: samples,
+                    dimension: TextureDimension::D2,
+                    format: HDR_FORMAT,
+                    usage,
+                })
+                .create_view(&TextureViewDescriptor::default())
+        };
+        let sampled = TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING;
+
+        let hdr_view = texture("hdr target", size, 1, sampled);
+        let msaa_view = (target.samples > 1).then(|| {
+            texture(
+                "msaa target",
+                size,
+                target.samples,
+                TextureUsages::RENDER_ATTACHMENT,
+            )
+        });
+        let bloom_size = PhysicalSize::new((size.width / 2).max(1), (size.height / 2).max(1));
+        let bloom_views = [(); 2].map(|_| texture("bloom", bloom_size, 1, sampled));
+
+        let bind_group = |source: &TextureView, bloom: &TextureView| {
+            device.create_bind_group(&BindGroupDescriptor {
+                label: None,
+                layout: &post.bind_group_layout,
+                entries: &[
+                    BindGroupEntry {
+                        binding: 0,
+                        resource: BindingResource::TextureView(source),
+                    },
+                    BindGroupEntry {
+                        binding: 1,
+                        resource: BindingResource::TextureView(bloom),
+                    },
+                    BindGroupEntry {
+                        binding: 2,
+                        resource: BindingResource::Sampler(&post.sampler),
+                    },
+                ],
+            })
+        };
+        // the second texture is only read by the composite, the others get one that
+        // isn't their render target
+        let [bloom, blurred] = &bloom_views;
+        let bind_groups = [
+            bind_group(&hdr_view, blurred),
+            bind_group(bloom, &hdr_view),
+            bind_group(blurred, &hdr_view),
+            bind_group(&hdr_view, bloom),
+        ];
+
+        Self {
+            hdr_view,
+            msaa_view,
+            bloom_views,
+            bloom_size,
+            bind_groups,
+        }
+    }
+}
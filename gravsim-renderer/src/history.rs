@@ -0,0 +1,167 @@
+use crate::console::Parameter;
+use gravsim_simulation::backend::{BodyAttributes, SimulationBackend};
+use gravsim_simulation::thermal::ThermalNoise;
+use gravsim_simulation::{Real, Star, StarId};
+use nalgebra::Vector2;
+use std::collections::VecDeque;
+
+/// A reversible interactive change to a simulation.
+#[derive(Clone, Debug)]
+pub enum Edit {
+    /// stars appended after all existing ones
+    Spawn(Vec<Star>),
+    /// a star with everything else the simulation kept of it, restored by undo
+    Delete(StarId, Star, BodyAttributes),
+    /// velocity change of a star
+    Impulse(StarId, Vector2<Real>),
+    /// parameter change from the first value to the second
+    Set(Parameter, Real, Real),
+}
+
+impl Edit {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Edit::Spawn(_) => "spawn",
+            Edit::Delete(..) => "delete",
+            Edit::Impulse(..) => "impulse",
+            Edit::Set(..) => "parameter change",
+        }
+    }
+
+    pub fn apply(&self, simulation: &mut dyn SimulationBackend) {
+        match self {
+            Edit::Spawn(stars) => {
+                for &star in stars {
+                    simulation.add_star(star);
+                }
+            }
+            Edit::Delete(id, ..) => {
+                simulation.remove_star(*id);
+            }
+            Edit::Impulse(id, impulse) => kick(simulation, *id, *impulse),
+            Edit::Set(parameter, _, value) => set(simulation, *parameter, *value),
+        }
+    }
+
+    pub fn revert(&self, simulation: &mut dyn SimulationBackend) {
+        match self {
+            Edit::Spawn(stars) => {
+                for _ in stars {
+                    let last = simulation.snapshot().len() - 1;
+                    simulation.remove_star(last);
+                }
+            }
+            Edit::Delete(id, star, attributes) => simulation.insert_body(*id, *star, *attributes),
+            Edit::Impulse(id, impulse) => kick(simulation, *id, -impulse),
+            Edit::Set(parameter, old, _) => set(simulation, *parameter, *old),
+        }
+    }
+}
+
+fn kick(simulation: &mut dyn SimulationBackend, id: StarId, impulse: Vector2<Real>) {
+    if let Some(star) = simulation.snapshot().get(id) {
+        let vel = star.vel + impulse;
+        simulation.set_velocity(id, vel);
+    }
+}
+
+fn set(simulation: &mut dyn SimulationBackend, parameter: Parameter, value: Real) {
+    if let Some(simulation) = simulation.as_simulation_mut() {
+        match parameter {
+            Parameter::Theta => simulation.config.theta = value,
+            Parameter::Gravity => simulation.config.gravity = value,
+            Parameter::Softening => simulation.config.softening = value,
+            Parameter::TimeStep => simulation.config.dt = value,
+            // keeps the seed of the noise, so undoing a change repeats the same kicks
+            Parameter::Temperature => {
+                simulation.config.thermal_noise = match simulation.config.thermal_noise {
+                    _ if value <= 0.0 => None,
+                    Some(noise) => Some(ThermalNoise {
+                        temperature: value,
+                        ..noise
+                    }),
+                    None => Some(ThermalNoise::unseeded(value)),
+                }
+            }
+        }
+    }
+}
+
+/// Applied edits that can be undone, and undone edits that can be redone.
+/// Only the last `CAPACITY` edits are kept.
+#[derive(Default)]
+pub struct History {
+    undo: VecDeque<Edit>,
+    redo: Vec<Edit>,
+}
+
+impl History {
+    pub const CAPACITY: usize = 100;
+
+    /// Records an edit that was just applied, which discards everything that could be redone.
+    /// Consecutive changes of the same parameter are undone at once, like dragging a slider.
+    pub fn push(&mut self, edit: Edit) {
+        self.redo.clear();
+        if let (Some(Edit::Set(last, _, value)), Edit::Set(parameter, _, new)) =
+            (self.undo.back_mut(), &edit)
+        {
+            if last == parameter {
+                *value = *new;
+                return;
+            }
+        }
+        self.undo.push_back(edit);
+        if self.undo.len() > Self::CAPACITY {
+            self.undo.pop_front();
+        }
+    }
+
+    /// Returns the edit to revert, if there is one.
+    pub fn undo(&mut self) -> Option<&Edit> {
+        let edit = self.undo.pop_back()?;
+        self.redo.push(edit);
+        self.redo.last()
+    }
+
+    /// Returns the edit to apply again, if there is one.
+    pub fn redo(&mut self) -> Option<&Edit> {
+        let edit = self.redo.pop()?;
+        self.undo.push_back(edit);
+        self.undo.back()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gravsim_simulation::{BodyKind, Simulation};
+
+    #[test]
+    fn undoing_a_delete_restores_a_black_hole() {
+        let mut simulation = Simulation::new(
+            (0..4).map(|i| Star::from_arrays([i as Real * 100.0, 0.0], [0.0; 2], 10.0 + i as Real)),
+        );
+        simulation.kinds = vec![BodyKind::Star, BodyKind::Star, BodyKind::BlackHole];
+        simulation.spins = vec![0.0, 0.0, 2.5];
+        simulation.ages = vec![0.0, 0.0, 40.0];
+        simulation.species = vec![0, 0, 3];
+
+        let star = simulation.stars[2];
+        let edit = Edit::Delete(2, star, simulation.attributes(2));
+        edit.apply(&mut simulation);
+        assert_eq!(simulation.kind(2), BodyKind::Star);
+        assert_eq!(simulation.stars.len(), 3);
+
+        edit.revert(&mut simulation);
+        assert_eq!(simulation.stars[2].mass(), star.mass());
+        assert_eq!(simulation.kind(2), BodyKind::BlackHole);
+        assert_eq!(
+            (
+                simulation.spins[2],
+                simulation.ages[2],
+                simulation.species[2]
+            ),
+            (2.5, 40.0, 3)
+        );
+    }
+}
@@ -0,0 +1,68 @@
+use crate::camera::PushConstants;
+use crate::hdr::ADDITIVE;
+use crate::state::{GpuStar, StarAttributes, TargetFormat, STAR_ATTRIBS};
+use std::mem::size_of;
+use wgpu::{
+    include_wgsl, ColorTargetState, ColorWrites, Device, FragmentState, MultisampleState,
+    PipelineLayoutDescriptor, PrimitiveState, PrimitiveTopology, PushConstantRange, RenderPipeline,
+    RenderPipelineDescriptor, ShaderStages, VertexBufferLayout, VertexState, VertexStepMode,
+};
+
+/// Vertices of the quad drawn per star.
+pub const VERTEX_COUNT: u32 = 4;
+
+/// A pipeline drawing every star as a single quad, with the circle cut out in the fragment
+/// shader instead of being tessellated. The star and attribute buffers are bound as
+/// instance buffers 0 and 1, there is no vertex buffer.
+pub fn impostor_pipeline(device: &Device, target: TargetFormat) -> RenderPipeline {
+    let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+        label: None,
+        bind_group_layouts: &[],
+        push_constant_ranges: &[PushConstantRange {
+            stages: ShaderStages::VERTEX,
+            range: 0..size_of::<PushConstants>() as u32,
+        }],
+    });
+    let shader = device.create_shader_module(include_wgsl!("shaders/impostor.wgsl"));
+    device.create_render_pipeline(&RenderPipelineDescriptor {
+        label: Some("impostors"),
+        layout: Some(&layout),
+        vertex: VertexState {
+            module: &shader,
+            entry_point: "vs_main",
+            buffers: &[
+                VertexBufferLayout {
+                    array_stride: size_of::<GpuStar>() as u64,
+                    step_mode: VertexStepMode::Instance,
+                    attributes: STAR_ATTRIBS,
+                },
+                VertexBufferLayout {
+                    array_stride: size_of::<StarAttributes>() as u64,
+                    step_mode: VertexStepMode::Instance,
+                    attributes: StarAttributes::ATTRIBS,
+                },
+            ],
+        },
+        primitive: PrimitiveState {
+            topology: PrimitiveTopology::TriangleStrip,
+            // quads of stars smaller than a pixel still get a fragment
+            conservative: true,
+            ..Default::default()
+        },
+        depth_stencil: None,
+        multisample: MultisampleState {
+            count: target.samples,
+            ..Default::default()
+        },
+        fragment: Some(FragmentState {
+            module: &shader,
+            entry_point: "fs_main",
+            targets: &[Some(ColorTargetState {
+                format: target.format,
+                blend: Some(ADDITIVE),
+                write_mask: ColorWrites::ALL,
+            })],
+        }),
+        multiview: None,
+    })
+}
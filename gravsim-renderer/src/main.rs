@@ -1,71 +1,364 @@
+// casts between `Real` and `f32` are no-ops in one of the precisions
+#![allow(clippy::unnecessary_cast)]
+
+pub mod adapter;
+pub mod camera;
+pub mod capture;
+pub mod cli;
+pub mod compare;
+pub mod config;
+pub mod console;
+pub mod cull;
+pub mod hdr;
+pub mod history;
+pub mod impostor;
+pub mod markers;
+pub mod overlay;
+pub mod paths;
+pub mod prediction;
+pub mod probes;
+pub mod project;
+pub mod quiver;
+pub mod record;
+pub mod reload;
+pub mod remote;
+pub mod session;
 pub mod state;
+pub mod tabs;
+pub mod timestamps;
+pub mod trails;
+pub mod upload;
 
+use crate::cli::{Options, Subcommand};
+use crate::config::Config;
+use crate::paths::Paths;
+use crate::project::Projected;
+use crate::record::Recording;
+use crate::session::Session;
 use crate::state::State;
-use gravsim_simulation::{Galaxy, MassDistribution, Simulation, Star};
-use nalgebra::{Vector2, Vector3};
+use gravsim_simulation::backend::SimulationBackend;
+use gravsim_simulation::replay::Replay;
+use gravsim_simulation::scenario::{GalaxySpec, Scenario, ScriptSpec};
+use gravsim_simulation::script::Script;
+use gravsim_simulation::solver::Solver;
+use gravsim_simulation::three_d::{Simulation3, Star3};
+use gravsim_simulation::{Real, Simulation};
 use rand::rngs::StdRng;
 use rand::{Rng, SeedableRng};
+use std::path::{Path, PathBuf};
 use std::time::{Duration, Instant};
 use wgpu::SurfaceError;
 use winit::event::{ElementState, Event, KeyboardInput, VirtualKeyCode, WindowEvent};
 use winit::event_loop::{ControlFlow, EventLoop};
 use winit::window::Window;
 
-#[tokio::main]
-async fn main() {
+fn main() {
+    // the window is opened by `view`, see `Subcommand` for the others, where
+    // `--3d` simulates a thick disc in 3d, viewed at an angle,
+    // `--scenario=<path>` reads the initial conditions from a file, every further
+    // `--scenario=<path>` opens in another tab,
+    // `--replay=<path>` plays back a recording instead of simulating,
+    // `--solver=<name>` replaces the solver of every scenario, see `Solver`,
+    // see `Config` for the other flags
+    let (flags, mut paths): (Vec<_>, Vec<_>) = std::env::args()
+        .skip(1)
+        .partition(|arg| arg.starts_with("--"));
+    let workspace_config = flags
+        .iter()
+        .find_map(|flag| flag.strip_prefix("--config="))
+        .map(std::path::Path::new);
+    let mut config = Config::load(workspace_config, &flags).unwrap_or_else(|e| panic!("{}", e));
+
+    if paths == ["config", "show"] {
+        print!("{}", config.show());
+        return;
+    }
+    if paths == ["paths"] {
+        print!("{}", Paths::default().show());
+        return;
+    }
+
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(config.threads)
+        .build_global()
+        .expect("failed to start the thread pool");
+
+    let subcommand = Subcommand::take(&mut paths);
+    if !subcommand.windowed() {
+        let options = Options::parse(&flags, &config).unwrap_or_else(|e| panic!("{}", e));
+        cli::run(subcommand, &options, &paths);
+        return;
+    }
+    // renders every frame of the replay, unless the config records less often
+    let render_replay = subcommand == Subcommand::RenderReplay;
+    if render_replay {
+        config.record.get_or_insert_with(|| Recording {
+            every: 1,
+            ..Recording::default()
+        });
+    }
+
+    let mut scenarios: Vec<(String, Scenario)> = flags
+        .iter()
+        .filter_map(|flag| flag.strip_prefix("--scenario="))
+        .map(|path| {
+            let scenario = Scenario::from_path(path).unwrap_or_else(|e| panic!("{}", e));
+            (tab_name(Path::new(path)), scenario)
+        })
+        .collect();
+    if scenarios.is_empty() {
+        let scenario = Scenario {
+            galaxies: vec![GalaxySpec {
+                stars: config.quality.stars(),
+                ..GalaxySpec::default()
+            }],
+            ..Scenario::default()
+        };
+        scenarios.push(("main".to_string(), scenario));
+    }
+    if let Some(solver) = flags.iter().find_map(|flag| flag.strip_prefix("--solver=")) {
+        let solver: Solver = solver.parse().unwrap_or_else(|e| panic!("{}", e));
+        for (_, scenario) in &mut scenarios {
+            scenario.config.solver = solver;
+        }
+    }
+
+    // the first scenario picks the frames of a replay recording
+    let output = scenarios[0].1.output.clone();
+
+    let session_dir = Session::dir();
+    let restored = (!render_replay && Session::crashed(&session_dir) && ask_restore(&session_dir))
+        .then(|| Session::restore(&session_dir))
+        .and_then(|restored| {
+            restored
+                .map_err(|e| eprintln!("failed to restore the session: {}", e))
+                .ok()
+        });
+
+    // an optional script run before every step, passed as the first argument of `view` or
+    // given by the first scenario
+    let mut script = match paths.first() {
+        _ if render_replay => None,
+        Some(path) => Some(ScriptSpec::Path(path.into())),
+        None => scenarios[0].1.script.clone(),
+    }
+    .map(|spec| {
+        let source = spec.source().unwrap_or_else(|e| panic!("{}", e));
+        Script::new(&source).unwrap_or_else(|e| panic!("failed to compile the script: {}", e))
+    });
+
     let event_loop = EventLoop::new();
     let window = Window::new(&event_loop).expect("failed to create window");
 
-    let mass_distribution = MassDistribution::new(100.0, 15000.0);
-    let galaxy = Galaxy::new(
-        Star::new(Vector2::zeros(), Vector2::zeros(), [1.0; 3], 1e1),
-        Simulation::N_STARS,
-        10_000.0,
-        &mass_distribution,
-        [1.0; 3],
-    );
-
-    let simulation = Simulation::new(galaxy.into_stars());
+    // the window is shown right away, while the stars are generated in the background
+    let three_d = flags.iter().any(|flag| flag == "--3d");
+    let replay = match render_replay {
+        true => Some(PathBuf::from(
+            paths.first().expect("render-replay needs a replay"),
+        )),
+        false => flags
+            .iter()
+            .find_map(|flag| flag.strip_prefix("--replay="))
+            .map(PathBuf::from),
+    };
+    let mut loading = Some(std::thread::spawn(move || {
+        let mut scenarios = scenarios.into_iter();
+        let (name, scenario) = scenarios.next().expect("there is at least one scenario");
+        let first = match replay {
+            Some(path) => replay_simulation(&path),
+            None => initial_simulation(scenario, restored, three_d),
+        };
+        // the other tabs
+        let others: Vec<_> = scenarios
+            .map(|(name, scenario)| (name, initial_simulation(scenario, None, three_d)))
+            .collect();
+        (name, first, others)
+    }));
+    let loading_since = Instant::now();
 
-    let mut state = State::new(&window, simulation).await;
+    let mut state: Option<State> = None;
     let mut last = Instant::now();
-    event_loop.run(move |event, _, control_flow| match event {
-        Event::WindowEvent {
-            window_id,
-            ref event,
-        } if window_id == window.id() && !state.input(event) => match event {
-            WindowEvent::Resized(new_size) => state.resize(*new_size),
-            WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
-                state.resize(**new_inner_size)
+    event_loop.run(move |event, _, control_flow| {
+        let Some(state) = &mut state else {
+            match event {
+                Event::WindowEvent {
+                    event: WindowEvent::CloseRequested,
+                    ..
+                } => *control_flow = ControlFlow::Exit,
+                Event::MainEventsCleared => match loading.take() {
+                    Some(stars) if stars.is_finished() => {
+                        window.set_title("gravsim - loading 2/2: preparing gpu resources");
+                        let (name, (simulation, colors), others) =
+                            stars.join().expect("failed to generate the stars");
+
+                        let ready =
+                            pollster::block_on(State::new(&window, simulation, colors, &config));
+                        let mut ready = match ready {
+                            Ok(ready) => ready,
+                            Err(e) => {
+                                eprintln!("{}", e);
+                                *control_flow = ControlFlow::Exit;
+                                return;
+                            }
+                        };
+                        ready.script = script.take();
+                        ready.tabs.names[0] = name;
+                        for (name, (simulation, colors)) in others {
+                            ready.add_tab(name, simulation, colors);
+                        }
+                        ready.session = Session::start(session_dir.clone())
+                            .map_err(|e| {
+                                eprintln!("failed to start a session, autosave is disabled: {}", e)
+                            })
+                            .ok();
+                        if let Some(path) = &config.record_replay {
+                            if let Err(e) = ready.start_replay_recording(path, output.clone()) {
+                                eprintln!("{}", e);
+                                *control_flow = ControlFlow::Exit;
+                                return;
+                            }
+                        }
+                        if let Some(recording) = &config.record {
+                            if let Err(e) = ready.start_recording(recording) {
+                                eprintln!("{}", e);
+                                *control_flow = ControlFlow::Exit;
+                                return;
+                            }
+                        }
+                        state = Some(ready);
+                        *control_flow = ControlFlow::Poll;
+                    }
+                    stars => {
+                        window.set_title(&format!(
+                            "gravsim - loading 1/2: generating stars ({}s)",
+                            loading_since.elapsed().as_secs()
+                        ));
+                        loading = stars;
+                        *control_flow =
+                            ControlFlow::WaitUntil(Instant::now() + Duration::from_millis(100));
+                    }
+                },
+                _ => {}
+            }
+            return;
+        };
+
+        match event {
+            Event::WindowEvent {
+                window_id,
+                ref event,
+            } if window_id == window.id() && !state.input(event) => match event {
+                WindowEvent::Resized(new_size) => state.resize(*new_size),
+                WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+                    state.resize(**new_inner_size)
+                }
+                WindowEvent::CloseRequested
+                | WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(VirtualKeyCode::Escape),
+                            ..
+                        },
+                    ..
+                } => *control_flow = ControlFlow::Exit,
+                _ => {}
+            },
+            Event::MainEventsCleared => window.request_redraw(),
+            Event::LoopDestroyed => {
+                if let Some(session) = state.session.take() {
+                    session.finish();
+                }
+                if let Some(recorder) = state.recorder.take() {
+                    recorder.finish();
+                }
+                if let Some(recorder) = state.replay_recorder.take() {
+                    let frames = recorder.frames();
+                    match recorder.finish() {
+                        Ok(_) => println!("recorded {} frames of the replay", frames),
+                        Err(e) => eprintln!("failed to finish the replay: {}", e),
+                    }
+                }
             }
-            WindowEvent::CloseRequested
-            | WindowEvent::KeyboardInput {
-                input:
-                    KeyboardInput {
-                        state: ElementState::Pressed,
-                        virtual_keycode: Some(VirtualKeyCode::Escape),
-                        ..
+            Event::RedrawRequested(window_id)
+                if window_id == window.id() && last.elapsed() > Duration::from_millis(30) =>
+            {
+                state.serve_remote();
+                state.update();
+                if render_replay && state.simulation.finished() {
+                    *control_flow = ControlFlow::Exit;
+                }
+                last = Instant::now();
+                window.set_title(&state.stats_line());
+                state.run_overlay(&window);
+
+                match state.render() {
+                    Ok(_) => {}
+                    Err(e) => match e {
+                        SurfaceError::OutOfMemory => *control_flow = ControlFlow::Exit,
+                        SurfaceError::Lost => state.resize(state.size),
+                        _ => eprintln!("Render Error: {:?}", e),
                     },
-                ..
-            } => *control_flow = ControlFlow::Exit,
-            _ => {}
-        },
-        Event::MainEventsCleared => window.request_redraw(),
-        Event::RedrawRequested(window_id)
-            if window_id == window.id() && last.elapsed() > Duration::from_millis(30) =>
-        {
-            state.update();
-            last = Instant::now();
-
-            match state.render() {
-                Ok(_) => {}
-                Err(e) => match e {
-                    SurfaceError::OutOfMemory => *control_flow = ControlFlow::Exit,
-                    SurfaceError::Lost => state.resize(state.size),
-                    _ => eprintln!("Render Error: {:?}", e),
-                },
+                }
             }
+            _ => {}
         }
-        _ => {}
     });
 }
+
+/// A recording of `--record_replay=`, played back without simulating.
+fn replay_simulation(path: &Path) -> (Box<dyn SimulationBackend + Send>, Vec<[f32; 3]>) {
+    let replay = Replay::open(path)
+        .unwrap_or_else(|e| panic!("failed to open the replay {}: {}", path.display(), e));
+    let colors = vec![[1.0; 3]; replay.stars().len()];
+    (Box::new(replay), colors)
+}
+
+/// The file name of `path` without its extension, for tabs.
+fn tab_name(path: &Path) -> String {
+    path.file_stem().map_or_else(
+        || path.display().to_string(),
+        |stem| stem.to_string_lossy().into_owned(),
+    )
+}
+
+/// The restored session, or the stars of the scenario, with their colors.
+fn initial_simulation(
+    scenario: Scenario,
+    restored: Option<(Simulation, Vec<[f32; 3]>)>,
+    three_d: bool,
+) -> (Box<dyn SimulationBackend + Send>, Vec<[f32; 3]>) {
+    if let Some((mut simulation, colors)) = restored {
+        simulation.record_stats = true;
+        return (Box::new(simulation), colors);
+    }
+
+    let (stars, colors) = scenario.generate();
+    let simulation: Box<dyn SimulationBackend + Send> = if three_d {
+        let mut rng = match scenario.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        let stars = stars
+            .iter()
+            .map(|star| Star3::from_2d(star, (rng.gen::<Real>() - 0.5) * 1000.0));
+        Box::new(Projected::new(Simulation3::new(stars)))
+    } else {
+        let mut simulation = scenario.to_simulation_with(stars);
+        simulation.record_stats = true;
+        Box::new(simulation)
+    };
+    (simulation, colors)
+}
+
+/// Asks on the terminal whether the session left behind by a crash should be restored.
+fn ask_restore(dir: &std::path::Path) -> bool {
+    print!(
+        "the last session didn't exit cleanly, restore it from {}? [y/N] ",
+        dir.display()
+    );
+    let _ = std::io::Write::flush(&mut std::io::stdout());
+
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer).is_ok() && answer.trim().eq_ignore_ascii_case("y")
+}
@@ -0,0 +1,145 @@
+use crate::camera::{Camera, PushConstants};
+use crate::state::TargetFormat;
+use crate::trails::{line_pipeline, vertex_buffer, TrailVertex};
+use gravsim_simulation::group::{Disruption, Pericenter};
+use nalgebra::Vector2;
+use wgpu::{Buffer, Device, Queue, RenderPass, RenderPipeline, ShaderStages};
+
+/// A ring around the location of an event, e.g. a tidal disruption or a close approach.
+struct Marker {
+    position: Vector2<f32>,
+    color: [f32; 3],
+    /// shown in the window title while the marker is visible
+    label: String,
+    /// frames since the marker appeared
+    age: u32,
+}
+
+/// Transient rings drawn at the locations of events, growing and fading over `LIFETIME` frames,
+/// and steady rings around black holes.
+pub struct Markers {
+    pub pipeline: RenderPipeline,
+    pub vertex_buffer: Buffer,
+
+    markers: Vec<Marker>,
+    /// number of vertices `vertex_buffer` can hold
+    capacity: usize,
+    /// number of vertices written by the last `write`
+    vertex_count: u32,
+}
+
+impl Markers {
+    pub const LIFETIME: u32 = 180;
+    /// Segments of a ring.
+    const SEGMENTS: usize = 48;
+    const DISRUPTION_COLOR: [f32; 3] = [1.0, 0.35, 0.2];
+    const PERICENTER_COLOR: [f32; 3] = [0.3, 0.8, 1.0];
+    const BLACK_HOLE_COLOR: [f32; 3] = [0.7, 0.45, 1.0];
+    const SELECTED_COLOR: [f32; 3] = [0.3, 1.0, 0.5];
+
+    pub fn new(device: &Device, target: TargetFormat) -> Self {
+        Self {
+            pipeline: line_pipeline(device, target),
+            vertex_buffer: vertex_buffer(device, 0),
+            markers: Vec::new(),
+            capacity: 0,
+            vertex_count: 0,
+        }
+    }
+
+    pub fn add_disruption(&mut self, disruption: &Disruption, group: &str) {
+        self.markers.push(Marker {
+            position: disruption.position.cast(),
+            color: Self::DISRUPTION_COLOR,
+            label: format!(
+                "{} lost {:.0}% of its bound mass",
+                group,
+                disruption.lost_fraction * 100.0
+            ),
+            age: 0,
+        });
+    }
+
+    /// Marks the midpoint of the closest approach of the groups `a` and `b`.
+    pub fn add_pericenter(&mut self, pericenter: &Pericenter, a: &str, b: &str) {
+        self.markers.push(Marker {
+            position: pericenter.position.cast(),
+            color: Self::PERICENTER_COLOR,
+            label: format!(
+                "{} and {} passed at distance {:.0}",
+                a, b, pericenter.distance
+            ),
+            age: 0,
+        });
+    }
+
+    /// Ages all markers by a frame, dropping the ones past their lifetime.
+    pub fn tick(&mut self) {
+        self.markers.iter_mut().for_each(|marker| marker.age += 1);
+        self.markers.retain(|marker| marker.age < Self::LIFETIME);
+    }
+
+    /// Labels of the visible markers, newest first.
+    pub fn labels(&self) -> impl Iterator<Item = &str> {
+        self.markers
+            .iter()
+            .rev()
+            .map(|marker| marker.label.as_str())
+    }
+
+    /// Uploads the rings and those around `black_holes` and the `selected` star, sized
+    /// relative to the view so they stay visible at any zoom.
+    pub fn write(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        black_holes: &[Vector2<f32>],
+        selected: Option<Vector2<f32>>,
+        camera: &Camera,
+    ) {
+        let rings = self.markers.len() + black_holes.len() + selected.iter().len();
+        let vertex_count = rings * Self::SEGMENTS * 2;
+        if self.capacity < vertex_count {
+            self.vertex_buffer = vertex_buffer(device, vertex_count);
+            self.capacity = vertex_count;
+        }
+
+        let mut vertices = Vec::with_capacity(vertex_count);
+        let mut ring = |center: Vector2<f32>, radius: f32, [r, g, b]: [f32; 3], alpha: f32| {
+            let point = |i: usize| {
+                let angle = i as f32 / Self::SEGMENTS as f32 * std::f32::consts::TAU;
+                let position = center + Vector2::new(angle.cos(), angle.sin()) * radius;
+                TrailVertex::new(position.into(), [r, g, b, alpha])
+            };
+            for i in 0..Self::SEGMENTS {
+                vertices.extend([point(i), point(i + 1)]);
+            }
+        };
+        for marker in &self.markers {
+            let t = marker.age as f32 / Self::LIFETIME as f32;
+            let radius = camera.view_extent() * (0.03 + 0.12 * t);
+            ring(marker.position, radius, marker.color, 1.0 - t);
+        }
+        for &black_hole in black_holes {
+            let radius = camera.view_extent() * 0.015;
+            ring(black_hole, radius, Self::BLACK_HOLE_COLOR, 1.0);
+        }
+        if let Some(selected) = selected {
+            let radius = camera.view_extent() * 0.025;
+            ring(selected, radius, Self::SELECTED_COLOR, 1.0);
+        }
+
+        queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&vertices));
+        self.vertex_count = vertices.len() as u32;
+    }
+
+    pub fn draw<'a>(&'a self, render_pass: &mut RenderPass<'a>, camera: &PushConstants) {
+        if self.vertex_count == 0 {
+            return;
+        }
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_push_constants(ShaderStages::VERTEX, 0, bytemuck::bytes_of(camera));
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.draw(0..self.vertex_count, 0..1);
+    }
+}
@@ -0,0 +1,223 @@
+use crate::console::{Command, Parameter};
+use crate::timestamps::GpuTimes;
+use egui::{ClippedPrimitive, Context, Slider, TexturesDelta, Ui};
+use egui_wgpu_backend::{RenderPass, ScreenDescriptor};
+use gravsim_simulation::backend::SimulationBackend;
+use gravsim_simulation::{Simulation, SimulationConfig};
+use std::time::{Duration, Instant};
+use wgpu::{CommandEncoder, Device, Queue, TextureFormat, TextureView};
+use winit::dpi::PhysicalSize;
+use winit::event::WindowEvent;
+use winit::window::Window;
+
+/// Window drawn with egui on top of the stars, with sliders for the parameters of the
+/// simulation and the frame rate, step and gpu time and energy drift. Changes are applied as
+/// the `set` commands of the console, so they can be undone the same way.
+pub struct Overlay {
+    pub visible: bool,
+    context: Context,
+    input: egui_winit::State,
+    pass: RenderPass,
+    /// shapes of the last frame and the textures they need, until they are drawn
+    frame: Option<(Vec<ClippedPrimitive>, TexturesDelta)>,
+    /// frames per second, smoothed over the last few frames
+    fps: f32,
+    last_frame: Instant,
+    /// total energy the drift is relative to and the last measurement of it
+    energy: Option<(f64, f64)>,
+    measured: Option<Instant>,
+}
+
+impl Overlay {
+    /// The energy is summed over all pairs of stars, which gets too slow beyond this.
+    const MAX_ENERGY_STARS: usize = 20_000;
+    const ENERGY_INTERVAL: Duration = Duration::from_secs(1);
+
+    pub fn new(device: &Device, format: TextureFormat, window: &Window) -> Self {
+        let max_texture_side = device.limits().max_texture_dimension_2d as usize;
+        Self {
+            visible: false,
+            context: Context::default(),
+            input: egui_winit::State::new(max_texture_side, window),
+            pass: RenderPass::new(device, format, 1),
+            frame: None,
+            fps: 0.0,
+            last_frame: Instant::now(),
+            energy: None,
+            measured: None,
+        }
+    }
+
+    /// Passes a window event to egui, returns whether it was meant for the overlay, e.g. a
+    /// click on a slider.
+    pub fn on_event(&mut self, event: &WindowEvent) -> bool {
+        self.visible && self.input.on_event(&self.context, event)
+    }
+
+    /// Lays out the overlay for the next `draw`, returns the changes made with it.
+    pub fn run(
+        &mut self,
+        window: &Window,
+        simulation: &dyn SimulationBackend,
+        step_time: Duration,
+        gpu_times: Option<Option<GpuTimes>>,
+        paused: &mut bool,
+    ) -> Vec<Command> {
+        let elapsed = self.last_frame.elapsed().as_secs_f32();
+        self.last_frame = Instant::now();
+        if elapsed > 0.0 {
+            self.fps += (1.0 / elapsed - self.fps) * 0.1;
+        }
+        if !self.visible {
+            return Vec::new();
+        }
+        self.measure_energy(simulation);
+
+        let mut commands = Vec::new();
+        let mut reset_energy = false;
+        let input = self.input.take_egui_input(window);
+        let output = self.context.run(input, |context| {
+            egui::Window::new("gravsim").show(context, |ui| {
+                ui.label(format!("{:.0} fps", self.fps));
+                ui.label(format!("step {:.1}ms", step_time.as_secs_f32() * 1000.0));
+                ui.label(match gpu_times {
+                    Some(Some(times)) => format!("gpu {}", times.summary()),
+                    Some(None) => "gpu time isn't measured yet".to_string(),
+                    None => "gpu time needs timestamp queries".to_string(),
+                });
+                let drift = match self.energy {
+                    Some((initial, energy)) => {
+                        format!("energy drift {:+.3e}", (energy - initial) / initial.abs())
+                    }
+                    None => format!(
+                        "energy drift isn't measured above {} stars",
+                        Self::MAX_ENERGY_STARS
+                    ),
+                };
+                ui.horizontal(|ui| {
+                    ui.label(drift);
+                    reset_energy = ui.small_button("reset").clicked();
+                });
+                ui.checkbox(paused, "paused");
+                ui.separator();
+
+                match simulation.as_simulation() {
+                    Some(simulation) => commands = parameter_sliders(ui, &simulation.config),
+                    None => {
+                        ui.label("parameters need a Barnes-Hut simulation");
+                    }
+                }
+            });
+        });
+        self.input
+            .handle_platform_output(window, &self.context, output.platform_output);
+        self.frame = Some((
+            self.context.tessellate(output.shapes),
+            output.textures_delta,
+        ));
+
+        // the energy changes along with these
+        let rescaled = commands.iter().any(|command| {
+            matches!(
+                command,
+                Command::Set(Parameter::Gravity | Parameter::Softening, _)
+            )
+        });
+        if reset_energy || rescaled {
+            self.energy = None;
+            self.measured = None;
+        }
+        commands
+    }
+
+    /// Draws the last frame laid out by `run` into `view`, of the size of the window.
+    pub fn draw(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        command_encoder: &mut CommandEncoder,
+        view: &TextureView,
+        size: PhysicalSize<u32>,
+    ) {
+        let Some((primitives, textures)) = self.frame.take() else {
+            return;
+        };
+        let screen = ScreenDescriptor {
+            physical_width: size.width,
+            physical_height: size.height,
+            scale_factor: self.input.pixels_per_point(),
+        };
+        let drawn = self
+            .pass
+            .add_textures(device, queue, &textures)
+            .and_then(|_| {
+                self.pass
+                    .update_buffers(device, queue, &primitives, &screen);
+                self.pass
+                    .execute(command_encoder, view, &primitives, &screen, None)
+            })
+            .and_then(|_| self.pass.remove_textures(textures));
+        if let Err(e) = drawn {
+            eprintln!("failed to draw the overlay: {:?}", e);
+        }
+    }
+
+    /// Measures the total energy about once every `ENERGY_INTERVAL`, the first measurement
+    /// is what the drift is relative to.
+    fn measure_energy(&mut self, simulation: &dyn SimulationBackend) {
+        if self
+            .measured
+            .is_some_and(|measured| measured.elapsed() < Self::ENERGY_INTERVAL)
+        {
+            return;
+        }
+        self.measured = Some(Instant::now());
+        if simulation.snapshot().len() > Self::MAX_ENERGY_STARS {
+            self.energy = None;
+            return;
+        }
+        let energy = simulation.diagnostics().total_energy();
+        let initial = self.energy.map_or(energy, |(initial, _)| initial);
+        self.energy = Some((initial, energy));
+    }
+}
+
+/// Sliders for the parameters of the simulation, returns the changes of those that were moved.
+fn parameter_sliders(ui: &mut Ui, config: &SimulationConfig) -> Vec<Command> {
+    let gravity = Simulation::GRAVITY;
+    let sliders = [
+        (Parameter::Theta, "theta", config.theta, 0.0..=1.5, false),
+        (
+            Parameter::Gravity,
+            "gravity",
+            config.gravity,
+            gravity * 0.01..=gravity * 100.0,
+            true,
+        ),
+        (
+            Parameter::Softening,
+            "softening",
+            config.softening,
+            0.0..=1000.0,
+            true,
+        ),
+        (
+            Parameter::TimeStep,
+            "time step",
+            config.dt,
+            0.01..=100.0,
+            true,
+        ),
+    ];
+    sliders
+        .into_iter()
+        .filter_map(|(parameter, name, mut value, range, logarithmic)| {
+            let slider = Slider::new(&mut value, range)
+                .logarithmic(logarithmic)
+                .text(name);
+            ui.add(slider)
+                .changed()
+                .then_some(Command::Set(parameter, value))
+        })
+        .collect()
+}
@@ -0,0 +1,71 @@
+use crate::config::Config;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+/// Directories the viewer writes to, so files end up in the usual places of the platform
+/// rather than wherever the viewer was started. All of them are in the data directory,
+/// `GRAVSIM_DATA_DIR` or e.g. `~/.local/share/gravsim` on Linux,
+/// `~/Library/Application Support/gravsim` on macOS and `%APPDATA%\gravsim` on Windows.
+/// Directories are only created once something is written to them.
+#[derive(Clone, Debug)]
+pub struct Paths {
+    /// the user config, see `Config::user_path`
+    pub config: Option<PathBuf>,
+    pub data: PathBuf,
+    /// autosaves for crash recovery, `GRAVSIM_SESSION_DIR` overrides it
+    pub session: PathBuf,
+    /// written and read by the snapshot hotkeys
+    pub snapshots: PathBuf,
+    pub screenshots: PathBuf,
+    /// default target of `Config::record`
+    pub recordings: PathBuf,
+}
+
+impl Default for Paths {
+    fn default() -> Self {
+        let data = std::env::var_os("GRAVSIM_DATA_DIR")
+            .map(PathBuf::from)
+            .or_else(|| dirs::data_dir().map(|dir| dir.join("gravsim")))
+            // without a home directory, e.g. in some containers
+            .unwrap_or_else(|| PathBuf::from("gravsim"));
+        Self {
+            config: Config::user_path(),
+            session: std::env::var_os("GRAVSIM_SESSION_DIR")
+                .map(PathBuf::from)
+                .unwrap_or_else(|| data.join("session")),
+            snapshots: data.join("snapshots"),
+            screenshots: data.join("screenshots"),
+            recordings: data.join("recordings"),
+            data,
+        }
+    }
+}
+
+impl Paths {
+    /// `name` in `dir`, creating `dir` if it doesn't exist yet.
+    pub fn file(dir: &Path, name: &str) -> Result<PathBuf, String> {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("failed to create {}: {}", dir.display(), e))?;
+        Ok(dir.join(name))
+    }
+
+    /// One line per directory, printed by `gravsim paths`.
+    pub fn show(&self) -> String {
+        let mut lines = String::new();
+        let config = self
+            .config
+            .as_ref()
+            .map_or("none".to_string(), |path| path.display().to_string());
+        for (name, path) in [
+            ("config", config),
+            ("data", self.data.display().to_string()),
+            ("session", self.session.display().to_string()),
+            ("snapshots", self.snapshots.display().to_string()),
+            ("screenshots", self.screenshots.display().to_string()),
+            ("recordings", self.recordings.display().to_string()),
+        ] {
+            let _ = writeln!(lines, "{:<12} {}", name, path);
+        }
+        lines
+    }
+}
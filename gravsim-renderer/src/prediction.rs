@@ -0,0 +1,118 @@
+use crate::camera::PushConstants;
+use crate::state::TargetFormat;
+use crate::trails::{line_pipeline, vertex_buffer, TrailVertex};
+use gravsim_simulation::backend::SimulationBackend;
+use gravsim_simulation::{Real, SimulationConfig, StarId};
+use nalgebra::Vector2;
+use std::time::{Duration, Instant};
+use wgpu::{Buffer, Device, Queue, RenderPass, RenderPipeline, ShaderStages};
+
+/// Whose orbit is predicted.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Prediction {
+    /// a star of the simulation, which is left out of the field it moves through
+    Star(StarId),
+    /// a star about to be launched from a position with a velocity
+    Launch(Vector2<Real>, Vector2<Real>),
+}
+
+/// Dashed curve along the predicted orbit of the selected star, or of a star being
+/// launched, through the current field of the other stars, see `Simulation::predict_orbit`.
+/// The prediction is redone right away when its target or the parameters change, and
+/// otherwise every `INTERVAL` while the field evolves.
+pub struct OrbitPreview {
+    pub pipeline: RenderPipeline,
+    pub vertex_buffer: Buffer,
+
+    /// number of vertices `vertex_buffer` can hold
+    capacity: usize,
+    /// number of vertices written by the last prediction
+    vertex_count: u32,
+    /// what the last prediction was of, with which parameters and when
+    last: Option<(Prediction, SimulationConfig, Instant)>,
+}
+
+impl OrbitPreview {
+    /// Updates predicted ahead, a dash per update.
+    pub const UPDATES: usize = 300;
+    const INTERVAL: Duration = Duration::from_millis(200);
+    const COLOR: [f32; 3] = [1.0, 0.85, 0.4];
+
+    pub fn new(device: &Device, target: TargetFormat) -> Self {
+        Self {
+            pipeline: line_pipeline(device, target),
+            vertex_buffer: vertex_buffer(device, 0),
+            capacity: 0,
+            vertex_count: 0,
+            last: None,
+        }
+    }
+
+    /// Predicts and uploads the orbit of `prediction` if it is due, or clears it for `None`.
+    /// Only Barnes-Hut simulations can predict orbits.
+    pub fn write(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        simulation: &dyn SimulationBackend,
+        prediction: Option<Prediction>,
+    ) {
+        let (Some(simulation), Some(prediction)) = (simulation.as_simulation(), prediction) else {
+            self.vertex_count = 0;
+            self.last = None;
+            return;
+        };
+        let due = match self.last {
+            Some((last, config, predicted)) => {
+                last != prediction
+                    || config != simulation.config
+                    || predicted.elapsed() >= Self::INTERVAL
+            }
+            None => true,
+        };
+        if !due {
+            return;
+        }
+        self.last = Some((prediction, simulation.config, Instant::now()));
+
+        let (pos, vel, excluded) = match prediction {
+            Prediction::Star(id) => match simulation.stars.get(id) {
+                Some(star) => (*star.pos(), star.vel, Some(id)),
+                None => {
+                    self.vertex_count = 0;
+                    return;
+                }
+            },
+            Prediction::Launch(pos, vel) => (pos, vel, None),
+        };
+        let orbit = simulation.predict_orbit(pos, vel, Self::UPDATES, excluded);
+
+        let [r, g, b] = Self::COLOR;
+        let vertex = |age: usize| {
+            let alpha = 0.9 * (1.0 - age as f32 / orbit.len() as f32);
+            TrailVertex::new(orbit[age].cast::<f32>().into(), [r, g, b, alpha])
+        };
+        // every other segment, for the dashes
+        let vertices: Vec<_> = (0..orbit.len() - 1)
+            .step_by(2)
+            .take_while(|&age| orbit[age + 1].iter().all(|x| x.is_finite()))
+            .flat_map(|age| [vertex(age), vertex(age + 1)])
+            .collect();
+        if self.capacity < vertices.len() {
+            self.vertex_buffer = vertex_buffer(device, vertices.len());
+            self.capacity = vertices.len();
+        }
+        queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&vertices));
+        self.vertex_count = vertices.len() as u32;
+    }
+
+    pub fn draw<'a>(&'a self, render_pass: &mut RenderPass<'a>, camera: &PushConstants) {
+        if self.vertex_count == 0 {
+            return;
+        }
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_push_constants(ShaderStages::VERTEX, 0, bytemuck::bytes_of(camera));
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.draw(0..self.vertex_count, 0..1);
+    }
+}
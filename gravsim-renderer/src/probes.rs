@@ -0,0 +1,83 @@
+use crate::camera::{Camera, PushConstants};
+use crate::state::TargetFormat;
+use crate::trails::{line_pipeline, vertex_buffer, TrailVertex};
+use gravsim_simulation::probe::Probe;
+use gravsim_simulation::Real;
+use nalgebra::Vector2;
+use wgpu::{Buffer, Device, Queue, RenderPass, RenderPipeline, ShaderStages};
+
+/// Trajectories of the probes of the simulation, fading towards their start, with a cross
+/// at the current position of every probe.
+pub struct ProbePaths {
+    pub pipeline: RenderPipeline,
+    pub vertex_buffer: Buffer,
+
+    /// number of vertices `vertex_buffer` can hold
+    capacity: usize,
+    /// number of vertices written by the last `write`
+    vertex_count: u32,
+}
+
+impl ProbePaths {
+    const COLOR: [f32; 3] = [0.3, 1.0, 0.6];
+
+    pub fn new(device: &Device, target: TargetFormat) -> Self {
+        Self {
+            pipeline: line_pipeline(device, target),
+            vertex_buffer: vertex_buffer(device, 0),
+            capacity: 0,
+            vertex_count: 0,
+        }
+    }
+
+    /// Uploads the trajectories, the crosses are sized relative to the view so they stay
+    /// visible at any zoom.
+    pub fn write(&mut self, device: &Device, queue: &Queue, probes: &[Probe], camera: &Camera) {
+        let vertex_count: usize = probes
+            .iter()
+            .map(|probe| probe.trajectory.len().saturating_sub(1) * 2 + 4)
+            .sum();
+        if self.capacity < vertex_count {
+            self.vertex_buffer = vertex_buffer(device, vertex_count);
+            self.capacity = vertex_count;
+        }
+
+        let [r, g, b] = Self::COLOR;
+        let mut vertices = Vec::with_capacity(vertex_count);
+        for probe in probes {
+            let length = probe.trajectory.len() as f32;
+            let vertex = |age: usize, pos: &Vector2<Real>| {
+                let alpha = 0.2 + 0.8 * (1.0 - age as f32 / length);
+                TrailVertex::new(pos.cast::<f32>().into(), [r, g, b, alpha])
+            };
+            // from the newest position backwards
+            let newest_first = probe.trajectory.iter().rev();
+            let segments = newest_first.clone().zip(newest_first.skip(1));
+            for (age, (newer, older)) in segments.enumerate() {
+                vertices.extend([vertex(age, newer), vertex(age + 1, older)]);
+            }
+
+            let pos = probe.pos.cast::<f32>();
+            let size = camera.view_extent() * 0.01;
+            for axis in [Vector2::new(size, size), Vector2::new(size, -size)] {
+                vertices.extend(
+                    [pos - axis, pos + axis]
+                        .map(|end| TrailVertex::new(end.into(), [r, g, b, 1.0])),
+                );
+            }
+        }
+
+        queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&vertices));
+        self.vertex_count = vertices.len() as u32;
+    }
+
+    pub fn draw<'a>(&'a self, render_pass: &mut RenderPass<'a>, camera: &PushConstants) {
+        if self.vertex_count == 0 {
+            return;
+        }
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_push_constants(ShaderStages::VERTEX, 0, bytemuck::bytes_of(camera));
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.draw(0..self.vertex_count, 0..1);
+    }
+}
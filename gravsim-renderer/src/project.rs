@@ -0,0 +1,99 @@
+use gravsim_simulation::backend::SimulationBackend;
+use gravsim_simulation::diagnostics::Diagnostics;
+use gravsim_simulation::three_d::{Simulation3, Star3};
+use gravsim_simulation::{Real, Star, StarId};
+use nalgebra::{Rotation3, Vector2, Vector3};
+
+/// Hosts a 3d simulation, rendering an orthographic projection of it.
+pub struct Projected {
+    pub simulation: Simulation3,
+    /// rotates simulation space into view space, whose xy plane is the screen
+    pub view: Rotation3<Real>,
+    stars: Vec<Star>,
+}
+
+impl Projected {
+    /// Tilt of the default view, so a disc in the xy plane is seen at an angle.
+    pub const TILT: Real = 1.0;
+
+    pub fn new(simulation: Simulation3) -> Self {
+        let mut projected = Self {
+            simulation,
+            view: Rotation3::from_axis_angle(&Vector3::x_axis(), Self::TILT),
+            stars: Vec::new(),
+        };
+        projected.project();
+        projected
+    }
+
+    fn project(&mut self) {
+        let view = self.view;
+        self.stars = self
+            .simulation
+            .stars
+            .iter()
+            .map(|star| {
+                Star::new(
+                    (view * star.pos()).xy(),
+                    (view * star.vel).xy(),
+                    star.mass(),
+                )
+            })
+            .collect();
+    }
+}
+
+impl SimulationBackend for Projected {
+    fn step(&mut self) {
+        self.simulation.update();
+        self.project();
+    }
+
+    fn snapshot(&self) -> &[Star] {
+        &self.stars
+    }
+
+    /// Adds a star in the view plane.
+    fn add_star(&mut self, star: Star) -> StarId {
+        self.insert_star(self.stars.len(), star);
+        self.stars.len() - 1
+    }
+
+    fn remove_star(&mut self, id: StarId) -> Option<Star> {
+        let star = (id < self.stars.len()).then(|| self.stars[id]);
+        if star.is_some() {
+            self.simulation.stars.remove(id);
+            self.project();
+        }
+        star
+    }
+
+    /// Inserts a star in the view plane.
+    fn insert_star(&mut self, id: StarId, star: Star) {
+        let inverse = self.view.inverse();
+        self.simulation.stars.insert(
+            id,
+            Star3::new(
+                inverse * star.pos().push(0.0),
+                inverse * star.vel.push(0.0),
+                star.mass(),
+            ),
+        );
+        self.project();
+    }
+
+    /// Sets the velocity in the view plane, keeping the velocity along the view direction.
+    fn set_velocity(&mut self, id: StarId, vel: Vector2<Real>) -> bool {
+        let Some(star) = self.simulation.stars.get_mut(id) else {
+            return false;
+        };
+        let depth = (self.view * star.vel).z;
+        star.vel = self.view.inverse() * vel.push(depth);
+        self.project();
+        true
+    }
+
+    fn diagnostics(&self) -> Diagnostics {
+        self.simulation.diagnostics()
+    }
+}
@@ -0,0 +1,131 @@
+use crate::camera::{Camera, PushConstants};
+use crate::state::TargetFormat;
+use crate::trails::{line_pipeline, vertex_buffer, TrailVertex};
+use gravsim_simulation::Star;
+use nalgebra::Vector2;
+use std::collections::HashMap;
+use wgpu::{Buffer, Device, Queue, RenderPass, RenderPipeline, ShaderStages};
+
+/// What the arrows of a `Quiver` stand for.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum QuiverMode {
+    /// the velocity of every star, or of an even subset of them
+    Stars,
+    /// the mass weighted mean velocity of the stars in each cell of a grid over the view
+    Grid,
+}
+
+/// Debug view of the velocity field, to spot stars set up on the wrong orbits. Every arrow
+/// starts faint at its star or cell and ends bright in the direction of motion, its length
+/// relative to the mean speed of all arrows.
+pub struct Quiver {
+    pub mode: QuiverMode,
+    pub pipeline: RenderPipeline,
+    pub vertex_buffer: Buffer,
+
+    /// number of vertices `vertex_buffer` can hold
+    capacity: usize,
+    /// number of vertices written by the last `write`
+    vertex_count: u32,
+}
+
+impl Quiver {
+    const COLOR: [f32; 3] = [0.4, 0.9, 1.0];
+    /// Upper bound of arrows in `QuiverMode::Stars`, spread evenly over all stars.
+    pub const MAX_ARROWS: usize = 4096;
+    /// Rows of cells in `QuiverMode::Grid`.
+    pub const GRID_ROWS: usize = 32;
+    /// Length of an arrow at the mean speed in `QuiverMode::Stars`, relative to the view.
+    const STAR_LENGTH: f32 = 0.04;
+
+    pub fn new(device: &Device, target: TargetFormat, mode: QuiverMode) -> Self {
+        Self {
+            mode,
+            pipeline: line_pipeline(device, target),
+            vertex_buffer: vertex_buffer(device, 0),
+            capacity: 0,
+            vertex_count: 0,
+        }
+    }
+
+    /// Uploads an arrow per star or cell, the grid follows the view.
+    pub fn write(&mut self, device: &Device, queue: &Queue, stars: &[Star], camera: &Camera) {
+        let (arrows, length) = match self.mode {
+            QuiverMode::Stars => (star_arrows(stars), camera.view_extent() * Self::STAR_LENGTH),
+            QuiverMode::Grid => {
+                let cell = 2.0 * camera.view_extent() / Self::GRID_ROWS as f32;
+                (grid_arrows(stars, cell), cell)
+            }
+        };
+        let mean_speed =
+            arrows.iter().map(|(_, vel)| vel.norm()).sum::<f32>() / arrows.len().max(1) as f32;
+        let scale = match mean_speed > 0.0 {
+            true => length / mean_speed,
+            false => 0.0,
+        };
+
+        let vertex_count = arrows.len() * 2;
+        if self.capacity < vertex_count {
+            self.vertex_buffer = vertex_buffer(device, vertex_count);
+            self.capacity = vertex_count;
+        }
+        let [r, g, b] = Self::COLOR;
+        let vertices: Vec<_> = arrows
+            .iter()
+            .flat_map(|(pos, vel)| {
+                [
+                    TrailVertex::new((*pos).into(), [r, g, b, 0.1]),
+                    TrailVertex::new((pos + vel * scale).into(), [r, g, b, 0.9]),
+                ]
+            })
+            .collect();
+
+        queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&vertices));
+        self.vertex_count = vertices.len() as u32;
+    }
+
+    pub fn draw<'a>(&'a self, render_pass: &mut RenderPass<'a>, camera: &PushConstants) {
+        if self.vertex_count == 0 {
+            return;
+        }
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_push_constants(ShaderStages::VERTEX, 0, bytemuck::bytes_of(camera));
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.draw(0..self.vertex_count, 0..1);
+    }
+}
+
+/// Position and velocity of up to `Quiver::MAX_ARROWS` stars, skipping stars that left the
+/// simulation.
+fn star_arrows(stars: &[Star]) -> Vec<(Vector2<f32>, Vector2<f32>)> {
+    let stride = stars.len().div_ceil(Quiver::MAX_ARROWS).max(1);
+    stars
+        .iter()
+        .step_by(stride)
+        .map(|star| (star.pos().cast::<f32>(), star.vel.cast::<f32>()))
+        .filter(|(pos, vel)| pos.iter().chain(vel.iter()).all(|x| x.is_finite()))
+        .collect()
+}
+
+/// Center and mass weighted mean velocity of every cell of size `cell` containing stars.
+fn grid_arrows(stars: &[Star], cell: f32) -> Vec<(Vector2<f32>, Vector2<f32>)> {
+    let mut cells: HashMap<(i64, i64), (Vector2<f32>, f32)> = HashMap::new();
+    for star in stars {
+        let (pos, vel) = (star.pos().cast::<f32>(), star.vel.cast::<f32>());
+        let mass = star.mass() as f32;
+        if !pos.iter().chain(vel.iter()).all(|x| x.is_finite()) || mass <= 0.0 {
+            continue;
+        }
+        let key = ((pos.x / cell).floor() as i64, (pos.y / cell).floor() as i64);
+        let (momentum, total) = cells.entry(key).or_insert((Vector2::zeros(), 0.0));
+        *momentum += vel * mass;
+        *total += mass;
+    }
+    cells
+        .into_iter()
+        .map(|((x, y), (momentum, mass))| {
+            let center = Vector2::new(x as f32 + 0.5, y as f32 + 0.5) * cell;
+            (center, momentum / mass)
+        })
+        .collect()
+}
@@ -0,0 +1,113 @@
+use crate::capture::{self, Offscreen};
+use crate::paths::Paths;
+use crate::state::State;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Child, ChildStdin, Command, Stdio};
+
+/// Settings of a recording, the `[record]` table of the config.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Recording {
+    /// a directory the frames are written to as numbered pngs, or a video file with an
+    /// extension ffmpeg knows (e.g. `galaxy.mp4`), which the raw frames are piped to.
+    /// By default `frames` in the recordings directory, see `Paths`.
+    pub path: PathBuf,
+    /// simulation steps per frame, replaces the substeps of the quality preset
+    pub every: u32,
+    pub width: u32,
+    pub height: u32,
+    /// frame rate of the video, unused for pngs
+    pub fps: u32,
+}
+
+impl Default for Recording {
+    fn default() -> Self {
+        Self {
+            path: Paths::default().recordings.join("frames"),
+            every: 4,
+            width: 1920,
+            height: 1080,
+            fps: 60,
+        }
+    }
+}
+
+/// Captures a frame after every update of the simulation, so a video covers the same
+/// simulated time no matter how fast frames are rendered.
+pub struct Recorder {
+    target: Offscreen,
+    sink: Sink,
+    frames: u64,
+}
+
+enum Sink {
+    Pngs(PathBuf),
+    Ffmpeg(Child, ChildStdin),
+}
+
+impl Recorder {
+    pub fn start(state: &State, recording: &Recording) -> Result<Self, String> {
+        let sink = if recording.path.extension().is_some() {
+            let mut ffmpeg = Command::new("ffmpeg")
+                .args(["-y", "-loglevel", "error"])
+                .args(["-f", "rawvideo", "-pix_fmt", "rgba"])
+                .arg("-s")
+                .arg(format!("{}x{}", recording.width, recording.height))
+                .arg("-r")
+                .arg(recording.fps.to_string())
+                .args(["-i", "-", "-pix_fmt", "yuv420p"])
+                .arg(&recording.path)
+                .stdin(Stdio::piped())
+                .spawn()
+                .map_err(|e| format!("failed to start ffmpeg: {}", e))?;
+            let stdin = ffmpeg.stdin.take().expect("stdin of ffmpeg is piped");
+            Sink::Ffmpeg(ffmpeg, stdin)
+        } else {
+            std::fs::create_dir_all(&recording.path)
+                .map_err(|e| format!("failed to create {}: {}", recording.path.display(), e))?;
+            Sink::Pngs(recording.path.clone())
+        };
+
+        Ok(Self {
+            target: Offscreen::new(state, recording.width, recording.height),
+            sink,
+            frames: 0,
+        })
+    }
+
+    /// Renders the current frame and writes it out.
+    pub fn capture(&mut self, state: &State) -> Result<(), String> {
+        let pixels = self.target.render(state)?;
+        let size = self.target.size();
+        match &mut self.sink {
+            Sink::Pngs(dir) => {
+                let path = dir.join(format!("frame_{:06}.png", self.frames));
+                capture::write_png(&path.to_string_lossy(), &pixels, size.width, size.height)?;
+            }
+            Sink::Ffmpeg(_, stdin) => stdin
+                .write_all(&pixels)
+                .map_err(|e| format!("failed to pipe a frame to ffmpeg: {}", e))?,
+        }
+        self.frames += 1;
+        Ok(())
+    }
+
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    /// Waits for ffmpeg to encode the remaining frames.
+    pub fn finish(self) {
+        if let Sink::Ffmpeg(mut ffmpeg, stdin) = self.sink {
+            drop(stdin);
+            match ffmpeg.wait() {
+                Ok(status) if status.success() => {}
+                Ok(status) => eprintln!("ffmpeg failed: {}", status),
+                Err(e) => eprintln!("failed to wait for ffmpeg: {}", e),
+            }
+        }
+        println!("recorded {} frames", self.frames);
+    }
+}
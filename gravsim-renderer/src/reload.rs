@@ -0,0 +1,39 @@
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Polls a shader source file for changes, so shaders can be edited while the viewer runs.
+/// The shaders are compiled into the binary, this only helps when running from a checkout.
+pub struct ShaderWatcher {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+impl ShaderWatcher {
+    /// The star shader in the source tree the viewer was built from.
+    pub const STARS: &'static str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/shaders/stars.wgsl");
+
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let modified = modified(&path);
+        Self { path, modified }
+    }
+
+    /// The source of the shader if the file changed since the last call, or since `new`.
+    pub fn poll(&mut self) -> Option<Result<String, String>> {
+        let modified = modified(&self.path);
+        if modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+        Some(
+            std::fs::read_to_string(&self.path)
+                .map_err(|e| format!("failed to read {}: {}", self.path.display(), e)),
+        )
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
@@ -0,0 +1,169 @@
+use crate::console::{Command, Parameter};
+use gravsim_simulation::Real;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+/// JSON-RPC 2.0 over HTTP, so a presentation tool or a notebook can drive the viewer, e.g.
+///
+/// ```text
+/// curl -d '{"jsonrpc": "2.0", "id": 1, "method": "set", "params": {"parameter": "theta", "value": 0.7}}' localhost:7878
+/// ```
+///
+/// The methods are `pause`, `resume`, `screenshot`, `set` with a `parameter` and `value`,
+/// `load_scenario` with a `path`, which opens it in a new tab, and `command` with a `line`
+/// for anything else the console can do. Calls are accepted on a background thread and
+/// executed between frames, the result is what the console would print.
+pub struct Remote {
+    requests: Receiver<Request>,
+}
+
+/// A call waiting to be executed between frames.
+pub struct Request {
+    pub command: Command,
+    result: Sender<Result<String, String>>,
+}
+
+impl Request {
+    /// Answers the caller.
+    pub fn reply(self, result: Result<String, String>) {
+        // the caller may have hung up
+        let _ = self.result.send(result);
+    }
+}
+
+impl Remote {
+    /// Longest request body that is read, calls are tiny.
+    const MAX_BODY: usize = 64 * 1024;
+
+    /// Listens on `address`, one connection at a time.
+    pub fn start(address: SocketAddr) -> Result<Self, String> {
+        let listener = TcpListener::bind(address)
+            .map_err(|e| format!("failed to listen on {}: {}", address, e))?;
+        println!("remote control listening on {}", address);
+
+        let (sender, requests) = channel();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                if let Err(e) = serve(stream, &sender) {
+                    eprintln!("remote control: {}", e);
+                }
+            }
+        });
+        Ok(Self { requests })
+    }
+
+    /// Calls that arrived since the last frame.
+    pub fn pending(&self) -> impl Iterator<Item = Request> + '_ {
+        self.requests.try_iter()
+    }
+}
+
+/// Answers a single http request, only its body matters.
+fn serve(mut stream: TcpStream, requests: &Sender<Request>) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut length = 0;
+    let mut line = String::new();
+    // the request line and the headers, up to an empty line
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse().unwrap_or(0);
+            }
+        }
+    }
+    let mut body = vec![0; length.min(Remote::MAX_BODY)];
+    reader.read_exact(&mut body)?;
+
+    let response = respond(&body, requests).to_string();
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response.len(),
+        response
+    )
+}
+
+#[derive(Deserialize)]
+struct Call {
+    /// missing for notifications, which are answered anyway
+    #[serde(default)]
+    id: Value,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Deserialize)]
+struct Set {
+    parameter: Parameter,
+    value: Real,
+}
+
+#[derive(Deserialize)]
+struct LoadScenario {
+    path: PathBuf,
+}
+
+#[derive(Deserialize)]
+struct Line {
+    line: String,
+}
+
+/// Runs a call through the frame loop and builds the response.
+fn respond(body: &[u8], requests: &Sender<Request>) -> Value {
+    let call: Call = match serde_json::from_slice(body) {
+        Ok(call) => call,
+        Err(e) => return error(Value::Null, -32700, format!("invalid request: {}", e)),
+    };
+    let command = match call.method.as_str() {
+        "pause" => Ok(Command::Pause(true)),
+        "resume" => Ok(Command::Pause(false)),
+        "screenshot" => Ok(Command::Screenshot),
+        "set" => params(call.params).map(|set: Set| Command::Set(set.parameter, set.value)),
+        "load_scenario" => {
+            params(call.params).map(|load: LoadScenario| Command::OpenTab(load.path))
+        }
+        "command" => params(call.params).and_then(|line: Line| line.line.parse()),
+        method => return error(call.id, -32601, format!("unknown method: {}", method)),
+    };
+    let command = match command {
+        Ok(command) => command,
+        Err(e) => return error(call.id, -32602, e),
+    };
+
+    let (result, receiver) = channel();
+    let output = requests
+        .send(Request { command, result })
+        .ok()
+        .and_then(|_| receiver.recv().ok());
+    match output {
+        Some(Ok(output)) => json!({ "jsonrpc": "2.0", "id": call.id, "result": output }),
+        Some(Err(e)) => error(call.id, -32000, e),
+        None => error(call.id, -32000, "the viewer closed".to_string()),
+    }
+}
+
+fn params<T: DeserializeOwned>(params: Value) -> Result<T, String> {
+    serde_json::from_value(params).map_err(|e| format!("invalid params: {}", e))
+}
+
+fn error(id: Value, code: i32, message: String) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message },
+    })
+}
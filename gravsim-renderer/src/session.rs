@@ -0,0 +1,118 @@
+use crate::paths::Paths;
+use gravsim_simulation::snapshot::Snapshot;
+use gravsim_simulation::{Simulation, Star};
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Autosaves of an interactive session, so it can be restored after a crash.
+///
+/// The session directory holds the last saved simulation with the colors of its stars
+/// (`state.snapshot`, see `Snapshot`) and a log of all console commands.
+/// A marker file exists while the session is running, and is removed on a clean exit.
+/// Only Barnes-Hut simulations are saved.
+pub struct Session {
+    dir: PathBuf,
+    log: File,
+    last_save: Instant,
+}
+
+impl Session {
+    pub const INTERVAL: Duration = Duration::from_secs(30);
+
+    const MARKER: &'static str = "running";
+    const SNAPSHOT: &'static str = "state.snapshot";
+    const LOG: &'static str = "commands.log";
+
+    /// `GRAVSIM_SESSION_DIR`, or a directory in the data directory, see `Paths`.
+    pub fn dir() -> PathBuf {
+        Paths::default().session
+    }
+
+    /// Whether the last session in `dir` didn't exit cleanly and has something to restore.
+    pub fn crashed(dir: &Path) -> bool {
+        dir.join(Self::MARKER).exists() && dir.join(Self::SNAPSHOT).exists()
+    }
+
+    /// Starts a new session in `dir`, replacing the previous one.
+    pub fn start(dir: PathBuf) -> io::Result<Self> {
+        std::fs::create_dir_all(&dir)?;
+        let _ = std::fs::remove_file(dir.join(Self::SNAPSHOT));
+        std::fs::write(dir.join(Self::MARKER), "")?;
+        let log = File::create(dir.join(Self::LOG))?;
+        Ok(Self {
+            dir,
+            log,
+            last_save: Instant::now(),
+        })
+    }
+
+    /// Restores the simulation saved in `dir`, with the colors of its stars.
+    pub fn restore(dir: &Path) -> io::Result<(Simulation, Vec<[f32; 3]>)> {
+        let snapshot = Snapshot::load(dir.join(Self::SNAPSHOT))?;
+        let simulation = snapshot.to_simulation();
+        let colors = match snapshot.colors.len() == snapshot.stars.len() {
+            true => snapshot.colors,
+            false => vec![[1.0; 3]; snapshot.stars.len()],
+        };
+        Ok((simulation, colors))
+    }
+
+    /// Appends a console command to the interaction log.
+    pub fn log(&mut self, line: &str) {
+        if let Err(e) = writeln!(self.log, "{}", line) {
+            eprintln!("failed to write the session log: {}", e);
+        }
+    }
+
+    /// Saves `simulation` and the `colors` of its stars if the last save is at least
+    /// `INTERVAL` ago.
+    pub fn autosave(&mut self, simulation: &Simulation, colors: &[[f32; 3]]) {
+        if self.last_save.elapsed() < Self::INTERVAL {
+            return;
+        }
+        self.last_save = Instant::now();
+        if let Err(e) = self.save(simulation, colors) {
+            eprintln!("autosave failed: {}", e);
+        }
+    }
+
+    /// Writes to a temporary file first, so a crash while saving keeps the last save intact.
+    pub fn save(&self, simulation: &Simulation, colors: &[[f32; 3]]) -> io::Result<()> {
+        let mut snapshot = Snapshot::of(simulation);
+        snapshot.colors = colors.to_vec();
+
+        let path = self.dir.join(Self::SNAPSHOT);
+        let temporary = path.with_extension("tmp");
+        snapshot.save(&temporary)?;
+        File::open(&temporary)?.sync_all()?;
+        std::fs::rename(temporary, path)
+    }
+
+    /// Ends the session cleanly, so it isn't offered for restoring.
+    pub fn finish(self) {
+        let _ = std::fs::remove_file(self.dir.join(Self::MARKER));
+    }
+}
+
+/// One line per star, with its id, position, velocity and mass.
+pub fn csv(stars: &[Star]) -> String {
+    let mut csv = "id,x,y,vx,vy,mass\n".to_string();
+    for (id, star) in stars.iter().enumerate() {
+        let (pos, vel) = (star.pos(), star.vel);
+        writeln!(
+            csv,
+            "{},{},{},{},{},{}",
+            id,
+            pos.x,
+            pos.y,
+            vel.x,
+            vel.y,
+            star.mass()
+        )
+        .unwrap();
+    }
+    csv
+}
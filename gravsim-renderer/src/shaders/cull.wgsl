@@ -0,0 +1,67 @@
+// Writes the indices of all stars that are visible with the current camera into `visible`
+// and their count into the instance count of the indirect draw arguments. Counts the
+// culled stars and the visible ones less than a pixel across in `counters`.
+
+struct Uniforms {
+    // world to clip space, see `Camera::view`
+    view: mat3x3<f32>,
+    star_count: u32,
+    viewport_height: f32,
+};
+
+struct StarAttributes {
+    color: vec3<f32>,
+    radius: f32,
+};
+
+// arguments of `draw_indexed_indirect`
+struct DrawArgs {
+    index_count: u32,
+    instance_count: atomic<u32>,
+    first_index: u32,
+    base_vertex: i32,
+    first_instance: u32,
+};
+
+struct Counters {
+    culled: atomic<u32>,
+    sub_pixel: atomic<u32>,
+};
+
+var<push_constant> uniforms: Uniforms;
+
+// `Star` is { vec2 position, float mass, vec2 velocity }, tightly packed
+@group(0) @binding(0) var<storage, read> stars: array<f32>;
+@group(0) @binding(1) var<storage, read> attributes: array<StarAttributes>;
+@group(0) @binding(2) var<storage, read_write> visible: array<u32>;
+@group(0) @binding(3) var<storage, read_write> draw_args: DrawArgs;
+@group(0) @binding(4) var<storage, read_write> counters: Counters;
+
+let STAR_FLOATS: u32 = 5u;
+
+@compute @workgroup_size(64)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let index = id.x;
+    if (index >= uniforms.star_count) {
+        return;
+    }
+
+    let star = index * STAR_FLOATS;
+    let star_pos = vec2<f32>(stars[star], stars[star + 1u]);
+    // the view only translates and scales, so the scale is on its diagonal
+    let scale = abs(vec2<f32>(uniforms.view[0].x, uniforms.view[1].y));
+    let position = (uniforms.view * vec3<f32>(star_pos, 1.0)).xy;
+    let radius = attributes[index].radius;
+    let extent = vec2<f32>(1.0) + radius * scale;
+
+    // stars that left the simulation have a NaN position, which fails this test as well
+    if (abs(position.x) <= extent.x && abs(position.y) <= extent.y) {
+        visible[atomicAdd(&draw_args.instance_count, 1u)] = index;
+        // clip space is 2 high
+        if (radius * scale.y * uniforms.viewport_height < 1.0) {
+            atomicAdd(&counters.sub_pixel, 1u);
+        }
+    } else {
+        atomicAdd(&counters.culled, 1u);
+    }
+}
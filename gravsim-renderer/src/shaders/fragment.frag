@@ -1,9 +0,0 @@
-#version 450
-
-layout (location = 0) out vec4 fragColor;
-
-layout (location = 0) in vec3 color;
-
-void main() {
-    fragColor = vec4(color, 1.0);
-}
@@ -0,0 +1,45 @@
+// Stars as single quads with the circle cut out per fragment, see `impostor_pipeline`.
+
+struct Uniforms {
+    // world to clip space, see `Camera::view`
+    view: mat3x3<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) color: vec3<f32>,
+    // position in the quad, the circle has radius 1
+    @location(1) local: vec2<f32>,
+};
+
+var<push_constant> uniforms: Uniforms;
+
+// a triangle strip of four vertices per instance
+@vertex
+fn vs_main(
+    @builtin(vertex_index) index: u32,
+    @location(1) star_pos: vec2<f32>,
+    @location(2) color: vec3<f32>,
+    @location(3) radius: f32,
+) -> VertexOutput {
+    let local = vec2<f32>(f32(index & 1u), f32(index >> 1u)) * 2.0 - 1.0;
+    // like the circles of the other paths, whose vertices are at half of `radius`
+    let position = star_pos + local * 0.5 * radius;
+
+    var out: VertexOutput;
+    out.position = vec4<f32>((uniforms.view * vec3<f32>(position, 1.0)).xy, 0.0, 1.0);
+    out.color = color;
+    out.local = local;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // antialiased edge one pixel wide, stars smaller than a pixel end up partially covered
+    let distance = length(in.local);
+    let coverage = clamp((1.0 - distance) / max(fwidth(distance), 1e-4) + 0.5, 0.0, 1.0);
+    if (coverage <= 0.0) {
+        discard;
+    }
+    return vec4<f32>(in.color * coverage, coverage);
+}
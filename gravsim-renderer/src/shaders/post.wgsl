@@ -0,0 +1,64 @@
+// Turns the hdr image of the stars into the final one, see `PostProcess`.
+
+struct Constants {
+    // texel step of the blur
+    direction: vec2<f32>,
+    // brightness above which pixels bloom, or the strength of the bloom when compositing
+    amount: f32,
+    exposure: f32,
+};
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+var<push_constant> constants: Constants;
+
+@group(0) @binding(0) var source: texture_2d<f32>;
+@group(0) @binding(1) var bloom: texture_2d<f32>;
+@group(0) @binding(2) var source_sampler: sampler;
+
+// a single triangle covering the screen
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+
+    var out: VertexOutput;
+    out.position = vec4<f32>(uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+    out.uv = uv;
+    return out;
+}
+
+@fragment
+fn fs_bright(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(source, source_sampler, in.uv).rgb;
+    return vec4<f32>(max(color - vec3<f32>(constants.amount), vec3<f32>(0.0)), 1.0);
+}
+
+// 9 tap gaussian along `direction`
+@fragment
+fn fs_blur(in: VertexOutput) -> @location(0) vec4<f32> {
+    var weights = array<f32, 5>(0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216);
+
+    var color = textureSample(source, source_sampler, in.uv).rgb * weights[0];
+    for (var i = 1; i < 5; i = i + 1) {
+        let offset = constants.direction * f32(i);
+        color = color + textureSample(source, source_sampler, in.uv + offset).rgb * weights[i];
+        color = color + textureSample(source, source_sampler, in.uv - offset).rgb * weights[i];
+    }
+    return vec4<f32>(color, 1.0);
+}
+
+// fitted ACES filmic curve by Krzysztof Narkowicz
+fn tonemap(x: vec3<f32>) -> vec3<f32> {
+    let mapped = (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14);
+    return clamp(mapped, vec3<f32>(0.0), vec3<f32>(1.0));
+}
+
+@fragment
+fn fs_composite(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(source, source_sampler, in.uv).rgb
+        + textureSample(bloom, source_sampler, in.uv).rgb * constants.amount;
+    return vec4<f32>(tonemap(color * constants.exposure), 1.0);
+}
@@ -0,0 +1,69 @@
+// Stars as instanced circle meshes, see `create_star_pipeline`. The vertex entry points
+// differ in where they read the per star data from: instance buffers (`vs_instanced`),
+// storage buffers (`vs_storage`), or storage buffers indexed by the stars that survived
+// culling (`vs_culled`).
+
+struct Uniforms {
+    // world to clip space, see `Camera::view`
+    view: mat3x3<f32>,
+};
+
+struct StarAttributes {
+    color: vec3<f32>,
+    radius: f32,
+};
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) color: vec3<f32>,
+};
+
+var<push_constant> uniforms: Uniforms;
+
+// `Star` is { vec2 position, float mass, vec2 velocity }, tightly packed
+@group(0) @binding(0) var<storage, read> stars: array<f32>;
+@group(0) @binding(1) var<storage, read> attributes: array<StarAttributes>;
+// indices of the stars that survived culling
+@group(0) @binding(2) var<storage, read> visible: array<u32>;
+
+let STAR_FLOATS: u32 = 5u;
+
+fn project(vertex_pos: vec2<f32>, star_pos: vec2<f32>, attribs: StarAttributes) -> VertexOutput {
+    let position = vertex_pos * attribs.radius + star_pos;
+
+    var out: VertexOutput;
+    out.position = vec4<f32>((uniforms.view * vec3<f32>(position, 1.0)).xy, 0.0, 1.0);
+    out.color = attribs.color;
+    return out;
+}
+
+fn stored(index: u32, vertex_pos: vec2<f32>) -> VertexOutput {
+    let star = index * STAR_FLOATS;
+    let star_pos = vec2<f32>(stars[star], stars[star + 1u]);
+    return project(vertex_pos, star_pos, attributes[index]);
+}
+
+@vertex
+fn vs_instanced(
+    @location(0) vertex_pos: vec2<f32>,
+    @location(1) star_pos: vec2<f32>,
+    @location(2) color: vec3<f32>,
+    @location(3) radius: f32,
+) -> VertexOutput {
+    return project(vertex_pos, star_pos, StarAttributes(color, radius));
+}
+
+@vertex
+fn vs_storage(@builtin(instance_index) instance: u32, @location(0) vertex_pos: vec2<f32>) -> VertexOutput {
+    return stored(instance, vertex_pos);
+}
+
+@vertex
+fn vs_culled(@builtin(instance_index) instance: u32, @location(0) vertex_pos: vec2<f32>) -> VertexOutput {
+    return stored(visible[instance], vertex_pos);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(in.color, 1.0);
+}
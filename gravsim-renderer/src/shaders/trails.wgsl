@@ -0,0 +1,26 @@
+// Fading line segments behind stars, see `Trails`.
+
+struct Uniforms {
+    // world to clip space, see `Camera::view`
+    view: mat3x3<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+};
+
+var<push_constant> uniforms: Uniforms;
+
+@vertex
+fn vs_main(@location(0) position: vec2<f32>, @location(1) color: vec4<f32>) -> VertexOutput {
+    var out: VertexOutput;
+    out.position = vec4<f32>((uniforms.view * vec3<f32>(position, 1.0)).xy, 0.0, 1.0);
+    out.color = color;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return in.color;
+}
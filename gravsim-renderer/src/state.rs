@@ -1,21 +1,67 @@
+use crate::adapter::{select_adapter, REQUIRED_FEATURES};
+use crate::camera::{Camera, Follow, PushConstants};
+use crate::capture::{self, Panel};
+use crate::compare::Comparison;
+use crate::config::{Action, Config, Keybindings};
+use crate::console::{Command, Console, Location, Parameter};
+use crate::cull::{CullConstants, Culling};
+use crate::hdr::{HdrTargets, PostProcess, ADDITIVE, HDR_FORMAT};
+use crate::history::{Edit, History};
+use crate::impostor::{self, impostor_pipeline};
+use crate::markers::Markers;
+use crate::overlay::Overlay;
+use crate::paths::Paths;
+use crate::prediction::{OrbitPreview, Prediction};
+use crate::probes::ProbePaths;
+use crate::quiver::{Quiver, QuiverMode};
+use crate::record::{Recorder, Recording};
+use crate::reload::ShaderWatcher;
+use crate::remote::Remote;
+use crate::session::{self, Session};
+use crate::tabs::Tabs;
+use crate::timestamps::{GpuTimer, Mark};
+use crate::trails::Trails;
+use crate::upload::StagingRing;
 use bytemuck::{Pod, Zeroable};
-use gravsim_simulation::Simulation;
-use rayon::prelude::*;
+use gravsim_simulation::backend::SimulationBackend;
+use gravsim_simulation::fits::{self, Units};
+use gravsim_simulation::kepler::Orbit;
+use gravsim_simulation::map::{Grid, Map, Quantity};
+use gravsim_simulation::output::{Cadence, OutputPolicy};
+use gravsim_simulation::probe::Probe;
+use gravsim_simulation::replay;
+use gravsim_simulation::scenario::Scenario;
+use gravsim_simulation::schedule;
+use gravsim_simulation::script::Script;
+use gravsim_simulation::snapshot::Snapshot;
+use gravsim_simulation::tree::TraversalStats;
+use gravsim_simulation::{DilationZone, Galaxy, Real, Simulation, Star, StarId};
+use nalgebra::Vector2;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 use std::cmp::Ordering;
+use std::fs::File;
+use std::io::BufWriter;
 use std::mem::size_of;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use wgpu::util::{BufferInitDescriptor, DeviceExt};
 use wgpu::{
-    include_spirv, vertex_attr_array, Backends, BlendState, Buffer, BufferUsages, Color,
-    ColorTargetState, ColorWrites, CommandEncoderDescriptor, Device, DeviceDescriptor, Face,
-    Features, FragmentState, IndexFormat, Instance, Limits, LoadOp, Operations,
-    PipelineLayoutDescriptor, PowerPreference, PresentMode, PrimitiveState, PushConstantRange,
-    Queue, RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline,
-    RenderPipelineDescriptor, RequestAdapterOptions, ShaderStages, Surface, SurfaceConfiguration,
-    SurfaceError, TextureUsages, TextureViewDescriptor, VertexAttribute, VertexBufferLayout,
-    VertexState, VertexStepMode,
+    include_wgsl, vertex_attr_array, Backends, BindGroup, BindGroupDescriptor, BindGroupEntry,
+    BindGroupLayout, BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingType, Buffer,
+    BufferBindingType, BufferUsages, Color, ColorTargetState, ColorWrites, CommandEncoder,
+    CommandEncoderDescriptor, Device, DeviceDescriptor, ErrorFilter, Face, Features, FragmentState,
+    IndexFormat, Instance, Limits, LoadOp, MultisampleState, Operations, PipelineLayout,
+    PipelineLayoutDescriptor, PresentMode, PrimitiveState, PushConstantRange, Queue,
+    RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor,
+    ShaderModule, ShaderModuleDescriptor, ShaderSource, ShaderStages, Surface,
+    SurfaceConfiguration, SurfaceError, TextureFormat, TextureUsages, TextureView,
+    TextureViewDescriptor, VertexAttribute, VertexBufferLayout, VertexState, VertexStepMode,
+};
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+use winit::event::{
+    ElementState, KeyboardInput, MouseButton, MouseScrollDelta, VirtualKeyCode, WindowEvent,
 };
-use winit::dpi::PhysicalSize;
-use winit::event::{ElementState, KeyboardInput, MouseScrollDelta, VirtualKeyCode, WindowEvent};
 use winit::window::Window;
 
 #[repr(C)]
@@ -28,29 +74,73 @@ impl Vertex {
     pub const ATTRIBS: &'static [VertexAttribute] = &vertex_attr_array![0 => Float32x2];
 }
 
+/// Per instance attributes read straight from `Simulation::stars`.
+/// `Star` is `repr(C)` and starts with the position of its mass point.
+pub const STAR_ATTRIBS: &[VertexAttribute] = &vertex_attr_array![1 => Float32x2];
+
+/// A star as the shaders see it: position, mass and velocity in single precision.
+pub type GpuStar = [f32; 5];
+
+/// The bytes of `stars` as `GpuStar`s. In single precision that's the layout of `Star`,
+/// so they are uploaded as is.
+#[cfg(not(feature = "f64"))]
+pub fn star_bytes(stars: &[Star]) -> Cow<'_, [u8]> {
+    Cow::Borrowed(bytemuck::cast_slice(stars))
+}
+
+#[cfg(feature = "f64")]
+pub fn star_bytes(stars: &[Star]) -> Cow<'_, [u8]> {
+    let stars: Vec<GpuStar> = stars
+        .iter()
+        .map(|star| {
+            let (pos, vel) = (star.pos(), star.vel);
+            [pos.x, pos.y, star.mass(), vel.x, vel.y].map(|x| x as f32)
+        })
+        .collect();
+    Cow::Owned(bytemuck::cast_slice(&stars).to_vec())
+}
+
+/// Per instance attributes that don't change while simulating.
 #[repr(C)]
 #[derive(Copy, Clone, Pod, Zeroable)]
-pub struct RenderInstance {
-    position: [f32; 2],
+pub struct StarAttributes {
     color: [f32; 3],
     radius: f32,
 }
 
-impl RenderInstance {
+impl StarAttributes {
     pub const ATTRIBS: &'static [VertexAttribute] =
-        &vertex_attr_array![1 => Float32x2, 2 => Float32x3, 3 => Float32];
+        &vertex_attr_array![2 => Float32x3, 3 => Float32];
 }
 
-#[repr(C)]
-#[derive(Copy, Clone, bytemuck::Zeroable, bytemuck::Pod)]
-pub struct PushConstants {
-    inv_aspect: f32,
-    render_scale: f32,
-    pos: [f32; 2],
+/// How per star data gets to the vertex shader.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RenderPath {
+    /// per instance vertex attributes
+    VertexBuffer,
+    /// storage buffers indexed by the instance index, which scales to far more instances
+    StorageBuffer,
+    /// storage buffers, drawing only the stars that survived gpu culling
+    Culled,
+    /// per instance vertex attributes, but a single quad per star whose circle is cut out
+    /// by the fragment shader, which saves vertex throughput with very many stars
+    Impostor,
+}
+
+/// What the color of a star shows.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ColorMode {
+    /// `State::colors`
+    Base,
+    /// accumulated force error estimate, on a logarithmic scale
+    Error,
 }
 
 pub struct State {
-    pub simulation: Simulation,
+    pub simulation: Box<dyn SimulationBackend>,
+    /// render color of each star, indexed by `StarId`
+    pub colors: Vec<[f32; 3]>,
 
     pub size: PhysicalSize<u32>,
     pub surface: Surface,
@@ -59,47 +149,136 @@ pub struct State {
     pub queue: Queue,
 
     pub render_pipeline: RenderPipeline,
+    pub storage_pipeline: RenderPipeline,
+    pub impostor_pipeline: RenderPipeline,
+    pub storage_bind_group_layout: BindGroupLayout,
+    pub storage_bind_group: BindGroup,
+    /// all entry points of the star pipelines, including the culled one
+    pub star_shader: ShaderModule,
+    /// if set, the star pipelines are rebuilt whenever the shader source changes
+    pub shader_watcher: Option<ShaderWatcher>,
+    pub render_path: RenderPath,
+    pub target: TargetFormat,
+    /// hdr and bloom textures for the window size, stars are drawn into them
+    pub hdr: HdrTargets,
+    /// bloom and tonemapping of `hdr` into the surface
+    pub post: PostProcess,
+    pub color_mode: ColorMode,
+    pub culling: Culling,
+    /// gpu time of the passes of a frame, if the adapter supports timestamp queries
+    pub gpu_timer: Option<GpuTimer>,
+    /// if set, fading trails are drawn behind some of the stars
+    pub trails: Option<Trails>,
+    /// if set, arrows show the velocity of the stars or of grid cells
+    pub quiver: Option<Quiver>,
+    /// transient rings at the locations of events, e.g. tidal disruptions
+    pub markers: Markers,
+    /// trajectories of the probes of the simulation
+    pub probe_paths: ProbePaths,
+    /// if set, rendered in split screen next to `simulation`
+    pub comparison: Option<Comparison>,
+    /// opening angle the comparison is stepped with, set with `compare theta <value>`
+    pub comparison_theta: Real,
+    /// run before every substep, on the comparison as well
+    pub script: Option<Script>,
+    pub console: Console,
+    pub overlay: Overlay,
+    pub history: History,
+    /// other simulations the viewer can switch to
+    pub tabs: Tabs,
+    /// autosaves for crash recovery
+    pub session: Option<Session>,
+    /// if set, a frame is captured after every update
+    pub recorder: Option<Recorder>,
+    /// if set, the stars are written to a replay after every simulation step
+    pub replay_recorder: Option<replay::Recorder<BufWriter<File>>>,
+    /// where `replay_recorder` writes to, read back for the trails of beauty shots
+    pub replay_path: Option<PathBuf>,
+    /// if set, picks the steps written to the replay, otherwise all of them are
+    pub replay_cadence: Option<Cadence>,
+    /// if set, calls from other programs are executed between frames
+    pub remote: Option<Remote>,
+    /// star shown in the window title and ringed, picked by clicking or with `select`
+    pub selected: Option<StarId>,
+    /// pair of stars whose orbit is shown in the window title
+    pub pair: Option<(StarId, StarId)>,
+    /// what the camera keeps centered, panning stops following
+    pub follow: Option<Follow>,
 
     pub vertex_buffer: Buffer,
-    pub instance_buffer: Buffer,
+    pub star_buffer: Buffer,
+    /// streams the stars into `star_buffer` every frame
+    pub star_upload: StagingRing,
+    pub attribute_buffer: Buffer,
     pub index_buffer: Buffer,
 
     pub index_count: u32,
 
-    pub push_constants: PushConstants,
-    pub instances: Vec<RenderInstance>,
+    pub camera: Camera,
+
+    pub paused: bool,
+    pub keybindings: Keybindings,
+    /// physical units of the simulation, used by exports
+    pub units: Units,
+    /// where snapshots and screenshots are written
+    pub paths: Paths,
+    /// views captured by `capture_panels`
+    pub panels: Vec<Panel>,
+    /// simulation steps per frame
+    pub substeps: u32,
+    /// last known cursor position in the window
+    pub cursor: PhysicalPosition<f64>,
+    /// world position a star is being launched from by dragging with the right mouse
+    /// button, its velocity grows with the distance to the cursor
+    pub launch: Option<Vector2<Real>>,
+    /// predicted orbit of the star being launched, or else of the selected star
+    pub orbit_preview: OrbitPreview,
+
+    /// tree traversal statistics accumulated over the substeps of the last frame
+    pub frame_stats: TraversalStats,
+    pub step_time: Duration,
 }
 
+/// File written and read by the snapshot hotkeys, in `Paths::snapshots`.
+pub const SNAPSHOT: &str = "gravsim.snapshot";
+
 impl State {
     const VERTEX_COUNT: usize = 6;
+    /// Pixels a click may miss a star by and still select it.
+    const PICK_RADIUS: f64 = 12.0;
+    /// Mass of stars launched with the mouse.
+    const LAUNCH_MASS: Real = 1000.0;
+    /// Speed of a launched star per world unit dragged.
+    const LAUNCH_SPEED: Real = 1e-4;
 
-    pub async fn new(window: &Window, simulation: Simulation) -> Self {
+    pub async fn new(
+        window: &Window,
+        simulation: Box<dyn SimulationBackend>,
+        colors: Vec<[f32; 3]>,
+        settings: &Config,
+    ) -> Result<Self, String> {
         let size = window.inner_size();
 
-        let instance = Instance::new(Backends::VULKAN);
+        // all backends, so there is something to fall back to
+        let instance = Instance::new(Backends::all());
         let surface = unsafe { instance.create_surface(window) };
-        let adapter = instance
-            .request_adapter(&RequestAdapterOptions {
-                power_preference: PowerPreference::HighPerformance,
-                force_fallback_adapter: false,
-                compatible_surface: Some(&surface),
-            })
-            .await
-            .unwrap();
+        let adapter = select_adapter(&instance, &surface, settings.backend)?;
         let (device, queue) = adapter
             .request_device(
                 &DeviceDescriptor {
                     label: None,
-                    features: Features::CONSERVATIVE_RASTERIZATION | Features::PUSH_CONSTANTS,
+                    // timestamps are only for profiling
+                    features: REQUIRED_FEATURES | (adapter.features() & Features::TIMESTAMP_QUERY),
+                    // downlevel, so adapters of older backends like GL qualify as well
                     limits: Limits {
-                        max_push_constant_size: size_of::<PushConstants>() as u32,
-                        ..Default::default()
+                        max_push_constant_size: size_of::<CullConstants>() as u32,
+                        ..Limits::downlevel_defaults().using_resolution(adapter.limits())
                     },
                 },
                 None,
             )
             .await
-            .unwrap();
+            .map_err(|e| format!("failed to open {}: {}", adapter.get_info().name, e))?;
 
         let config = SurfaceConfiguration {
             usage: TextureUsages::RENDER_ATTACHMENT,
@@ -110,55 +289,29 @@ impl State {
         };
 
         surface.configure(&device, &config);
+        let target = TargetFormat {
+            format: HDR_FORMAT,
+            samples: settings.quality.msaa_samples(),
+        };
 
-        let vert_shader = device.create_shader_module(include_spirv!("../shaders/vert.spv"));
-        let frag_shader = device.create_shader_module(include_spirv!("../shaders/frag.spv"));
-
-        let rp_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
-            label: None,
-            bind_group_layouts: &[],
-            push_constant_ranges: &[PushConstantRange {
-                stages: ShaderStages::VERTEX,
-                range: 0..size_of::<PushConstants>() as u32,
-            }],
-        });
-        let render_pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
-            label: None,
-            layout: Some(&rp_layout),
-            vertex: VertexState {
-                module: &vert_shader,
-                entry_point: "main",
-                buffers: &[
-                    VertexBufferLayout {
-                        array_stride: size_of::<Vertex>() as u64,
-                        step_mode: VertexStepMode::Vertex,
-                        attributes: Vertex::ATTRIBS,
-                    },
-                    VertexBufferLayout {
-                        array_stride: size_of::<RenderInstance>() as u64,
-                        step_mode: VertexStepMode::Instance,
-                        attributes: RenderInstance::ATTRIBS,
+        let star_shader = device.create_shader_module(include_wgsl!("shaders/stars.wgsl"));
+        // the storage path only uses the vertex buffer, per star data is fetched from storage buffers
+        let storage_bind_group_layout =
+            device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: None,
+                entries: &[0, 1].map(|binding| BindGroupLayoutEntry {
+                    binding,
+                    visibility: ShaderStages::VERTEX,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
                     },
-                ],
-            },
-            primitive: PrimitiveState {
-                cull_mode: Some(Face::Back),
-                conservative: true,
-                ..Default::default()
-            },
-            depth_stencil: None,
-            multisample: Default::default(),
-            fragment: Some(FragmentState {
-                module: &frag_shader,
-                entry_point: "main",
-                targets: &[Some(ColorTargetState {
-                    format: config.format,
-                    blend: Some(BlendState::REPLACE),
-                    write_mask: ColorWrites::ALL,
-                })],
-            }),
-            multiview: None,
-        });
+                    count: None,
+                }),
+            });
+        let (render_pipeline, storage_pipeline) =
+            create_star_pipelines(&device, &star_shader, &storage_bind_group_layout, target);
 
         let vertices: Vec<_> = (0..Self::VERTEX_COUNT)
             .map(|i| i as f32 / Self::VERTEX_COUNT as f32 * std::f32::consts::TAU)
@@ -182,29 +335,36 @@ impl State {
             usage: BufferUsages::INDEX,
         });
 
-        let instances: Vec<_> = simulation
-            .stars
-            .iter()
-            .map(|star| RenderInstance {
-                position: [star.pos().x, star.pos().y],
-                color: star.color(),
-                radius: star.radius(),
-            })
-            .collect();
-        let instance_buffer = device.create_buffer_init(&BufferInitDescriptor {
-            label: None,
-            contents: bytemuck::cast_slice(&instances),
-            usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
-        });
+        let (star_buffer, attribute_buffer, storage_bind_group, culling) =
+            Self::create_star_buffers(
+                &device,
+                &*simulation,
+                &colors,
+                &storage_bind_group_layout,
+                &star_shader,
+                indices.len() as u32,
+                target,
+            );
 
-        let push_constants = PushConstants {
-            inv_aspect: size.height as f32 / size.width as f32,
-            render_scale: 1.0,
-            pos: [0.0; 2],
-        };
+        let post = PostProcess::new(&device, config.format);
+        let hdr = HdrTargets::new(&device, &post, target, size);
+        let impostor_pipeline = impostor_pipeline(&device, target);
+        let markers = Markers::new(&device, target);
+        let probe_paths = ProbePaths::new(&device, target);
+        let orbit_preview = OrbitPreview::new(&device, target);
+        let overlay = Overlay::new(&device, config.format, window);
+        let gpu_timer = device
+            .features()
+            .contains(Features::TIMESTAMP_QUERY)
+            .then(|| GpuTimer::new(&device, &queue));
+        let star_upload = StagingRing::new(&device, star_bytes(simulation.snapshot()).len() as u64);
+
+        let mut camera = Camera::new(size);
+        camera.fit(simulation.snapshot());
 
-        Self {
+        Ok(Self {
             simulation,
+            colors,
 
             size,
             surface,
@@ -213,15 +373,229 @@ impl State {
             queue,
 
             render_pipeline,
+            storage_pipeline,
+            impostor_pipeline,
+            storage_bind_group_layout,
+            storage_bind_group,
+            star_shader,
+            shader_watcher: settings
+                .hot_reload_shaders
+                .then(|| ShaderWatcher::new(ShaderWatcher::STARS)),
+            render_path: settings
+                .render_path
+                .unwrap_or_else(|| settings.quality.render_path()),
+            target,
+            hdr,
+            post,
+            color_mode: ColorMode::Base,
+            culling,
+            gpu_timer,
+            trails: None,
+            quiver: None,
+            markers,
+            probe_paths,
+            comparison: None,
+            comparison_theta: Comparison::DEFAULT_THETA,
+            script: None,
+            console: Console::default(),
+            overlay,
+            history: History::default(),
+            tabs: Tabs::new("main".to_string()),
+            session: None,
+            recorder: None,
+            replay_recorder: None,
+            replay_path: None,
+            replay_cadence: None,
+            remote: settings.remote.map(Remote::start).transpose()?,
+            selected: None,
+            pair: None,
+            follow: None,
 
             vertex_buffer,
             index_buffer,
-            instance_buffer,
+            star_upload,
+            star_buffer,
+            attribute_buffer,
 
             index_count: indices.len() as u32,
 
-            push_constants,
-            instances,
+            camera,
+
+            paused: false,
+            keybindings: settings.keybindings.clone(),
+            units: settings.units.clone(),
+            paths: Paths::default(),
+            panels: settings.panels.clone(),
+            substeps: settings.quality.substeps(),
+            cursor: PhysicalPosition::default(),
+            launch: None,
+            orbit_preview,
+
+            frame_stats: TraversalStats::default(),
+            step_time: Duration::ZERO,
+        })
+    }
+
+    /// Creates the buffers holding per star data, and everything referencing them.
+    fn create_star_buffers(
+        device: &Device,
+        simulation: &dyn SimulationBackend,
+        colors: &[[f32; 3]],
+        storage_bind_group_layout: &BindGroupLayout,
+        star_shader: &ShaderModule,
+        index_count: u32,
+        target: TargetFormat,
+    ) -> (Buffer, Buffer, BindGroup, Culling) {
+        let stars = simulation.snapshot();
+        let star_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: None,
+            contents: &star_bytes(stars),
+            usage: BufferUsages::VERTEX | BufferUsages::STORAGE | BufferUsages::COPY_DST,
+        });
+
+        let attributes: Vec<_> = stars
+            .iter()
+            .zip(colors)
+            .map(|(star, &color)| StarAttributes {
+                color,
+                radius: simulation.radius(star) as f32,
+            })
+            .collect();
+        let attribute_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::cast_slice(&attributes),
+            usage: BufferUsages::VERTEX | BufferUsages::STORAGE | BufferUsages::COPY_DST,
+        });
+
+        let storage_bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: None,
+            layout: storage_bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: star_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: attribute_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let culling = Culling::new(
+            device,
+            &star_buffer,
+            &attribute_buffer,
+            stars.len(),
+            index_count,
+            star_shader,
+            target,
+        );
+
+        (star_buffer, attribute_buffer, storage_bind_group, culling)
+    }
+
+    /// Recreates the per star buffers if a script added stars since they were created.
+    pub fn sync_star_count(&mut self) {
+        if let Some(comparison) = &mut self.comparison {
+            comparison.reserve(&self.device);
+        }
+        if self.colors.len() == self.simulation.snapshot().len() {
+            return;
+        }
+
+        self.colors
+            .resize(self.simulation.snapshot().len(), [1.0; 3]);
+        self.recreate_star_buffers();
+    }
+
+    /// Creates the star buffers again for the current number of stars.
+    pub fn recreate_star_buffers(&mut self) {
+        (
+            self.star_buffer,
+            self.attribute_buffer,
+            self.storage_bind_group,
+            self.culling,
+        ) = Self::create_star_buffers(
+            &self.device,
+            &*self.simulation,
+            &self.colors,
+            &self.storage_bind_group_layout,
+            &self.star_shader,
+            self.index_count,
+            self.target,
+        );
+    }
+
+    /// Rebuilds the star pipelines if the star shader changed, see `Config::hot_reload_shaders`.
+    /// The old pipelines stay if the new shader fails to compile.
+    fn reload_shaders(&mut self) {
+        let Some(source) = self.shader_watcher.as_mut().and_then(ShaderWatcher::poll) else {
+            return;
+        };
+        let source = match source {
+            Ok(source) => source,
+            Err(e) => {
+                eprintln!("{}", e);
+                return;
+            }
+        };
+
+        self.device.push_error_scope(ErrorFilter::Validation);
+        let star_shader = self.device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("stars"),
+            source: ShaderSource::Wgsl(source.into()),
+        });
+        let (render_pipeline, storage_pipeline) = create_star_pipelines(
+            &self.device,
+            &star_shader,
+            &self.storage_bind_group_layout,
+            self.target,
+        );
+        // the culled pipeline references the star buffers, which are recreated with it
+        let star_buffers = Self::create_star_buffers(
+            &self.device,
+            &*self.simulation,
+            &self.colors,
+            &self.storage_bind_group_layout,
+            &star_shader,
+            self.index_count,
+            self.target,
+        );
+        if let Some(error) = pollster::block_on(self.device.pop_error_scope()) {
+            eprintln!("failed to reload the star shader: {}", error);
+            return;
+        }
+
+        self.star_shader = star_shader;
+        self.render_pipeline = render_pipeline;
+        self.storage_pipeline = storage_pipeline;
+        (
+            self.star_buffer,
+            self.attribute_buffer,
+            self.storage_bind_group,
+            self.culling,
+        ) = star_buffers;
+        println!("reloaded the star shader");
+    }
+
+    /// Runs the script on both simulations, disabling it if it fails.
+    fn run_script(&mut self) {
+        let Some(script) = &self.script else {
+            return;
+        };
+
+        let result = match self.simulation.as_simulation_mut() {
+            Some(simulation) => script.run(simulation),
+            None => Err("scripts need a Barnes-Hut simulation".to_string()),
+        }
+        .and_then(|_| match &mut self.comparison {
+            Some(comparison) => script.run(&mut comparison.simulation),
+            None => Ok(()),
+        });
+        if let Err(e) = result {
+            eprintln!("script failed, disabling it: {}", e);
+            self.script = None;
         }
     }
 
@@ -231,16 +605,51 @@ impl State {
             self.config.width = self.size.width;
             self.config.height = self.size.height;
 
-            self.push_constants.inv_aspect = self.config.height as f32 / self.config.width as f32;
+            self.camera = self.camera.with_aspect(self.size);
 
             self.surface.configure(&self.device, &self.config);
+            self.hdr = HdrTargets::new(&self.device, &self.post, self.target, self.size);
         }
     }
 
     pub fn input(&mut self, event: &WindowEvent) -> bool {
         const STEP: f32 = 0.25;
 
+        if let WindowEvent::KeyboardInput {
+            input:
+                KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(key),
+                    ..
+                },
+            ..
+        } = event
+        {
+            if self.keybindings.action(*key) == Some(Action::ToggleConsole) {
+                self.console.open = !self.console.open;
+                return true;
+            }
+        }
+        if !self.console.open && self.overlay.on_event(event) {
+            return true;
+        }
+
         match event {
+            WindowEvent::ReceivedCharacter(c) if self.console.open => {
+                if let Some(line) = self.console.type_char(*c) {
+                    self.run_command(&line);
+                }
+            }
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        virtual_keycode: Some(VirtualKeyCode::Escape),
+                        ..
+                    },
+                ..
+            } if self.console.open => self.console.open = false,
+            // everything else typed goes to the console
+            WindowEvent::KeyboardInput { .. } if self.console.open => {}
             WindowEvent::KeyboardInput {
                 input:
                     KeyboardInput {
@@ -249,31 +658,112 @@ impl State {
                         ..
                     },
                 ..
-            } => match key {
-                VirtualKeyCode::W | VirtualKeyCode::Up => {
-                    self.push_constants.pos[1] -= STEP / self.push_constants.render_scale
+            } => match self.keybindings.action(*key) {
+                Some(Action::Up) => self.pan(Vector2::new(0.0, STEP)),
+                Some(Action::Left) => self.pan(Vector2::new(-STEP, 0.0)),
+                Some(Action::Down) => self.pan(Vector2::new(0.0, -STEP)),
+                Some(Action::Right) => self.pan(Vector2::new(STEP, 0.0)),
+                Some(Action::Pause) => self.paused = !self.paused,
+                Some(Action::CycleRenderPath) => {
+                    self.render_path = match self.render_path {
+                        RenderPath::VertexBuffer => RenderPath::StorageBuffer,
+                        RenderPath::StorageBuffer => RenderPath::Culled,
+                        RenderPath::Culled => RenderPath::Impostor,
+                        RenderPath::Impostor => RenderPath::VertexBuffer,
+                    }
                 }
-                VirtualKeyCode::A | VirtualKeyCode::Left => {
-                    self.push_constants.pos[0] += STEP / self.push_constants.render_scale
+                Some(Action::PrintStats) => self.print_stats(),
+                Some(Action::ToggleOverlay) => self.overlay.visible = !self.overlay.visible,
+                Some(Action::ToggleErrorColors) => self.toggle_error_colors(),
+                Some(Action::ToggleTrails) => {
+                    self.trails = match self.trails {
+                        Some(_) => None,
+                        None => Some(Trails::new(
+                            &self.device,
+                            self.target,
+                            self.simulation.snapshot(),
+                        )),
+                    }
                 }
-                VirtualKeyCode::S | VirtualKeyCode::Down => {
-                    self.push_constants.pos[1] += STEP / self.push_constants.render_scale
+                Some(Action::CycleQuiver) => {
+                    let mode = match self.quiver.as_ref().map(|quiver| quiver.mode) {
+                        None => Some(QuiverMode::Stars),
+                        Some(QuiverMode::Stars) => Some(QuiverMode::Grid),
+                        Some(QuiverMode::Grid) => None,
+                    };
+                    self.quiver = mode.map(|mode| match self.quiver.take() {
+                        Some(mut quiver) => {
+                            quiver.mode = mode;
+                            quiver
+                        }
+                        None => Quiver::new(&self.device, self.target, mode),
+                    });
                 }
-                VirtualKeyCode::D | VirtualKeyCode::Right => {
-                    self.push_constants.pos[0] -= STEP / self.push_constants.render_scale
+                Some(Action::ToggleComparison) => {
+                    self.comparison = match self.comparison {
+                        Some(_) => None,
+                        None => self.simulation.as_simulation().map(|simulation| {
+                            Comparison::new(&self.device, simulation, self.comparison_theta)
+                        }),
+                    }
                 }
-                VirtualKeyCode::Return => {
-                    self.push_constants.render_scale = 1.0;
-                    self.push_constants.pos = [0.0; 2];
+                Some(Action::Screenshot) => self.beauty_shot(),
+                Some(Action::CapturePanels) => self.capture_panels(),
+                Some(Action::SaveSnapshot) => self.save_snapshot(),
+                Some(Action::LoadSnapshot) => self.load_snapshot(),
+                Some(Action::AddDilationZone) => self.add_dilation_zone(),
+                Some(Action::ClearDilationZones) => self.for_each_simulation(|simulation| {
+                    simulation.dilation_zones.clear();
+                }),
+                Some(Action::ResetView) => self.camera = Camera::new(self.size),
+                Some(Action::FitView) => self.camera.fit(self.simulation.snapshot()),
+                // the selected star if there is one
+                Some(Action::ToggleFollow) => {
+                    self.follow = match self.follow {
+                        Some(_) => None,
+                        None => Some(self.selected.map_or(Follow::CenterOfMass, Follow::Star)),
+                    }
                 }
+                Some(Action::NextTab) => self.cycle_tab(1),
+                Some(Action::PreviousTab) => self.cycle_tab(-1),
                 _ => return false,
             },
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor = *position;
+                return false;
+            }
+            WindowEvent::MouseInput {
+                state: ElementState::Pressed,
+                button: MouseButton::Left,
+                ..
+            } => {
+                self.selected = self.star_at(self.cursor);
+                if self.selected.is_none() {
+                    self.pair = None;
+                }
+            }
+            WindowEvent::MouseInput {
+                state: ElementState::Pressed,
+                button: MouseButton::Right,
+                ..
+            } => self.launch = Some(self.window_to_world(self.cursor)),
+            WindowEvent::MouseInput {
+                state: ElementState::Released,
+                button: MouseButton::Right,
+                ..
+            } => {
+                let Some(start) = self.launch.take() else {
+                    return false;
+                };
+                let star = Star::new(start, self.launch_velocity(start), Self::LAUNCH_MASS);
+                self.perform(Edit::Spawn(vec![star]));
+            }
             WindowEvent::MouseWheel {
                 delta: MouseScrollDelta::LineDelta(_, y),
                 ..
             } => match y.total_cmp(&0.0) {
-                Ordering::Greater => self.push_constants.render_scale *= 0.8,
-                Ordering::Less => self.push_constants.render_scale *= 1.25,
+                Ordering::Greater => self.camera.render_scale *= 0.8,
+                Ordering::Less => self.camera.render_scale *= 1.25,
                 _ => return false,
             },
             _ => return false,
@@ -281,21 +771,793 @@ impl State {
         true
     }
 
+    /// Runs a line typed into the console, reporting the result in the window title.
+    fn run_command(&mut self, line: &str) {
+        if line.trim().is_empty() {
+            return;
+        }
+
+        let output = line
+            .parse()
+            .and_then(|command| self.execute(command))
+            .unwrap_or_else(|e| e);
+        if let Some(session) = &mut self.session {
+            session.log(line);
+        }
+        println!("> {}\n{}", line, output);
+        self.console.output = output;
+    }
+
+    fn execute(&mut self, command: Command) -> Result<String, String> {
+        match command {
+            Command::Select(id) => {
+                if let Some(id) = id.filter(|&id| id >= self.simulation.snapshot().len()) {
+                    return Err(format!("no star with id {}", id));
+                }
+                self.selected = id;
+                if id.is_none() {
+                    self.pair = None;
+                }
+                Ok(match id {
+                    Some(id) => format!("selected star {}", id),
+                    None => "selection cleared".to_string(),
+                })
+            }
+            Command::Follow(follow) => {
+                if let Some(Follow::Star(id)) = follow {
+                    if id >= self.simulation.snapshot().len() {
+                        return Err(format!("no star with id {}", id));
+                    }
+                }
+                self.follow = follow;
+                Ok(match follow {
+                    Some(Follow::Star(id)) => format!("following star {}", id),
+                    Some(Follow::CenterOfMass) => "following the center of mass".to_string(),
+                    None => "stopped following".to_string(),
+                })
+            }
+            Command::SelectPair(a, b) => {
+                let star_count = self.simulation.snapshot().len();
+                if let Some(id) = [a, b].into_iter().find(|&id| id >= star_count) {
+                    return Err(format!("no star with id {}", id));
+                }
+                if a == b {
+                    return Err("a pair needs two different stars".to_string());
+                }
+                self.pair = Some((a, b));
+                Ok(format!("selected pair {} and {}", a, b))
+            }
+            Command::Set(parameter, value) => {
+                let Some(simulation) = self.simulation.as_simulation() else {
+                    return Err("parameters need a Barnes-Hut simulation".to_string());
+                };
+                let old = match parameter {
+                    Parameter::Theta => simulation.config.theta,
+                    Parameter::Gravity => simulation.config.gravity,
+                    Parameter::Softening => simulation.config.softening,
+                    Parameter::TimeStep => simulation.config.dt,
+                    Parameter::Temperature => simulation
+                        .config
+                        .thermal_noise
+                        .map_or(0.0, |noise| noise.temperature),
+                };
+                self.perform(Edit::Set(parameter, old, value));
+                Ok(format!("{:?} set to {}", parameter, value))
+            }
+            Command::CompareTheta(theta) => {
+                let Some(simulation) = self.simulation.as_simulation() else {
+                    return Err("comparisons need a Barnes-Hut simulation".to_string());
+                };
+                self.comparison_theta = theta;
+                match &mut self.comparison {
+                    Some(comparison) => comparison.simulation.config.theta = theta,
+                    None => {
+                        self.comparison = Some(Comparison::new(&self.device, simulation, theta))
+                    }
+                }
+                Ok(format!("comparing with theta {}", theta))
+            }
+            Command::SpawnGalaxy(count, location) => {
+                let center = match location {
+                    Location::Cursor => self.window_to_world(self.cursor),
+                    Location::World(pos) => pos,
+                };
+                let radius = 10_000.0 * (count as Real / Simulation::N_STARS as Real).sqrt();
+                let galaxy = Galaxy::new(
+                    Star::new(center, Vector2::zeros(), 1e1),
+                    count,
+                    radius,
+                    &Scenario::MASS_DISTRIBUTION,
+                );
+                let count = galaxy.stars().len();
+                self.perform(Edit::Spawn(galaxy.into_stars()));
+                Ok(format!(
+                    "spawned {} stars at ({:.0}, {:.0})",
+                    count, center.x, center.y
+                ))
+            }
+            Command::Delete(id) => {
+                let star = *self
+                    .simulation
+                    .snapshot()
+                    .get(id)
+                    .ok_or_else(|| format!("no star with id {}", id))?;
+                let attributes = self.simulation.attributes(id);
+                self.perform(Edit::Delete(id, star, attributes));
+                Ok(format!("deleted star {}", id))
+            }
+            Command::Impulse(id, impulse) => {
+                if id >= self.simulation.snapshot().len() {
+                    return Err(format!("no star with id {}", id));
+                }
+                self.perform(Edit::Impulse(id, impulse));
+                Ok(format!("kicked star {}", id))
+            }
+            Command::SpawnProbe(location, velocity) => {
+                let position = match location {
+                    Location::Cursor => self.window_to_world(self.cursor),
+                    Location::World(pos) => pos,
+                };
+                let Some(simulation) = self.simulation.as_simulation_mut() else {
+                    return Err("probes need a Barnes-Hut simulation".to_string());
+                };
+                simulation.probes.push(Probe::new(position, velocity));
+                Ok(format!(
+                    "launched probe {} from ({:.0}, {:.0})",
+                    simulation.probes.len() - 1,
+                    position.x,
+                    position.y
+                ))
+            }
+            Command::ClearProbes => {
+                let Some(simulation) = self.simulation.as_simulation_mut() else {
+                    return Err("probes need a Barnes-Hut simulation".to_string());
+                };
+                let count = simulation.probes.len();
+                simulation.probes.clear();
+                Ok(format!("removed {} probes", count))
+            }
+            Command::Undo => {
+                let edit = self.history.undo().cloned().ok_or("nothing to undo")?;
+                self.apply_edit(&edit, true);
+                Ok(format!("undid {}", edit.name()))
+            }
+            Command::Redo => {
+                let edit = self.history.redo().cloned().ok_or("nothing to redo")?;
+                self.apply_edit(&edit, false);
+                Ok(format!("redid {}", edit.name()))
+            }
+            Command::ExportCsv(path) => {
+                std::fs::write(&path, session::csv(self.simulation.snapshot()))
+                    .map_err(|e| format!("failed to write {}: {}", path.display(), e))?;
+                Ok(format!("exported {}", path.display()))
+            }
+            Command::ExportFits(quantity, path) => {
+                let grid = self.view_grid();
+                let stars = self.simulation.snapshot();
+                let (map, time) = match (quantity, self.simulation.as_simulation()) {
+                    (Quantity::SurfaceDensity, simulation) => (
+                        Map::surface_density(stars, grid),
+                        simulation.map_or(0.0, |simulation| simulation.time),
+                    ),
+                    (Quantity::Potential, Some(simulation)) => (
+                        Map::potential(stars, &simulation.config, grid),
+                        simulation.time,
+                    ),
+                    (Quantity::Potential, None) => {
+                        return Err("potentials need a Barnes-Hut simulation".to_string())
+                    }
+                };
+                std::fs::write(&path, fits::encode(&map, &self.units, time))
+                    .map_err(|e| format!("failed to write {}: {}", path.display(), e))?;
+                Ok(format!("exported {}", path.display()))
+            }
+            Command::SwitchTab(index) => {
+                self.switch_tab(index)?;
+                Ok(format!("switched to tab {}", self.tab_label()))
+            }
+            Command::CycleTab(offset) => {
+                self.cycle_tab(offset);
+                Ok(format!("switched to tab {}", self.tab_label()))
+            }
+            Command::OpenTab(path) => {
+                self.open_tab(&path)?;
+                Ok(format!("opened tab {}", self.tab_label()))
+            }
+            Command::CapturePanels => {
+                self.capture_panels();
+                Ok(format!("captured {} panels", self.panels.len()))
+            }
+            Command::Pause(paused) => {
+                self.paused = paused;
+                Ok(match paused {
+                    true => "paused".to_string(),
+                    false => "resumed".to_string(),
+                })
+            }
+            Command::Screenshot => {
+                let path = self.save_beauty_shot()?;
+                Ok(format!("saved beauty shot to {}", path.display()))
+            }
+        }
+    }
+
+    /// Number and name of the active tab.
+    fn tab_label(&self) -> String {
+        format!(
+            "{}/{} {}",
+            self.tabs.active + 1,
+            self.tabs.len(),
+            self.tabs.names[self.tabs.active]
+        )
+    }
+
+    /// The pixels of the window, in world coordinates.
+    fn view_grid(&self) -> Grid {
+        let min = self.window_to_world(PhysicalPosition::new(0.0, self.size.height as f64));
+        let max = self.window_to_world(PhysicalPosition::new(self.size.width as f64, 0.0));
+        Grid {
+            min,
+            pixel_size: (max.x - min.x) / self.size.width as Real,
+            width: self.size.width as usize,
+            height: self.size.height as usize,
+        }
+    }
+
+    /// Applies an edit to both simulations and records it for undo.
+    fn perform(&mut self, edit: Edit) {
+        self.apply_edit(&edit, false);
+        self.history.push(edit);
+    }
+
+    fn apply_edit(&mut self, edit: &Edit, revert: bool) {
+        // the comparison keeps its own opening angle, that is what it compares
+        let compares_theta = matches!(edit, Edit::Set(Parameter::Theta, ..));
+        let simulations = std::iter::once(&mut *self.simulation).chain(
+            self.comparison
+                .as_mut()
+                .filter(|_| !compares_theta)
+                .map(|comparison| &mut comparison.simulation as &mut dyn SimulationBackend),
+        );
+        for simulation in simulations {
+            match revert {
+                false => edit.apply(simulation),
+                true => edit.revert(simulation),
+            }
+        }
+
+        // colors are indexed by `StarId` as well, restored stars get the default color
+        if let Edit::Delete(id, ..) = *edit {
+            if revert {
+                self.colors.insert(id, [1.0; 3]);
+            } else {
+                self.colors.remove(id);
+            }
+        }
+        self.sync_star_count();
+        self.write_attributes();
+        self.write_stars();
+    }
+
+    /// Moves the view by `offset` half window heights, and stops following.
+    fn pan(&mut self, offset: Vector2<f32>) {
+        self.follow = None;
+        self.camera.pan(offset);
+    }
+
+    /// Converts a position in the window to simulation space.
+    pub fn window_to_world(&self, position: PhysicalPosition<f64>) -> Vector2<Real> {
+        self.camera.screen_to_world(position, self.size).cast()
+    }
+
+    /// Velocity of a star launched from `start`, towards the cursor.
+    fn launch_velocity(&self, start: Vector2<Real>) -> Vector2<Real> {
+        (self.window_to_world(self.cursor) - start) * Self::LAUNCH_SPEED
+    }
+
+    /// The star closest to `position` in the window, if one is within `PICK_RADIUS` pixels.
+    pub fn star_at(&self, position: PhysicalPosition<f64>) -> Option<StarId> {
+        let world = self.window_to_world(position);
+        let pixels_per_unit = self.camera.render_scale as f64 * self.size.height as f64 / 2.0;
+        let max_dist = Self::PICK_RADIUS / pixels_per_unit;
+        self.simulation
+            .snapshot()
+            .iter()
+            .enumerate()
+            .map(|(id, star)| (id, (star.pos() - world).norm() as f64))
+            .filter(|(_, dist)| dist.is_finite() && *dist <= max_dist)
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(id, _)| id)
+    }
+
+    /// Adds a slow motion bubble under the cursor, sized relative to the view.
+    fn add_dilation_zone(&mut self) {
+        let zone = DilationZone {
+            center: self.window_to_world(self.cursor),
+            radius: 0.2 * self.camera.view_extent() as Real,
+            time_scale: 0.2,
+        };
+        self.for_each_simulation(|simulation| simulation.dilation_zones.push(zone));
+    }
+
+    /// Applies `f` to the Barnes-Hut simulation and its comparison, if there are any.
+    fn for_each_simulation(&mut self, mut f: impl FnMut(&mut Simulation)) {
+        if let Some(simulation) = self.simulation.as_simulation_mut() {
+            f(simulation);
+        }
+        if let Some(comparison) = &mut self.comparison {
+            f(&mut comparison.simulation);
+        }
+    }
+
     pub fn update(&mut self) {
+        if self.paused {
+            return;
+        }
+
         // update simulation state
-        const SUBSTEPS: u32 = 4;
-        for _ in 0..SUBSTEPS {
-            self.simulation.update();
+        let start = Instant::now();
+        self.frame_stats = TraversalStats::default();
+        let mut merged = false;
+        let mut sorted = false;
+        let mut markers = Vec::new();
+        // colors of debris, which has no room in `colors` until `sync_star_count`
+        let mut debris_colors = Vec::new();
+        for _ in 0..self.substeps {
+            self.run_script();
+            self.simulation.step();
+            let due = match (&mut self.replay_cadence, self.simulation.as_simulation()) {
+                (Some(cadence), Some(simulation)) => cadence.due(simulation),
+                _ => true,
+            };
+            if due {
+                self.record_replay();
+            }
+            if let Some(simulation) = self.simulation.as_simulation_mut() {
+                // before anything else refers to the stars by their new ids
+                if let Some(order) = &simulation.sorted {
+                    reorder_colors(&mut self.colors, &mut debris_colors, order);
+                    let new_id = |old| order.iter().position(|&id| id == old);
+                    self.selected = self.selected.and_then(new_id);
+                    if let Some(Follow::Star(id)) = self.follow {
+                        self.follow = new_id(id).map(Follow::Star);
+                    }
+                    self.pair = self.pair.and_then(|(a, b)| Some((new_id(a)?, new_id(b)?)));
+                    if let Some(trails) = &mut self.trails {
+                        trails.reorder(order);
+                    }
+                    if let Some(comparison) = &mut self.comparison {
+                        comparison.simulation.reorder_stars(order);
+                    }
+                    // edits refer to stars by id
+                    self.history = History::default();
+                    sorted = true;
+                }
+
+                markers.extend(
+                    simulation
+                        .fired
+                        .iter()
+                        .filter_map(|event| match &event.action {
+                            schedule::Action::Marker(name) => Some(name.clone()),
+                            _ => None,
+                        }),
+                );
+                self.frame_stats = std::mem::take(&mut self.frame_stats)
+                    .merge(std::mem::take(&mut simulation.traversal_stats));
+
+                // merged stars get the mass weighted mix of both colors, debris the color
+                // of the star it broke off of
+                for merge in &simulation.merges {
+                    let from = *color_mut(&mut self.colors, &mut debris_colors, merge.from);
+                    let into = color_mut(&mut self.colors, &mut debris_colors, merge.into);
+                    for (into, from) in into.iter_mut().zip(from) {
+                        *into += (from - *into) * merge.fraction as f32;
+                    }
+                }
+                for debris in &simulation.debris {
+                    let from = *color_mut(&mut self.colors, &mut debris_colors, debris.from);
+                    *color_mut(&mut self.colors, &mut debris_colors, debris.id) = from;
+                }
+                merged |= !simulation.merges.is_empty() || !simulation.bounces.is_empty();
+
+                for disruption in &simulation.disruptions {
+                    let group = simulation
+                        .disruption_monitor
+                        .as_ref()
+                        .map_or("a group", |monitor| {
+                            monitor.groups[disruption.group].name.as_str()
+                        });
+                    self.markers.add_disruption(disruption, group);
+                }
+                if let Some(monitor) = &simulation.pericenter_monitor {
+                    for pericenter in &simulation.pericenters {
+                        let [a, b] = pericenter.groups.map(|group| &monitor.groups[group].name);
+                        self.markers.add_pericenter(pericenter, a, b);
+                    }
+                }
+            }
+
+            if let Some(comparison) = &mut self.comparison {
+                comparison.update(self.simulation.snapshot());
+            }
+        }
+        let known = self.colors.len();
+        self.sync_star_count();
+        for (color, debris) in self.colors.iter_mut().skip(known).zip(debris_colors) {
+            *color = debris;
+        }
+        if let Some(trails) = &mut self.trails {
+            trails.record(&self.device, self.simulation.snapshot());
+        }
+        self.markers.tick();
+        if let (Some(session), Some(simulation)) =
+            (&mut self.session, self.simulation.as_simulation())
+        {
+            session.autosave(simulation, &self.colors);
         }
+        // merges change radii as well, aging changes colors
+        let aging = self
+            .simulation
+            .as_simulation()
+            .is_some_and(|simulation| simulation.config.evolution.is_some());
+        if self.color_mode == ColorMode::Error || merged || sorted || aging {
+            self.write_attributes();
+        }
+        self.step_time = start.elapsed();
+        self.record_frame();
+
+        for marker in markers {
+            self.on_marker(&marker);
+        }
+    }
+
+    /// Executes the calls of remote control programs that arrived since the last frame.
+    pub fn serve_remote(&mut self) {
+        let requests: Vec<_> = match &self.remote {
+            Some(remote) => remote.pending().collect(),
+            None => return,
+        };
+        for request in requests {
+            let result = self.execute(request.command.clone());
+            request.reply(result);
+        }
+    }
 
-        // update instance buffer
-        self.instances
-            .par_iter_mut()
+    /// Lays out the overlay for the next frame and applies the changes made with it.
+    pub fn run_overlay(&mut self, window: &Window) {
+        let gpu_times = self.gpu_timer.as_ref().map(|timer| timer.times);
+        let commands = self.overlay.run(
+            window,
+            &*self.simulation,
+            self.step_time,
+            gpu_times,
+            &mut self.paused,
+        );
+        for command in commands {
+            if let Err(e) = self.execute(command) {
+                self.console.output = e;
+            }
+        }
+    }
+
+    /// Starts capturing a frame every `recording.every` simulation steps.
+    pub fn start_recording(&mut self, recording: &Recording) -> Result<(), String> {
+        self.recorder = Some(Recorder::start(self, recording)?);
+        self.substeps = recording.every.max(1);
+        Ok(())
+    }
+
+    /// Starts writing the stars to a replay at `path` after every simulation step, or the
+    /// steps `output` picks, which `--replay=<path>` plays back.
+    pub fn start_replay_recording(
+        &mut self,
+        path: &Path,
+        output: Option<OutputPolicy>,
+    ) -> Result<(), String> {
+        let config = self
+            .simulation
+            .as_simulation()
+            .map_or_else(Default::default, |simulation| simulation.config);
+        let recorder = replay::Recorder::create(path, &config, true)
+            .map_err(|e| format!("failed to create {}: {}", path.display(), e))?;
+        self.replay_recorder = Some(recorder);
+        self.replay_path = Some(path.to_path_buf());
+        self.replay_cadence = output
+            .zip(self.simulation.as_simulation())
+            .map(|(policy, simulation)| Cadence::new(policy, simulation));
+        // the initial state is the first frame
+        self.record_replay();
+        Ok(())
+    }
+
+    /// Appends the stars to the replay if recording one, a failed write ends the recording.
+    fn record_replay(&mut self) {
+        let Some(recorder) = &mut self.replay_recorder else {
+            return;
+        };
+        let (step, time) = match self.simulation.as_simulation() {
+            Some(simulation) => (simulation.step, simulation.time),
+            None => (recorder.frames(), 0.0),
+        };
+        if let Err(e) = recorder.record(step, time, self.simulation.snapshot()) {
+            eprintln!("failed to record the replay, stopping the recording: {}", e);
+            self.replay_recorder = None;
+        }
+    }
+
+    /// Captures the current frame if recording, a failed capture ends the recording.
+    fn record_frame(&mut self) {
+        let Some(mut recorder) = self.recorder.take() else {
+            return;
+        };
+        match recorder.capture(self) {
+            Ok(_) => self.recorder = Some(recorder),
+            Err(e) => {
+                eprintln!("failed to record a frame, stopping the recording: {}", e);
+                recorder.finish();
+            }
+        }
+    }
+
+    /// Reacts to a `schedule::Action::Marker` event.
+    fn on_marker(&mut self, name: &str) {
+        match name {
+            "screenshot" => self.beauty_shot(),
+            "panels" => self.capture_panels(),
+            "snapshot" => self.save_snapshot(),
+            "pause" => self.paused = true,
+            _ => println!("marker: {}", name),
+        }
+    }
+
+    /// Switches between base colors and the error heatmap, tracking errors only while shown.
+    fn toggle_error_colors(&mut self) {
+        let Some(simulation) = self.simulation.as_simulation_mut() else {
+            eprintln!("error estimates need a Barnes-Hut simulation");
+            return;
+        };
+
+        self.color_mode = match self.color_mode {
+            ColorMode::Base => {
+                simulation.error_estimate = Some(Default::default());
+                ColorMode::Error
+            }
+            ColorMode::Error => {
+                simulation.error_estimate = None;
+                ColorMode::Base
+            }
+        };
+        self.write_attributes();
+    }
+
+    /// Uploads the colors of the current `color_mode` together with the star radii.
+    pub fn write_attributes(&self) {
+        let stars = self.simulation.snapshot();
+        let errors = self
+            .simulation
+            .as_simulation()
+            .and_then(|simulation| simulation.error_estimate.as_ref())
+            .filter(|_| self.color_mode == ColorMode::Error)
+            .map(|estimate| &estimate.accumulated);
+
+        let colors: Vec<_> = match errors {
+            Some(errors) => {
+                // four decades below the largest error
+                const DECADES: Real = 4.0;
+                let max = errors.iter().copied().fold(Real::MIN_POSITIVE, Real::max);
+                errors
+                    .iter()
+                    .map(|error| heat((1.0 + (error / max).log10() / DECADES) as f32))
+                    .chain(std::iter::repeat(heat(0.0)))
+                    .take(stars.len())
+                    .collect()
+            }
+            None => {
+                let evolution = self.simulation.as_simulation().and_then(|simulation| {
+                    let evolution = simulation.config.evolution?;
+                    Some((evolution, &simulation.ages))
+                });
+                match evolution {
+                    // stars redden and fade as they age
+                    Some((evolution, ages)) => self
+                        .colors
+                        .iter()
+                        .zip(stars)
+                        .enumerate()
+                        .map(|(id, (color, star))| {
+                            let age = ages.get(id).copied().unwrap_or(0.0);
+                            let tint = evolution.tint(star.mass(), age);
+                            [0, 1, 2].map(|i| (color[i] * tint[i]).min(1.0))
+                        })
+                        .collect(),
+                    None => self.colors.clone(),
+                }
+            }
+        };
+
+        let attributes: Vec<_> = stars
+            .iter()
+            .zip(colors)
+            .map(|(star, color)| StarAttributes {
+                color,
+                radius: self.simulation.radius(star) as f32,
+            })
+            .collect();
+        self.queue
+            .write_buffer(&self.attribute_buffer, 0, bytemuck::cast_slice(&attributes));
+    }
+
+    /// Encodes uploading the current star state through `star_upload`, or uploads it right
+    /// away if no staging buffer is free.
+    fn stream_stars(&mut self, command_encoder: &mut CommandEncoder) {
+        let bytes = star_bytes(self.simulation.snapshot());
+        if !self
+            .star_upload
+            .upload(&self.device, command_encoder, &bytes, &self.star_buffer)
+        {
+            self.queue.write_buffer(&self.star_buffer, 0, &bytes);
+        }
+    }
+
+    /// Uploads the current star state, which is used as instance buffer as is.
+    pub fn write_stars(&self) {
+        self.queue.write_buffer(
+            &self.star_buffer,
+            0,
+            &star_bytes(self.simulation.snapshot()),
+        );
+    }
+
+    /// One line summary of the last frame, shown in the window title.
+    pub fn stats_line(&self) -> String {
+        if self.console.open {
+            return format!("> {}_ | {}", self.console.line, self.console.output);
+        }
+
+        let mut line = format!(
+            "gravsim | {} stars | step {:.1}ms",
+            self.simulation.snapshot().len(),
+            self.step_time.as_secs_f32() * 1000.0
+        );
+        if self.tabs.len() > 1 {
+            line += &format!(" | tab {}", self.tab_label());
+        }
+        if let Some(ratio) = self.frame_stats.acceptance_ratio() {
+            line += &format!(" | accepted {:.1}%", ratio * 100.0);
+        }
+        if let (RenderPath::Culled, None, Some(stats)) =
+            (self.render_path, &self.comparison, self.culling.stats)
+        {
+            line += &format!(
+                " | drawn {} culled {} sub-pixel {}",
+                stats.drawn, stats.culled, stats.sub_pixel
+            );
+        }
+        if let (Some(comparison), Some(simulation)) =
+            (&self.comparison, self.simulation.as_simulation())
+        {
+            line += &format!(
+                " | theta {} vs {} divergence {:.3e}",
+                simulation.config.theta, comparison.simulation.config.theta, comparison.divergence
+            );
+        }
+        if let Some(simulation) = self.simulation.as_simulation() {
+            if !simulation.dilation_zones.is_empty() {
+                line += &format!(" | {} dilation zones", simulation.dilation_zones.len());
+            }
+            if simulation.config.incremental_rebuild.is_some() {
+                line += &format!(" | tree {} steps old", simulation.tree_staleness);
+            }
+        }
+        if let Some(id) = self.selected {
+            line += &format!(" | {}", self.star_line(id));
+        }
+        for label in self.markers.labels() {
+            line += &format!(" | {}", label);
+        }
+        if let Some((a, b)) = self.pair {
+            line += &format!(" | pair {} {}: {}", a, b, self.orbit_line(a, b));
+        }
+        match self.follow {
+            Some(Follow::Star(id)) => line += &format!(" | following star {}", id),
+            Some(Follow::CenterOfMass) => line += " | following the center of mass",
+            None => {}
+        }
+        if let Some(recorder) = &self.recorder {
+            line += &format!(" | recorded {} frames", recorder.frames());
+        }
+        if self.paused {
+            line += " | paused";
+        }
+        line
+    }
+
+    /// Position, mass and speed of the star `id`, and its distance to the star pulling it
+    /// the most, which it orbits in most setups (e.g. the center of its galaxy).
+    fn star_line(&self, id: StarId) -> String {
+        let stars = self.simulation.snapshot();
+        let Some(star) = stars.get(id) else {
+            return format!("star {} is gone", id);
+        };
+        let mut line = format!(
+            "star {} at ({:.1}, {:.1}) mass {:.1} speed {:.3}",
+            id,
+            star.pos().x,
+            star.pos().y,
+            star.mass(),
+            star.vel.norm()
+        );
+        let primary = stars
+            .iter()
             .enumerate()
-            .for_each(|(i, instance)| {
-                let position = self.simulation.stars[i].pos();
-                instance.position = [position.x, position.y];
-            });
+            .filter(|&(other, _)| other != id)
+            .map(|(other, primary)| {
+                let dist_sq = (primary.pos() - star.pos()).norm_squared();
+                (other, primary.mass() / dist_sq, dist_sq.sqrt())
+            })
+            .filter(|(_, pull, _)| pull.is_finite())
+            .max_by(|(_, a, _), (_, b, _)| a.total_cmp(b));
+        if let Some((primary, _, radius)) = primary {
+            line += &format!(" orbits {} at r {:.1}", primary, radius);
+        }
+        line
+    }
+
+    /// Keplerian elements of the pair `a`, `b`, refitted every frame.
+    fn orbit_line(&self, a: StarId, b: StarId) -> String {
+        let stars = self.simulation.snapshot();
+        let gravity = self
+            .simulation
+            .as_simulation()
+            .map_or(Simulation::GRAVITY, |simulation| simulation.config.gravity);
+        let orbit = match (stars.get(a), stars.get(b)) {
+            (Some(a), Some(b)) => Orbit::of(a, b, gravity),
+            _ => None,
+        };
+        match orbit {
+            Some(orbit) => match orbit.period {
+                Some(period) => format!(
+                    "bound, a {:.1} e {:.3} period {:.0} pericenter {:.1}",
+                    orbit.semi_major_axis, orbit.eccentricity, period, orbit.pericenter
+                ),
+                None => format!(
+                    "unbound, e {:.3} pericenter {:.1}",
+                    orbit.eccentricity, orbit.pericenter
+                ),
+            },
+            None => "no orbit".to_string(),
+        }
+    }
+
+    /// Prints the gpu time and the per depth acceptance ratio of the last frame.
+    pub fn print_stats(&self) {
+        match self.gpu_timer.as_ref().map(|timer| timer.times) {
+            Some(Some(times)) => println!("gpu: {}", times.summary()),
+            Some(None) => println!("gpu: no frame timed yet"),
+            None => println!("gpu: the adapter doesn't support timestamp queries"),
+        }
+        println!(
+            "star upload: {} frames waited for a staging buffer",
+            self.star_upload.stalls
+        );
+        let stats = &self.frame_stats;
+        println!(
+            "tree traversal: {} accepted, {} opened",
+            stats.total_accepted(),
+            stats.total_opened()
+        );
+        for depth in 0..stats.accepted.len() {
+            if let Some(ratio) = stats.acceptance_ratio_at(depth) {
+                println!(
+                    "  depth {:>2}: {:>10} accepted {:>10} opened ({:.1}%)",
+                    depth,
+                    stats.accepted[depth],
+                    stats.opened[depth],
+                    ratio * 100.0
+                );
+            }
+        }
     }
 
     pub fn render(&mut self) -> Result<(), SurfaceError> {
@@ -304,44 +1566,486 @@ impl State {
             .texture
             .create_view(&TextureViewDescriptor::default());
 
+        if let Some(follow) = self.follow {
+            self.camera.follow(follow, self.simulation.snapshot());
+        }
         let mut command_encoder = self
             .device
             .create_command_encoder(&CommandEncoderDescriptor::default());
-        {
-            let mut render_pass = command_encoder.begin_render_pass(&RenderPassDescriptor {
-                label: None,
-                color_attachments: &[Some(RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
-                    ops: Operations {
-                        load: LoadOp::Clear(Color::BLACK),
-                        store: true,
-                    },
-                })],
-                depth_stencil_attachment: None,
-            });
+        self.reload_shaders();
+        self.culling.read_stats(&self.device);
+        if let Some(timer) = &mut self.gpu_timer {
+            timer.read_times(&self.device);
+        }
+        // the copy out of the staging buffer has to be encoded before the star pass
+        self.stream_stars(&mut command_encoder);
+        if let Some(timer) = &self.gpu_timer {
+            timer.begin(&mut command_encoder);
+        }
+        self.draw(
+            &mut command_encoder,
+            &view,
+            &self.hdr,
+            self.size,
+            &self.camera,
+        );
+        if let Some(timer) = &mut self.gpu_timer {
+            timer.end(&mut command_encoder);
+        }
 
-            render_pass.set_pipeline(&self.render_pipeline);
-            render_pass.set_push_constants(
-                ShaderStages::VERTEX,
-                0,
-                bytemuck::bytes_of(&self.push_constants),
+        if let Some(comparison) = &self.comparison {
+            comparison.write_stars(&self.queue);
+        }
+        if let Some(trails) = &mut self.trails {
+            trails.write(&self.queue, &self.colors);
+        }
+        if let Some(quiver) = &mut self.quiver {
+            quiver.write(
+                &self.device,
+                &self.queue,
+                self.simulation.snapshot(),
+                &self.camera,
             );
+        }
+        let black_holes: Vec<_> =
+            self.simulation
+                .as_simulation()
+                .map_or(Vec::new(), |simulation| {
+                    simulation
+                        .black_holes()
+                        .filter_map(|id| simulation.stars.get(id))
+                        .filter(|star| star.pos().iter().all(|x| x.is_finite()))
+                        .map(|star| star.pos().cast())
+                        .collect()
+                });
+        let selected = self
+            .selected
+            .and_then(|id| self.simulation.snapshot().get(id))
+            .filter(|star| star.pos().iter().all(|x| x.is_finite()))
+            .map(|star| star.pos().cast());
+        self.markers.write(
+            &self.device,
+            &self.queue,
+            &black_holes,
+            selected,
+            &self.camera,
+        );
+        let probes = self
+            .simulation
+            .as_simulation()
+            .map_or(&[][..], |simulation| &simulation.probes);
+        self.probe_paths
+            .write(&self.device, &self.queue, probes, &self.camera);
+        let prediction = match self.launch {
+            Some(start) => Some(Prediction::Launch(start, self.launch_velocity(start))),
+            None => self.selected.map(Prediction::Star),
+        };
+        self.orbit_preview
+            .write(&self.device, &self.queue, &*self.simulation, prediction);
+        self.overlay.draw(
+            &self.device,
+            &self.queue,
+            &mut command_encoder,
+            &view,
+            self.size,
+        );
+        self.queue.submit(Some(command_encoder.finish()));
+        self.star_upload.submitted();
+        self.culling.submitted();
+        if let Some(timer) = &mut self.gpu_timer {
+            timer.submitted();
+        }
+
+        current_texture.present();
+        Ok(())
+    }
+
+    /// Encodes the star pass into `targets` of size `target_size` using `camera`, followed
+    /// by the post pass from `targets` into the given view.
+    pub fn draw(
+        &self,
+        command_encoder: &mut CommandEncoder,
+        view: &TextureView,
+        targets: &HdrTargets,
+        target_size: PhysicalSize<u32>,
+        camera: &Camera,
+    ) {
+        self.draw_stars(command_encoder, targets, target_size, camera);
+        self.mark(command_encoder, Mark::Stars);
+        self.post.apply(command_encoder, targets, view);
+        self.mark(command_encoder, Mark::Post);
+    }
+
+    /// Writes the timestamp of `mark` if the frame is timed, see `GpuTimer`.
+    fn mark(&self, command_encoder: &mut CommandEncoder, mark: Mark) {
+        if let Some(timer) = &self.gpu_timer {
+            timer.mark(command_encoder, mark);
+        }
+    }
+
+    /// Draws the stars into the hdr target. With msaa, they are drawn into the multisampled
+    /// target and resolved into it.
+    fn draw_stars(
+        &self,
+        command_encoder: &mut CommandEncoder,
+        targets: &HdrTargets,
+        target_size: PhysicalSize<u32>,
+        camera: &Camera,
+    ) {
+        let push_constants = &camera.push_constants();
+        if self.render_path == RenderPath::Culled && self.comparison.is_none() {
+            self.culling.cull(
+                &self.queue,
+                command_encoder,
+                push_constants,
+                self.simulation.snapshot().len(),
+                target_size.height,
+            );
+        }
+        self.mark(command_encoder, Mark::Culled);
+
+        let mut render_pass = command_encoder.begin_render_pass(&RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: targets.msaa_view.as_ref().unwrap_or(&targets.hdr_view),
+                resolve_target: targets.msaa_view.as_ref().map(|_| &targets.hdr_view),
+                ops: Operations {
+                    load: LoadOp::Clear(Color::BLACK),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+
+        if let Some(comparison) = &self.comparison {
+            // split screen, this simulation on the left, the comparison on the right
+            let half_width = target_size.width as f32 / 2.0;
+            let half = PhysicalSize::new(target_size.width / 2, target_size.height);
+            let camera = camera.with_aspect(half).push_constants();
+
+            render_pass.set_pipeline(&self.render_pipeline);
+            render_pass.set_push_constants(ShaderStages::VERTEX, 0, bytemuck::bytes_of(&camera));
             render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-            render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+            render_pass.set_vertex_buffer(2, self.attribute_buffer.slice(..));
             render_pass.set_index_buffer(self.index_buffer.slice(..), IndexFormat::Uint16);
 
-            render_pass.draw_indexed(0..self.index_count, 0, 0..self.instances.len() as u32);
+            for (x, star_buffer) in [
+                (0.0, &self.star_buffer),
+                (half_width, &comparison.star_buffer),
+            ] {
+                render_pass.set_viewport(x, 0.0, half_width, target_size.height as f32, 0.0, 1.0);
+                render_pass.set_vertex_buffer(1, star_buffer.slice(..));
+                render_pass.draw_indexed(
+                    0..self.index_count,
+                    0,
+                    0..self.simulation.snapshot().len() as u32,
+                );
+            }
+            return;
         }
 
-        self.queue.write_buffer(
-            &self.instance_buffer,
+        // trails, arrows, markers, probes and orbits go behind the stars
+        if let Some(trails) = &self.trails {
+            trails.draw(&mut render_pass, push_constants);
+        }
+        if let Some(quiver) = &self.quiver {
+            quiver.draw(&mut render_pass, push_constants);
+        }
+        self.markers.draw(&mut render_pass, push_constants);
+        self.probe_paths.draw(&mut render_pass, push_constants);
+        self.orbit_preview.draw(&mut render_pass, push_constants);
+        match self.render_path {
+            RenderPath::VertexBuffer => {
+                render_pass.set_pipeline(&self.render_pipeline);
+                render_pass.set_vertex_buffer(1, self.star_buffer.slice(..));
+                render_pass.set_vertex_buffer(2, self.attribute_buffer.slice(..));
+            }
+            RenderPath::StorageBuffer => {
+                render_pass.set_pipeline(&self.storage_pipeline);
+                render_pass.set_bind_group(0, &self.storage_bind_group, &[]);
+            }
+            RenderPath::Impostor => {
+                render_pass.set_pipeline(&self.impostor_pipeline);
+                render_pass.set_push_constants(
+                    ShaderStages::VERTEX,
+                    0,
+                    bytemuck::bytes_of(push_constants),
+                );
+                render_pass.set_vertex_buffer(0, self.star_buffer.slice(..));
+                render_pass.set_vertex_buffer(1, self.attribute_buffer.slice(..));
+                render_pass.draw(
+                    0..impostor::VERTEX_COUNT,
+                    0..self.simulation.snapshot().len() as u32,
+                );
+                return;
+            }
+            RenderPath::Culled => {
+                self.culling.draw(
+                    &mut render_pass,
+                    &self.vertex_buffer,
+                    &self.index_buffer,
+                    push_constants,
+                );
+                return;
+            }
+        }
+        render_pass.set_push_constants(ShaderStages::VERTEX, 0, bytemuck::bytes_of(push_constants));
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(self.index_buffer.slice(..), IndexFormat::Uint16);
+
+        render_pass.draw_indexed(
+            0..self.index_count,
             0,
-            bytemuck::cast_slice(&self.instances),
+            0..self.simulation.snapshot().len() as u32,
         );
-        self.queue.submit(Some(command_encoder.finish()));
+    }
 
-        current_texture.present();
-        Ok(())
+    /// Pauses the simulation and saves a supersampled still of the current frame, with long
+    /// trails through the frames of the replay being recorded.
+    pub fn beauty_shot(&mut self) {
+        match self.save_beauty_shot() {
+            Ok(path) => println!("saved beauty shot to {}", path.display()),
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+
+    /// Like `beauty_shot`, returns where it was saved.
+    fn save_beauty_shot(&mut self) -> Result<PathBuf, String> {
+        self.paused = true;
+
+        let name = format!(
+            "beauty_{}.png",
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs()
+        );
+        Paths::file(&self.paths.screenshots, &name)
+            .and_then(|path| {
+                let camera = self.camera;
+                capture::beauty_shot(self, &camera, &path.to_string_lossy())?;
+                Ok(path)
+            })
+            .map_err(|e| format!("failed to save beauty shot: {}", e))
+    }
+
+    /// Pauses the simulation and saves a beauty shot of every panel, named after the step.
+    pub fn capture_panels(&mut self) {
+        self.paused = true;
+
+        let step = self
+            .simulation
+            .as_simulation()
+            .map_or(0, |simulation| simulation.step);
+        for panel in &self.panels {
+            let camera = panel.camera(&self.camera, self.simulation.snapshot());
+            let name = format!("panel_{:08}_{}.png", step, panel.name);
+            let saved = Paths::file(&self.paths.screenshots, &name).and_then(|path| {
+                capture::supersampled(self, &camera, &path.to_string_lossy())?;
+                Ok(path)
+            });
+            match saved {
+                Ok(path) => println!("saved panel {} to {}", panel.name, path.display()),
+                Err(e) => eprintln!("failed to save panel {}: {}", panel.name, e),
+            }
+        }
+    }
+
+    /// Checkpoints the simulation and the star colors to `SNAPSHOT`.
+    pub fn save_snapshot(&self) {
+        let Some(simulation) = self.simulation.as_simulation() else {
+            eprintln!("only Barnes-Hut simulations can be saved");
+            return;
+        };
+        let mut snapshot = Snapshot::of(simulation);
+        snapshot.colors = self.colors.clone();
+        let saved = Paths::file(&self.paths.snapshots, SNAPSHOT).and_then(|path| {
+            snapshot.save(&path).map_err(|e| e.to_string())?;
+            Ok(path)
+        });
+        match saved {
+            Ok(path) => println!("saved snapshot to {}", path.display()),
+            Err(e) => eprintln!("failed to save snapshot: {}", e),
+        }
+    }
+
+    /// Replaces the simulation with the one saved by `save_snapshot`, which can't be undone.
+    pub fn load_snapshot(&mut self) {
+        let path = self.paths.snapshots.join(SNAPSHOT);
+        let snapshot = match Snapshot::load(&path) {
+            Ok(snapshot) => snapshot,
+            Err(e) => {
+                eprintln!("failed to load snapshot: {}", e);
+                return;
+            }
+        };
+        self.simulation = Box::new(snapshot.to_simulation());
+        self.comparison = None;
+        self.history = History::default();
+        self.selected = None;
+        self.pair = None;
+        self.color_mode = ColorMode::Base;
+
+        self.sync_star_count();
+        if snapshot.colors.len() == snapshot.stars.len() {
+            self.colors = snapshot.colors;
+        }
+        self.write_attributes();
+        self.write_stars();
+        println!("loaded snapshot from {}", path.display());
+    }
+}
+
+/// Maps `t` in `[0, 1]` to a color going from dark blue over red to light yellow.
+fn heat(t: f32) -> [f32; 3] {
+    let t = if t.is_finite() {
+        t.clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    [
+        (t * 2.0).min(1.0),
+        (t * 2.0 - 1.0).max(0.0),
+        (0.4 - t * 2.0).max(0.0) + (t * 4.0 - 3.0).max(0.0),
+    ]
+}
+
+/// Format and msaa sample count of the targets stars are drawn into.
+#[derive(Copy, Clone, Debug)]
+pub struct TargetFormat {
+    pub format: TextureFormat,
+    /// 1 if msaa is disabled
+    pub samples: u32,
+}
+
+/// The pipelines of the instanced and the storage render paths, see `stars.wgsl`.
+fn create_star_pipelines(
+    device: &Device,
+    shader: &ShaderModule,
+    storage_bind_group_layout: &BindGroupLayout,
+    target: TargetFormat,
+) -> (RenderPipeline, RenderPipeline) {
+    let push_constant_ranges = &[PushConstantRange {
+        stages: ShaderStages::VERTEX,
+        range: 0..size_of::<PushConstants>() as u32,
+    }];
+    let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+        label: None,
+        bind_group_layouts: &[],
+        push_constant_ranges,
+    });
+    let instanced = create_star_pipeline(
+        device,
+        &layout,
+        shader,
+        "vs_instanced",
+        &[
+            VertexBufferLayout {
+                array_stride: size_of::<Vertex>() as u64,
+                step_mode: VertexStepMode::Vertex,
+                attributes: Vertex::ATTRIBS,
+            },
+            VertexBufferLayout {
+                array_stride: size_of::<GpuStar>() as u64,
+                step_mode: VertexStepMode::Instance,
+                attributes: STAR_ATTRIBS,
+            },
+            VertexBufferLayout {
+                array_stride: size_of::<StarAttributes>() as u64,
+                step_mode: VertexStepMode::Instance,
+                attributes: StarAttributes::ATTRIBS,
+            },
+        ],
+        target,
+    );
+
+    let storage_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+        label: None,
+        bind_group_layouts: &[storage_bind_group_layout],
+        push_constant_ranges,
+    });
+    let storage = create_star_pipeline(
+        device,
+        &storage_layout,
+        shader,
+        "vs_storage",
+        &[VertexBufferLayout {
+            array_stride: size_of::<Vertex>() as u64,
+            step_mode: VertexStepMode::Vertex,
+            attributes: Vertex::ATTRIBS,
+        }],
+        target,
+    );
+    (instanced, storage)
+}
+
+/// A pipeline drawing the star meshes with the vertex entry point `vertex_entry` of the
+/// star shader, and its fragment entry point `fs_main`.
+pub fn create_star_pipeline(
+    device: &Device,
+    layout: &PipelineLayout,
+    shader: &ShaderModule,
+    vertex_entry: &str,
+    buffers: &[VertexBufferLayout],
+    target: TargetFormat,
+) -> RenderPipeline {
+    device.create_render_pipeline(&RenderPipelineDescriptor {
+        label: None,
+        layout: Some(layout),
+        vertex: VertexState {
+            module: shader,
+            entry_point: vertex_entry,
+            buffers,
+        },
+        primitive: PrimitiveState {
+            cull_mode: Some(Face::Back),
+            conservative: true,
+            ..Default::default()
+        },
+        depth_stencil: None,
+        multisample: MultisampleState {
+            count: target.samples,
+            ..Default::default()
+        },
+        fragment: Some(FragmentState {
+            module: shader,
+            entry_point: "fs_main",
+            targets: &[Some(ColorTargetState {
+                format: target.format,
+                blend: Some(ADDITIVE),
+                write_mask: ColorWrites::ALL,
+            })],
+        }),
+        multiview: None,
+    })
+}
+
+/// The color of star `id`, from `added` if it was added after the first `colors.len()`
+/// stars, which is grown as needed.
+/// Moves colors along with a sort of the stars, `order` is the previous id at each id.
+/// Stars beyond `colors` keep theirs in `added`, like in `color_mut`.
+fn reorder_colors(colors: &mut Vec<[f32; 3]>, added: &mut Vec<[f32; 3]>, order: &[StarId]) {
+    let known = colors.len().min(order.len());
+    let mut sorted: Vec<_> = order
+        .iter()
+        .map(|&id| *color_mut(colors, added, id))
+        .collect();
+    *added = sorted.split_off(known);
+    *colors = sorted;
+}
+
+fn color_mut<'a>(
+    colors: &'a mut [[f32; 3]],
+    added: &'a mut Vec<[f32; 3]>,
+    id: StarId,
+) -> &'a mut [f32; 3] {
+    match id.checked_sub(colors.len()) {
+        None => &mut colors[id],
+        Some(index) => {
+            if added.len() <= index {
+                added.resize(index + 1, [1.0; 3]);
+            }
+            &mut added[index]
+        }
     }
 }
@@ -0,0 +1,152 @@
+use crate::camera::{Camera, Follow};
+use crate::compare::Comparison;
+use crate::history::History;
+use crate::markers::Markers;
+use crate::probes::ProbePaths;
+use crate::quiver::Quiver;
+use crate::state::{ColorMode, State};
+use crate::trails::Trails;
+use gravsim_simulation::backend::SimulationBackend;
+use gravsim_simulation::scenario::Scenario;
+use gravsim_simulation::StarId;
+use std::mem::swap;
+use std::path::Path;
+
+/// A simulation in the background, with everything that belongs to it rather than to the
+/// window: its camera, view settings and edit history. The active tab lives in the fields
+/// of `State` and trades places with a parked one when switching, see `State::switch_tab`.
+pub struct Tab {
+    simulation: Box<dyn SimulationBackend>,
+    colors: Vec<[f32; 3]>,
+    camera: Camera,
+    paused: bool,
+    color_mode: ColorMode,
+    trails: Option<Trails>,
+    quiver: Option<Quiver>,
+    markers: Markers,
+    probe_paths: ProbePaths,
+    comparison: Option<Comparison>,
+    history: History,
+    selected: Option<StarId>,
+    pair: Option<(StarId, StarId)>,
+    follow: Option<Follow>,
+}
+
+/// Names of all tabs and the parked ones, so several prepared scenarios can be flipped
+/// through without restarting the viewer.
+pub struct Tabs {
+    pub names: Vec<String>,
+    /// by index, `None` for the active tab
+    parked: Vec<Option<Tab>>,
+    pub active: usize,
+}
+
+impl Tabs {
+    /// Just the active tab.
+    pub fn new(name: String) -> Self {
+        Self {
+            names: vec![name],
+            parked: vec![None],
+            active: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// `offset` tabs after the active one, wrapping around.
+    pub fn relative(&self, offset: isize) -> usize {
+        (self.active as isize + offset).rem_euclid(self.len() as isize) as usize
+    }
+}
+
+impl State {
+    /// Parks a new tab with `simulation`, viewed to fit all of its stars. Returns its index.
+    pub fn add_tab(
+        &mut self,
+        name: String,
+        simulation: Box<dyn SimulationBackend>,
+        colors: Vec<[f32; 3]>,
+    ) -> usize {
+        let mut camera = self.camera;
+        camera.fit(simulation.snapshot());
+        let tab = Tab {
+            simulation,
+            colors,
+            camera,
+            paused: false,
+            color_mode: ColorMode::Base,
+            trails: None,
+            quiver: None,
+            markers: Markers::new(&self.device, self.target),
+            probe_paths: ProbePaths::new(&self.device, self.target),
+            comparison: None,
+            history: History::default(),
+            selected: None,
+            pair: None,
+            follow: None,
+        };
+        self.tabs.names.push(name);
+        self.tabs.parked.push(Some(tab));
+        self.tabs.len() - 1
+    }
+
+    /// Parks the active tab and brings up the tab at `index`.
+    pub fn switch_tab(&mut self, index: usize) -> Result<(), String> {
+        if index == self.tabs.active {
+            return Ok(());
+        }
+        let mut tab = self
+            .tabs
+            .parked
+            .get_mut(index)
+            .and_then(Option::take)
+            .ok_or_else(|| format!("no tab {}", index + 1))?;
+
+        swap(&mut self.simulation, &mut tab.simulation);
+        swap(&mut self.colors, &mut tab.colors);
+        swap(&mut self.camera, &mut tab.camera);
+        swap(&mut self.paused, &mut tab.paused);
+        swap(&mut self.color_mode, &mut tab.color_mode);
+        swap(&mut self.trails, &mut tab.trails);
+        swap(&mut self.quiver, &mut tab.quiver);
+        swap(&mut self.markers, &mut tab.markers);
+        swap(&mut self.probe_paths, &mut tab.probe_paths);
+        swap(&mut self.comparison, &mut tab.comparison);
+        swap(&mut self.history, &mut tab.history);
+        swap(&mut self.selected, &mut tab.selected);
+        swap(&mut self.pair, &mut tab.pair);
+        swap(&mut self.follow, &mut tab.follow);
+        self.tabs.parked[self.tabs.active] = Some(tab);
+        self.tabs.active = index;
+
+        // the window may have been resized while the tab was parked
+        self.camera = self.camera.with_aspect(self.size);
+        self.recreate_star_buffers();
+        self.sync_star_count();
+        self.write_attributes();
+        self.write_stars();
+        Ok(())
+    }
+
+    /// Switches to the tab `offset` tabs after the active one, wrapping around.
+    pub fn cycle_tab(&mut self, offset: isize) {
+        let index = self.tabs.relative(offset);
+        if let Err(e) = self.switch_tab(index) {
+            eprintln!("{}", e);
+        }
+    }
+
+    /// Opens the scenario at `path` in a new tab and switches to it.
+    pub fn open_tab(&mut self, path: &Path) -> Result<(), String> {
+        let scenario = Scenario::from_path(path)?;
+        let (simulation, colors) = crate::initial_simulation(scenario, None, false);
+        let index = self.add_tab(crate::tab_name(path), simulation, colors);
+        self.switch_tab(index)
+    }
+}
@@ -0,0 +1,175 @@
+use std::cell::Cell;
+use std::mem::size_of;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use wgpu::{
+    Buffer, BufferDescriptor, BufferUsages, CommandEncoder, Device, Maintain, MapMode, QuerySet,
+    QuerySetDescriptor, QueryType, Queue,
+};
+
+/// Points of a frame a timestamp is written at, in order.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Mark {
+    Start,
+    /// after the culling compute pass, right after `Start` on other render paths
+    Culled,
+    /// after the star pass, including trails and markers
+    Stars,
+    /// after bloom and tonemapping
+    Post,
+}
+
+/// How long the gpu took for the passes of a frame.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct GpuTimes {
+    pub cull: Duration,
+    pub stars: Duration,
+    pub post: Duration,
+}
+
+impl GpuTimes {
+    pub fn total(&self) -> Duration {
+        self.cull + self.stars + self.post
+    }
+
+    /// e.g. `"4.10ms (cull 0.20, stars 3.50, post 0.40)"`
+    pub fn summary(&self) -> String {
+        let millis = |time: Duration| time.as_secs_f32() * 1000.0;
+        format!(
+            "{:.2}ms (cull {:.2}, stars {:.2}, post {:.2})",
+            millis(self.total()),
+            millis(self.cull),
+            millis(self.stars),
+            millis(self.post)
+        )
+    }
+}
+
+/// Timestamp queries around the passes of the frames presented in the window, for telling
+/// gpu bound frames from cpu bound ones. Needs `Features::TIMESTAMP_QUERY`, which not every
+/// adapter has. Like `Culling`, the results are read back without waiting for the gpu, so
+/// `times` lags a few frames behind.
+pub struct GpuTimer {
+    query_set: QuerySet,
+    /// the resolved timestamps, copied into `readback`
+    resolved: Buffer,
+    readback: Buffer,
+    /// nanoseconds per timestamp tick
+    period: f32,
+    /// whether the frame being encoded is timed, captures aren't
+    timing: Cell<bool>,
+    /// whether a copy into `readback` was encoded and still has to be mapped
+    copied: bool,
+    /// whether `readback` is being mapped
+    mapping: bool,
+    mapped: Arc<AtomicBool>,
+    /// of the last frame that was read back, `None` until the first one is
+    pub times: Option<GpuTimes>,
+}
+
+impl GpuTimer {
+    const COUNT: u32 = 4;
+    const SIZE: u64 = Self::COUNT as u64 * size_of::<u64>() as u64;
+
+    pub fn new(device: &Device, queue: &Queue) -> Self {
+        let query_set = device.create_query_set(&QuerySetDescriptor {
+            label: Some("gpu timer"),
+            ty: QueryType::Timestamp,
+            count: Self::COUNT,
+        });
+        let buffer = |label, usage| {
+            device.create_buffer(&BufferDescriptor {
+                label: Some(label),
+                size: Self::SIZE,
+                usage,
+                mapped_at_creation: false,
+            })
+        };
+        Self {
+            query_set,
+            resolved: buffer("timestamps", BufferUsages::COPY_SRC),
+            readback: buffer(
+                "timestamps readback",
+                BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+            ),
+            period: queue.get_timestamp_period(),
+            timing: Cell::new(false),
+            copied: false,
+            mapping: false,
+            mapped: Arc::new(AtomicBool::new(false)),
+            times: None,
+        }
+    }
+
+    /// Starts timing the frame encoded into `command_encoder`, unless the readback of an
+    /// earlier frame is still in flight.
+    pub fn begin(&self, command_encoder: &mut CommandEncoder) {
+        if self.mapping {
+            return;
+        }
+        self.timing.set(true);
+        self.mark(command_encoder, Mark::Start);
+    }
+
+    /// Writes the timestamp of `mark` if the frame is timed.
+    pub fn mark(&self, command_encoder: &mut CommandEncoder, mark: Mark) {
+        if self.timing.get() {
+            command_encoder.write_timestamp(&self.query_set, mark as u32);
+        }
+    }
+
+    /// Encodes copying the timestamps of a timed frame out after its last pass.
+    pub fn end(&mut self, command_encoder: &mut CommandEncoder) {
+        if !self.timing.replace(false) {
+            return;
+        }
+        command_encoder.resolve_query_set(&self.query_set, 0..Self::COUNT, &self.resolved, 0);
+        command_encoder.copy_buffer_to_buffer(&self.resolved, 0, &self.readback, 0, Self::SIZE);
+        self.copied = true;
+    }
+
+    /// Maps the readback of the last `end`, call after submitting its commands.
+    pub fn submitted(&mut self) {
+        if !std::mem::replace(&mut self.copied, false) {
+            return;
+        }
+        self.mapping = true;
+        let mapped = self.mapped.clone();
+        self.readback
+            .slice(..)
+            .map_async(MapMode::Read, move |result| {
+                mapped.store(result.is_ok(), Ordering::Release)
+            });
+    }
+
+    /// Updates `times` if the readback finished mapping, without blocking.
+    pub fn read_times(&mut self, device: &Device) {
+        if !self.mapping {
+            return;
+        }
+        device.poll(Maintain::Poll);
+        if !self.mapped.swap(false, Ordering::Acquire) {
+            return;
+        }
+
+        let ticks: Vec<u64> = self
+            .readback
+            .slice(..)
+            .get_mapped_range()
+            .chunks_exact(size_of::<u64>())
+            .map(|tick| u64::from_ne_bytes(tick.try_into().unwrap()))
+            .collect();
+        self.readback.unmap();
+        self.mapping = false;
+        let span = |from: Mark, to: Mark| {
+            let ticks = ticks[to as usize].saturating_sub(ticks[from as usize]);
+            Duration::from_nanos((ticks as f64 * self.period as f64) as u64)
+        };
+        self.times = Some(GpuTimes {
+            cull: span(Mark::Start, Mark::Culled),
+            stars: span(Mark::Culled, Mark::Stars),
+            post: span(Mark::Stars, Mark::Post),
+        });
+    }
+}
@@ -0,0 +1,248 @@
+use crate::camera::PushConstants;
+use crate::state::TargetFormat;
+use bytemuck::{Pod, Zeroable};
+use gravsim_simulation::replay::Replay;
+use gravsim_simulation::{Star, StarId};
+use std::mem::size_of;
+use std::path::Path;
+use wgpu::{
+    include_wgsl, vertex_attr_array, BlendState, Buffer, BufferDescriptor, BufferUsages,
+    ColorTargetState, ColorWrites, Device, FragmentState, MultisampleState,
+    PipelineLayoutDescriptor, PrimitiveState, PrimitiveTopology, PushConstantRange, Queue,
+    RenderPass, RenderPipeline, RenderPipelineDescriptor, ShaderStages, VertexAttribute,
+    VertexBufferLayout, VertexState, VertexStepMode,
+};
+
+/// A vertex of a line segment, e.g. of a trail faded by its age.
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+pub struct TrailVertex {
+    position: [f32; 2],
+    color: [f32; 4],
+}
+
+impl TrailVertex {
+    pub const ATTRIBS: &'static [VertexAttribute] =
+        &vertex_attr_array![0 => Float32x2, 1 => Float32x4];
+
+    pub fn new(position: [f32; 2], color: [f32; 4]) -> Self {
+        Self { position, color }
+    }
+}
+
+/// Fading lines behind a subset of the stars, through their positions of the last `length`
+/// frames. The positions are kept in a ring buffer per star and uploaded as a line list.
+pub struct Trails {
+    pub pipeline: RenderPipeline,
+    pub vertex_buffer: Buffer,
+
+    /// stars with a trail
+    stars: Vec<StarId>,
+    /// number of stars when `stars` was chosen, the ids are stale once it changes
+    star_count: usize,
+    /// positions per trail
+    length: usize,
+    /// `length` positions per star in `stars`, the newest at `head`
+    positions: Vec<[f32; 2]>,
+    head: usize,
+    /// number of frames recorded, up to `length`
+    recorded: usize,
+    /// number of vertices written by the last `write`
+    vertex_count: u32,
+}
+
+impl Trails {
+    /// Positions per trail of the interactive view.
+    pub const LENGTH: usize = 64;
+    /// Positions per trail of beauty shots, see `from_replay`.
+    pub const LONG_LENGTH: usize = 2048;
+    /// Upper bound of trails, the others are spread evenly over all stars.
+    pub const MAX_TRAILS: usize = 1024;
+
+    pub fn new(device: &Device, target: TargetFormat, stars: &[Star]) -> Self {
+        Self::with_length(device, target, stars, Self::LENGTH)
+    }
+
+    pub fn with_length(
+        device: &Device,
+        target: TargetFormat,
+        stars: &[Star],
+        length: usize,
+    ) -> Self {
+        let mut trails = Self {
+            pipeline: line_pipeline(device, target),
+            vertex_buffer: vertex_buffer(device, 0),
+            stars: Vec::new(),
+            star_count: 0,
+            length: length.max(2),
+            positions: Vec::new(),
+            head: 0,
+            recorded: 0,
+            vertex_count: 0,
+        };
+        trails.reset(device, stars);
+        trails
+    }
+
+    /// Trails of `length` positions through the frames of the replay at `path`, up to its
+    /// last frame.
+    pub fn from_replay(
+        device: &Device,
+        target: TargetFormat,
+        path: &Path,
+        length: usize,
+    ) -> Result<Self, String> {
+        let mut replay = Replay::open(path)
+            .map_err(|e| format!("failed to read the replay {}: {}", path.display(), e))?;
+        let mut trails = Self::with_length(device, target, replay.stars(), length);
+        loop {
+            trails.record(device, replay.stars());
+            match replay.advance() {
+                Ok(true) => {}
+                Ok(false) => return Ok(trails),
+                Err(e) => return Err(format!("invalid replay {}: {}", path.display(), e)),
+            }
+        }
+    }
+
+    /// Chooses the stars with a trail and forgets all recorded positions.
+    fn reset(&mut self, device: &Device, stars: &[Star]) {
+        let stride = stars.len().div_ceil(Self::MAX_TRAILS).max(1);
+        self.stars = (0..stars.len()).step_by(stride).collect();
+        self.star_count = stars.len();
+        self.positions = vec![[f32::NAN; 2]; self.stars.len() * self.length];
+        self.head = 0;
+        self.recorded = 0;
+        self.vertex_count = 0;
+        self.vertex_buffer = vertex_buffer(device, self.stars.len() * (self.length - 1) * 2);
+    }
+
+    /// Follows the stars with a trail when the stars are sorted, `order` is the previous id
+    /// at each id.
+    pub fn reorder(&mut self, order: &[StarId]) {
+        let mut new_ids = vec![0; order.len()];
+        for (new, &old) in order.iter().enumerate() {
+            new_ids[old] = new;
+        }
+        for id in &mut self.stars {
+            *id = new_ids.get(*id).copied().unwrap_or(*id);
+        }
+    }
+
+    /// Appends the current position of every star with a trail, dropping the oldest one.
+    /// Starts over if stars were added or removed, as ids may have shifted.
+    pub fn record(&mut self, device: &Device, stars: &[Star]) {
+        if stars.len() != self.star_count {
+            self.reset(device, stars);
+        }
+
+        self.head = (self.head + 1) % self.length;
+        for (trail, &id) in self
+            .positions
+            .chunks_exact_mut(self.length)
+            .zip(&self.stars)
+        {
+            let pos = stars[id].pos();
+            trail[self.head] = [pos.x as f32, pos.y as f32];
+        }
+        self.recorded = (self.recorded + 1).min(self.length);
+    }
+
+    /// Uploads the segments of all trails, colored like their star and fading with age.
+    /// Segments touching stars that left the simulation are skipped.
+    pub fn write(&mut self, queue: &Queue, colors: &[[f32; 3]]) {
+        let length = self.length;
+        let mut vertices = Vec::with_capacity(self.stars.len() * (length - 1) * 2);
+        for (trail, &id) in self.positions.chunks_exact(length).zip(&self.stars) {
+            let [r, g, b] = colors.get(id).copied().unwrap_or([1.0; 3]);
+            // from the newest position backwards
+            let vertex = |age: usize| {
+                let position = trail[(self.head + length - age) % length];
+                let alpha = 0.6 * (1.0 - age as f32 / length as f32);
+                TrailVertex {
+                    position,
+                    color: [r, g, b, alpha],
+                }
+            };
+            for age in 1..self.recorded {
+                let (newer, older) = (vertex(age - 1), vertex(age));
+                if newer
+                    .position
+                    .iter()
+                    .chain(&older.position)
+                    .all(|x| x.is_finite())
+                {
+                    vertices.extend([newer, older]);
+                }
+            }
+        }
+
+        queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&vertices));
+        self.vertex_count = vertices.len() as u32;
+    }
+
+    /// Draws the segments of the last `write`.
+    pub fn draw<'a>(&'a self, render_pass: &mut RenderPass<'a>, camera: &PushConstants) {
+        if self.vertex_count == 0 {
+            return;
+        }
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_push_constants(ShaderStages::VERTEX, 0, bytemuck::bytes_of(camera));
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.draw(0..self.vertex_count, 0..1);
+    }
+}
+
+/// A pipeline drawing alpha blended `TrailVertex` line lists, shared by trails and markers.
+pub fn line_pipeline(device: &Device, target: TargetFormat) -> RenderPipeline {
+    let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+        label: None,
+        bind_group_layouts: &[],
+        push_constant_ranges: &[PushConstantRange {
+            stages: ShaderStages::VERTEX,
+            range: 0..size_of::<PushConstants>() as u32,
+        }],
+    });
+    let shader = device.create_shader_module(include_wgsl!("shaders/trails.wgsl"));
+    device.create_render_pipeline(&RenderPipelineDescriptor {
+        label: Some("lines"),
+        layout: Some(&layout),
+        vertex: VertexState {
+            module: &shader,
+            entry_point: "vs_main",
+            buffers: &[VertexBufferLayout {
+                array_stride: size_of::<TrailVertex>() as u64,
+                step_mode: VertexStepMode::Vertex,
+                attributes: TrailVertex::ATTRIBS,
+            }],
+        },
+        primitive: PrimitiveState {
+            topology: PrimitiveTopology::LineList,
+            ..Default::default()
+        },
+        depth_stencil: None,
+        multisample: MultisampleState {
+            count: target.samples,
+            ..Default::default()
+        },
+        fragment: Some(FragmentState {
+            module: &shader,
+            entry_point: "fs_main",
+            targets: &[Some(ColorTargetState {
+                format: target.format,
+                blend: Some(BlendState::ALPHA_BLENDING),
+                write_mask: ColorWrites::ALL,
+            })],
+        }),
+        multiview: None,
+    })
+}
+
+pub fn vertex_buffer(device: &Device, vertices: usize) -> Buffer {
+    device.create_buffer(&BufferDescriptor {
+        label: Some("lines"),
+        size: (vertices.max(1) * size_of::<TrailVertex>()) as u64,
+        usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
@@ -0,0 +1,114 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use wgpu::{
+    Buffer, BufferAddress, BufferDescriptor, BufferUsages, CommandEncoder, Device, Maintain,
+    MapMode,
+};
+
+/// A staging buffer and whether it is mapped, i.e. the gpu is done copying out of it.
+struct Staging {
+    buffer: Buffer,
+    mapped: Arc<AtomicBool>,
+}
+
+/// Streams per frame data (e.g. the star instances) to a gpu buffer through a ring of
+/// persistently mapped staging buffers. The copy is encoded into the frame's commands,
+/// so unlike `Queue::write_buffer` it doesn't go through a temporary allocation, and
+/// writing the next frame's data doesn't wait for the gpu to finish the last copy.
+pub struct StagingRing {
+    staging: Vec<Staging>,
+    size: BufferAddress,
+    /// staging buffer of the next upload
+    next: usize,
+    /// staging buffer of the last upload, mapped again once it was submitted
+    submitted: Option<usize>,
+    /// uploads that found no mapped staging buffer, i.e. the gpu was more than
+    /// `FRAMES` frames behind
+    pub stalls: u64,
+}
+
+impl StagingRing {
+    /// Frames in flight, 2 is double buffering.
+    pub const FRAMES: usize = 2;
+
+    pub fn new(device: &Device, size: BufferAddress) -> Self {
+        let staging = (0..Self::FRAMES)
+            .map(|_| Staging {
+                buffer: device.create_buffer(&BufferDescriptor {
+                    label: Some("staging"),
+                    size,
+                    usage: BufferUsages::MAP_WRITE | BufferUsages::COPY_SRC,
+                    mapped_at_creation: true,
+                }),
+                mapped: Arc::new(AtomicBool::new(true)),
+            })
+            .collect();
+        Self {
+            staging,
+            size,
+            next: 0,
+            submitted: None,
+            stalls: 0,
+        }
+    }
+
+    /// Writes `bytes` into the next staging buffer and encodes copying them to the start of
+    /// `target`, before whatever is encoded afterwards. Returns `false` without encoding
+    /// anything if that staging buffer is still in use, callers then upload another way.
+    /// The staging buffers are recreated if the size of `bytes` changed.
+    pub fn upload(
+        &mut self,
+        device: &Device,
+        command_encoder: &mut CommandEncoder,
+        bytes: &[u8],
+        target: &Buffer,
+    ) -> bool {
+        let size = bytes.len() as BufferAddress;
+        if size == 0 {
+            return true;
+        }
+        if size != self.size {
+            *self = Self {
+                stalls: self.stalls,
+                ..Self::new(device, size)
+            };
+        }
+
+        // runs the callbacks of finished mappings
+        device.poll(Maintain::Poll);
+        let staging = &self.staging[self.next];
+        if !staging.mapped.load(Ordering::Acquire) {
+            self.stalls += 1;
+            return false;
+        }
+
+        staging
+            .buffer
+            .slice(..)
+            .get_mapped_range_mut()
+            .copy_from_slice(bytes);
+        staging.buffer.unmap();
+        staging.mapped.store(false, Ordering::Release);
+        command_encoder.copy_buffer_to_buffer(&staging.buffer, 0, target, 0, size);
+
+        self.submitted = Some(self.next);
+        self.next = (self.next + 1) % self.staging.len();
+        true
+    }
+
+    /// Maps the staging buffer of the last `upload` again, call after submitting its commands.
+    /// The mapping only completes once the gpu is done with the copy.
+    pub fn submitted(&mut self) {
+        let Some(index) = self.submitted.take() else {
+            return;
+        };
+        let staging = &self.staging[index];
+        let mapped = staging.mapped.clone();
+        staging
+            .buffer
+            .slice(..)
+            .map_async(MapMode::Write, move |result| {
+                mapped.store(result.is_ok(), Ordering::Release)
+            });
+    }
+}
@@ -0,0 +1,104 @@
+//! Tree build and force evaluation on clustered bodies, which make for far deeper trees than
+//! the uniform test data of `gravity`. The bodies are generated, a million of them would be
+//! too large to check in.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use gravsim_simulation::direct::DirectSum;
+use gravsim_simulation::tree::FlatTree;
+use gravsim_simulation::{MassData, Real, SimulationConfig};
+use nalgebra::Vector2;
+use rand::{Rng, SeedableRng};
+use rand_xorshift::XorShiftRng;
+
+/// Sizes of the clustered datasets.
+const SIZES: [usize; 3] = [50_000, 100_000, 1_000_000];
+/// Sizes small enough for the direct sum, to compare the tree against.
+const DIRECT_SIZES: [usize; 2] = [1_000, 5_000];
+/// Half the size of the square the clusters are in, like the uniform test data.
+const EXTENT: Real = 500.0;
+const CLUSTERS: usize = 16;
+
+/// `n` bodies of equal mass in `CLUSTERS` Plummer spheres of random position and scale,
+/// projected onto the plane. The same `n` always gives the same bodies.
+fn plummer_clusters(n: usize) -> Vec<MassData> {
+    let mut rng = XorShiftRng::seed_from_u64(n as u64);
+    let clusters: Vec<(Vector2<Real>, Real)> = (0..CLUSTERS)
+        .map(|_| {
+            let center = Vector2::from_fn(|_, _| rng.gen_range(-0.7 * EXTENT..0.7 * EXTENT));
+            (center, rng.gen_range(0.005 * EXTENT..0.04 * EXTENT))
+        })
+        .collect();
+
+    let mut bodies = Vec::with_capacity(n);
+    while bodies.len() < n {
+        let (center, scale) = clusters[bodies.len() % CLUSTERS];
+        // inverse of the cumulative mass of a Plummer sphere, cut off at 20 scale radii
+        let mass_fraction: Real = rng.gen_range(1e-6..0.996);
+        let radius = scale / (mass_fraction.powf(-2.0 / 3.0) - 1.0).sqrt();
+        // the projection of a uniformly random direction
+        let cos: Real = rng.gen_range(-1.0..1.0);
+        let angle: Real = rng.gen_range(0.0..std::f64::consts::TAU as Real);
+        let projected = radius * (1.0 - cos * cos).sqrt();
+        let position = center + Vector2::new(angle.cos(), angle.sin()) * projected;
+        if position.iter().all(|x| x.abs() < EXTENT) {
+            bodies.push(MassData {
+                position,
+                mass: 1.0,
+            });
+        }
+    }
+    bodies
+}
+
+fn build(bodies: &[MassData]) -> FlatTree {
+    let mut tree =
+        FlatTree::with_capacity(Vector2::repeat(-EXTENT), 2.0 * EXTENT, 2 * bodies.len());
+    for body in bodies {
+        tree.insert(body);
+    }
+    tree.summarize();
+    tree
+}
+
+fn build_tree(c: &mut Criterion) {
+    let mut group = c.benchmark_group("build-tree clustered");
+    group.sample_size(10);
+    for n in SIZES {
+        let bodies = plummer_clusters(n);
+        group.throughput(Throughput::Elements(n as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(n), &bodies, |b, bodies| {
+            b.iter(|| build(bodies))
+        });
+    }
+    group.finish();
+}
+
+/// Forces on every body from a tree built beforehand, and the direct sum at small sizes.
+fn forces(c: &mut Criterion) {
+    let config = SimulationConfig::default();
+    let mut group = c.benchmark_group("forces clustered");
+    group.sample_size(10);
+    for n in DIRECT_SIZES.into_iter().chain(SIZES) {
+        let bodies = plummer_clusters(n);
+        let tree = build(&bodies);
+        group.throughput(Throughput::Elements(n as u64));
+        group.bench_with_input(BenchmarkId::new("tree", n), &bodies, |b, bodies| {
+            b.iter(|| {
+                bodies
+                    .iter()
+                    .map(|body| tree.force_on(body, &config))
+                    .collect::<Vec<_>>()
+            })
+        });
+        if DIRECT_SIZES.contains(&n) {
+            let direct = DirectSum::new(bodies);
+            group.bench_function(BenchmarkId::new("direct", n), |b| {
+                b.iter(|| direct.forces(&config))
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(clustered, build_tree, forces);
+criterion_main!(clustered);
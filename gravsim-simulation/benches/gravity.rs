@@ -1,6 +1,9 @@
 use criterion::{criterion_group, criterion_main, Criterion};
-use gravsim_simulation::tree::Node;
-use gravsim_simulation::{MassData, MassDistribution, Simulation, Star};
+use gravsim_simulation::fmm::Fmm;
+use gravsim_simulation::reuse::TreeReuse;
+use gravsim_simulation::solver::{BarnesHut, ForceSolver};
+use gravsim_simulation::tree::{FlatTree, Node};
+use gravsim_simulation::{MassData, Real, Simulation, SimulationConfig, Star};
 use nalgebra::Vector2;
 use once_cell::sync::OnceCell;
 
@@ -23,6 +26,7 @@ fn build_tree(c: &mut Criterion) {
             for mass_data in objs_1k {
                 tree.insert(mass_data);
             }
+            tree.summarize();
             tree
         })
     });
@@ -33,6 +37,30 @@ fn build_tree(c: &mut Criterion) {
             for mass_data in objs_5k {
                 tree.insert(mass_data);
             }
+            tree.summarize();
+            tree
+        })
+    });
+
+    c.bench_function("build-flat-tree 1k", |b| {
+        b.iter(|| {
+            let mut tree = FlatTree::with_capacity(Vector2::repeat(-500.0), 1000.0, 2000);
+
+            for mass_data in objs_1k {
+                tree.insert(mass_data);
+            }
+            tree.summarize();
+            tree
+        })
+    });
+    c.bench_function("build-flat-tree 5k", |b| {
+        b.iter(|| {
+            let mut tree = FlatTree::with_capacity(Vector2::repeat(-500.0), 1000.0, 10_000);
+
+            for mass_data in objs_5k {
+                tree.insert(mass_data);
+            }
+            tree.summarize();
             tree
         })
     });
@@ -51,7 +79,6 @@ fn update_simulation(c: &mut Criterion) {
         objs_1k
             .iter()
             .map(|obj| Star::new(obj.position, Vector2::zeros(), obj.mass)),
-        MassDistribution::new(1.0, 1.0),
     );
     c.bench_function("step 1k", |b| b.iter(|| simulation.update()));
 
@@ -59,12 +86,40 @@ fn update_simulation(c: &mut Criterion) {
         objs_5k
             .iter()
             .map(|obj| Star::new(obj.position, Vector2::zeros(), obj.mass)),
-        MassDistribution::new(1.0, 1.0),
     );
     c.bench_function("step 5k", |b| b.iter(|| simulation.update()));
+
+    // the test data is in random order, sorting keeps neighbors close in memory
+    let mut simulation = Simulation::new(
+        objs_5k
+            .iter()
+            .map(|obj| Star::new(obj.position, Vector2::zeros(), obj.mass)),
+    );
+    simulation.config.sort_every = Some(16);
+    c.bench_function("step 5k morton sorted", |b| b.iter(|| simulation.update()));
+
+    let mut simulation = Simulation::new(
+        objs_5k
+            .iter()
+            .map(|obj| Star::new(obj.position, Vector2::zeros(), obj.mass)),
+    );
+    simulation.config.reuse_tree = Some(TreeReuse::default());
+    c.bench_function("step 5k reused tree", |b| b.iter(|| simulation.update()));
+}
+
+fn force_solvers(c: &mut Criterion) {
+    let objs_5k = OBJS_5K
+        .get_or_try_init(|| bincode::deserialize(include_bytes!("test_data/stars_5k.bin")))
+        .unwrap();
+
+    let config = SimulationConfig::default();
+    c.bench_function("barnes-hut 5k", |b| {
+        b.iter(|| BarnesHut.forces(objs_5k, &config))
+    });
+    c.bench_function("fmm 5k", |b| b.iter(|| Fmm.forces(objs_5k, &config)));
 }
 
-criterion_group!(gravity, update_simulation);
+criterion_group!(gravity, build_tree, update_simulation, force_solvers);
 criterion_main!(gravity);
 
 // #[test]
@@ -78,7 +133,7 @@ pub fn gen_test_data() {
     for n in [1000, 5000] {
         let mass_datas: Vec<_> = (0..n)
             .map(|_| MassData {
-                position: Vector2::from_fn(|_, _| rng.gen::<f32>() * 1000.0 - 500.0),
+                position: Vector2::from_fn(|_, _| rng.gen::<Real>() * 1000.0 - 500.0),
                 mass: 1.0,
             })
             .collect();
@@ -0,0 +1,42 @@
+//! A light star on an eccentric orbit around a heavy one, integrated with `Verlet`.
+//! Prints the Keplerian elements of the pair, which should barely change, and the drift
+//! of the total energy at the end.
+//!
+//! `cargo run --example basic_orbit`
+
+use gravsim_simulation::integrator::Verlet;
+use gravsim_simulation::kepler::Orbit;
+use gravsim_simulation::{Real, Simulation, SimulationConfig, Star};
+use nalgebra::Vector2;
+
+fn main() {
+    let config = SimulationConfig::default();
+    let sun = Star::new(Vector2::zeros(), Vector2::zeros(), 1e6);
+    // faster than on a circular orbit, so this is the pericenter
+    let distance: Real = 1000.0;
+    let speed = 1.2 * (config.gravity * sun.mass() / distance).sqrt();
+    let planet = Star::new(Vector2::new(distance, 0.0), Vector2::new(0.0, speed), 1.0);
+
+    let mut simulation = Simulation::with_config([sun, planet], config);
+    simulation.set_integrator(Box::new(Verlet));
+    let initial = simulation.diagnostics().total_energy();
+
+    for _ in 0..12 {
+        let [sun, planet] = [&simulation.stars[0], &simulation.stars[1]];
+        if let Some(orbit) = Orbit::of(sun, planet, config.gravity) {
+            println!(
+                "t {:>6}: distance {:>7.1} semi-major axis {:.1} eccentricity {:.4}",
+                simulation.time, orbit.separation, orbit.semi_major_axis, orbit.eccentricity
+            );
+        }
+        for _ in 0..5000 {
+            simulation.update();
+        }
+    }
+
+    let energy = simulation.diagnostics().total_energy();
+    println!(
+        "relative energy drift {:.2e}",
+        (energy - initial) / initial.abs()
+    );
+}
@@ -0,0 +1,63 @@
+//! A force of its own added to the gravity of the stars: a harmonic pull towards the center
+//! of mass of all stars, like the potential of a dark matter halo, read from the root of the
+//! tree of every update. Prints how the cloud of stars contracts compared to gravity alone.
+//!
+//! `cargo run --example custom_force`
+
+use gravsim_simulation::force::ForceTerm;
+use gravsim_simulation::tree::FlatTree;
+use gravsim_simulation::{Real, Simulation, Star};
+use nalgebra::Vector2;
+use rand::{Rng, SeedableRng};
+use rand_xorshift::XorShiftRng;
+
+/// Pulls every star towards the center of mass with an acceleration growing linearly with
+/// the distance, so on its own a star swings through the center with a period of
+/// `2π / stiffness.sqrt()`.
+struct Halo {
+    stiffness: Real,
+}
+
+impl ForceTerm for Halo {
+    fn acceleration(&self, star: &Star, tree: &FlatTree) -> Vector2<Real> {
+        let center = tree.root().center_of_mass().position;
+        (center - star.pos()) * self.stiffness
+    }
+}
+
+/// Mean distance of the stars from their center of mass.
+fn spread(stars: &[Star]) -> Real {
+    let center = stars.iter().map(|star| star.pos()).sum::<Vector2<Real>>() / stars.len() as Real;
+    stars
+        .iter()
+        .map(|star| (star.pos() - center).norm())
+        .sum::<Real>()
+        / stars.len() as Real
+}
+
+fn main() {
+    let mut rng = XorShiftRng::seed_from_u64(0x4a10);
+    let stars: Vec<_> = (0..1000)
+        .map(|_| {
+            let pos = Vector2::from_fn(|_, _| rng.gen_range(-5000.0..5000.0));
+            Star::new(pos, Vector2::zeros(), rng.gen_range(1.0..100.0))
+        })
+        .collect();
+
+    let mut plain = Simulation::new(stars.iter().copied());
+    let mut halo = Simulation::new(stars);
+    halo.add_force(Box::new(Halo { stiffness: 1e-6 }));
+
+    for _ in 0..10 {
+        println!(
+            "t {:>5}: spread {:>6.0} with gravity alone, {:>6.0} in the halo",
+            plain.time,
+            spread(&plain.stars),
+            spread(&halo.stars)
+        );
+        for _ in 0..100 {
+            plain.update();
+            halo.update();
+        }
+    }
+}
@@ -0,0 +1,43 @@
+//! Two galaxies merging, without a window. Reports tidal disruptions of either galaxy as
+//! they are detected, and the energy every few hundred updates.
+//!
+//! `cargo run --release --example galaxy_collision --features scenario`
+
+use gravsim_simulation::scenario::{Collision, DisruptionSpec, Scenario};
+
+fn main() {
+    let mut scenario = Scenario::collision(Collision {
+        stars: 2000,
+        ..Collision::default()
+    });
+    scenario.disruption = Some(DisruptionSpec::default());
+    let mut simulation = scenario.to_simulation();
+    let names: Vec<_> = scenario
+        .groups()
+        .into_iter()
+        .map(|group| group.name)
+        .collect();
+    println!("{} stars", simulation.stars.len());
+
+    for step in 1..=3000 {
+        simulation.update();
+
+        for disruption in &simulation.disruptions {
+            println!(
+                "t {:>6}: the {} galaxy lost {:.0}% of its bound mass",
+                disruption.time,
+                names[disruption.group],
+                disruption.lost_fraction * 100.0
+            );
+        }
+        if step % 500 == 0 {
+            let diagnostics = simulation.diagnostics();
+            println!(
+                "t {:>6}: energy {:.4e}, half mass radius {:.0}",
+                simulation.time,
+                diagnostics.total_energy(),
+                diagnostics.lagrangian_radii[1]
+            );
+        }
+    }
+}
@@ -0,0 +1,51 @@
+//! Reads initial conditions from a scenario file, toml or json, and runs it without a
+//! window, printing the events of the scenario as they fire. Takes the path of the file and
+//! the number of updates, by default the two galaxies of the scenarios directory.
+//!
+//! `cargo run --release --example import_scenario --features scenario -- scenarios/collision.toml 500`
+
+use gravsim_simulation::scenario::Scenario;
+
+fn main() -> Result<(), String> {
+    let mut args = std::env::args().skip(1);
+    let path = args.next().unwrap_or_else(|| {
+        concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../scenarios/two_galaxies.toml"
+        )
+        .to_string()
+    });
+    let updates: u64 = match args.next() {
+        Some(updates) => updates
+            .parse()
+            .map_err(|_| format!("invalid number of updates: {}", updates))?,
+        None => 100,
+    };
+
+    let scenario = Scenario::from_path(&path)?;
+    let mut simulation = scenario.to_simulation();
+    println!(
+        "{}: {} stars, {} probes, {} events",
+        path,
+        simulation.stars.len(),
+        simulation.probes.len(),
+        scenario.events.len()
+    );
+
+    for _ in 0..updates {
+        simulation.update();
+        for event in &simulation.fired {
+            println!("t {:>6}: {:?}", event.time, event.action);
+        }
+    }
+
+    let diagnostics = simulation.diagnostics();
+    println!(
+        "t {:>6}: energy {:.4e}, center of mass ({:.1}, {:.1})",
+        simulation.time,
+        diagnostics.total_energy(),
+        diagnostics.center_of_mass.x,
+        diagnostics.center_of_mass.y
+    );
+    Ok(())
+}
@@ -0,0 +1,16 @@
+//! Plays back a recording of `Recorder` to its end.
+//! Run with `cargo fuzz run replay` from `gravsim-simulation`, seeding the corpus with recordings of `--record_replay=`.
+#![no_main]
+
+use gravsim_simulation::backend::SimulationBackend;
+use gravsim_simulation::replay::Replay;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(mut replay) = Replay::new(data) else {
+        return;
+    };
+
+    while let Ok(true) = replay.advance() {}
+    replay.diagnostics();
+});
@@ -0,0 +1,33 @@
+//! Reads a scenario as toml and as json, and generates the simulations of small ones.
+//! Run with `cargo fuzz run scenario` from `gravsim-simulation`, seeding the corpus from `scenarios`.
+#![no_main]
+
+use gravsim_simulation::scenario::Scenario;
+use libfuzzer_sys::fuzz_target;
+
+/// Scenarios may ask for any number of stars, larger ones are only parsed.
+const MAX_STARS: usize = 4096;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(source) = std::str::from_utf8(data) else {
+        return;
+    };
+
+    for scenario in [Scenario::from_toml(source), Scenario::from_json(source)] {
+        let Ok(scenario) = scenario else {
+            continue;
+        };
+        let collision = scenario.collision.map(|collision| collision.galaxies());
+        let stars = scenario
+            .galaxies
+            .iter()
+            .chain(collision.iter().flatten())
+            .fold(scenario.stars.len(), |stars, galaxy| {
+                stars.saturating_add(galaxy.stars)
+            });
+        if stars <= MAX_STARS {
+            let mut simulation = scenario.to_simulation();
+            simulation.update();
+        }
+    }
+});
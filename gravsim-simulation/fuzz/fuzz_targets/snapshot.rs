@@ -0,0 +1,18 @@
+//! Reads a snapshot of `Simulation::save` and steps it.
+//! Run with `cargo fuzz run snapshot` from `gravsim-simulation`, seeding the corpus with snapshots of `gravsim datagen`.
+#![no_main]
+
+use gravsim_simulation::snapshot::Snapshot;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(snapshot) = Snapshot::read(data) else {
+        return;
+    };
+
+    let mut simulation = snapshot.to_simulation();
+    for _ in 0..2 {
+        simulation.update();
+    }
+    simulation.diagnostics();
+});
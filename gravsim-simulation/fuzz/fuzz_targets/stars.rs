@@ -0,0 +1,22 @@
+//! Loads a list of bodies in the bincode format of `benches/test_data` and steps it.
+//! Run with `cargo fuzz run stars` from `gravsim-simulation`, seeding the corpus from `benches/test_data`.
+#![no_main]
+
+use gravsim_simulation::nalgebra::Vector2;
+use gravsim_simulation::{MassData, Simulation, Star};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(objs) = bincode::deserialize::<Vec<MassData>>(data) else {
+        return;
+    };
+
+    let mut simulation = Simulation::new(
+        objs.iter()
+            .map(|obj| Star::new(obj.position, Vector2::zeros(), obj.mass)),
+    );
+    for _ in 0..2 {
+        simulation.update();
+    }
+    simulation.diagnostics();
+});
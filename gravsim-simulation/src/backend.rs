@@ -0,0 +1,196 @@
+use crate::collision::Species;
+use crate::diagnostics::Diagnostics;
+use crate::group::Group;
+use crate::rebuild::TreeRebuild;
+use crate::{BodyKind, Real, Simulation, Star, StarId};
+use alloc::vec::Vec;
+use nalgebra::Vector2;
+
+/// Anything that advances a set of stars over time, so frontends can host the
+/// Barnes-Hut `Simulation` and other engines (e.g. on the gpu, or replays) interchangeably.
+pub trait SimulationBackend {
+    /// Advances all stars by one time step.
+    fn step(&mut self);
+
+    /// Current state of all stars, indexed by `StarId`.
+    fn snapshot(&self) -> &[Star];
+
+    fn add_star(&mut self, star: Star) -> StarId;
+
+    /// Removes a star, which shifts the ids of all following stars down by one.
+    fn remove_star(&mut self, id: StarId) -> Option<Star>;
+
+    /// Inserts a star at `id`, which shifts the ids of all following stars up by one.
+    /// Undoes `remove_star`, `id` must not be greater than the number of stars.
+    fn insert_star(&mut self, id: StarId, star: Star);
+
+    /// Everything else the backend keeps of star `id`, so it can be inserted again with
+    /// `insert_body`. Backends that only know the stars return the defaults.
+    fn attributes(&self, _id: StarId) -> BodyAttributes {
+        BodyAttributes::default()
+    }
+
+    /// Like `insert_star`, with the `attributes` the star had before it was removed.
+    fn insert_body(&mut self, id: StarId, star: Star, _attributes: BodyAttributes) {
+        self.insert_star(id, star);
+    }
+
+    /// Returns false if there is no star with this id.
+    fn set_velocity(&mut self, id: StarId, vel: Vector2<Real>) -> bool;
+
+    fn diagnostics(&self) -> Diagnostics;
+
+    /// Radius of `star` for collisions and rendering, see `MassRadiusRelation`.
+    fn radius(&self, star: &Star) -> Real {
+        star.radius()
+    }
+
+    /// Whether the stars won't change anymore, e.g. at the end of a replay.
+    fn finished(&self) -> bool {
+        false
+    }
+
+    /// The Barnes-Hut simulation behind this backend, for features specific to it.
+    fn as_simulation(&self) -> Option<&Simulation> {
+        None
+    }
+
+    fn as_simulation_mut(&mut self) -> Option<&mut Simulation> {
+        None
+    }
+}
+
+/// What `Simulation` keeps of a star besides the star itself, see `SimulationBackend::attributes`.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct BodyAttributes {
+    pub kind: BodyKind,
+    pub spin: Real,
+    pub age: f64,
+    pub species: Species,
+}
+
+impl SimulationBackend for Simulation {
+    fn step(&mut self) {
+        self.update();
+    }
+
+    fn snapshot(&self) -> &[Star] {
+        &self.stars
+    }
+
+    fn add_star(&mut self, star: Star) -> StarId {
+        self.stars.push(star);
+        self.stars.len() - 1
+    }
+
+    /// Everything the simulation keys by `StarId` moves along, an incremental rebuild in
+    /// progress starts over, like for `reorder_stars`.
+    fn remove_star(&mut self, id: StarId) -> Option<Star> {
+        if id >= self.stars.len() {
+            return None;
+        }
+        remove_id(&mut self.species, id);
+        remove_id(&mut self.spins, id);
+        remove_id(&mut self.kinds, id);
+        remove_id(&mut self.ages, id);
+        self.reused.remove(id);
+        if let Some(estimate) = &mut self.error_estimate {
+            estimate.remove(id);
+        }
+        for group in groups_mut(self) {
+            group.stars.retain(|&star| star != id);
+            group
+                .stars
+                .iter_mut()
+                .filter(|star| **star > id)
+                .for_each(|star| *star -= 1);
+        }
+        self.rebuild = TreeRebuild::default();
+        Some(self.stars.remove(id))
+    }
+
+    /// The star gets the defaults of everything keyed by `StarId`, e.g. `BodyKind::Star`.
+    fn insert_star(&mut self, id: StarId, star: Star) {
+        self.insert_body(id, star, BodyAttributes::default());
+    }
+
+    fn attributes(&self, id: StarId) -> BodyAttributes {
+        BodyAttributes {
+            kind: self.kind(id),
+            spin: self.spins.get(id).copied().unwrap_or_default(),
+            age: self.ages.get(id).copied().unwrap_or_default(),
+            species: self.species.get(id).copied().unwrap_or_default(),
+        }
+    }
+
+    fn insert_body(&mut self, id: StarId, star: Star, attributes: BodyAttributes) {
+        insert_id(&mut self.species, id, attributes.species);
+        insert_id(&mut self.spins, id, attributes.spin);
+        insert_id(&mut self.kinds, id, attributes.kind);
+        insert_id(&mut self.ages, id, attributes.age);
+        self.reused.insert(id);
+        if let Some(estimate) = &mut self.error_estimate {
+            estimate.insert(id);
+        }
+        for group in groups_mut(self) {
+            group
+                .stars
+                .iter_mut()
+                .filter(|star| **star >= id)
+                .for_each(|star| *star += 1);
+        }
+        self.rebuild = TreeRebuild::default();
+        self.stars.insert(id, star);
+    }
+
+    fn set_velocity(&mut self, id: StarId, vel: Vector2<Real>) -> bool {
+        self.stars.get_mut(id).map(|star| star.vel = vel).is_some()
+    }
+
+    fn diagnostics(&self) -> Diagnostics {
+        Simulation::diagnostics(self)
+    }
+
+    fn radius(&self, star: &Star) -> Real {
+        Simulation::radius(self, star)
+    }
+
+    fn as_simulation(&self) -> Option<&Simulation> {
+        Some(self)
+    }
+
+    fn as_simulation_mut(&mut self) -> Option<&mut Simulation> {
+        Some(self)
+    }
+}
+
+/// Removes the entry of `id` from values keyed by `StarId`, if there is one.
+fn remove_id<T>(values: &mut Vec<T>, id: StarId) {
+    if id < values.len() {
+        values.remove(id);
+    }
+}
+
+/// Makes room for a star inserted at `id`, values keyed by `StarId` may end before it and
+/// are only filled up to it if `value` isn't the default.
+fn insert_id<T: Clone + Default + PartialEq>(values: &mut Vec<T>, id: StarId, value: T) {
+    if id < values.len() {
+        values.insert(id, value);
+    } else if value != T::default() {
+        values.resize(id, T::default());
+        values.push(value);
+    }
+}
+
+/// Groups of the monitors, which refer to stars by id.
+fn groups_mut(simulation: &mut Simulation) -> impl Iterator<Item = &mut Group> {
+    let disruption_groups = simulation
+        .disruption_monitor
+        .iter_mut()
+        .flat_map(|monitor| &mut monitor.groups);
+    let pericenter_groups = simulation
+        .pericenter_monitor
+        .iter_mut()
+        .flat_map(|monitor| &mut monitor.groups);
+    disruption_groups.chain(pericenter_groups)
+}
@@ -0,0 +1,460 @@
+use crate::near_field::{cell_of, Cell, NeighborGrid};
+use crate::radius::MassRadiusRelation;
+use crate::{consts, Real, Star, StarId};
+use alloc::vec::Vec;
+use nalgebra::Vector2;
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Kind of a star, collisions are resolved depending on the kinds of both stars, see
+/// `CollisionModel`. Stars without a species are of species 0.
+pub type Species = u16;
+
+/// How a pair of colliding stars is resolved.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(tag = "outcome", rename_all = "snake_case")
+)]
+pub enum Outcome {
+    /// the heavier star absorbs the lighter one, see `merge_overlapping`
+    #[default]
+    Merge,
+    /// both stars bounce off each other, keeping the share `restitution` of their speed
+    /// along the line between them, 1 is elastic
+    Bounce { restitution: Real },
+    /// like `Merge`, but the share `debris_fraction` of the mass of the lighter star is
+    /// ejected as `fragments` new stars, fanned out around the point of impact with
+    /// `ejection` times the impact speed. Collisions whose fragments would be lighter than
+    /// `min_mass` merge instead, so debris doesn't shatter forever.
+    Fragment {
+        debris_fraction: Real,
+        fragments: u32,
+        ejection: Real,
+        min_mass: Real,
+    },
+}
+
+/// The outcome of collisions between two species, in either order.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Rule {
+    pub species: [Species; 2],
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub outcome: Outcome,
+}
+
+/// Selects the outcome of every collision by the species of both stars.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+pub struct CollisionModel {
+    /// outcome of pairs no rule matches
+    pub default: Outcome,
+    /// the first matching rule wins
+    pub rules: Vec<Rule>,
+}
+
+impl CollisionModel {
+    pub fn outcome(&self, a: Species, b: Species) -> Outcome {
+        self.rules
+            .iter()
+            .find(|rule| rule.species == [a, b] || rule.species == [b, a])
+            .map_or(self.default, |rule| rule.outcome)
+    }
+}
+
+/// A star ejected by a fragmenting collision, appended to the stars.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Debris {
+    pub id: StarId,
+    /// the lighter star of the collision, which the debris broke off of
+    pub from: StarId,
+}
+
+/// Everything that happened in `collide`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Collisions {
+    /// merges of the `Merge` and `Fragment` outcomes
+    pub merges: Vec<Merge>,
+    /// pairs of stars that bounced off each other
+    pub bounces: Vec<[StarId; 2]>,
+    pub debris: Vec<Debris>,
+}
+
+/// Two stars that collided, `time` is the share of the last step after which they
+/// touched, 1 if they overlap at its end.
+#[derive(Copy, Clone, Debug)]
+struct Contact {
+    a: StarId,
+    b: StarId,
+    time: Real,
+}
+
+/// A star absorbed by another one, see `merge_overlapping`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Merge {
+    pub into: StarId,
+    pub from: StarId,
+    /// share of the absorbed star in the mass of the merged one, e.g. to blend colors
+    pub fraction: Real,
+    /// orbital angular momentum of both stars around their center of mass, which the
+    /// merged star keeps as spin, see `Simulation::spins`
+    pub spin: Real,
+}
+
+/// Merges stars whose radii overlap, conserving mass and momentum. The heavier star absorbs
+/// the lighter one, which is removed like stars leaving the domain: its position becomes NaN
+/// and its mass 0, so the ids of all other stars stay the same.
+///
+/// Overlaps are found by sweeping the stars sorted along the x axis.
+pub fn merge_overlapping(stars: &mut [Star], relation: &dyn MassRadiusRelation) -> Vec<Merge> {
+    let mut merges = Vec::new();
+    overlapping(stars, relation, &mut |stars, contact| {
+        merges.push(merge_heavier(stars, contact))
+    });
+    merges
+}
+
+/// Calls `resolve` for every pair of overlapping stars, see `merge_overlapping`.
+fn overlapping(
+    stars: &mut [Star],
+    relation: &dyn MassRadiusRelation,
+    resolve: &mut impl FnMut(&mut [Star], Contact),
+) {
+    let radius = |star: &Star| relation.radius(star.mass());
+    let mut order: Vec<StarId> = (0..stars.len())
+        .filter(|&id| is_alive(&stars[id]))
+        .collect();
+    order.sort_unstable_by(|&a, &b| stars[a].pos().x.total_cmp(&stars[b].pos().x));
+    let max_radius = order
+        .iter()
+        .map(|&id| radius(&stars[id]))
+        .fold(0.0, Real::max);
+
+    for (i, &a) in order.iter().enumerate() {
+        for &b in &order[i + 1..] {
+            if !is_alive(&stars[a]) {
+                break;
+            }
+            if stars[b].pos().x - stars[a].pos().x > radius(&stars[a]) + max_radius {
+                break;
+            }
+            let reach = radius(&stars[a]) + radius(&stars[b]);
+            if !is_alive(&stars[b]) || (stars[a].pos() - stars[b].pos()).norm() >= reach {
+                continue;
+            }
+            resolve(stars, Contact { a, b, time: 1.0 });
+        }
+    }
+}
+
+/// Merges stars that collided during the last step, see `merge_swept` and
+/// `merge_overlapping`. `previous` are the positions of the stars before the step.
+pub fn merge_colliding(
+    stars: &mut [Star],
+    previous: &[Vector2<Real>],
+    relation: &dyn MassRadiusRelation,
+) -> Vec<Merge> {
+    let mut merges = merge_swept(stars, previous, relation);
+    merges.extend(merge_overlapping(stars, relation));
+    merges
+}
+
+/// Merges stars whose paths during the last step came closer than their radii, which
+/// catches fast stars that tunnel through each other within a step. Paths are straight
+/// lines from the `previous` positions to the current ones, and only pairs with a star
+/// moving further than its radius are checked, the others are left to `merge_overlapping`.
+/// Collisions are merged in the order they happened, a star merges at most once.
+///
+/// Candidates are found with a grid of cells twice the largest radius, in which every star
+/// is placed in all cells its path crosses.
+pub fn merge_swept(
+    stars: &mut [Star],
+    previous: &[Vector2<Real>],
+    relation: &dyn MassRadiusRelation,
+) -> Vec<Merge> {
+    let mut merges = Vec::new();
+    swept(stars, previous, relation, &mut |stars, contact| {
+        merges.push(merge_heavier(stars, contact))
+    });
+    merges
+}
+
+/// Calls `resolve` for every pair of stars whose paths crossed, see `merge_swept`.
+fn swept(
+    stars: &mut [Star],
+    previous: &[Vector2<Real>],
+    relation: &dyn MassRadiusRelation,
+    resolve: &mut impl FnMut(&mut [Star], Contact),
+) {
+    let alive: Vec<StarId> = (0..stars.len().min(previous.len()))
+        .filter(|&id| is_alive(&stars[id]) && previous[id].iter().all(|x| x.is_finite()))
+        .collect();
+    let radius = |star: &Star| relation.radius(star.mass());
+    let is_fast = |id: StarId| (stars[id].pos() - previous[id]).norm() > radius(&stars[id]);
+    let size = 2.0
+        * alive
+            .iter()
+            .map(|&id| radius(&stars[id]))
+            .fold(0.0, Real::max);
+    if size <= 0.0 || !alive.iter().any(|&id| is_fast(id)) {
+        return;
+    }
+
+    // all cells of the bounding box of the path of a star, grown by `margin` cells
+    let path_cells = |id: StarId, margin: i32| {
+        let (a, b) = (previous[id], *stars[id].pos());
+        let (min, max) = (cell_of(&a.inf(&b), size), cell_of(&a.sup(&b), size));
+        (min.0 - margin..=max.0 + margin)
+            .flat_map(move |x| (min.1 - margin..=max.1 + margin).map(move |y| (x, y)))
+    };
+    let grid = NeighborGrid::new(
+        alive
+            .iter()
+            .flat_map(|&id| path_cells(id, 0).map(move |cell: Cell| (cell, id))),
+    );
+
+    // paths closer than the sum of radii are at most a cell apart
+    let mut candidates: Vec<(StarId, StarId)> = alive
+        .iter()
+        .filter(|&&id| is_fast(id))
+        .flat_map(|&a| {
+            path_cells(a, 1)
+                .filter_map(|cell| grid.range(cell))
+                .flat_map(|range| grid.ids(range))
+                .filter(move |&b| b != a)
+                .map(move |b| (a.min(b), a.max(b)))
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    candidates.sort_unstable();
+    candidates.dedup();
+
+    let mut hits: Vec<(Real, StarId, StarId)> = candidates
+        .into_iter()
+        .filter_map(|(a, b)| {
+            let t = closest_approach(stars, previous, a, b);
+            let offset =
+                (previous[a] - previous[b]) * (1.0 - t) + (stars[a].pos() - stars[b].pos()) * t;
+            (offset.norm() < radius(&stars[a]) + radius(&stars[b])).then_some((t, a, b))
+        })
+        .collect();
+    hits.sort_by(|x, y| x.0.total_cmp(&y.0).then((x.1, x.2).cmp(&(y.1, y.2))));
+
+    let mut resolved = Vec::new();
+    for (time, a, b) in hits {
+        if resolved.contains(&a) || resolved.contains(&b) {
+            continue;
+        }
+        resolve(stars, Contact { a, b, time });
+        resolved.extend([a, b]);
+    }
+}
+
+/// Resolves the collisions of the last step like `merge_colliding`, but with the outcome
+/// `model` selects for the `species` of both stars, by id. Debris is appended to `stars`
+/// once all collisions are resolved, so it only collides from the next call on.
+pub fn collide(
+    stars: &mut Vec<Star>,
+    previous: &[Vector2<Real>],
+    species: &[Species],
+    model: &CollisionModel,
+    relation: &dyn MassRadiusRelation,
+) -> Collisions {
+    let mut collisions = Collisions::default();
+    let mut debris = Vec::new();
+    let mut resolve = |stars: &mut [Star], contact: Contact| {
+        let species_of = |id: StarId| species.get(id).copied().unwrap_or(0);
+        match model.outcome(species_of(contact.a), species_of(contact.b)) {
+            Outcome::Merge => collisions.merges.push(merge_heavier(stars, contact)),
+            Outcome::Bounce { restitution } => {
+                bounce(stars, previous, contact, restitution, relation);
+                collisions.bounces.push([contact.a, contact.b]);
+            }
+            Outcome::Fragment {
+                debris_fraction,
+                fragments,
+                ejection,
+                min_mass,
+            } => {
+                let (heavier, lighter) = heavier_first(stars, contact);
+                let mass = stars[lighter].mass() * debris_fraction.clamp(0.0, 1.0);
+                let fragment_mass = mass / fragments.max(1) as Real;
+                if fragments == 0 || fragment_mass <= 0.0 || fragment_mass < min_mass {
+                    collisions.merges.push(merge_heavier(stars, contact));
+                    return;
+                }
+                let (merge, ejected) =
+                    fragment(stars, heavier, lighter, fragments, mass, ejection, relation);
+                debris.extend(ejected.into_iter().map(|star| (star, merge.from)));
+                collisions.merges.push(merge);
+            }
+        }
+    };
+    swept(stars, previous, relation, &mut resolve);
+    overlapping(stars, relation, &mut resolve);
+
+    for (star, from) in debris {
+        collisions.debris.push(Debris {
+            id: stars.len(),
+            from,
+        });
+        stars.push(star);
+    }
+    collisions
+}
+
+/// The time in `[0, 1]` of the step at which stars `a` and `b` are closest, assuming both
+/// move in a straight line from their `previous` positions.
+fn closest_approach(stars: &[Star], previous: &[Vector2<Real>], a: StarId, b: StarId) -> Real {
+    let offset = previous[a] - previous[b];
+    let motion = (stars[a].pos() - previous[a]) - (stars[b].pos() - previous[b]);
+    let speed_sq = motion.norm_squared();
+    match speed_sq > 0.0 {
+        true => (-offset.dot(&motion) / speed_sq).clamp(0.0, 1.0),
+        false => 0.0,
+    }
+}
+
+fn is_alive(star: &Star) -> bool {
+    star.mass() > 0.0 && star.pos().iter().all(|x| x.is_finite())
+}
+
+/// The heavier and the lighter star of `contact`, `a` if both are equally heavy.
+fn heavier_first(stars: &[Star], contact: Contact) -> (StarId, StarId) {
+    let Contact { a, b, .. } = contact;
+    match stars[a].mass() >= stars[b].mass() {
+        true => (a, b),
+        false => (b, a),
+    }
+}
+
+fn merge_heavier(stars: &mut [Star], contact: Contact) -> Merge {
+    let (into, from) = heavier_first(stars, contact);
+    merge(stars, into, from)
+}
+
+/// Exchanges momentum along the line between both stars, where they touched, and pushes
+/// them apart until they no longer overlap. Both stars end up where they touched, the
+/// rest of the step is lost.
+fn bounce(
+    stars: &mut [Star],
+    previous: &[Vector2<Real>],
+    contact: Contact,
+    restitution: Real,
+    relation: &dyn MassRadiusRelation,
+) {
+    let Contact { a, b, time } = contact;
+    let at = |id: StarId| match time < 1.0 {
+        true => previous[id] + (stars[id].pos() - previous[id]) * time,
+        false => *stars[id].pos(),
+    };
+    let (pos_a, pos_b) = (at(a), at(b));
+    let offset = pos_b - pos_a;
+    let distance = offset.norm();
+    let normal = match distance > 0.0 {
+        true => offset / distance,
+        false => Vector2::x(),
+    };
+
+    let (mass_a, mass_b) = (stars[a].mass(), stars[b].mass());
+    let approach = (stars[b].vel - stars[a].vel).dot(&normal);
+    if approach < 0.0 {
+        let impulse = -(1.0 + restitution) * approach / (1.0 / mass_a + 1.0 / mass_b);
+        stars[a].vel -= normal * (impulse / mass_a);
+        stars[b].vel += normal * (impulse / mass_b);
+    }
+
+    // the lighter star moves further, so the center of mass stays put
+    let reach = relation.radius(mass_a) + relation.radius(mass_b);
+    let overlap = (reach - distance).max(0.0);
+    let mass = mass_a + mass_b;
+    stars[a].mass_point.position = pos_a - normal * (overlap * mass_b / mass);
+    stars[b].mass_point.position = pos_b + normal * (overlap * mass_a / mass);
+}
+
+/// Merges `from` into `into` and ejects `mass` of the merged star again as `fragments`
+/// equally heavy stars. They are fanned out over the half plane facing the side `from` hit,
+/// far enough apart not to overlap each other or the merged star, and moving away from it
+/// with `ejection` times the impact speed. The merged star recoils, so mass, momentum and
+/// the center of mass are conserved.
+fn fragment(
+    stars: &mut [Star],
+    into: StarId,
+    from: StarId,
+    fragments: u32,
+    mass: Real,
+    ejection: Real,
+    relation: &dyn MassRadiusRelation,
+) -> (Merge, Vec<Star>) {
+    let offset = stars[from].pos() - stars[into].pos();
+    let impact = stars[from].vel - stars[into].vel;
+    let normal = [offset, -impact]
+        .into_iter()
+        .find(|direction| direction.norm() > 0.0)
+        .map_or(Vector2::x(), |direction| direction.normalize());
+    let speed = ejection * impact.norm();
+
+    let mut merge = merge(stars, into, from);
+    let merged = stars[into];
+    let fragment_mass = mass / fragments as Real;
+    let radius = relation.radius(fragment_mass);
+    // neighbors are `PI / fragments` apart as seen from the merged star
+    let half_angle = consts::FRAC_PI_2 / fragments as Real;
+    let distance = (relation.radius(merged.mass()) + radius).max(radius / half_angle.sin()) * 1.01;
+
+    let ejected: Vec<Star> = (0..fragments)
+        .map(|i| {
+            let angle = (2 * i + 1) as Real * half_angle - consts::FRAC_PI_2;
+            let (sin, cos) = angle.sin_cos();
+            let direction = Vector2::new(
+                normal.x * cos - normal.y * sin,
+                normal.x * sin + normal.y * cos,
+            );
+            Star::new(
+                merged.pos() + direction * distance,
+                merged.vel + direction * speed,
+                fragment_mass,
+            )
+        })
+        .collect();
+
+    let rest = merged.mass() - mass;
+    let (mut offsets, mut momentum) = (Vector2::zeros(), Vector2::zeros());
+    for star in &ejected {
+        offsets += (star.pos() - merged.pos()) * fragment_mass;
+        momentum += (star.vel - merged.vel) * fragment_mass;
+    }
+    stars[into] = Star::new(
+        merged.pos() - offsets / rest,
+        merged.vel - momentum / rest,
+        rest,
+    );
+
+    // the fragments and the recoil carry angular momentum away from the spin
+    let orbital =
+        |star: &Star| star.mass() * (star.pos() - merged.pos()).perp(&(star.vel - merged.vel));
+    merge.spin -= ejected.iter().map(orbital).sum::<Real>() + orbital(&stars[into]);
+    (merge, ejected)
+}
+
+fn merge(stars: &mut [Star], into: StarId, from: StarId) -> Merge {
+    let (a, b) = (stars[into], stars[from]);
+    let mass = a.mass() + b.mass();
+    let weighted = |x: &Vector2<Real>, y: &Vector2<Real>| (x * a.mass() + y * b.mass()) / mass;
+
+    let reduced_mass = a.mass() * b.mass() / mass;
+    let spin = reduced_mass * (b.pos() - a.pos()).perp(&(b.vel - a.vel));
+
+    stars[into] = Star::new(weighted(a.pos(), b.pos()), weighted(&a.vel, &b.vel), mass);
+    stars[from] = Star::new(Vector2::from_element(Real::NAN), Vector2::zeros(), 0.0);
+    Merge {
+        into,
+        from,
+        fraction: b.mass() / mass,
+        spin,
+    }
+}
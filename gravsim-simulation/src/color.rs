@@ -0,0 +1,74 @@
+use crate::{Real, Star};
+use alloc::vec;
+use alloc::vec::Vec;
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// How the stars of a galaxy are colored, so galaxies of one scene can be told apart.
+/// Colors are linear rgb in `[0, 1]`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum ColorPolicy {
+    Uniform([f32; 3]),
+    /// from the first color for the lightest to the second for the heaviest star,
+    /// on a logarithmic scale
+    ByMass([f32; 3], [f32; 3]),
+    /// from the first color at the center to the second at the outermost star
+    Radial([f32; 3], [f32; 3]),
+    /// a pseudo random color of the palette per star, the same on every call
+    Palette(Vec<[f32; 3]>),
+}
+
+impl Default for ColorPolicy {
+    fn default() -> Self {
+        Self::Uniform([1.0; 3])
+    }
+}
+
+impl ColorPolicy {
+    /// Colors of `stars`, the first of which is the center of the galaxy.
+    pub fn colors(&self, stars: &[Star]) -> Vec<[f32; 3]> {
+        match self {
+            Self::Uniform(color) => vec![*color; stars.len()],
+            Self::ByMass(light, heavy) => {
+                let masses = stars.iter().map(|star| star.mass().ln());
+                let min = masses.clone().fold(Real::INFINITY, Real::min);
+                let max = masses.clone().fold(Real::NEG_INFINITY, Real::max);
+                masses
+                    .map(|mass| mix(light, heavy, (mass - min) / (max - min)))
+                    .collect()
+            }
+            Self::Radial(inner, outer) => {
+                let Some(center) = stars.first() else {
+                    return Vec::new();
+                };
+                let distances = stars.iter().map(|star| (star.pos() - center.pos()).norm());
+                let max = distances.clone().fold(0.0, Real::max);
+                distances
+                    .map(|distance| mix(inner, outer, distance / max))
+                    .collect()
+            }
+            Self::Palette(colors) if colors.is_empty() => Self::default().colors(stars),
+            Self::Palette(colors) => (0..stars.len())
+                .map(|i| {
+                    // Knuth's multiplicative hash, so neighboring ids get unrelated colors
+                    let hash = (i as u32).wrapping_mul(2654435761) >> 16;
+                    colors[hash as usize % colors.len()]
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Linear interpolation, `t` outside of `[0, 1]` (e.g. NaN for a single star) is clamped.
+fn mix(a: &[f32; 3], b: &[f32; 3], t: Real) -> [f32; 3] {
+    let t = if t.is_finite() {
+        t.clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    [0, 1, 2].map(|i| a[i] + (b[i] - a[i]) * t as f32)
+}
@@ -0,0 +1,247 @@
+use crate::{Real, Simulation, Star, StarId};
+use alloc::vec::Vec;
+use nalgebra::{Matrix2, SVector, Vector2};
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+/// Summary statistics of a simulation state. Stars that left the simulation are ignored.
+#[derive(Clone, Debug)]
+pub struct Diagnostics {
+    pub kinetic_energy: f64,
+    /// exact potential energy using the same softening as the force calculation
+    pub potential_energy: f64,
+    /// for 3d simulations, projected onto the xy plane
+    pub center_of_mass: Vector2<f64>,
+    /// orbital angular momentum of the stars around their center of mass, for 3d
+    /// simulations around the z axis
+    pub angular_momentum: f64,
+    /// total spin of the stars, the angular momentum merges took out of their orbits,
+    /// see `Simulation::spins`
+    pub spin: f64,
+    /// radii around the center of mass enclosing the fractions of mass in `LAGRANGIAN_FRACTIONS`
+    pub lagrangian_radii: Vec<f64>,
+}
+
+/// Position, velocity and mass of a star in `D` dimensions.
+type Body<const D: usize> = (SVector<f64, D>, SVector<f64, D>, f64);
+
+impl Diagnostics {
+    pub const LAGRANGIAN_FRACTIONS: [f64; 3] = [0.1, 0.5, 0.9];
+
+    /// Calculates the diagnostics of `stars` under the gravitational constant `gravity` in O(n²),
+    /// with the default softening.
+    pub fn new(stars: &[Star], gravity: Real) -> Self {
+        Self::with_softening(stars, gravity, Simulation::SOFTENING)
+    }
+
+    /// Like `new`, with the potential energy softened by `softening`, see
+    /// `SimulationConfig::softening`.
+    pub fn with_softening(stars: &[Star], gravity: Real, softening: Real) -> Self {
+        let bodies = stars
+            .iter()
+            .map(|star| (star.pos().cast(), star.vel.cast(), star.mass() as f64))
+            .collect();
+        Self::from_bodies(bodies, gravity, softening)
+    }
+
+    /// Like `new`, for the stars of a 3d simulation.
+    #[cfg(feature = "3d")]
+    pub fn new_3d(stars: &[crate::three_d::Star3], gravity: Real, softening: Real) -> Self {
+        let bodies = stars
+            .iter()
+            .map(|star| (star.pos().cast(), star.vel.cast(), star.mass() as f64))
+            .collect();
+        Self::from_bodies(bodies, gravity, softening)
+    }
+
+    fn from_bodies<const D: usize>(bodies: Vec<Body<D>>, gravity: Real, softening: Real) -> Self {
+        let bodies: Vec<_> = bodies
+            .into_iter()
+            .filter(|(pos, _, _)| pos.iter().all(|x| x.is_finite()))
+            .collect();
+
+        let kinetic_energy = bodies
+            .iter()
+            .map(|(_, vel, mass)| 0.5 * mass * vel.norm_squared())
+            .sum();
+
+        let pair_energies = |(i, (a, _, a_mass)): (usize, &Body<D>)| {
+            bodies[i + 1..]
+                .iter()
+                .map(|(b, _, b_mass)| {
+                    let dist_sq = (a - b).norm_squared();
+                    -a_mass * b_mass / ((softening as f64).powi(2) + dist_sq).sqrt()
+                })
+                .sum::<f64>()
+        };
+        // summed in order, so the result doesn't depend on the number of threads
+        #[cfg(feature = "rayon")]
+        let potential_energy = bodies
+            .par_iter()
+            .enumerate()
+            .map(pair_energies)
+            .collect::<Vec<f64>>()
+            .into_iter()
+            .sum::<f64>();
+        #[cfg(not(feature = "rayon"))]
+        let potential_energy = bodies.iter().enumerate().map(pair_energies).sum::<f64>();
+        let potential_energy = potential_energy * gravity as f64;
+
+        let total_mass: f64 = bodies.iter().map(|(_, _, mass)| mass).sum();
+        let center_of_mass = bodies
+            .iter()
+            .map(|(pos, _, mass)| pos * *mass)
+            .sum::<SVector<f64, D>>()
+            / total_mass;
+
+        let velocity = bodies
+            .iter()
+            .map(|(_, vel, mass)| vel * *mass)
+            .sum::<SVector<f64, D>>()
+            / total_mass;
+        let angular_momentum = bodies
+            .iter()
+            .map(|(pos, vel, mass)| {
+                let (offset, relative) = (pos - center_of_mass, vel - velocity);
+                mass * (offset[0] * relative[1] - offset[1] * relative[0])
+            })
+            .sum();
+
+        let mut by_radius: Vec<_> = bodies
+            .iter()
+            .map(|(pos, _, mass)| ((pos - center_of_mass).norm(), *mass))
+            .collect();
+        by_radius.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut lagrangian_radii = Vec::with_capacity(Self::LAGRANGIAN_FRACTIONS.len());
+        let mut enclosed = 0.0;
+        let mut fractions = Self::LAGRANGIAN_FRACTIONS.iter().peekable();
+        for (radius, mass) in by_radius {
+            enclosed += mass;
+            while fractions
+                .next_if(|&&f| enclosed >= f * total_mass)
+                .is_some()
+            {
+                lagrangian_radii.push(radius);
+            }
+        }
+
+        Self {
+            kinetic_energy,
+            potential_energy,
+            center_of_mass: Vector2::new(center_of_mass[0], center_of_mass[1]),
+            angular_momentum,
+            spin: 0.0,
+            lagrangian_radii,
+        }
+    }
+
+    pub fn total_energy(&self) -> f64 {
+        self.kinetic_energy + self.potential_energy
+    }
+
+    /// Orbital angular momentum and spin, conserved by gravity and by collisions.
+    pub fn total_angular_momentum(&self) -> f64 {
+        self.angular_momentum + self.spin
+    }
+}
+
+/// Accumulates a per star estimate of the force error. Every update, the velocity change of
+/// each star is compared to the linear extrapolation of its two previous velocity changes.
+/// Smooth orbits extrapolate well, so large values highlight where the integrator or the
+/// opening criterion struggles, e.g. dense cores and close binaries.
+#[derive(Clone, Debug, Default)]
+pub struct ErrorEstimate {
+    /// accumulated error of each star, indexed by `StarId`
+    pub accumulated: Vec<Real>,
+    /// velocity changes of the last two updates
+    history: Vec<[Vector2<Real>; 2]>,
+    /// number of updates recorded, up to two
+    recorded: usize,
+}
+
+impl ErrorEstimate {
+    /// Records one update, given the velocities of all stars before it.
+    pub fn record(&mut self, stars: &[Star], old_velocities: &[Vector2<Real>]) {
+        self.accumulated.resize(stars.len(), 0.0);
+        self.history.resize(stars.len(), [Vector2::zeros(); 2]);
+
+        for ((star, old_velocity), (accumulated, history)) in stars
+            .iter()
+            .zip(old_velocities)
+            .zip(self.accumulated.iter_mut().zip(&mut self.history))
+        {
+            let change = star.vel - old_velocity;
+            if self.recorded == 2 && change.iter().all(|x| x.is_finite()) {
+                let extrapolated = 2.0 * history[0] - history[1];
+                *accumulated += (change - extrapolated).norm();
+            }
+            *history = [change, history[0]];
+        }
+        self.recorded = (self.recorded + 1).min(2);
+    }
+
+    /// Takes the values of star `id` out, the stars after it move down an id.
+    pub(crate) fn remove(&mut self, id: StarId) {
+        if id < self.accumulated.len() {
+            self.accumulated.remove(id);
+        }
+        if id < self.history.len() {
+            self.history.remove(id);
+        }
+    }
+
+    /// Makes room for a star at `id`, which starts without an accumulated error.
+    pub(crate) fn insert(&mut self, id: StarId) {
+        if id < self.accumulated.len() {
+            self.accumulated.insert(id, 0.0);
+        }
+        if id < self.history.len() {
+            self.history.insert(id, [Vector2::zeros(); 2]);
+        }
+    }
+
+    /// Moves the values along with the stars, `order` is the previous id at each id.
+    pub(crate) fn reorder(&mut self, order: &[StarId]) {
+        self.accumulated = order
+            .iter()
+            .map(|&id| self.accumulated.get(id).copied().unwrap_or(0.0))
+            .collect();
+        self.history = order
+            .iter()
+            .map(|&id| {
+                self.history
+                    .get(id)
+                    .copied()
+                    .unwrap_or([Vector2::zeros(); 2])
+            })
+            .collect();
+    }
+
+    /// Rotates the recorded velocity changes along with the stars.
+    pub(crate) fn rotate(&mut self, rotation: &Matrix2<Real>) {
+        for changes in &mut self.history {
+            changes
+                .iter_mut()
+                .for_each(|change| *change = rotation * *change);
+        }
+    }
+}
+
+impl Simulation {
+    pub fn diagnostics(&self) -> Diagnostics {
+        let spin = self
+            .stars
+            .iter()
+            .zip(&self.spins)
+            .filter(|(star, _)| star.pos().iter().all(|x| x.is_finite()))
+            .map(|(_, &spin)| spin as f64)
+            .sum();
+        Diagnostics {
+            spin,
+            ..Diagnostics::with_softening(&self.stars, self.config.gravity, self.config.softening)
+        }
+    }
+}
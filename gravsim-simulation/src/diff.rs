@@ -0,0 +1,140 @@
+use crate::{Simulation, Star, StarId};
+use alloc::vec::Vec;
+use core::fmt;
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+
+/// Differences of one star between two states.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct StarDelta {
+    pub id: StarId,
+    /// distance between the positions
+    pub position: f64,
+    /// norm of the velocity difference
+    pub velocity: f64,
+    pub mass: f64,
+}
+
+/// Per star differences between two simulation states, e.g. to check that a resumed run
+/// or another backend reproduces a reference. Stars are matched by `StarId`, a star exists
+/// in a state if its id is in range and it hasn't been removed.
+#[derive(Clone, Debug, Default)]
+pub struct Diff {
+    /// stars existing in both states, by id
+    pub deltas: Vec<StarDelta>,
+    /// stars existing only in the first state
+    pub only_in_a: Vec<StarId>,
+    /// stars existing only in the second state
+    pub only_in_b: Vec<StarId>,
+}
+
+impl Diff {
+    pub fn new(a: &[Star], b: &[Star]) -> Self {
+        let exists = |stars: &[Star], id: StarId| {
+            stars
+                .get(id)
+                .is_some_and(|star| star.mass() > 0.0 && star.pos().iter().all(|x| x.is_finite()))
+        };
+
+        let mut diff = Self::default();
+        for id in 0..a.len().max(b.len()) {
+            match (exists(a, id), exists(b, id)) {
+                (true, true) => {
+                    let (a, b) = (&a[id], &b[id]);
+                    diff.deltas.push(StarDelta {
+                        id,
+                        position: (a.pos().cast::<f64>() - b.pos().cast::<f64>()).norm(),
+                        velocity: (a.vel.cast::<f64>() - b.vel.cast::<f64>()).norm(),
+                        mass: (a.mass() as f64 - b.mass() as f64).abs(),
+                    });
+                }
+                (true, false) => diff.only_in_a.push(id),
+                (false, true) => diff.only_in_b.push(id),
+                (false, false) => {}
+            }
+        }
+        diff
+    }
+
+    /// Whether both states contain the same stars at exactly the same state.
+    pub fn is_identical(&self) -> bool {
+        self.only_in_a.is_empty()
+            && self.only_in_b.is_empty()
+            && self.differing(0.0).next().is_none()
+    }
+
+    /// Stars whose position, velocity or mass differ by more than `tolerance`.
+    pub fn differing(&self, tolerance: f64) -> impl Iterator<Item = &StarDelta> {
+        self.deltas.iter().filter(move |delta| {
+            delta.position > tolerance || delta.velocity > tolerance || delta.mass > tolerance
+        })
+    }
+
+    /// The `count` stars that moved apart the most, largest first.
+    pub fn largest(&self, count: usize) -> Vec<StarDelta> {
+        let mut deltas = self.deltas.clone();
+        deltas.sort_by(|a, b| b.position.total_cmp(&a.position));
+        deltas.truncate(count);
+        deltas
+    }
+
+    pub fn max_position_delta(&self) -> Option<f64> {
+        self.deltas
+            .iter()
+            .map(|delta| delta.position)
+            .reduce(f64::max)
+    }
+
+    pub fn max_velocity_delta(&self) -> Option<f64> {
+        self.deltas
+            .iter()
+            .map(|delta| delta.velocity)
+            .reduce(f64::max)
+    }
+
+    /// Root mean square of the position differences.
+    pub fn rms_position_delta(&self) -> Option<f64> {
+        rms(self.deltas.iter().map(|delta| delta.position))
+    }
+
+    /// Root mean square of the velocity differences.
+    pub fn rms_velocity_delta(&self) -> Option<f64> {
+        rms(self.deltas.iter().map(|delta| delta.velocity))
+    }
+}
+
+fn rms(values: impl ExactSizeIterator<Item = f64>) -> Option<f64> {
+    let count = values.len();
+    (count > 0).then(|| (values.map(|x| x * x).sum::<f64>() / count as f64).sqrt())
+}
+
+impl fmt::Display for Diff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} stars in both, {} only in a, {} only in b, {} differing",
+            self.deltas.len(),
+            self.only_in_a.len(),
+            self.only_in_b.len(),
+            self.differing(0.0).count()
+        )?;
+        match (
+            self.max_position_delta().zip(self.rms_position_delta()),
+            self.max_velocity_delta().zip(self.rms_velocity_delta()),
+        ) {
+            (Some((max_position, rms_position)), Some((max_velocity, rms_velocity))) => write!(
+                f,
+                "position: rms {:.3e}, max {:.3e}\nvelocity: rms {:.3e}, max {:.3e}",
+                rms_position, max_position, rms_velocity, max_velocity
+            ),
+            _ => write!(f, "no stars to compare"),
+        }
+    }
+}
+
+impl Simulation {
+    /// Differences of the stars of this simulation to the ones of `other`, see `Diff`.
+    pub fn diff(&self, other: &Simulation) -> Diff {
+        Diff::new(&self.stars, &other.stars)
+    }
+}
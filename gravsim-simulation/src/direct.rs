@@ -0,0 +1,36 @@
+use crate::tree::direct_force_on;
+use crate::{MassData, Real, SimulationConfig};
+use alloc::vec::Vec;
+use nalgebra::Vector2;
+
+/// The exact force of every body on every other one, the O(n²) reference the trees
+/// approximate. Too slow to simulate with, but the yardstick for the accuracy of
+/// `Node::force_on` and `FlatTree::force_on` at a given `theta`.
+#[derive(Clone, Debug, Default)]
+pub struct DirectSum {
+    bodies: Vec<MassData>,
+}
+
+impl DirectSum {
+    pub fn new(bodies: Vec<MassData>) -> Self {
+        Self { bodies }
+    }
+
+    pub fn bodies(&self) -> &[MassData] {
+        &self.bodies
+    }
+
+    /// Calculates the force on `obj`, with the same softening as the trees. Bodies at the
+    /// position of `obj`, including `obj` itself, are skipped.
+    pub fn force_on(&self, obj: &MassData, config: &SimulationConfig) -> Vector2<Real> {
+        direct_force_on(obj, &self.bodies, config)
+    }
+
+    /// The force on each body, in order.
+    pub fn forces(&self, config: &SimulationConfig) -> Vec<Vector2<Real>> {
+        self.bodies
+            .iter()
+            .map(|body| self.force_on(body, config))
+            .collect()
+    }
+}
@@ -0,0 +1,81 @@
+use crate::{BodyKind, Real, Simulation, SimulationConfig};
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A simple stellar evolution track, set as `SimulationConfig::evolution`. Stars age with the
+/// simulated time they live through, see `Simulation::ages`, heavier stars burn out sooner.
+/// A star keeps its color for most of its life, reddens as a giant towards the end and dims
+/// to a remnant after it, see `tint`. Black holes don't age.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(default, deny_unknown_fields)
+)]
+pub struct Evolution {
+    /// lifetime of a star of `mass` in simulated time
+    pub lifetime: f64,
+    pub mass: Real,
+    /// lifetimes scale with `mass^-exponent`, about 2.5 on the main sequence
+    pub exponent: f64,
+}
+
+impl Default for Evolution {
+    fn default() -> Self {
+        Self {
+            lifetime: 1e5,
+            mass: 1000.0,
+            exponent: 2.5,
+        }
+    }
+}
+
+impl Evolution {
+    /// Share of its lifetime a star spends as a giant, at the end.
+    pub const GIANT_PHASE: f64 = 0.2;
+    const GIANT_TINT: [f32; 3] = [1.4, 0.55, 0.3];
+    const REMNANT_TINT: [f32; 3] = [0.35, 0.4, 0.5];
+
+    /// Lifetime of a star of `mass`, infinite for massless stars.
+    pub fn lifetime(&self, mass: Real) -> f64 {
+        self.lifetime * (mass as f64 / self.mass as f64).powf(-self.exponent)
+    }
+
+    /// Factors of the color of a star of `mass` at `age`: none for most of its life, towards
+    /// `GIANT_TINT` over the giant phase and `REMNANT_TINT` once it ended.
+    pub fn tint(&self, mass: Real, age: f64) -> [f32; 3] {
+        let progress = age / self.lifetime(mass);
+        if progress >= 1.0 {
+            return Self::REMNANT_TINT;
+        }
+        let giant = ((progress - 1.0) / Self::GIANT_PHASE + 1.0).clamp(0.0, 1.0) as f32;
+        Self::GIANT_TINT.map(|tint| 1.0 + (tint - 1.0) * giant)
+    }
+}
+
+impl Simulation {
+    /// Ages every star by its time step of the update, if `config.evolution` is set. Stars
+    /// reaching the end of their lifetime are reported in `expired`.
+    pub(crate) fn age_stars(&mut self, config: &SimulationConfig) {
+        self.expired.clear();
+        let Some(evolution) = config.evolution else {
+            return;
+        };
+
+        let dt = self.time_steps(config);
+        self.ages.resize(self.stars.len(), 0.0);
+        for (id, (age, dt)) in self.ages.iter_mut().zip(dt).enumerate() {
+            if self.kinds.get(id) == Some(&BodyKind::BlackHole) {
+                continue;
+            }
+            let lifetime = evolution.lifetime(self.stars[id].mass());
+            let before = *age;
+            *age += dt as f64;
+            if before < lifetime && *age >= lifetime {
+                self.expired.push(id);
+            }
+        }
+    }
+}
@@ -0,0 +1,158 @@
+use crate::map::{Map, Quantity};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Physical units of the simulation, for exports. Simulation units are multiplied by the
+/// factors and labelled with the unit strings, which follow the FITS conventions, e.g.
+/// `kpc`, `solMass` and `km/s`. By default values stay in unlabelled simulation units.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+pub struct Units {
+    pub length: f64,
+    pub length_unit: String,
+    pub mass: f64,
+    pub mass_unit: String,
+    pub velocity: f64,
+    pub velocity_unit: String,
+}
+
+impl Default for Units {
+    fn default() -> Self {
+        Self {
+            length: 1.0,
+            length_unit: String::new(),
+            mass: 1.0,
+            mass_unit: String::new(),
+            velocity: 1.0,
+            velocity_unit: String::new(),
+        }
+    }
+}
+
+impl Units {
+    /// Factor and unit string of a quantity, the unit is empty if any part of it is.
+    fn of(&self, quantity: Quantity) -> (f64, String) {
+        match quantity {
+            Quantity::SurfaceDensity => {
+                let unit = match self.mass_unit.is_empty() || self.length_unit.is_empty() {
+                    true => String::new(),
+                    false => format!("{} {}-2", self.mass_unit, self.length_unit),
+                };
+                (self.mass / (self.length * self.length), unit)
+            }
+            Quantity::Potential => {
+                let unit = match self.velocity_unit.is_empty() {
+                    true => String::new(),
+                    false => format!("({})**2", self.velocity_unit),
+                };
+                (self.velocity * self.velocity, unit)
+            }
+        }
+    }
+}
+
+/// Size of FITS header and data blocks.
+const BLOCK: usize = 2880;
+const CARD: usize = 80;
+
+/// Encodes `map` as a FITS file: a single image of 32 bit floats, with linear world
+/// coordinates (`CTYPE` `X` and `Y`) of the pixel centers in `units`. `time` is the
+/// simulated time of the map, written as `SIMTIME` in simulation units.
+pub fn encode(map: &Map, units: &Units, time: f64) -> Vec<u8> {
+    let grid = &map.grid;
+    let (scale, unit) = units.of(map.quantity);
+    let first = grid.center(0, 0);
+
+    let mut header = Header::default();
+    header.logical("SIMPLE", true, "conforms to FITS standard");
+    header.integer("BITPIX", -32, "32 bit floats");
+    header.integer("NAXIS", 2, "");
+    header.integer("NAXIS1", grid.width as i64, "columns, along x");
+    header.integer("NAXIS2", grid.height as i64, "rows, along y");
+    header.string(
+        "OBJECT",
+        match map.quantity {
+            Quantity::SurfaceDensity => "surface density",
+            Quantity::Potential => "gravitational potential",
+        },
+        "",
+    );
+    if !unit.is_empty() {
+        header.string("BUNIT", &unit, "");
+    }
+    header.integer("WCSAXES", 2, "");
+    for (axis, name, reference) in [(1, "X", first.x), (2, "Y", first.y)] {
+        header.string(&format!("CTYPE{}", axis), name, "linear");
+        header.real(&format!("CRPIX{}", axis), 1.0, "center of the first pixel");
+        header.real(
+            &format!("CRVAL{}", axis),
+            reference as f64 * units.length,
+            "",
+        );
+        header.real(
+            &format!("CDELT{}", axis),
+            grid.pixel_size as f64 * units.length,
+            "",
+        );
+        if !units.length_unit.is_empty() {
+            header.string(&format!("CUNIT{}", axis), &units.length_unit, "");
+        }
+    }
+    header.real("SIMTIME", time, "simulated time, in simulation units");
+    header.string("ORIGIN", "gravsim", "");
+
+    let mut bytes = header.finish();
+    for value in &map.values {
+        bytes.extend(((value * scale) as f32).to_be_bytes());
+    }
+    bytes.resize(bytes.len().div_ceil(BLOCK) * BLOCK, 0);
+    bytes
+}
+
+/// Header cards of 80 characters, as fixed format keyword records.
+#[derive(Default)]
+struct Header {
+    cards: String,
+}
+
+impl Header {
+    fn card(&mut self, keyword: &str, value: &str, comment: &str) {
+        let mut card = format!("{:<8}= {}", keyword, value);
+        if !comment.is_empty() {
+            card += " / ";
+            card += comment;
+        }
+        card.truncate(CARD);
+        self.cards += &format!("{:<80}", card);
+    }
+
+    fn logical(&mut self, keyword: &str, value: bool, comment: &str) {
+        let value = if value { "T" } else { "F" };
+        self.card(keyword, &format!("{:>20}", value), comment);
+    }
+
+    fn integer(&mut self, keyword: &str, value: i64, comment: &str) {
+        self.card(keyword, &format!("{:>20}", value), comment);
+    }
+
+    fn real(&mut self, keyword: &str, value: f64, comment: &str) {
+        self.card(keyword, &format!("{:>20.10E}", value), comment);
+    }
+
+    fn string(&mut self, keyword: &str, value: &str, comment: &str) {
+        // quotes are escaped by doubling them, at least 8 characters between the quotes
+        let quoted = format!("'{:<8}'", value.replace('\'', "''"));
+        self.card(keyword, &format!("{:<20}", quoted), comment);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        self.cards += &format!("{:<80}", "END");
+        let len = self.cards.len().div_ceil(BLOCK) * BLOCK;
+        let mut bytes = self.cards.into_bytes();
+        bytes.resize(len, b' ');
+        bytes
+    }
+}
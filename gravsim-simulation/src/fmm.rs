@@ -0,0 +1,203 @@
+use crate::solver::{tree_of, ForceSolver};
+use crate::tree::{self, FlatTree, NodeIndex};
+use crate::{MassData, Real, SimulationConfig};
+use alloc::vec;
+use alloc::vec::Vec;
+use nalgebra::{Matrix2, Vector2};
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+/// Fast multipole method on the quad tree of `FlatTree`, O(n) in the number of bodies.
+/// Instead of every body walking the tree, pairs of cells are walked once: cells whose
+/// sizes add up to less than `theta` times their distance interact through the monopole
+/// and quadrupole of the source, expanded to second order around the target. The
+/// expansions are passed down the tree to the bodies, pairs of leaves that are too close
+/// are summed directly. Softened like the tree walk, so `theta` 0 gives the direct sum.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Fmm;
+
+impl ForceSolver for Fmm {
+    fn forces(&self, bodies: &[MassData], config: &SimulationConfig) -> Vec<Vector2<Real>> {
+        let tree = tree_of(bodies, config);
+        let quadrupoles = quadrupoles(&tree);
+        let (mut locals, near) = interactions(&tree, &quadrupoles, config);
+
+        // parents come before their children, see `FlatTree`
+        for (index, node) in tree.nodes().iter().enumerate() {
+            let parent = locals[index];
+            for child in node.children() {
+                let offset =
+                    tree.node(child).center_of_mass().position - node.center_of_mass().position;
+                locals[child as usize].add(&parent.shifted(&offset));
+            }
+        }
+
+        let force = |body: &MassData| {
+            if !config.domain.contains(&body.position) {
+                return Vector2::zeros();
+            }
+            let Some(leaf) = tree.leaf_containing(&body.position) else {
+                return tree.force_on(body, config);
+            };
+
+            let center = tree.node(leaf).center_of_mass().position;
+            let mut force_part = locals[leaf as usize].at(&(body.position - center));
+            for &source in &near[leaf as usize] {
+                force_part += pull(tree.node(source).center_of_mass(), &body.position, config);
+            }
+            config.gravity * body.mass * force_part
+        };
+        let mut forces = vec![Vector2::zeros(); bodies.len()];
+        let fill = |(out, body): (&mut Vector2<Real>, &MassData)| *out = force(body);
+        #[cfg(feature = "rayon")]
+        forces.par_iter_mut().zip(bodies).for_each(fill);
+        #[cfg(not(feature = "rayon"))]
+        forces.iter_mut().zip(bodies).for_each(fill);
+        forces
+    }
+}
+
+/// The far field of a cell, as a second order Taylor expansion of the acceleration around
+/// its center of mass. Like `force_part` of the tree walk, without the gravitational constant.
+#[derive(Copy, Clone, Debug)]
+struct Local {
+    acceleration: Vector2<Real>,
+    jacobian: Matrix2<Real>,
+    /// second derivatives of each component of the acceleration
+    hessians: [Matrix2<Real>; 2],
+}
+
+impl Local {
+    fn zero() -> Self {
+        Self {
+            acceleration: Vector2::zeros(),
+            jacobian: Matrix2::zeros(),
+            hessians: [Matrix2::zeros(); 2],
+        }
+    }
+
+    fn add(&mut self, other: &Self) {
+        self.acceleration += other.acceleration;
+        self.jacobian += other.jacobian;
+        self.hessians[0] += other.hessians[0];
+        self.hessians[1] += other.hessians[1];
+    }
+
+    /// Adds the field of a cell with `source` as center of mass and the given quadrupole,
+    /// `r` is the center of this expansion relative to it. The quadrupole only contributes
+    /// to the acceleration, its derivatives are of higher order.
+    fn add_source(
+        &mut self,
+        source: &MassData,
+        quadrupole: &Matrix2<Real>,
+        r: Vector2<Real>,
+        softening: Real,
+    ) {
+        // powers of the softened distance, like the tree walk
+        let inverse = 1.0 / (softening * softening + r.norm_squared());
+        let d3 = inverse * inverse.sqrt();
+        let d5 = d3 * inverse;
+        let d7 = d5 * inverse;
+
+        let qr = quadrupole * r;
+        self.acceleration += -r * (source.mass * d3)
+            + (r * quadrupole.trace() + qr * 2.0) * (1.5 * d5)
+            - r * (7.5 * r.dot(&qr) * d7);
+
+        let rr = r * r.transpose();
+        self.jacobian += (rr * (3.0 * d5) - Matrix2::identity() * d3) * source.mass;
+        for (i, unit) in [Vector2::x(), Vector2::y()].into_iter().enumerate() {
+            let symmetric =
+                unit * r.transpose() + r * unit.transpose() + Matrix2::identity() * r[i];
+            self.hessians[i] += (symmetric * (3.0 * d5) - rr * (15.0 * r[i] * d7)) * source.mass;
+        }
+    }
+
+    /// The acceleration at `offset` from the center of the expansion.
+    fn at(&self, offset: &Vector2<Real>) -> Vector2<Real> {
+        let curvature = Vector2::from_fn(|i, _| offset.dot(&(self.hessians[i] * offset)));
+        self.acceleration + self.jacobian * offset + curvature * 0.5
+    }
+
+    /// The same field, expanded around a center at `offset` from the current one.
+    fn shifted(&self, offset: &Vector2<Real>) -> Self {
+        Self {
+            acceleration: self.at(offset),
+            jacobian: self.jacobian + Matrix2::from_fn(|i, j| (self.hessians[i] * offset)[j]),
+            hessians: self.hessians,
+        }
+    }
+}
+
+/// Second moments of the mass of every cell around its center of mass, from the back so
+/// children are done before their parents like in `FlatTree::summarize`.
+fn quadrupoles(tree: &FlatTree) -> Vec<Matrix2<Real>> {
+    let nodes = tree.nodes();
+    let mut quadrupoles = vec![Matrix2::zeros(); nodes.len()];
+    for index in (0..nodes.len()).rev() {
+        let center = nodes[index].center_of_mass().position;
+        let quadrupole: Matrix2<Real> = nodes[index]
+            .children()
+            .map(|child| {
+                let child_mass = nodes[child as usize].center_of_mass();
+                let offset = child_mass.position - center;
+                quadrupoles[child as usize] + offset * offset.transpose() * child_mass.mass
+            })
+            .sum();
+        quadrupoles[index] = quadrupole;
+    }
+    quadrupoles
+}
+
+/// Walks pairs of a target and a source cell from the root down. Well separated sources
+/// are added to the `Local` of the target, otherwise the larger cell is opened, until both
+/// are leaves, which go into the near list of the target to be summed directly.
+fn interactions(
+    tree: &FlatTree,
+    quadrupoles: &[Matrix2<Real>],
+    config: &SimulationConfig,
+) -> (Vec<Local>, Vec<Vec<NodeIndex>>) {
+    let nodes = tree.nodes();
+    let mut locals = vec![Local::zero(); nodes.len()];
+    let mut near = vec![Vec::new(); nodes.len()];
+
+    // a stack, trees can be deeper than the call stack allows
+    let mut pairs: Vec<(NodeIndex, NodeIndex)> = vec![(0, 0)];
+    while let Some((target, source)) = pairs.pop() {
+        let (a, b) = (tree.node(target), tree.node(source));
+        if a.center_of_mass().mass == 0.0 || b.center_of_mass().mass == 0.0 {
+            continue;
+        }
+
+        let offset = a.center_of_mass().position - b.center_of_mass().position;
+        if a.scale() + b.scale() < config.theta * offset.norm() {
+            locals[target as usize].add_source(
+                b.center_of_mass(),
+                &quadrupoles[source as usize],
+                offset,
+                config.softening,
+            );
+        } else if a.is_leaf() && b.is_leaf() {
+            near[target as usize].push(source);
+        } else if b.is_leaf() || (!a.is_leaf() && a.scale() > b.scale()) {
+            pairs.extend(a.children().map(|child| (child, source)));
+        } else {
+            pairs.extend(b.children().map(|child| (target, child)));
+        }
+    }
+    (locals, near)
+}
+
+/// `force_part` of `source` on a body at `pos`, skipping sources right at it.
+fn pull(source: &MassData, pos: &Vector2<Real>, config: &SimulationConfig) -> Vector2<Real> {
+    let diff = source.position - pos;
+    let dist_sq = diff.norm_squared();
+    if !dist_sq.is_normal() {
+        return Vector2::zeros();
+    }
+
+    let dist = tree::softened(dist_sq, config.softening);
+    diff / dist.powi(3) * source.mass
+}
@@ -0,0 +1,11 @@
+use crate::tree::FlatTree;
+use crate::{Real, Star};
+use nalgebra::Vector2;
+
+/// An additional force acting on every star, registered with `Simulation::add_force`.
+/// Accelerations are added to the gravitational one before integrating.
+pub trait ForceTerm: Send + Sync {
+    /// Returns the acceleration of `star`. `tree` is the quad tree of the current update,
+    /// already summarized, so nodes can be queried for their center of mass.
+    fn acceleration(&self, star: &Star, tree: &FlatTree) -> Vector2<Real>;
+}
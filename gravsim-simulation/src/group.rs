@@ -0,0 +1,296 @@
+use crate::schedule::{Action, Event};
+use crate::{Real, Simulation, Star, StarId};
+use alloc::collections::VecDeque;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use nalgebra::Vector2;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Stars that were created together and are tracked as one, e.g. a galaxy of a scenario.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Group {
+    pub name: String,
+    pub stars: Vec<StarId>,
+}
+
+/// The part of a group that is still gravitationally bound to it.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BoundState {
+    pub mass: f64,
+    pub center_of_mass: Vector2<f64>,
+}
+
+impl Group {
+    /// Iterations of `bound_state`, each one drops the stars unbound from the previous estimate.
+    const BOUND_ITERATIONS: usize = 3;
+
+    /// Finds the bound part of this group, treating it as a point mass at its center of mass.
+    /// A star is bound if its kinetic energy relative to the center of mass is smaller than
+    /// its potential energy, stars that left the simulation never are.
+    pub fn bound_state(&self, stars: &[Star], gravity: Real) -> BoundState {
+        let mut bound: Vec<&Star> = self
+            .stars
+            .iter()
+            .filter_map(|&id| stars.get(id))
+            .filter(|star| star.mass() > 0.0 && star.pos().iter().all(|x| x.is_finite()))
+            .collect();
+
+        let mut state = center_of_mass(&bound);
+        for _ in 0..Self::BOUND_ITERATIONS {
+            let velocity = bound
+                .iter()
+                .map(|star| star.vel.cast::<f64>() * star.mass() as f64)
+                .sum::<Vector2<f64>>()
+                / state.mass;
+            let mu = gravity as f64 * state.mass;
+            bound.retain(|star| {
+                let distance = (star.pos().cast::<f64>() - state.center_of_mass).norm();
+                let speed_sq = (star.vel.cast::<f64>() - velocity).norm_squared();
+                distance == 0.0 || 0.5 * speed_sq < mu / distance
+            });
+            state = center_of_mass(&bound);
+        }
+        state
+    }
+}
+
+fn center_of_mass(stars: &[&Star]) -> BoundState {
+    let mass: f64 = stars.iter().map(|star| star.mass() as f64).sum();
+    let weighted = stars
+        .iter()
+        .map(|star| star.pos().cast::<f64>() * star.mass() as f64)
+        .sum::<Vector2<f64>>();
+    BoundState {
+        mass,
+        center_of_mass: match mass > 0.0 {
+            true => weighted / mass,
+            false => Vector2::zeros(),
+        },
+    }
+}
+
+/// A group losing much of its bound mass in a short time, see `DisruptionMonitor`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Disruption {
+    /// index of the group in `DisruptionMonitor::groups`
+    pub group: usize,
+    pub time: f64,
+    /// center of mass of the group before it lost the mass
+    pub position: Vector2<Real>,
+    /// lost share of the bound mass at the start of the window
+    pub lost_fraction: f64,
+}
+
+/// Watches the bound mass of groups during `update`, and reports a `Disruption` when a group
+/// loses at least `fraction` of it within `window` units of simulated time. A group is only
+/// reported again once it has recovered, so a single tidal event isn't reported every update.
+#[derive(Clone, Debug)]
+pub struct DisruptionMonitor {
+    pub groups: Vec<Group>,
+    pub fraction: f64,
+    pub window: f64,
+    /// recent bound states of each group with their time, oldest first
+    history: Vec<VecDeque<(f64, BoundState)>>,
+    /// whether each group is currently reported as disrupted
+    disrupted: Vec<bool>,
+}
+
+impl DisruptionMonitor {
+    pub fn new(groups: Vec<Group>, fraction: f64, window: f64) -> Self {
+        Self {
+            history: vec![VecDeque::new(); groups.len()],
+            disrupted: vec![false; groups.len()],
+            groups,
+            fraction,
+            window,
+        }
+    }
+
+    /// Records the bound mass of every group at `time` and returns the new disruptions.
+    pub fn record(&mut self, stars: &[Star], gravity: Real, time: f64) -> Vec<Disruption> {
+        let mut disruptions = Vec::new();
+        for (index, group) in self.groups.iter().enumerate() {
+            let history = &mut self.history[index];
+            while history
+                .front()
+                .is_some_and(|&(recorded, _)| recorded < time - self.window)
+            {
+                history.pop_front();
+            }
+            let state = group.bound_state(stars, gravity);
+            history.push_back((time, state));
+
+            // the heaviest bound state in the window is the reference
+            let (_, reference) = history
+                .iter()
+                .copied()
+                .max_by(|a, b| a.1.mass.total_cmp(&b.1.mass))
+                .unwrap_or((time, state));
+            let lost_fraction = match reference.mass > 0.0 {
+                true => 1.0 - state.mass / reference.mass,
+                false => 0.0,
+            };
+
+            let disrupted = lost_fraction >= self.fraction;
+            if disrupted && !self.disrupted[index] {
+                disruptions.push(Disruption {
+                    group: index,
+                    time,
+                    position: reference.center_of_mass.cast(),
+                    lost_fraction,
+                });
+            }
+            self.disrupted[index] = disrupted;
+        }
+        disruptions
+    }
+}
+
+/// Closest approach of the centers of mass of two groups, see `PericenterMonitor`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pericenter {
+    /// indices of the groups in `PericenterMonitor::groups`, the lower one first
+    pub groups: [usize; 2],
+    /// time of the update closest to the passage, the one before it was detected
+    pub time: f64,
+    /// distance of the centers of mass at `time`
+    pub distance: f64,
+    /// midway between the centers of mass at `time`
+    pub position: Vector2<Real>,
+}
+
+/// Watches the distance between the bound centers of mass of every pair of groups during
+/// `update`, e.g. of two merging galaxies, and reports a `Pericenter` once it passed a
+/// minimum. A pair is near a passage from when it is expected within `window` units of
+/// simulated time, judged by the current speed of approach, until `window` after it, so
+/// output around it can be dense, see `output::Trigger::NearPericenter`.
+#[derive(Clone, Debug)]
+pub struct PericenterMonitor {
+    pub groups: Vec<Group>,
+    pub window: f64,
+    /// by pair of groups, in the order of `pairs`
+    approaches: Vec<Approach>,
+}
+
+/// Recent distances of a pair of groups.
+#[derive(Copy, Clone, Debug, Default)]
+struct Approach {
+    /// time, distance and midpoint after the last two updates, oldest first
+    samples: [Option<(f64, f64, Vector2<f64>)>; 2],
+    /// whether the pair is getting closer and expected to pass within the window
+    closing: bool,
+    /// time of the last passage
+    passed: Option<f64>,
+}
+
+impl PericenterMonitor {
+    pub fn new(groups: Vec<Group>, window: f64) -> Self {
+        let pairs = groups.len() * groups.len().saturating_sub(1) / 2;
+        Self {
+            approaches: vec![Approach::default(); pairs],
+            groups,
+            window,
+        }
+    }
+
+    /// Records the distances of all pairs of groups at `time` and returns the new passages.
+    pub fn record(&mut self, stars: &[Star], gravity: Real, time: f64) -> Vec<Pericenter> {
+        let states: Vec<_> = self
+            .groups
+            .iter()
+            .map(|group| group.bound_state(stars, gravity))
+            .collect();
+
+        let mut pericenters = Vec::new();
+        for (groups, approach) in pairs(self.groups.len()).zip(&mut self.approaches) {
+            let [a, b] = groups.map(|group| states[group]);
+            if a.mass <= 0.0 || b.mass <= 0.0 {
+                *approach = Approach::default();
+                continue;
+            }
+            let distance = (b.center_of_mass - a.center_of_mass).norm();
+            let midpoint = (a.center_of_mass + b.center_of_mass) / 2.0;
+
+            match approach.samples {
+                [Some((_, before, _)), Some((last_time, last, position))]
+                    if last < before && distance > last =>
+                {
+                    pericenters.push(Pericenter {
+                        groups,
+                        time: last_time,
+                        distance: last,
+                        position: position.cast(),
+                    });
+                    approach.passed = Some(last_time);
+                    approach.closing = false;
+                }
+                [_, Some((last_time, last, _))] if distance < last && time > last_time => {
+                    let speed = (last - distance) / (time - last_time);
+                    approach.closing |= distance / speed <= self.window;
+                }
+                _ => {}
+            }
+            approach.samples = [approach.samples[1], Some((time, distance, midpoint))];
+        }
+        pericenters
+    }
+
+    /// Whether any pair of groups is near a passage after the last `record`, at `time`.
+    pub fn is_near(&self, time: f64) -> bool {
+        self.approaches.iter().any(|approach| {
+            approach.closing
+                || approach
+                    .passed
+                    .is_some_and(|passed| time <= passed + self.window)
+        })
+    }
+}
+
+/// Indices of every pair of `count` groups, the lower one first.
+fn pairs(count: usize) -> impl Iterator<Item = [usize; 2]> {
+    (0..count).flat_map(move |a| (a + 1..count).map(move |b| [a, b]))
+}
+
+impl Simulation {
+    /// Runs the pericenter monitor after an update, if there is one. New passages are
+    /// reported in `pericenters` and as `Marker` events in `fired`.
+    pub(crate) fn monitor_pericenters(&mut self) {
+        self.pericenters.clear();
+        let Some(monitor) = &mut self.pericenter_monitor else {
+            return;
+        };
+
+        self.pericenters = monitor.record(&self.stars, self.config.gravity, self.time);
+        for pericenter in &self.pericenters {
+            let [a, b] = pericenter.groups.map(|group| &monitor.groups[group].name);
+            self.fired.push(Event {
+                time: pericenter.time,
+                action: Action::Marker(format!("pericenter of {} and {}", a, b)),
+            });
+        }
+    }
+
+    /// Runs the disruption monitor after an update, if there is one. New disruptions are
+    /// reported in `disruptions` and as `Marker` events in `fired`.
+    pub(crate) fn monitor_disruptions(&mut self) {
+        self.disruptions.clear();
+        let Some(monitor) = &mut self.disruption_monitor else {
+            return;
+        };
+
+        self.disruptions = monitor.record(&self.stars, self.config.gravity, self.time);
+        for disruption in &self.disruptions {
+            self.fired.push(Event {
+                time: disruption.time,
+                action: Action::Marker(format!(
+                    "disruption of {}",
+                    monitor.groups[disruption.group].name
+                )),
+            });
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use crate::{Real, Star};
+use alloc::vec::Vec;
+use nalgebra::Vector2;
+
+/// Evaluates the acceleration of every star in the given state, building a new tree each call.
+pub type Accelerations<'a> = dyn FnMut(&[Star]) -> Vec<Vector2<Real>> + 'a;
+
+/// Advances all stars by one update, selected with `Simulation::set_integrator`.
+pub trait Integrator: Send + Sync {
+    /// Moves every star by its own time step `dt[i]`, evaluating `accelerations` as often as needed.
+    fn integrate(&self, stars: &mut [Star], dt: &[Real], accelerations: &mut Accelerations);
+}
+
+/// Semi-implicit Euler, one force evaluation per update. This is the default.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Euler;
+
+impl Integrator for Euler {
+    fn integrate(&self, stars: &mut [Star], dt: &[Real], accelerations: &mut Accelerations) {
+        let acc = accelerations(stars);
+        for ((star, acc), &dt) in stars.iter_mut().zip(acc).zip(dt) {
+            star.vel += acc * dt;
+            star.mass_point.position += star.vel * dt;
+        }
+    }
+}
+
+/// Drift-kick-drift leapfrog: symplectic and second order, for the cost of one force evaluation.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Leapfrog;
+
+impl Integrator for Leapfrog {
+    fn integrate(&self, stars: &mut [Star], dt: &[Real], accelerations: &mut Accelerations) {
+        drift(stars, dt, 0.5);
+        let acc = accelerations(stars);
+        for ((star, acc), &dt) in stars.iter_mut().zip(acc).zip(dt) {
+            star.vel += acc * dt;
+        }
+        drift(stars, dt, 0.5);
+    }
+}
+
+/// Velocity Verlet: symplectic and second order like `Leapfrog`, but positions and velocities
+/// are synchronized at every step. Takes two force evaluations per update.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Verlet;
+
+impl Integrator for Verlet {
+    fn integrate(&self, stars: &mut [Star], dt: &[Real], accelerations: &mut Accelerations) {
+        let old = accelerations(stars);
+        for ((star, acc), &dt) in stars.iter_mut().zip(&old).zip(dt) {
+            star.mass_point.position += (star.vel + acc * (0.5 * dt)) * dt;
+        }
+
+        let new = accelerations(stars);
+        for ((star, (old, new)), &dt) in stars.iter_mut().zip(old.into_iter().zip(new)).zip(dt) {
+            star.vel += (old + new) * (0.5 * dt);
+        }
+    }
+}
+
+/// Classic fourth order Runge-Kutta. Very accurate for short runs, but not symplectic,
+/// so energy still drifts over long ones. Takes four force evaluations per update.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct RungeKutta4;
+
+impl Integrator for RungeKutta4 {
+    fn integrate(&self, stars: &mut [Star], dt: &[Real], accelerations: &mut Accelerations) {
+        // each stage is the (velocity, acceleration) derivative of every star
+        let mut stages: Vec<Vec<(Vector2<Real>, Vector2<Real>)>> = Vec::with_capacity(4);
+        for fraction in [0.0, 0.5, 0.5, 1.0] {
+            let state: Vec<_> = match stages.last() {
+                Some(previous) => stars
+                    .iter()
+                    .zip(previous)
+                    .zip(dt)
+                    .map(|((star, (vel, acc)), &dt)| {
+                        let mut star = *star;
+                        star.mass_point.position += vel * (fraction * dt);
+                        star.vel += acc * (fraction * dt);
+                        star
+                    })
+                    .collect(),
+                None => stars.to_vec(),
+            };
+            let acc = accelerations(&state);
+            stages.push(state.iter().map(|star| star.vel).zip(acc).collect());
+        }
+
+        for (i, (star, &dt)) in stars.iter_mut().zip(dt).enumerate() {
+            let [k1, k2, k3, k4] = [0, 1, 2, 3].map(|stage| stages[stage][i]);
+            star.mass_point.position += (k1.0 + (k2.0 + k3.0) * 2.0 + k4.0) * (dt / 6.0);
+            star.vel += (k1.1 + (k2.1 + k3.1) * 2.0 + k4.1) * (dt / 6.0);
+        }
+    }
+}
+
+fn drift(stars: &mut [Star], dt: &[Real], fraction: Real) {
+    for (star, &dt) in stars.iter_mut().zip(dt) {
+        star.mass_point.position += star.vel * (fraction * dt);
+    }
+}
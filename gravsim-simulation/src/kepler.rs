@@ -0,0 +1,62 @@
+use crate::{Real, Star};
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+
+/// Instantaneous Keplerian elements of two stars, as if they were alone. Computed in f64
+/// from their relative position and velocity, ignoring the softening of the force.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Orbit {
+    /// negative for unbound pairs
+    pub semi_major_axis: f64,
+    pub eccentricity: f64,
+    /// closest distance of the stars along the orbit
+    pub pericenter: f64,
+    /// `None` for unbound pairs, which never return
+    pub period: Option<f64>,
+    /// distance and relative speed at the moment of the fit
+    pub separation: f64,
+    pub relative_speed: f64,
+}
+
+impl Orbit {
+    /// Fits the orbit of `a` and `b` under the gravitational constant `gravity`.
+    /// Returns `None` for coincident stars, stars without mass or stars that left the simulation.
+    pub fn of(a: &Star, b: &Star, gravity: Real) -> Option<Self> {
+        let pos = (b.pos() - a.pos()).cast::<f64>();
+        let vel = (b.vel - a.vel).cast::<f64>();
+        let mu = gravity as f64 * (a.mass() as f64 + b.mass() as f64);
+        let separation = pos.norm();
+        if !(separation > 0.0 && mu > 0.0 && vel.iter().all(|x| x.is_finite())) {
+            return None;
+        }
+
+        let energy = 0.5 * vel.norm_squared() - mu / separation;
+        let angular_momentum = pos.x * vel.y - pos.y * vel.x;
+        let eccentricity = (1.0 + 2.0 * energy * angular_momentum.powi(2) / mu.powi(2))
+            .max(0.0)
+            .sqrt();
+        let semi_major_axis = -mu / (2.0 * energy);
+        let bound = energy < 0.0;
+
+        Some(Self {
+            semi_major_axis,
+            eccentricity,
+            pericenter: angular_momentum.powi(2) / (mu * (1.0 + eccentricity)),
+            period: bound
+                .then(|| 2.0 * core::f64::consts::PI * (semi_major_axis.powi(3) / mu).sqrt()),
+            separation,
+            relative_speed: vel.norm(),
+        })
+    }
+
+    /// Whether the pair would stay together if nothing else acted on it.
+    pub fn is_bound(&self) -> bool {
+        self.period.is_some()
+    }
+
+    /// Farthest distance of the stars along the orbit, `None` for unbound pairs.
+    pub fn apocenter(&self) -> Option<f64> {
+        self.is_bound()
+            .then_some(self.semi_major_axis * (1.0 + self.eccentricity))
+    }
+}
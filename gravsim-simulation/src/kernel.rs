@@ -0,0 +1,160 @@
+use crate::{MassData, Real};
+use nalgebra::Vector2;
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+
+/// Instruction sets the force kernels are compiled for, picked at runtime with
+/// `InstructionSet::detect`, so prebuilt binaries use wide vectors on machines that have them.
+/// All of them sum in the same order and give bit identical results, which keeps
+/// simulations reproducible across machines.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum InstructionSet {
+    Scalar,
+    Avx2,
+    Avx512,
+    Neon,
+}
+
+/// Sources are summed into this many independent partial sums, which compilers can keep
+/// in vector registers without reordering any addition.
+const LANES: usize = 16;
+
+impl InstructionSet {
+    pub const ALL: [Self; 4] = [Self::Avx512, Self::Avx2, Self::Neon, Self::Scalar];
+
+    /// The widest instruction set of this machine. Without `std` only what the build
+    /// targets is used, as there is no runtime detection.
+    pub fn detect() -> Self {
+        Self::ALL
+            .into_iter()
+            .find(|set| set.is_supported())
+            .unwrap_or(Self::Scalar)
+    }
+
+    pub fn is_supported(self) -> bool {
+        match self {
+            Self::Scalar => true,
+            #[cfg(all(target_arch = "x86_64", feature = "std"))]
+            Self::Avx2 => {
+                std::arch::is_x86_feature_detected!("avx2")
+                    && std::arch::is_x86_feature_detected!("fma")
+            }
+            #[cfg(all(target_arch = "x86_64", not(feature = "std")))]
+            Self::Avx2 => cfg!(all(target_feature = "avx2", target_feature = "fma")),
+            #[cfg(all(target_arch = "x86_64", feature = "std"))]
+            Self::Avx512 => std::arch::is_x86_feature_detected!("avx512f"),
+            #[cfg(all(target_arch = "x86_64", not(feature = "std")))]
+            Self::Avx512 => cfg!(target_feature = "avx512f"),
+            #[cfg(all(target_arch = "aarch64", feature = "std"))]
+            Self::Neon => std::arch::is_aarch64_feature_detected!("neon"),
+            #[cfg(all(target_arch = "aarch64", not(feature = "std")))]
+            Self::Neon => cfg!(target_feature = "neon"),
+            _ => false,
+        }
+    }
+
+    /// `force_part` compiled for this instruction set, or for `Scalar` if this machine
+    /// doesn't support it.
+    pub fn force_part(
+        self,
+        pos: &Vector2<Real>,
+        sources: &[MassData],
+        softening: Real,
+    ) -> Vector2<Real> {
+        if !self.is_supported() {
+            return lanes(pos, sources, softening);
+        }
+
+        // SAFETY: the target features of each version were detected just now
+        match self {
+            #[cfg(target_arch = "x86_64")]
+            Self::Avx2 => unsafe { x86::avx2(pos, sources, softening) },
+            #[cfg(target_arch = "x86_64")]
+            Self::Avx512 => unsafe { x86::avx512(pos, sources, softening) },
+            #[cfg(target_arch = "aarch64")]
+            Self::Neon => unsafe { aarch64::neon(pos, sources, softening) },
+            _ => lanes(pos, sources, softening),
+        }
+    }
+}
+
+/// The pull of all `sources` on a body at `pos` without the gravitational constant and
+/// the mass of the body, like `force_part` of the tree walk, skipping sources right at
+/// `pos`. Runs the version of `InstructionSet::detect`.
+pub fn force_part(pos: &Vector2<Real>, sources: &[MassData], softening: Real) -> Vector2<Real> {
+    InstructionSet::detect().force_part(pos, sources, softening)
+}
+
+/// The kernel all instruction sets compile, inlined into each of them.
+#[inline(always)]
+fn lanes(pos: &Vector2<Real>, sources: &[MassData], softening: Real) -> Vector2<Real> {
+    let softening_sq = softening * softening;
+    let mut x = [0.0; LANES];
+    let mut y = [0.0; LANES];
+    let mut add = |lane: usize, source: &MassData| {
+        let dx = source.position.x - pos.x;
+        let dy = source.position.y - pos.y;
+        let dist_sq = dx * dx + dy * dy;
+        let dist = (softening_sq + dist_sq).sqrt();
+        // a select instead of a branch, so lanes stay in lockstep
+        let weight = match dist_sq.is_normal() {
+            true => source.mass / (dist * dist * dist),
+            false => 0.0,
+        };
+        x[lane] += dx * weight;
+        y[lane] += dy * weight;
+    };
+
+    let mut chunks = sources.chunks_exact(LANES);
+    for chunk in &mut chunks {
+        for (lane, source) in chunk.iter().enumerate() {
+            add(lane, source);
+        }
+    }
+    for (lane, source) in chunks.remainder().iter().enumerate() {
+        add(lane, source);
+    }
+
+    let sum = |lanes: [Real; LANES]| lanes.into_iter().fold(0.0, |sum, lane| sum + lane);
+    Vector2::new(sum(x), sum(y))
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use super::*;
+
+    // `fma` only speeds up explicit `mul_add`s, `a * b + c` is never fused, so the
+    // results stay those of `Scalar`
+
+    #[target_feature(enable = "avx2,fma")]
+    pub(super) unsafe fn avx2(
+        pos: &Vector2<Real>,
+        sources: &[MassData],
+        softening: Real,
+    ) -> Vector2<Real> {
+        lanes(pos, sources, softening)
+    }
+
+    #[target_feature(enable = "avx512f")]
+    pub(super) unsafe fn avx512(
+        pos: &Vector2<Real>,
+        sources: &[MassData],
+        softening: Real,
+    ) -> Vector2<Real> {
+        lanes(pos, sources, softening)
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+mod aarch64 {
+    use super::*;
+
+    #[target_feature(enable = "neon")]
+    pub(super) unsafe fn neon(
+        pos: &Vector2<Real>,
+        sources: &[MassData],
+        softening: Real,
+    ) -> Vector2<Real> {
+        lanes(pos, sources, softening)
+    }
+}
@@ -1,143 +1,1110 @@
-use crate::tree::Node;
-use nalgebra::{Vector2, Vector3};
+//! Barnes-Hut simulation of stars in 2d, in O(n log n) per update.
+//!
+//! ```
+//! use gravsim_simulation::{Simulation, Star};
+//! use nalgebra::Vector2;
+//!
+//! let mut simulation = Simulation::new([
+//!     Star::new(Vector2::zeros(), Vector2::zeros(), 1e6),
+//!     Star::new(Vector2::new(1000.0, 0.0), Vector2::new(0.0, 0.3), 1.0),
+//! ]);
+//! simulation.update();
+//! assert_eq!(simulation.step, 1);
+//! ```
+//!
+//! The `examples` directory has complete programs: an orbit, a headless galaxy collision,
+//! a force of its own and a scenario read from a file.
+//!
+//! # Reproducibility
+//!
+//! Updates give bit identical results whatever the number of threads, with or without
+//! `rayon`: forces are computed per star, and every parallel sum (tree summaries, the near
+//! field buffers, diagnostics) is added up in a fixed order. The force kernels of all
+//! `kernel::InstructionSet`s agree as well, so a run repeats exactly on another machine of
+//! the same architecture, given the same stars, config and precision. Randomness only
+//! enters through seeds, use `Galaxy::new_seeded` and a seeded `ThermalNoise` to repeat
+//! runs from scratch.
+
+// casts between `Real` and `f32` or `f64` are no-ops in one of the precisions
+#![allow(clippy::unnecessary_cast)]
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use crate::collision::{CollisionModel, Debris, Merge, Species};
+#[cfg(all(feature = "rand", feature = "std"))]
+use crate::color::ColorPolicy;
+use crate::diagnostics::ErrorEstimate;
+use crate::evolution::Evolution;
+use crate::force::ForceTerm;
+use crate::group::{Disruption, DisruptionMonitor, Pericenter, PericenterMonitor};
+use crate::integrator::{Euler, Integrator};
+use crate::pipeline::Stage;
+use crate::potential::ExternalPotential;
+use crate::probe::Probe;
+use crate::radius::{ConstantDensity, MassRadiusRelation};
+use crate::rebuild::{IncrementalRebuild, TreeRebuild};
+use crate::reuse::{ReusedTree, TreeReuse};
+#[cfg(all(feature = "rand", feature = "std"))]
+use crate::sampling::{Sampling, UnitSquare};
+use crate::schedule::{Event, Schedule};
+use crate::solver::{ForceSolver, Solver};
+#[cfg(feature = "rand")]
+use crate::thermal::ThermalNoise;
+use crate::tree::{Aabb, FlatTree, TraversalStats};
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use bytemuck::{Pod, Zeroable};
+use nalgebra::Vector2;
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+#[cfg(feature = "rand")]
 use rand::{Rng, SeedableRng};
+#[cfg(feature = "rand")]
 use rand_xorshift::XorShiftRng;
+#[cfg(feature = "rayon")]
 use rayon::prelude::*;
+#[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
+#[cfg(all(feature = "rand", feature = "std"))]
+use smallvec::SmallVec;
 
+pub mod backend;
+pub mod collision;
+pub mod color;
+pub mod diagnostics;
+pub mod diff;
+pub mod direct;
+pub mod evolution;
+pub mod fits;
+pub mod fmm;
+pub mod force;
+pub mod group;
+pub mod integrator;
+pub mod kepler;
+pub mod kernel;
+pub mod map;
+pub mod morton;
+pub mod near_field;
+#[cfg(feature = "3d")]
+pub mod octree;
+pub mod output;
+pub mod pipeline;
+pub mod potential;
+pub mod probe;
+pub mod radius;
+pub mod rebuild;
+#[cfg(feature = "replay")]
+pub mod replay;
+pub mod reuse;
+pub mod sampling;
+#[cfg(feature = "scenario")]
+pub mod scenario;
+pub mod schedule;
+#[cfg(feature = "scripting")]
+pub mod script;
+#[cfg(feature = "snapshot")]
+pub mod snapshot;
+pub mod solver;
+#[cfg(feature = "rand")]
+pub mod thermal;
+#[cfg(feature = "3d")]
+pub mod three_d;
+pub mod transform;
 pub mod tree;
+pub mod validate;
 
-#[derive(Copy, Clone, Debug)]
+/// Re-exported so consumers use the same version as the public API.
+/// All positions and velocities can also be passed as plain `[Real; 2]` arrays instead.
+pub use nalgebra;
+
+/// Float type of all positions, velocities and masses. `f32` by default, `f64` with the
+/// `f64` feature for large domains and long runs.
+#[cfg(not(feature = "f64"))]
+pub type Real = f32;
+#[cfg(feature = "f64")]
+pub type Real = f64;
+#[cfg(not(feature = "f64"))]
+pub(crate) use core::f32::consts;
+#[cfg(feature = "f64")]
+pub(crate) use core::f64::consts;
+
+/// Index of a star in `Simulation::stars`. Renderers key per star attributes (e.g. colors) by it.
+pub type StarId = usize;
+
+/// `repr(C)` and `Pod`, so a slice of stars can be uploaded to the gpu as is (in single precision).
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Star {
     pub mass_point: MassData,
-    pub vel: Vector2<f32>,
-    pub color: [f32; 3],
+    pub vel: Vector2<Real>,
 }
 
 impl Star {
-    pub const DENSITY: f32 = 250.0;
+    pub const DENSITY: Real = 250.0;
 
-    pub fn new(pos: Vector2<f32>, vel: Vector2<f32>, color: [f32; 3], mass: f32) -> Self {
+    pub fn new(pos: Vector2<Real>, vel: Vector2<Real>, mass: Real) -> Self {
         Self {
             mass_point: MassData {
                 position: pos,
                 mass,
             },
             vel,
-            color,
         }
     }
 
-    pub fn radius(&self) -> f32 {
-        (0.75 * self.mass_point.mass / (Self::DENSITY * std::f32::consts::PI)).cbrt()
+    /// Like `new`, for callers that don't use nalgebra.
+    pub fn from_arrays(pos: [Real; 2], vel: [Real; 2], mass: Real) -> Self {
+        Self::new(pos.into(), vel.into(), mass)
     }
 
-    pub fn color(&self) -> [f32; 3] {
-        self.color
+    /// Radius with the default `ConstantDensity` relation, `Simulation::radius` takes the
+    /// relation of the simulation into account.
+    pub fn radius(&self) -> Real {
+        ConstantDensity::DEFAULT.radius(self.mass())
     }
 
-    pub fn mass(&self) -> f32 {
+    pub fn mass(&self) -> Real {
         self.mass_point.mass
     }
 
-    pub fn pos(&self) -> &Vector2<f32> {
+    pub fn pos(&self) -> &Vector2<Real> {
         &self.mass_point.position
     }
+
+    pub fn pos_array(&self) -> [Real; 2] {
+        self.mass_point.position.into()
+    }
+
+    pub fn vel_array(&self) -> [Real; 2] {
+        self.vel.into()
+    }
+}
+
+/// What a star stands for, see `Simulation::kinds`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum BodyKind {
+    #[default]
+    Star,
+    /// a supermassive body, e.g. the center of a galaxy. It is left out of the tree like
+    /// stars of `SimulationConfig::dominant_mass` and is pulled by every other star
+    /// directly, so approximation errors of the tree don't make it drift.
+    BlackHole,
+}
+
+/// Whether star `id` is left out of trees and its force summed directly, as it is a black
+/// hole or at least `dominant_mass` heavy.
+pub(crate) fn is_direct(
+    id: StarId,
+    star: &Star,
+    kinds: &[BodyKind],
+    config: &SimulationConfig,
+) -> bool {
+    kinds.get(id) == Some(&BodyKind::BlackHole)
+        || config.dominant_mass.is_some_and(|mass| star.mass() >= mass)
 }
 
 /// Represents a mass point in space.
-#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct MassData {
-    pub position: Vector2<f32>,
-    pub mass: f32,
+    pub position: Vector2<Real>,
+    pub mass: Real,
+}
+
+impl MassData {
+    pub fn from_array(position: [Real; 2], mass: Real) -> Self {
+        Self {
+            position: position.into(),
+            mass,
+        }
+    }
+
+    pub fn position_array(&self) -> [Real; 2] {
+        self.position.into()
+    }
+}
+
+/// Parameters of a simulation run, the defaults are the constants on `Simulation`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+pub struct SimulationConfig {
+    /// opening angle of the tree traversal
+    pub theta: Real,
+    /// gravitational constant
+    pub gravity: Real,
+    /// Plummer softening length, bodies pull each other as if `softening²` was added to
+    /// their squared distance, so close encounters don't explode velocities
+    pub softening: Real,
+    /// simulated region, stars leaving it are removed
+    pub domain: Aabb,
+    /// time step of an update
+    pub dt: Real,
+    /// stars at least this heavy (e.g. a central black hole) are left out of the tree,
+    /// their force on every star is summed directly. Force terms don't see them in the tree.
+    pub dominant_mass: Option<Real>,
+    /// whether stars that overlap or crossed paths collide after every update, resolved
+    /// as `Simulation::collision_model` selects, see `collision::collide`
+    pub merge_collisions: bool,
+    /// if set, stars closer than this are evaluated pairwise with `near_field::accelerations`
+    /// and skipped by the tree traversal of `FlatTree`
+    pub near_field: Option<Real>,
+    /// if set, the tree is built over several updates and forces use the previous one
+    /// meanwhile, which bounds the cost of an update for very many stars
+    pub incremental_rebuild: Option<IncrementalRebuild>,
+    /// if set, the tree is kept between force evaluations and only the stars that moved are
+    /// reinserted, unless `incremental_rebuild` is set as well, which takes precedence
+    pub reuse_tree: Option<TreeReuse>,
+    /// if set, `update` reorders the stars by Morton code every this many updates before
+    /// anything else, which speeds up large simulations, see `Simulation::sort_stars`
+    pub sort_every: Option<u64>,
+    /// if set, every star gets a random velocity kick in each update, see `ThermalNoise`
+    #[cfg(feature = "rand")]
+    pub thermal_noise: Option<ThermalNoise>,
+    /// if set, stars age and change color over their lifetime, see `Evolution`
+    pub evolution: Option<Evolution>,
+    /// computes the gravity between the stars, unless `Simulation::set_force_solver` is used
+    pub solver: Solver,
 }
 
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        Self {
+            theta: Simulation::THETA,
+            gravity: Simulation::GRAVITY,
+            softening: Simulation::SOFTENING,
+            domain: Aabb::centered(Vector2::repeat(Simulation::SCALE)),
+            dt: 1.0,
+            dominant_mass: None,
+            merge_collisions: false,
+            near_field: None,
+            incremental_rebuild: None,
+            reuse_tree: None,
+            sort_every: None,
+            #[cfg(feature = "rand")]
+            thermal_noise: None,
+            evolution: None,
+            solver: Solver::Tree,
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct Simulation {
     pub stars: Vec<Star>,
+    pub config: SimulationConfig,
+    /// number of updates so far
+    pub step: u64,
+    /// simulated time so far, the sum of the time steps of all updates
+    pub time: f64,
+    /// events applied by `update` once `time` reaches them
+    pub schedule: Schedule,
+    /// events applied during the last update
+    pub fired: Vec<Event>,
+    /// if the last update sorted the stars, the previous id of the star at each id
+    pub sorted: Option<Vec<StarId>>,
+
+    /// whether `traversal_stats` should be recorded during `update`
+    pub record_stats: bool,
+    /// tree traversal statistics of the last force evaluation
+    pub traversal_stats: TraversalStats,
+    /// if set, `theta` is randomly perturbed every update
+    #[cfg(feature = "rand")]
+    pub theta_dither: Option<ThetaDither>,
+    /// regions in which time passes at a different rate
+    pub dilation_zones: Vec<DilationZone>,
+    /// if set, updated with the force error of every update
+    pub error_estimate: Option<ErrorEstimate>,
+    /// species of the stars by id, stars without an entry are of species 0
+    pub species: Vec<Species>,
+    /// spin angular momentum of the stars by id, which merges turn the orbital angular
+    /// momentum of both stars into, stars without an entry don't spin
+    pub spins: Vec<Real>,
+    /// kinds of the stars by id, stars without an entry are `BodyKind::Star`. A star
+    /// merging into a black hole is swallowed by it, and the other way around.
+    pub kinds: Vec<BodyKind>,
+    /// simulated time each star lived through by id, tracked while `config.evolution` is
+    /// set. A merged star keeps the age of the star it merged into.
+    pub ages: Vec<f64>,
+    /// stars that reached the end of their lifetime during the last update
+    pub expired: Vec<StarId>,
+    /// outcomes of collisions, merging everything by default
+    pub collision_model: CollisionModel,
+    /// stars merged during the last update
+    pub merges: Vec<Merge>,
+    /// pairs of stars that bounced off each other during the last update
+    pub bounces: Vec<[StarId; 2]>,
+    /// stars ejected by collisions during the last update
+    pub debris: Vec<Debris>,
+    /// if set, watches groups of stars for tidal disruptions
+    pub disruption_monitor: Option<DisruptionMonitor>,
+    /// disruptions detected during the last update
+    pub disruptions: Vec<Disruption>,
+    /// if set, watches groups of stars for closest approaches, e.g. of merging galaxies
+    pub pericenter_monitor: Option<PericenterMonitor>,
+    /// pericenter passages detected during the last update
+    pub pericenters: Vec<Pericenter>,
+    /// test particles moved through the field of the stars, see `Probe`
+    pub probes: Vec<Probe>,
+    /// stages of `update`, in order, see `Stage`
+    pub pipeline: Vec<Stage>,
+    /// updates since the build of the tree used by the last update started,
+    /// 0 unless `config.incremental_rebuild` is set
+    pub tree_staleness: u64,
+    rebuild: TreeRebuild,
+    reused: ReusedTree,
+
+    /// shared between clones, so a copy keeps the same physics
+    forces: Vec<Arc<dyn ForceTerm>>,
+    potentials: Vec<Arc<dyn ExternalPotential>>,
+    force_solver: Option<Arc<dyn ForceSolver>>,
+    integrator: Arc<dyn Integrator>,
+    mass_radius: Arc<dyn MassRadiusRelation>,
+}
+
+/// Forces `Simulation::accelerations` evaluates, by the stages in the pipeline.
+#[derive(Copy, Clone, Debug)]
+struct ForceStages {
+    gravity: bool,
+    external: bool,
 }
 
 impl Simulation {
-    pub const SCALE: f32 = 50000.0;
+    pub const SCALE: Real = 50000.0;
     pub const N_STARS: usize = 5_000;
-    pub const THETA: f32 = 0.5;
-    pub const GRAVITY: f32 = 1e-4;
+    pub const THETA: Real = 0.5;
+    pub const GRAVITY: Real = 1e-4;
+    pub const SOFTENING: Real = 0.2236068;
 
     pub fn new<I>(stars: I) -> Self
+    where
+        I: IntoIterator<Item = Star>,
+    {
+        Self::with_config(stars, SimulationConfig::default())
+    }
+
+    pub fn with_config<I>(stars: I, config: SimulationConfig) -> Self
     where
         I: IntoIterator<Item = Star>,
     {
         Self {
             stars: stars.into_iter().collect(),
+            config,
+            step: 0,
+            time: 0.0,
+            schedule: Schedule::default(),
+            fired: Vec::new(),
+            sorted: None,
+            record_stats: false,
+            traversal_stats: TraversalStats::default(),
+            #[cfg(feature = "rand")]
+            theta_dither: None,
+            dilation_zones: Vec::new(),
+            error_estimate: None,
+            species: Vec::new(),
+            spins: Vec::new(),
+            kinds: Vec::new(),
+            ages: Vec::new(),
+            expired: Vec::new(),
+            collision_model: CollisionModel::default(),
+            merges: Vec::new(),
+            bounces: Vec::new(),
+            debris: Vec::new(),
+            disruption_monitor: None,
+            disruptions: Vec::new(),
+            pericenter_monitor: None,
+            pericenters: Vec::new(),
+            probes: Vec::new(),
+            pipeline: Stage::default_pipeline(),
+            tree_staleness: 0,
+            rebuild: TreeRebuild::default(),
+            reused: ReusedTree::default(),
+            forces: Vec::new(),
+            potentials: Vec::new(),
+            force_solver: None,
+            integrator: Arc::new(Euler),
+            mass_radius: Arc::new(ConstantDensity::DEFAULT),
         }
     }
 
+    pub fn kind(&self, id: StarId) -> BodyKind {
+        self.kinds.get(id).copied().unwrap_or_default()
+    }
+
+    /// Ids of all black holes, see `BodyKind::BlackHole`.
+    pub fn black_holes(&self) -> impl Iterator<Item = StarId> + '_ {
+        self.kinds
+            .iter()
+            .enumerate()
+            .filter(|(_, kind)| **kind == BodyKind::BlackHole)
+            .map(|(id, _)| id)
+    }
+
+    /// Registers an additional force acting on all stars.
+    pub fn add_force(&mut self, force: Box<dyn ForceTerm>) {
+        self.forces.push(force.into());
+    }
+
+    /// Registers a fixed potential all stars and probes move through, e.g. a dark matter
+    /// halo, see `potential::ExternalPotential`.
+    pub fn add_potential(&mut self, potential: Box<dyn ExternalPotential>) {
+        self.potentials.push(potential.into());
+    }
+
+    /// Potential energy of the stars in the domain in the potentials of `add_potential`,
+    /// which `diagnostics::Diagnostics` doesn't know about.
+    pub fn external_potential_energy(&self) -> f64 {
+        self.stars
+            .iter()
+            .filter(|star| self.config.domain.contains(star.pos()))
+            .map(|star| star.mass() as f64 * self.external_potential(star.pos()) as f64)
+            .sum()
+    }
+
+    /// Potential per unit of mass at `pos` of all potentials of `add_potential`.
+    pub fn external_potential(&self, pos: &Vector2<Real>) -> Real {
+        self.potentials
+            .iter()
+            .map(|potential| potential.potential(pos, self.config.gravity))
+            .sum()
+    }
+
+    /// Replaces the gravity between the stars in the tree with the forces of `solver`, e.g.
+    /// `fmm::Fmm` for very many stars. By default (`None`) the solver of `config.solver` is
+    /// used, which walks the tree of the update unless another one is selected. Solvers build
+    /// their own tree and don't record `traversal_stats`, and neither `near_field` nor
+    /// `incremental_rebuild` apply to them.
+    pub fn set_force_solver(&mut self, solver: Option<Box<dyn ForceSolver>>) {
+        self.force_solver = solver.map(Into::into);
+    }
+
+    /// Replaces the integrator used by `update`, `Euler` by default.
+    pub fn set_integrator(&mut self, integrator: Box<dyn Integrator>) {
+        self.integrator = integrator.into();
+    }
+
+    /// Replaces the relation sizing stars for collisions and rendering, `ConstantDensity`
+    /// by default.
+    pub fn set_mass_radius_relation(&mut self, relation: Box<dyn MassRadiusRelation>) {
+        self.mass_radius = relation.into();
+    }
+
+    pub fn radius(&self, star: &Star) -> Real {
+        self.mass_radius.radius(star.mass())
+    }
+
+    /// Advances the simulation by `config.dt`, see `update_by`.
     pub fn update(&mut self) {
-        let mut tree = Node::new_root(-Vector2::repeat(Self::SCALE / 2.0), Self::SCALE);
+        self.update_by(self.config.dt);
+    }
+
+    /// Advances the simulation by one update with time step `dt` instead of `config.dt`,
+    /// e.g. the time since the last frame, so that frame pacing doesn't change the physics.
+    /// Velocities and positions are both integrated over `dt`, only shortened to land on
+    /// scheduled events.
+    pub fn update_by(&mut self, dt: Real) {
+        // events added since the last update may already be due
+        self.fired.clear();
+        self.fire_due_events();
+        // before anything refers to the stars by id
+        self.sorted = None;
+        if let Some(every) = self.config.sort_every.filter(|&every| every > 0) {
+            if self.step.is_multiple_of(every) {
+                self.sorted = Some(self.sort_stars());
+            }
+        }
 
-        // insert stars into tree
-        for star in &self.stars {
-            if tree.contains(star.pos()) {
-                tree.insert(&star.mass_point);
+        let old_velocities: Option<Vec<_>> = self
+            .error_estimate
+            .is_some()
+            .then(|| self.stars.iter().map(|star| star.vel).collect());
+        let old_positions: Option<Vec<_>> = self
+            .config
+            .merge_collisions
+            .then(|| self.stars.iter().map(|star| *star.pos()).collect());
+
+        #[cfg(feature = "rand")]
+        let config = match &mut self.theta_dither {
+            Some(dither) => SimulationConfig {
+                theta: dither.sample(self.config.theta),
+                ..self.config
+            },
+            None => self.config,
+        };
+        #[cfg(not(feature = "rand"))]
+        let config = self.config;
+        let (dt, event_time) = self.scheduled_dt(dt);
+        let config = SimulationConfig { dt, ..config };
+
+        self.merges.clear();
+        self.bounces.clear();
+        self.debris.clear();
+        let pipeline = core::mem::take(&mut self.pipeline);
+        let forces = ForceStages {
+            gravity: pipeline.contains(&Stage::Gravity),
+            external: pipeline.contains(&Stage::ExternalForces),
+        };
+        for stage in &pipeline {
+            match stage {
+                Stage::BuildTree => self.build_tree(&config),
+                Stage::Gravity | Stage::ExternalForces => {}
+                Stage::Integrate => self.integrate(&config, forces),
+                Stage::Probes => self.move_probes(&config, forces),
+                Stage::Boundaries => self.remove_outside(&config.domain),
+                Stage::Diagnostics => {
+                    if let (Some(estimate), Some(old_velocities)) =
+                        (&mut self.error_estimate, &old_velocities)
+                    {
+                        estimate.record(&self.stars, old_velocities);
+                    }
+                }
+                Stage::Collisions => {
+                    if let Some(old_positions) = &old_positions {
+                        self.collide(old_positions);
+                    }
+                }
+                Stage::ThermalNoise => {
+                    #[cfg(feature = "rand")]
+                    self.kick(&config);
+                }
+                Stage::Custom(stage) => stage.run(self),
             }
         }
+        // unless a custom stage replaced it
+        if self.pipeline.is_empty() {
+            self.pipeline = pipeline;
+        }
+
+        self.age_stars(&config);
 
-        // calculate force on stars
+        self.step += 1;
+        // exactly on the event, even if `dt` can't represent the remaining time exactly
+        self.time = event_time.unwrap_or(self.time + config.dt as f64);
+        self.fire_due_events();
+        self.monitor_disruptions();
+        self.monitor_pericenters();
+    }
+
+    /// `Stage::BuildTree`
+    fn build_tree(&mut self, config: &SimulationConfig) {
+        match &config.incremental_rebuild {
+            Some(settings) => {
+                self.rebuild
+                    .advance(&self.stars, &self.kinds, config, settings, self.step);
+                self.tree_staleness = self.rebuild.staleness(self.step).unwrap_or(0);
+            }
+            None => {
+                self.rebuild = TreeRebuild::default();
+                self.tree_staleness = 0;
+            }
+        }
+    }
+
+    /// Time step of each star in an update, 0 outside of the domain.
+    fn time_steps(&self, config: &SimulationConfig) -> Vec<Real> {
         self.stars
-            .par_iter_mut()
-            .filter(|star| tree.contains(star.pos()))
-            .for_each(|star| {
-                let force = tree.force_on(&star.mass_point);
-                star.vel += force / star.mass();
+            .iter()
+            .map(|star| match config.domain.contains(star.pos()) {
+                true => config.dt * DilationZone::time_scale(&self.dilation_zones, star.pos()),
+                false => 0.0,
+            })
+            .collect()
+    }
 
-                // integration can be done here because tree doesn't change
-                star.mass_point.position += star.vel;
-            });
+    /// `Stage::Integrate`, stars outside of the domain don't move.
+    fn integrate(&mut self, config: &SimulationConfig, forces: ForceStages) {
+        let dt = self.time_steps(config);
+        let mut stars = core::mem::take(&mut self.stars);
+        let mut traversal_stats = TraversalStats::default();
+        // brought up to date for every force evaluation, dropped once reuse is turned off
+        let reuse = config
+            .reuse_tree
+            .filter(|_| config.incremental_rebuild.is_none());
+        let mut reused = match reuse {
+            Some(_) => core::mem::take(&mut self.reused),
+            None => ReusedTree::default(),
+        };
+        self.integrator.integrate(&mut stars, &dt, &mut |stars| {
+            if let Some(settings) = &reuse {
+                reused.update(stars, &self.kinds, config, settings);
+            }
+            let reused = reuse.and(reused.tree());
+            let (accelerations, stats) = self.accelerations(stars, config, forces, reused);
+            traversal_stats = stats;
+            accelerations
+        });
+        self.stars = stars;
+        self.reused = reused;
+        self.traversal_stats = traversal_stats;
+    }
+
+    /// `Stage::Probes`, in the field of a tree of the current stars. Dominant stars are
+    /// inserted like all others, and there is no near field.
+    fn move_probes(&mut self, config: &SimulationConfig, forces: ForceStages) {
+        if self.probes.is_empty() {
+            return;
+        }
+
+        let mut probes = core::mem::take(&mut self.probes);
+        let field = self.frozen_field(config, forces, None);
+        for probe in &mut probes {
+            let dt = config.dt * DilationZone::time_scale(&self.dilation_zones, &probe.pos);
+            probe.advance(dt, |pos| field.acceleration(pos));
+        }
+        self.probes = probes;
+    }
+
+    /// `Stage::ThermalNoise`, stars outside of the domain aren't kicked.
+    #[cfg(feature = "rand")]
+    fn kick(&mut self, config: &SimulationConfig) {
+        let Some(noise) = &config.thermal_noise else {
+            return;
+        };
+        let kicks = noise.kicks(&self.stars, &self.time_steps(config), self.step);
+        for (star, kick) in self.stars.iter_mut().zip(kicks) {
+            star.vel += kick;
+        }
+    }
 
+    /// `Stage::Boundaries`
+    fn remove_outside(&mut self, domain: &Aabb) {
         self.stars
             .iter_mut()
-            .filter(|star| !tree.contains(star.pos()))
-            .for_each(|star| star.mass_point.position = Vector2::from_element(f32::NAN))
+            .filter(|star| !domain.contains(star.pos()))
+            .for_each(|star| star.mass_point.position = Vector2::from_element(Real::NAN));
+    }
+
+    /// `Stage::Collisions`, `old_positions` are the positions at the start of the update.
+    fn collide(&mut self, old_positions: &[Vector2<Real>]) {
+        let collisions = collision::collide(
+            &mut self.stars,
+            old_positions,
+            &self.species,
+            &self.collision_model,
+            &*self.mass_radius,
+        );
+        // debris is of the species of the star it broke off of
+        if let Some(last) = collisions.debris.last() {
+            self.species.resize(self.species.len().max(last.id + 1), 0);
+            for debris in &collisions.debris {
+                self.species[debris.id] = self.species.get(debris.from).copied().unwrap_or(0);
+            }
+        }
+        // merged stars keep the spin of both and the orbital angular momentum between them
+        if !collisions.merges.is_empty() {
+            self.spins
+                .resize(self.spins.len().max(self.stars.len()), 0.0);
+            for merge in &collisions.merges {
+                let absorbed = core::mem::take(&mut self.spins[merge.from]);
+                self.spins[merge.into] += absorbed + merge.spin;
+            }
+        }
+        for merge in &collisions.merges {
+            let Some(from) = self.kinds.get_mut(merge.from) else {
+                continue;
+            };
+            if core::mem::take(from) == BodyKind::BlackHole {
+                self.kinds
+                    .resize(self.kinds.len().max(merge.into + 1), BodyKind::Star);
+                self.kinds[merge.into] = BodyKind::BlackHole;
+            }
+        }
+        self.merges.extend(collisions.merges);
+        self.bounces.extend(collisions.bounces);
+        self.debris.extend(collisions.debris);
+    }
+
+    /// Calculates the acceleration of all `stars` with a freshly built tree, with the tree
+    /// of `rebuild` if `incremental_rebuild` is set, or with `reused`, a tree of exactly
+    /// `stars`. Black holes sum the force of all stars instead. Stars outside of the domain
+    /// are not accelerated.
+    fn accelerations(
+        &self,
+        stars: &[Star],
+        config: &SimulationConfig,
+        forces: ForceStages,
+        reused: Option<&FlatTree>,
+    ) -> (Vec<Vector2<Real>>, TraversalStats) {
+        let mut dominant = Vec::new();
+        let mut bodies = Vec::new();
+        for (id, star) in stars.iter().enumerate() {
+            if !config.domain.contains(star.pos()) {
+                continue;
+            }
+            match is_direct(id, star, &self.kinds, config) {
+                true => dominant.push(star.mass_point),
+                false => bodies.push(id),
+            }
+        }
+        // only collected if there are black holes, which are pulled by all of them
+        let everything: Vec<_> = match self.kinds.contains(&BodyKind::BlackHole) {
+            true => stars
+                .iter()
+                .filter(|star| config.domain.contains(star.pos()))
+                .map(|star| star.mass_point)
+                .collect(),
+            false => Vec::new(),
+        };
+        let stale = config.incremental_rebuild.and(self.rebuild.current());
+        let fresh;
+        let tree = match (stale, reused) {
+            (Some(stale), _) => &stale.tree,
+            (None, Some(reused)) => reused,
+            (None, None) => {
+                let mut tree = FlatTree::bounding(&config.domain, 2 * stars.len());
+                bodies
+                    .iter()
+                    .for_each(|&id| tree.insert(&stars[id].mass_point));
+                tree.summarize();
+                fresh = tree;
+                &fresh
+            }
+        };
+        let solver = self
+            .force_solver
+            .as_deref()
+            .or_else(|| config.solver.force_solver());
+        let near_field = config
+            .near_field
+            .filter(|_| forces.gravity && solver.is_none())
+            .map(|radius| near_field::accelerations(stars, &bodies, radius, config));
+        // by id, dominant stars are pulled by the tree like without a solver
+        let solved = solver.filter(|_| forces.gravity).map(|solver| {
+            let masses: Vec<_> = bodies.iter().map(|&id| stars[id].mass_point).collect();
+            let config = SimulationConfig {
+                near_field: None,
+                ..*config
+            };
+            let mut solved = vec![None; stars.len()];
+            for (&id, force) in bodies.iter().zip(solver.forces(&masses, &config)) {
+                solved[id] = Some(force);
+            }
+            solved
+        });
+
+        let acceleration = |stats: &mut TraversalStats, (id, star): (StarId, &Star)| {
+            if !config.domain.contains(star.pos()) {
+                return Vector2::zeros();
+            }
+
+            let mut force = Vector2::zeros();
+            if forces.gravity && self.kind(id) == BodyKind::BlackHole {
+                force = tree::direct_force_on(&star.mass_point, &everything, config);
+            } else if forces.gravity {
+                if let Some(solved) = solved.as_ref().and_then(|solved| solved[id]) {
+                    force = solved;
+                } else {
+                    force = if self.record_stats {
+                        tree.force_on_with_stats(&star.mass_point, config, stats)
+                    } else {
+                        tree.force_on(&star.mass_point, config)
+                    };
+                    if let Some(stale) = stale {
+                        force -= stale.self_force(id, &star.mass_point, config);
+                    }
+                }
+                if !dominant.is_empty() {
+                    force += tree::direct_force_on(&star.mass_point, &dominant, config);
+                }
+            }
+            let acceleration: Vector2<Real> = self
+                .forces
+                .iter()
+                .filter(|_| forces.external)
+                .map(|term| term.acceleration(star, tree))
+                .chain(
+                    self.potentials
+                        .iter()
+                        .filter(|_| forces.external)
+                        .map(|potential| potential.acceleration(star.pos(), config.gravity)),
+                )
+                .sum();
+            let near_field = near_field
+                .as_ref()
+                .map_or(Vector2::zeros(), |near| near[id]);
+            force / star.mass() + acceleration + near_field
+        };
+
+        let mut accelerations = vec![Vector2::zeros(); stars.len()];
+        let fill = |mut stats: TraversalStats,
+                    (out, star): (&mut Vector2<Real>, (StarId, &Star))| {
+            *out = acceleration(&mut stats, star);
+            stats
+        };
+        #[cfg(feature = "rayon")]
+        let stats = accelerations
+            .par_iter_mut()
+            .zip(stars.par_iter().enumerate())
+            .fold(TraversalStats::default, fill)
+            .reduce(TraversalStats::default, TraversalStats::merge);
+        #[cfg(not(feature = "rayon"))]
+        let stats = accelerations
+            .iter_mut()
+            .zip(stars.iter().enumerate())
+            .fold(TraversalStats::default(), fill);
+        (accelerations, stats)
+    }
+
+    /// Stable hash of all positions, velocities and masses. Equal states hash equally
+    /// across runs and platforms, all NaNs are treated as the same value.
+    pub fn state_hash(&self) -> u64 {
+        self.hash_with(|x| {
+            if x.is_nan() {
+                Real::NAN.to_bits() as u64
+            } else {
+                x.to_bits() as u64
+            }
+        })
+    }
+
+    /// Like `state_hash`, but rounds every value to a multiple of `quantum` first,
+    /// so states that only differ by small rounding errors usually hash equally.
+    pub fn state_hash_quantized(&self, quantum: Real) -> u64 {
+        self.hash_with(|x| {
+            if x.is_nan() {
+                u64::MAX
+            } else {
+                (x as f64 / quantum as f64).round() as i64 as u64
+            }
+        })
+    }
+
+    fn hash_with(&self, quantize: impl Fn(Real) -> u64) -> u64 {
+        // FNV-1a, which unlike `DefaultHasher` is guaranteed to be stable
+        const OFFSET: u64 = 0xcbf29ce484222325;
+        const PRIME: u64 = 0x100000001b3;
+
+        self.stars
+            .iter()
+            .flat_map(|star| {
+                [
+                    star.pos().x,
+                    star.pos().y,
+                    star.vel.x,
+                    star.vel.y,
+                    star.mass(),
+                ]
+            })
+            .flat_map(|x| quantize(x).to_le_bytes())
+            .fold(OFFSET, |hash, byte| {
+                (hash ^ byte as u64).wrapping_mul(PRIME)
+            })
+    }
+}
+
+/// A circular region in which time passes at a different rate, e.g. a slow motion bubble.
+/// Stars inside are integrated with their time step scaled by `time_scale`.
+#[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DilationZone {
+    pub center: Vector2<Real>,
+    pub radius: Real,
+    pub time_scale: Real,
+}
+
+impl DilationZone {
+    /// Time step scale at `pos`, overlapping zones multiply.
+    pub fn time_scale(zones: &[Self], pos: &Vector2<Real>) -> Real {
+        zones
+            .iter()
+            .filter(|zone| (pos - zone.center).norm_squared() < zone.radius * zone.radius)
+            .map(|zone| zone.time_scale)
+            .product()
+    }
+}
+
+/// Randomizes the opening angle of each update, which decorrelates the systematic
+/// force errors of consecutive steps.
+#[cfg(feature = "rand")]
+#[derive(Clone, Debug)]
+pub struct ThetaDither {
+    /// relative amplitude, `theta` is sampled uniformly from `theta * (1 ± amplitude)`
+    pub amplitude: Real,
+    rng: XorShiftRng,
+}
+
+#[cfg(feature = "rand")]
+impl ThetaDither {
+    pub fn new(amplitude: Real, seed: u64) -> Self {
+        Self {
+            amplitude,
+            rng: XorShiftRng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn sample(&mut self, theta: Real) -> Real {
+        theta * (1.0 + self.amplitude * self.rng.gen_range(-1.0..=1.0))
     }
 }
 
+#[cfg(all(feature = "rand", feature = "std"))]
 pub struct Galaxy {
     /// `stars[0]` is the center
     stars: Vec<Star>,
 }
 
+#[cfg(all(feature = "rand", feature = "std"))]
 impl Galaxy {
     pub fn new(
         center: Star,
         num_stars: usize,
-        radius: f32,
+        radius: Real,
         mass_distribution: &MassDistribution,
-        color: [f32; 3],
     ) -> Self {
-        let mut rng = XorShiftRng::from_entropy();
+        Self::new_seeded(center, num_stars, radius, mass_distribution, rand::random())
+    }
 
-        Self {
-            stars: [center]
-                .into_iter()
-                .chain((0..num_stars).map(|_| {
-                    let a = rng.gen::<f32>() * std::f32::consts::TAU;
-                    let d = rng.gen::<f32>().sqrt() * radius;
-
-                    let relative_pos = Vector2::new(a.sin(), a.cos()) * d;
-                    let n = Vector3::cross(
-                        &*Vector3::z_axis(),
-                        &Vector3::new(relative_pos.x, relative_pos.y, 0.0),
-                    );
-                    let velocity = (Simulation::GRAVITY * center.mass() / d).sqrt();
-
-                    Star::new(
-                        center.pos() + relative_pos,
-                        center.vel + n.xy().normalize() * velocity,
-                        color,
-                        1.0 + mass_distribution.sample(rng.gen()),
-                    )
-                }))
-                .collect(),
+    /// Like `new`, but the same `seed` always generates the same stars.
+    pub fn new_seeded(
+        center: Star,
+        num_stars: usize,
+        radius: Real,
+        mass_distribution: &MassDistribution,
+        seed: u64,
+    ) -> Self {
+        Self::with_min_separation_seeded(center, num_stars, radius, mass_distribution, 0.0, seed)
+    }
+
+    /// Like `new`, but stars closer than `min_separation` to another one are placed again,
+    /// so the tree doesn't have to deal with (nearly) coincident stars.
+    pub fn with_min_separation(
+        center: Star,
+        num_stars: usize,
+        radius: Real,
+        mass_distribution: &MassDistribution,
+        min_separation: Real,
+    ) -> Self {
+        Self::with_min_separation_seeded(
+            center,
+            num_stars,
+            radius,
+            mass_distribution,
+            min_separation,
+            rand::random(),
+        )
+    }
+
+    /// Like `with_min_separation`, but the same `seed` always generates the same stars.
+    pub fn with_min_separation_seeded(
+        center: Star,
+        num_stars: usize,
+        radius: Real,
+        mass_distribution: &MassDistribution,
+        min_separation: Real,
+        seed: u64,
+    ) -> Self {
+        Self::with_arms_seeded(
+            center,
+            num_stars,
+            radius,
+            mass_distribution,
+            min_separation,
+            &SpiralArms::default(),
+            seed,
+        )
+    }
+
+    /// Like `with_min_separation_seeded`, but stars crowd into spiral `arms` and stream
+    /// along them, see `SpiralArms`. Without arms the stars are the same as those of
+    /// `with_min_separation_seeded`.
+    pub fn with_arms_seeded(
+        center: Star,
+        num_stars: usize,
+        radius: Real,
+        mass_distribution: &MassDistribution,
+        min_separation: Real,
+        arms: &SpiralArms,
+        seed: u64,
+    ) -> Self {
+        Self::with_sampling_seeded(
+            center,
+            num_stars,
+            radius,
+            mass_distribution,
+            min_separation,
+            arms,
+            Sampling::Random,
+            seed,
+        )
+    }
+
+    /// Like `with_arms_seeded`, but the positions of the stars are drawn with `sampling`,
+    /// which spreads small galaxies more evenly than independent random positions.
+    #[allow(clippy::too_many_arguments)]
+    pub fn with_sampling_seeded(
+        center: Star,
+        num_stars: usize,
+        radius: Real,
+        mass_distribution: &MassDistribution,
+        min_separation: Real,
+        arms: &SpiralArms,
+        sampling: Sampling,
+        seed: u64,
+    ) -> Self {
+        use nalgebra::Vector3;
+
+        let mut rng = XorShiftRng::seed_from_u64(seed);
+        let min_separation =
+            min_separation.max(sampling.poisson_disk_separation(num_stars, radius));
+        let mut grid = SeparationGrid::new(min_separation);
+        grid.insert(Vector2::zeros());
+        let mut square = UnitSquare::new(sampling, num_stars, &mut rng);
+
+        let mut stars = Vec::with_capacity(num_stars + 1);
+        stars.push(center);
+        for _ in 0..num_stars {
+            // rejection sampling of the arm density, which is at least half the mean one
+            let relative_pos = grid.sample(|| loop {
+                let [u, v] = square.sample(&mut rng);
+                let a = u * crate::consts::TAU;
+                let d = v.sqrt() * radius;
+                let pos = Vector2::new(a.sin(), a.cos()) * d;
+                if arms.is_disc() || rng.gen::<Real>() < arms.density(&pos, radius) {
+                    break pos;
+                }
+            });
+            let d = relative_pos.norm();
+            let n = Vector3::cross(
+                &*Vector3::z_axis(),
+                &Vector3::new(relative_pos.x, relative_pos.y, 0.0),
+            );
+            let velocity = (Simulation::GRAVITY * center.mass() / d).sqrt();
+            let mut orbit = n.xy().normalize() * velocity;
+            if !arms.is_disc() {
+                orbit += arms.streaming(&relative_pos, radius, &orbit);
+            }
+
+            stars.push(Star::new(
+                center.pos() + relative_pos,
+                center.vel + orbit,
+                1.0 + mass_distribution.sample(rng.gen()),
+            ));
         }
+        Self { stars }
+    }
+
+    /// Speeds the stars up so they stay on their orbits within `halo` as well, which the
+    /// simulation should then get with `Simulation::add_potential`. Outside of the center
+    /// mass the halo dominates, so the rotation curve flattens.
+    pub fn with_halo(mut self, halo: &dyn ExternalPotential) -> Self {
+        let center = self.stars[0];
+        for star in &mut self.stars[1..] {
+            let relative_vel = star.vel - center.vel;
+            let speed = relative_vel.norm();
+            if speed == 0.0 {
+                continue;
+            }
+            let halo_speed = halo.circular_velocity(star.pos(), center.pos(), Simulation::GRAVITY);
+            let boosted = (speed * speed + halo_speed * halo_speed).sqrt();
+            star.vel = center.vel + relative_vel * (boosted / speed);
+        }
+        self
     }
 
     pub fn stars(&self) -> &[Star] {
@@ -147,26 +1114,161 @@ impl Galaxy {
     pub fn into_stars(self) -> Vec<Star> {
         self.stars
     }
+
+    /// Colors of `stars`, computed on demand so the stars themselves stay uncolored.
+    pub fn colors(&self, policy: &ColorPolicy) -> Vec<[f32; 3]> {
+        policy.colors(&self.stars)
+    }
+}
+
+/// Spatial hash for rejection sampling points that keep a minimum distance to each other.
+#[cfg(all(feature = "rand", feature = "std"))]
+struct SeparationGrid {
+    min_separation: Real,
+    cells: std::collections::HashMap<(i32, i32), SmallVec<[Vector2<Real>; 1]>>,
+}
+
+#[cfg(all(feature = "rand", feature = "std"))]
+impl SeparationGrid {
+    /// Samples drawn before giving up and accepting a point that is too close.
+    const MAX_ATTEMPTS: usize = 32;
+
+    fn new(min_separation: Real) -> Self {
+        Self {
+            min_separation,
+            cells: Default::default(),
+        }
+    }
+
+    fn cell(&self, pos: &Vector2<Real>) -> (i32, i32) {
+        let cell = pos / self.min_separation;
+        (cell.x.floor() as i32, cell.y.floor() as i32)
+    }
+
+    fn is_free(&self, pos: &Vector2<Real>) -> bool {
+        let (x, y) = self.cell(pos);
+        (x - 1..=x + 1)
+            .flat_map(|x| (y - 1..=y + 1).map(move |y| (x, y)))
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .all(|other| (other - pos).norm_squared() >= self.min_separation.powi(2))
+    }
+
+    fn insert(&mut self, pos: Vector2<Real>) {
+        if self.min_separation > 0.0 {
+            self.cells.entry(self.cell(&pos)).or_default().push(pos);
+        }
+    }
+
+    /// Draws from `sample` until a point keeps the minimum separation, and inserts it.
+    fn sample(&mut self, mut sample: impl FnMut() -> Vector2<Real>) -> Vector2<Real> {
+        let mut pos = sample();
+        if self.min_separation > 0.0 {
+            for _ in 1..Self::MAX_ATTEMPTS {
+                if self.is_free(&pos) {
+                    break;
+                }
+                pos = sample();
+            }
+        }
+        self.insert(pos);
+        pos
+    }
+}
+
+/// Logarithmic spiral arms of a generated galaxy. The density of stars varies with
+/// `1 + contrast * cos(phase)` around the disc, where the phase is 0 along the middle of an
+/// arm. The arms trail the rotation of the disc, so they wind up rather than unwind.
+///
+/// Stars are slowed down along the arms and drift outwards ahead and inwards behind them,
+/// by a fraction `contrast * sin(pitch)` of their orbital velocity, a rough version of the
+/// streaming motions of a density wave, which keeps the arms from dissolving right away.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default, deny_unknown_fields))]
+pub struct SpiralArms {
+    /// number of arms, 0 for a featureless disc
+    pub count: u32,
+    /// angle between an arm and a circle around the center, in radians. Small angles wind
+    /// the arms tightly, around 0.2 to 0.5 look like real galaxies.
+    pub pitch: Real,
+    /// how much denser the arms are than the gaps between them, from 0 (no arms) to 1
+    /// (no stars in the middle of the gaps)
+    pub contrast: Real,
+}
+
+impl Default for SpiralArms {
+    fn default() -> Self {
+        Self {
+            count: 0,
+            pitch: 0.3,
+            contrast: 0.5,
+        }
+    }
+}
+
+impl SpiralArms {
+    pub fn new(count: u32, pitch: Real, contrast: Real) -> Self {
+        Self {
+            count,
+            pitch,
+            contrast,
+        }
+    }
+
+    /// Whether there are no arms at all.
+    pub fn is_disc(&self) -> bool {
+        self.count == 0 || self.contrast <= 0.0
+    }
+
+    /// Phase of the arm pattern at `offset` from the center of a galaxy of `radius`, 0 (or
+    /// a multiple of `TAU`) in the middle of an arm.
+    pub fn phase(&self, offset: &Vector2<Real>, radius: Real) -> Real {
+        let angle = offset.y.atan2(offset.x);
+        // the arms fall behind the (counterclockwise) rotation further out
+        let winding = (offset.norm() / radius).ln() / self.pitch.tan();
+        self.count as Real * (angle + winding)
+    }
+
+    /// Density of stars at `offset` relative to the densest parts of the arms, in [0, 1].
+    pub fn density(&self, offset: &Vector2<Real>, radius: Real) -> Real {
+        let contrast = self.contrast.clamp(0.0, 1.0);
+        (1.0 + contrast * self.phase(offset, radius).cos()) / (1.0 + contrast)
+    }
+
+    /// Change of the circular velocity `orbit` of a star at `offset`.
+    pub fn streaming(
+        &self,
+        offset: &Vector2<Real>,
+        radius: Real,
+        orbit: &Vector2<Real>,
+    ) -> Vector2<Real> {
+        let amplitude = self.contrast.clamp(0.0, 1.0) * self.pitch.sin();
+        let phase = self.phase(offset, radius);
+        let outwards = offset.normalize() * orbit.norm();
+        (-orbit * phase.cos() + outwards * phase.sin()) * amplitude
+    }
 }
 
 #[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct MassDistribution {
-    alpha: f32,
-    max_mass: f32,
+    alpha: Real,
+    max_mass: Real,
 }
 
 impl MassDistribution {
-    pub const fn new(alpha: f32, max_mass: f32) -> Self {
+    pub const fn new(alpha: Real, max_mass: Real) -> Self {
         Self { alpha, max_mass }
     }
 }
 
 impl MassDistribution {
-    pub fn sample(&self, t: f32) -> f32 {
+    pub fn sample(&self, t: Real) -> Real {
         self.max_mass * ((self.alpha * t).exp_m1() / self.alpha.exp_m1()).min(1.0)
     }
 
-    pub fn eval_inv(&self, x: f32) -> f32 {
+    pub fn eval_inv(&self, x: Real) -> Real {
         (self.alpha.exp_m1() * x / self.max_mass + 1.0).ln() / self.alpha
     }
 }
@@ -0,0 +1,114 @@
+use crate::tree::FlatTree;
+use crate::{Real, SimulationConfig, Star};
+use alloc::vec;
+use alloc::vec::Vec;
+use nalgebra::Vector2;
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+/// Square pixels covering a rectangle of the simulation, `width` per row. The first pixel
+/// has its lower left corner at `min`, rows go up from there like in FITS images.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Grid {
+    pub min: Vector2<Real>,
+    pub pixel_size: Real,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Grid {
+    pub fn len(&self) -> usize {
+        self.width * self.height
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Center of the pixel in column `x` and row `y`.
+    pub fn center(&self, x: usize, y: usize) -> Vector2<Real> {
+        self.min + Vector2::new(x as Real + 0.5, y as Real + 0.5) * self.pixel_size
+    }
+
+    /// Index of the pixel containing `pos`, if any.
+    pub fn index(&self, pos: &Vector2<Real>) -> Option<usize> {
+        let pixel = (pos - self.min) / self.pixel_size;
+        if !(pixel.x >= 0.0 && pixel.y >= 0.0) {
+            return None;
+        }
+        let (x, y) = (pixel.x.floor() as usize, pixel.y.floor() as usize);
+        (x < self.width && y < self.height).then(|| y * self.width + x)
+    }
+}
+
+/// What a `Map` shows.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Quantity {
+    /// mass per area
+    SurfaceDensity,
+    /// gravitational potential, energy per mass
+    Potential,
+}
+
+/// A quantity sampled on a `Grid`, e.g. to export it as an image.
+#[derive(Clone, Debug)]
+pub struct Map {
+    pub grid: Grid,
+    pub quantity: Quantity,
+    /// row major, starting with the row at `grid.min`
+    pub values: Vec<f64>,
+}
+
+impl Map {
+    /// The mass of the stars in each pixel over its area. Stars outside of the grid,
+    /// including removed ones, are left out.
+    pub fn surface_density(stars: &[Star], grid: Grid) -> Self {
+        let mut values = vec![0.0; grid.len()];
+        for star in stars {
+            if let Some(index) = grid.index(star.pos()) {
+                values[index] += star.mass() as f64;
+            }
+        }
+        let area = grid.pixel_size as f64 * grid.pixel_size as f64;
+        values.iter_mut().for_each(|value| *value /= area);
+
+        Self {
+            grid,
+            quantity: Quantity::SurfaceDensity,
+            values,
+        }
+    }
+
+    /// The potential at the center of each pixel, of all stars in `config.domain`, with
+    /// the opening angle and gravitational constant of `config`.
+    pub fn potential(stars: &[Star], config: &SimulationConfig, grid: Grid) -> Self {
+        let mut tree = FlatTree::bounding(&config.domain, 2 * stars.len());
+        stars
+            .iter()
+            .filter(|star| config.domain.contains(star.pos()))
+            .for_each(|star| tree.insert(&star.mass_point));
+        tree.summarize();
+
+        let mut values = vec![0.0; grid.len()];
+        let sample = |(index, value): (usize, &mut f64)| {
+            let center = grid.center(index % grid.width, index / grid.width);
+            *value = tree.potential_at(&center, config) as f64;
+        };
+        #[cfg(feature = "rayon")]
+        values.par_iter_mut().enumerate().for_each(sample);
+        #[cfg(not(feature = "rayon"))]
+        values.iter_mut().enumerate().for_each(sample);
+
+        Self {
+            grid,
+            quantity: Quantity::Potential,
+            values,
+        }
+    }
+
+    pub fn value(&self, x: usize, y: usize) -> f64 {
+        self.values[y * self.grid.width + x]
+    }
+}
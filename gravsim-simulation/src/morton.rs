@@ -0,0 +1,113 @@
+use crate::rebuild::TreeRebuild;
+use crate::tree::Aabb;
+use crate::{Real, Simulation, Star, StarId};
+use alloc::vec;
+use alloc::vec::Vec;
+use nalgebra::Vector2;
+
+/// Morton (Z-order) code of `pos`: both coordinates quantized to 32 bits across the square
+/// of `domain` that trees are built on, with their bits interleaved. x takes the lower bit
+/// like in `Quadrant`, so sorting by code visits cells in the order of their children.
+/// `None` outside of the domain.
+pub fn code(domain: &Aabb, pos: &Vector2<Real>) -> Option<u64> {
+    if !domain.contains(pos) {
+        return None;
+    }
+
+    let (min, scale) = domain.square();
+    let quantized = |i: usize| {
+        let fraction = (pos[i] - min[i]) as f64 / scale as f64;
+        (fraction * (1u64 << 32) as f64).min(u32::MAX as f64) as u32
+    };
+    Some(spread(quantized(0)) | spread(quantized(1)) << 1)
+}
+
+/// Ids of `stars` ordered by `code`. Stars outside of the domain, including removed ones,
+/// come last in their previous order.
+pub fn order(stars: &[Star], domain: &Aabb) -> Vec<StarId> {
+    let mut keys: Vec<(u64, StarId)> = stars
+        .iter()
+        .enumerate()
+        .map(|(id, star)| (code(domain, star.pos()).unwrap_or(u64::MAX), id))
+        .collect();
+    keys.sort_unstable();
+    keys.into_iter().map(|(_, id)| id).collect()
+}
+
+/// Spaces the bits of `x` out to every other bit.
+fn spread(x: u32) -> u64 {
+    let mut x = x as u64;
+    x = (x | x << 16) & 0x0000_ffff_0000_ffff;
+    x = (x | x << 8) & 0x00ff_00ff_00ff_00ff;
+    x = (x | x << 4) & 0x0f0f_0f0f_0f0f_0f0f;
+    x = (x | x << 2) & 0x3333_3333_3333_3333;
+    x = (x | x << 1) & 0x5555_5555_5555_5555;
+    x
+}
+
+impl Simulation {
+    /// Reorders `stars` by their Morton code, so stars close in space are close in memory
+    /// when the tree is built and forces are evaluated. Returns the previous id of the star
+    /// at each id, for callers to remap their own per star data, see `reorder_stars`.
+    pub fn sort_stars(&mut self) -> Vec<StarId> {
+        let order = order(&self.stars, &self.config.domain);
+        self.reorder_stars(&order);
+        order
+    }
+
+    /// Moves the star with id `order[id]` to `id`, `order` has to contain every id once.
+    /// Everything the simulation keys by `StarId` moves along, an incremental rebuild in
+    /// progress starts over.
+    pub fn reorder_stars(&mut self, order: &[StarId]) {
+        self.stars = order.iter().map(|&id| self.stars[id]).collect();
+
+        if !self.species.is_empty() {
+            self.species = order
+                .iter()
+                .map(|&id| self.species.get(id).copied().unwrap_or(0))
+                .collect();
+        }
+        if !self.spins.is_empty() {
+            self.spins = order
+                .iter()
+                .map(|&id| self.spins.get(id).copied().unwrap_or(0.0))
+                .collect();
+        }
+        if !self.ages.is_empty() {
+            self.ages = order
+                .iter()
+                .map(|&id| self.ages.get(id).copied().unwrap_or(0.0))
+                .collect();
+        }
+        if !self.kinds.is_empty() {
+            self.kinds = order.iter().map(|&id| self.kind(id)).collect();
+        }
+        self.reused.reorder(order);
+        if let Some(estimate) = &mut self.error_estimate {
+            estimate.reorder(order);
+        }
+        let disruption_groups = self
+            .disruption_monitor
+            .iter_mut()
+            .flat_map(|monitor| &mut monitor.groups);
+        let pericenter_groups = self
+            .pericenter_monitor
+            .iter_mut()
+            .flat_map(|monitor| &mut monitor.groups);
+        let mut groups = disruption_groups.chain(pericenter_groups).peekable();
+        if groups.peek().is_some() {
+            let mut new_ids = vec![0; order.len()];
+            for (new, &old) in order.iter().enumerate() {
+                new_ids[old] = new;
+            }
+            for group in groups {
+                group
+                    .stars
+                    .iter_mut()
+                    .for_each(|id| *id = new_ids.get(*id).copied().unwrap_or(*id));
+            }
+        }
+        // the pending tree refers to stars by id
+        self.rebuild = TreeRebuild::default();
+    }
+}
@@ -0,0 +1,141 @@
+use crate::tree;
+use crate::{Real, SimulationConfig, Star, StarId};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::Range;
+use nalgebra::Vector2;
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+/// Cell of a uniform grid.
+pub(crate) type Cell = (i32, i32);
+
+/// Neighbor cells that come after a cell, so every pair of neighbors is visited once.
+const FORWARD: [Cell; 4] = [(1, -1), (1, 0), (1, 1), (0, 1)];
+
+/// Chunks of cells `accelerations` sums separately.
+const BUFFERS: usize = 16;
+
+/// The cell of `pos` in a uniform grid with cells of size `size`.
+pub(crate) fn cell_of(pos: &Vector2<Real>, size: Real) -> Cell {
+    let cell = pos / size;
+    (cell.x.floor() as i32, cell.y.floor() as i32)
+}
+
+/// Stars sorted by their cell of a uniform grid, for finding the stars close to others.
+/// Only occupied cells are stored, so the grid covers any domain.
+pub(crate) struct NeighborGrid {
+    sorted: Vec<(Cell, StarId)>,
+    /// the range of every occupied cell in `sorted`
+    cells: Vec<(Cell, Range<usize>)>,
+}
+
+impl NeighborGrid {
+    /// A star may be placed in several cells, e.g. all cells its path crosses.
+    pub(crate) fn new(entries: impl IntoIterator<Item = (Cell, StarId)>) -> Self {
+        let mut sorted: Vec<(Cell, StarId)> = entries.into_iter().collect();
+        sorted.sort_unstable();
+
+        let mut cells: Vec<(Cell, Range<usize>)> = Vec::new();
+        for (i, &(cell, _)) in sorted.iter().enumerate() {
+            match cells.last_mut() {
+                Some((last, range)) if *last == cell => range.end = i + 1,
+                _ => cells.push((cell, i..i + 1)),
+            }
+        }
+        Self { sorted, cells }
+    }
+
+    pub(crate) fn cells(&self) -> &[(Cell, Range<usize>)] {
+        &self.cells
+    }
+
+    /// The range of `cell` in the grid, if it is occupied.
+    pub(crate) fn range(&self, cell: Cell) -> Option<Range<usize>> {
+        self.cells
+            .binary_search_by_key(&cell, |(cell, _)| *cell)
+            .ok()
+            .map(|index| self.cells[index].1.clone())
+    }
+
+    pub(crate) fn ids(&self, range: Range<usize>) -> impl Iterator<Item = StarId> + '_ {
+        self.sorted[range].iter().map(|(_, id)| *id)
+    }
+}
+
+/// Exact accelerations between all pairs of `bodies` closer than `radius`, indexed like
+/// `stars`, with the gravitational constant and softening of `config`. Every pair is
+/// evaluated once and accelerates both stars (Newton's third law), which halves the
+/// arithmetic compared to evaluating the force on every star separately.
+///
+/// Pairs are found with a uniform grid. The cells are split into `BUFFERS` chunks that are
+/// processed in parallel, each into its own buffer, and the buffers are summed in order,
+/// so the result doesn't depend on the number of threads.
+pub fn accelerations(
+    stars: &[Star],
+    bodies: &[StarId],
+    radius: Real,
+    config: &SimulationConfig,
+) -> Vec<Vector2<Real>> {
+    let grid = NeighborGrid::new(
+        bodies
+            .iter()
+            .map(|&id| (cell_of(stars[id].pos(), radius), id)),
+    );
+
+    let interact = |mut buffer: Vec<Vector2<Real>>, (cell, own): &(Cell, Range<usize>)| {
+        for (offset, a) in grid.ids(own.clone()).enumerate() {
+            for b in grid.ids(own.clone()).skip(offset + 1) {
+                pair(stars, &mut buffer, a, b, radius, config);
+            }
+        }
+        for (dx, dy) in FORWARD {
+            let Some(neighbor) = grid.range((cell.0 + dx, cell.1 + dy)) else {
+                continue;
+            };
+            for a in grid.ids(own.clone()) {
+                for b in grid.ids(neighbor.clone()) {
+                    pair(stars, &mut buffer, a, b, radius, config);
+                }
+            }
+        }
+        buffer
+    };
+    let zeros = || vec![Vector2::zeros(); stars.len()];
+    let chunk = grid.cells().len().div_ceil(BUFFERS).max(1);
+    let buffer = |cells: &[(Cell, Range<usize>)]| cells.iter().fold(zeros(), interact);
+    let add = |mut a: Vec<Vector2<Real>>, b: Vec<Vector2<Real>>| {
+        a.iter_mut().zip(b).for_each(|(a, b)| *a += b);
+        a
+    };
+
+    #[cfg(feature = "rayon")]
+    let buffers: Vec<_> = grid.cells().par_chunks(chunk).map(buffer).collect();
+    #[cfg(feature = "rayon")]
+    let accelerations = buffers.into_iter().fold(zeros(), add);
+    #[cfg(not(feature = "rayon"))]
+    let accelerations = grid.cells().chunks(chunk).map(buffer).fold(zeros(), add);
+    accelerations
+}
+
+fn pair(
+    stars: &[Star],
+    accelerations: &mut [Vector2<Real>],
+    a: StarId,
+    b: StarId,
+    radius: Real,
+    config: &SimulationConfig,
+) {
+    let diff = stars[b].pos() - stars[a].pos();
+    let dist_sq = diff.norm_squared();
+    if !dist_sq.is_normal() || dist_sq >= radius * radius {
+        return;
+    }
+
+    let dist = tree::softened(dist_sq, config.softening);
+    let part = diff * (config.gravity / dist.powi(3));
+    accelerations[a] += part * stars[b].mass();
+    accelerations[b] -= part * stars[a].mass();
+}
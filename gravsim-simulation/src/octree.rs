@@ -0,0 +1,241 @@
+use crate::three_d::{MassData3, Simulation3};
+use crate::tree;
+use crate::Real;
+use alloc::boxed::Box;
+use alloc::collections::VecDeque;
+use nalgebra::Vector3;
+use num_enum::TryFromPrimitive;
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+
+/// 3d counterpart of `Quadrant`, one octant of an octree node.
+/// The bits of its value represent its coordinates with `Octant::X`, `Octant::Y` and `Octant::Z`.
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, TryFromPrimitive)]
+pub enum Octant {
+    BottomNorthWest = 0b000,
+    BottomNorthEast = 0b001,
+    BottomSouthWest = 0b010,
+    BottomSouthEast = 0b011,
+    TopNorthWest = 0b100,
+    TopNorthEast = 0b101,
+    TopSouthWest = 0b110,
+    TopSouthEast = 0b111,
+}
+
+impl Octant {
+    pub const X: u8 = 0b001;
+    pub const Y: u8 = 0b010;
+    pub const Z: u8 = 0b100;
+
+    /// Returns the offset a child in this octant has to its parent node.
+    /// This value has to be scaled by half of the scale of its parent node.
+    pub fn offset(&self) -> Vector3<Real> {
+        let bits = *self as u8;
+        Vector3::new(
+            (bits & Self::X > 0) as u8 as Real,
+            (bits & Self::Y > 0) as u8 as Real,
+            (bits & Self::Z > 0) as u8 as Real,
+        )
+    }
+
+    /// Returns the octant a point of the given offset to its parent node
+    /// (which has the given scale) will fall into. Cells are half open like quadrants.
+    pub fn from_offset(offset: &Vector3<Real>, scale: Real) -> Self {
+        let bits_x = (offset.x >= 0.5 * scale) as u8 * Self::X;
+        let bits_y = (offset.y >= 0.5 * scale) as u8 * Self::Y;
+        let bits_z = (offset.z >= 0.5 * scale) as u8 * Self::Z;
+
+        (bits_x | bits_y | bits_z).try_into().unwrap()
+    }
+}
+
+/// 3d counterpart of `tree::Node`.
+#[derive(Clone, Debug)]
+pub struct Octree {
+    pos: Vector3<Real>,
+    scale: Real,
+
+    center_of_mass: MassData3,
+    children: [Option<Box<Octree>>; 8],
+    leaf: bool,
+}
+
+impl Octree {
+    pub fn new_root(pos: Vector3<Real>, scale: Real) -> Self {
+        Self {
+            pos,
+            scale,
+            center_of_mass: MassData3 {
+                position: Default::default(),
+                mass: 0.0,
+            },
+            children: Default::default(),
+            leaf: true,
+        }
+    }
+
+    pub fn new_child(parent: &Self, octant: Octant, mass_data: MassData3) -> Box<Self> {
+        Box::new(Self {
+            pos: parent.pos + octant.offset() * parent.scale * 0.5,
+            scale: parent.scale * 0.5,
+            center_of_mass: mass_data,
+            children: Default::default(),
+            leaf: true,
+        })
+    }
+
+    /// Places `obj` in the tree. Inner nodes don't have a valid center of mass
+    /// until `summarize` is called once all objects are inserted.
+    pub fn insert(&mut self, obj: &MassData3) {
+        if self.center_of_mass.mass == 0.0 {
+            self.center_of_mass = *obj;
+            return;
+        } else if obj.mass == 0.0 {
+            return;
+        }
+
+        if self.is_leaf() && !self.can_subdivide() {
+            // coincident (or nearly so) bodies would subdivide forever, merge them instead.
+            let mass = self.center_of_mass.mass + obj.mass;
+            let position = (self.center_of_mass.position * self.center_of_mass.mass
+                + obj.position * obj.mass)
+                / mass;
+            self.center_of_mass.position = self.clamp(&position);
+            self.center_of_mass.mass = mass;
+            return;
+        }
+
+        if self.is_leaf() {
+            // the center of mass of a leaf is the body previously inserted, move it to a child.
+            let offset = self.center_of_mass.position - self.pos;
+            let octant = Octant::from_offset(&offset, self.scale);
+
+            self.insert_into(octant, &self.center_of_mass.clone())
+        }
+
+        let offset = obj.position - self.pos;
+        let octant = Octant::from_offset(&offset, self.scale);
+
+        self.insert_into(octant, obj);
+    }
+
+    fn insert_into(&mut self, octant: Octant, obj: &MassData3) {
+        self.leaf = false;
+        if let Some(child) = &mut self.children[octant as usize] {
+            let obj = child.repaired(obj);
+            child.insert(&obj);
+        } else {
+            let mut child = Octree::new_child(self, octant, *obj);
+            child.center_of_mass = child.repaired(obj);
+            self.children[octant as usize] = Some(child);
+        }
+    }
+
+    /// Whether halving this cell still produces children of nonzero size in `Real`.
+    fn can_subdivide(&self) -> bool {
+        (0..3).all(|i| {
+            let center = self.pos[i] + self.scale * 0.5;
+            center > self.pos[i] && center < self.pos[i] + self.scale
+        })
+    }
+
+    /// Computes masses and centers of mass of all inner nodes in a single bottom up pass.
+    pub fn summarize(&mut self) {
+        self.summarize_at();
+    }
+
+    /// Returns the mass and the mass weighted position sum of this subtree.
+    fn summarize_at(&mut self) -> (f64, Vector3<f64>) {
+        if self.is_leaf() {
+            let mass = self.center_of_mass.mass as f64;
+            return (mass, self.center_of_mass.position.cast() * mass);
+        }
+
+        let (mass, weighted_position) = self
+            .children
+            .iter_mut()
+            .flatten()
+            .map(|child| child.summarize_at())
+            .fold((0.0, Vector3::zeros()), |a, b| (a.0 + b.0, a.1 + b.1));
+
+        self.center_of_mass = MassData3 {
+            position: (weighted_position / mass).cast(),
+            mass: mass as Real,
+        };
+        (mass, weighted_position)
+    }
+
+    /// Returns `obj` with its position clamped into this cell, see `Node::repaired`.
+    fn repaired(&self, obj: &MassData3) -> MassData3 {
+        let mut obj = *obj;
+        if !self.contains(&obj.position) {
+            obj.position = self.clamp(&obj.position);
+        }
+        obj
+    }
+
+    /// Clamps `pos` into the half open cell `[pos, pos + scale)` of this node.
+    pub fn clamp(&self, pos: &Vector3<Real>) -> Vector3<Real> {
+        fn below(x: Real) -> Real {
+            match x {
+                x if x > 0.0 => Real::from_bits(x.to_bits() - 1),
+                x if x < 0.0 => Real::from_bits(x.to_bits() + 1),
+                _ => -Real::from_bits(1),
+            }
+        }
+
+        Vector3::from_fn(|i, _| pos[i].clamp(self.pos[i], below(self.pos[i] + self.scale)))
+    }
+
+    /// Calculates the force on `obj`, approximating nodes whose size to distance ratio is below `theta`,
+    /// softened by `softening`.
+    pub fn force_on(&self, obj: &MassData3, theta: Real, softening: Real) -> Vector3<Real> {
+        let mut force_part = Vector3::zeros();
+
+        let mut queue = VecDeque::from([self]);
+        while let Some(node) = queue.pop_front() {
+            let diff = node.center_of_mass.position - obj.position;
+            let dist_sq = diff.norm_squared();
+            if !dist_sq.is_normal() {
+                continue;
+            }
+
+            let dist = tree::softened(dist_sq, softening);
+            if node.scale / dist < theta || node.is_leaf() {
+                force_part += diff / dist.powi(3) * node.center_of_mass.mass;
+            } else {
+                queue.extend(node.children());
+            }
+        }
+
+        Simulation3::GRAVITY * obj.mass * force_part
+    }
+
+    pub fn is_leaf(&self) -> bool {
+        self.leaf
+    }
+
+    pub fn pos(&self) -> &Vector3<Real> {
+        &self.pos
+    }
+
+    pub fn scale(&self) -> Real {
+        self.scale
+    }
+
+    pub fn center_of_mass(&self) -> &MassData3 {
+        &self.center_of_mass
+    }
+
+    pub fn children(&self) -> impl Iterator<Item = &Octree> {
+        self.children.iter().filter_map(|child| child.as_deref())
+    }
+
+    pub fn contains(&self, pos: &Vector3<Real>) -> bool {
+        self.pos
+            .iter()
+            .zip(pos.iter())
+            .all(|(&a, &b)| b >= a && b < a + self.scale)
+    }
+}
@@ -0,0 +1,212 @@
+use crate::schedule::Action;
+use crate::{Simulation, StarId};
+use alloc::string::String;
+use alloc::vec::Vec;
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// When recorders and exporters write the state of a simulation, the `[output]` table of a
+/// scenario, e.g.
+///
+/// ```toml
+/// [output]
+/// every_time = 500.0
+/// logarithmic = { start = 1.0, per_decade = 10 }
+/// events = ["merger", "pericenter", { marker = "flyby" }]
+/// ```
+///
+/// An update is written if any of the parts is due, a policy without parts writes nothing.
+/// Follow it with a `Cadence`.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(default, deny_unknown_fields)
+)]
+pub struct OutputPolicy {
+    /// every this many updates
+    pub every_steps: Option<u64>,
+    /// whenever another multiple of this simulated time has passed
+    pub every_time: Option<f64>,
+    /// ever sparser in simulated time, to follow a system settling down
+    pub logarithmic: Option<Logarithmic>,
+    /// after updates in which one of these happened
+    pub events: Vec<Trigger>,
+}
+
+impl OutputPolicy {
+    /// Writes every `steps` updates, like a fixed interval dump.
+    pub fn every_steps(steps: u64) -> Self {
+        Self {
+            every_steps: Some(steps.max(1)),
+            ..Self::default()
+        }
+    }
+}
+
+/// Outputs at the simulated times `start * 10^(i / per_decade)`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(deny_unknown_fields)
+)]
+pub struct Logarithmic {
+    pub start: f64,
+    pub per_decade: u32,
+}
+
+impl Logarithmic {
+    fn time(&self, i: u32) -> f64 {
+        self.start * 10f64.powf(i as f64 / self.per_decade.max(1) as f64)
+    }
+}
+
+/// Something interesting happening in an update.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum Trigger {
+    /// stars merged, see `Simulation::merges`
+    Merger,
+    /// groups passed their closest approach, see `Simulation::pericenters`, or without a
+    /// `PericenterMonitor`, the two heaviest stars, e.g. the centers of merging galaxies, did
+    /// in the previous update
+    Pericenter,
+    /// every update around a pericenter passage of groups, see `PericenterMonitor`
+    NearPericenter,
+    /// a group was disrupted, see `Simulation::disruptions`
+    Disruption,
+    /// a `schedule::Action::Marker` of this name fired
+    Marker(String),
+}
+
+/// Decides after every update whether it is written, following an `OutputPolicy`.
+#[derive(Clone, Debug)]
+pub struct Cadence {
+    pub policy: OutputPolicy,
+    /// simulated time of the next output of `every_time`
+    next_time: f64,
+    /// index of the next output of `logarithmic`
+    next_log: u32,
+    /// the two heaviest stars and their separation after the last two updates, oldest first
+    pair: Option<([StarId; 2], [f64; 2])>,
+}
+
+impl Cadence {
+    /// Follows `policy` from the current state of `simulation` on, so a continued run writes
+    /// the same updates as an uninterrupted one.
+    pub fn new(policy: OutputPolicy, simulation: &Simulation) -> Self {
+        let mut cadence = Self {
+            policy,
+            next_time: 0.0,
+            next_log: 0,
+            pair: None,
+        };
+        cadence.advance_times(simulation.time);
+        cadence
+    }
+
+    /// Whether the last update of `simulation` is written. Call it after every update.
+    pub fn due(&mut self, simulation: &Simulation) -> bool {
+        let policy = &self.policy;
+        let steps = policy
+            .every_steps
+            .is_some_and(|steps| simulation.step.is_multiple_of(steps.max(1)));
+        let time = policy.every_time.is_some_and(|interval| interval > 0.0)
+            && reached(simulation.time, self.next_time);
+        let logarithmic = policy
+            .logarithmic
+            .filter(|logarithmic| logarithmic.start > 0.0)
+            .is_some_and(|logarithmic| reached(simulation.time, logarithmic.time(self.next_log)));
+        let pericenter = match &simulation.pericenter_monitor {
+            Some(_) => !simulation.pericenters.is_empty(),
+            None => self.pericenter(simulation),
+        };
+        let events = self.policy.events.iter().any(|trigger| match trigger {
+            Trigger::Merger => !simulation.merges.is_empty(),
+            Trigger::Pericenter => pericenter,
+            Trigger::NearPericenter => simulation
+                .pericenter_monitor
+                .as_ref()
+                .is_some_and(|monitor| monitor.is_near(simulation.time)),
+            Trigger::Disruption => !simulation.disruptions.is_empty(),
+            Trigger::Marker(name) => simulation
+                .fired
+                .iter()
+                .any(|event| matches!(&event.action, Action::Marker(fired) if fired == name)),
+        });
+
+        self.advance_times(simulation.time);
+        steps || time || logarithmic || events
+    }
+
+    /// Moves the next output times of `every_time` and `logarithmic` past `time`.
+    fn advance_times(&mut self, time: f64) {
+        if let Some(interval) = self.policy.every_time.filter(|&interval| interval > 0.0) {
+            self.next_time = ((time / interval).floor() + 1.0) * interval;
+            if reached(time, self.next_time) {
+                self.next_time += interval;
+            }
+        }
+        if let Some(logarithmic) = self.policy.logarithmic.filter(|log| log.start > 0.0) {
+            while reached(time, logarithmic.time(self.next_log)) {
+                self.next_log += 1;
+            }
+        }
+    }
+
+    /// Tracks the two heaviest stars, whether they passed their closest approach in the
+    /// previous update. Only follows them while they stay the heaviest.
+    fn pericenter(&mut self, simulation: &Simulation) -> bool {
+        let mut heaviest: Vec<_> = simulation
+            .stars
+            .iter()
+            .enumerate()
+            .filter(|(_, star)| star.pos().iter().all(|x| x.is_finite()))
+            .map(|(id, star)| (id, star.mass()))
+            .collect();
+        if heaviest.len() < 2 {
+            self.pair = None;
+            return false;
+        }
+        heaviest.select_nth_unstable_by(1, |(_, a), (_, b)| b.total_cmp(a));
+        let mut ids = [heaviest[0].0, heaviest[1].0];
+        ids.sort_unstable();
+        let separation =
+            (simulation.stars[ids[1]].pos() - simulation.stars[ids[0]].pos()).norm() as f64;
+
+        // sorting the stars renumbers them
+        let previous = self.pair.take().map(|(mut pair, separations)| {
+            if let Some(sorted) = &simulation.sorted {
+                for id in &mut pair {
+                    *id = sorted
+                        .iter()
+                        .position(|&old| old == *id)
+                        .unwrap_or(usize::MAX);
+                }
+                pair.sort_unstable();
+            }
+            (pair, separations)
+        });
+        let (passed, separations) = match previous {
+            Some((pair, [before, last])) if pair == ids => {
+                (last < before && separation > last, [last, separation])
+            }
+            // a new pair has to approach first
+            _ => (false, [f64::NEG_INFINITY, separation]),
+        };
+        self.pair = Some((ids, separations));
+        passed
+    }
+}
+
+/// Whether `time` reached `target`, allowing for the rounding of summed time steps.
+fn reached(time: f64, target: f64) -> bool {
+    time >= target - 1e-9 * target.abs().max(1.0)
+}
@@ -0,0 +1,105 @@
+use crate::Simulation;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A stage of `Simulation::update`, which runs the stages of `Simulation::pipeline` in order.
+/// Events are fired before the first and after the last stage regardless of the pipeline.
+///
+/// `Gravity` and `ExternalForces` don't run on their own, they select the forces `Integrate`
+/// evaluates, once per force evaluation of the integrator, and have to come before it.
+#[derive(Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum Stage {
+    /// advances the tree of `SimulationConfig::incremental_rebuild`, without it the tree is
+    /// built fresh, or updated with `SimulationConfig::reuse_tree`, for every force evaluation
+    BuildTree,
+    /// gravity of the tree, of dominant stars and of the near field
+    Gravity,
+    /// the terms registered with `Simulation::add_force` and the potentials of
+    /// `Simulation::add_potential`
+    ExternalForces,
+    /// moves the stars with the integrator
+    Integrate,
+    /// moves `Simulation::probes` through the field of the stars, the gravity of which
+    /// `Gravity` selects as well
+    Probes,
+    /// removes stars outside of the domain
+    Boundaries,
+    /// records `Simulation::error_estimate`
+    Diagnostics,
+    /// resolves collisions if `SimulationConfig::merge_collisions` is set, against the
+    /// positions at the start of the update
+    Collisions,
+    /// kicks the velocities of the stars if `SimulationConfig::thermal_noise` is set
+    ThermalNoise,
+    /// a stage added from code
+    #[cfg_attr(feature = "serde", serde(skip))]
+    Custom(Arc<dyn CustomStage>),
+}
+
+impl Stage {
+    /// The stages of a plain update.
+    pub fn default_pipeline() -> Vec<Stage> {
+        Vec::from([
+            Stage::BuildTree,
+            Stage::Gravity,
+            Stage::ExternalForces,
+            Stage::Integrate,
+            Stage::Probes,
+            Stage::Boundaries,
+            Stage::Diagnostics,
+            Stage::Collisions,
+            Stage::ThermalNoise,
+        ])
+    }
+}
+
+/// Same kind of stage, custom stages are only equal to themselves.
+impl PartialEq for Stage {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Stage::Custom(a), Stage::Custom(b)) => Arc::ptr_eq(a, b),
+            _ => core::mem::discriminant(self) == core::mem::discriminant(other),
+        }
+    }
+}
+
+impl fmt::Debug for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Stage::BuildTree => f.write_str("BuildTree"),
+            Stage::Gravity => f.write_str("Gravity"),
+            Stage::ExternalForces => f.write_str("ExternalForces"),
+            Stage::Integrate => f.write_str("Integrate"),
+            Stage::Probes => f.write_str("Probes"),
+            Stage::Boundaries => f.write_str("Boundaries"),
+            Stage::Diagnostics => f.write_str("Diagnostics"),
+            Stage::Collisions => f.write_str("Collisions"),
+            Stage::ThermalNoise => f.write_str("ThermalNoise"),
+            Stage::Custom(_) => f.write_str("Custom"),
+        }
+    }
+}
+
+/// A stage run in the pipeline of `Simulation::update`, e.g. to apply a boundary condition
+/// of its own. `Simulation::pipeline` is empty while it runs. Closures taking the simulation
+/// are stages as well.
+pub trait CustomStage: Send + Sync {
+    fn run(&self, simulation: &mut Simulation);
+}
+
+impl<F> CustomStage for F
+where
+    F: Fn(&mut Simulation) + Send + Sync,
+{
+    fn run(&self, simulation: &mut Simulation) {
+        self(simulation)
+    }
+}
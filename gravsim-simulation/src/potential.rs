@@ -0,0 +1,130 @@
+use crate::Real;
+use nalgebra::Vector2;
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A fixed, analytic field every star and probe moves through, registered with
+/// `Simulation::add_potential`, like a dark matter halo that would take far too many
+/// particles to simulate. It pulls stars but isn't pulled by them, and `Stage::ExternalForces`
+/// selects it like the terms of `Simulation::add_force`.
+///
+/// The spherical potentials are those of 3d halos, cut through the plane of the simulation,
+/// so they combine with the inverse square gravity between the stars.
+pub trait ExternalPotential: Send + Sync {
+    /// Acceleration at `pos`, for the gravitational constant `gravity`.
+    fn acceleration(&self, pos: &Vector2<Real>, gravity: Real) -> Vector2<Real>;
+
+    /// Potential energy per unit of mass at `pos`, zero at infinity if the potential is finite
+    /// there.
+    fn potential(&self, pos: &Vector2<Real>, gravity: Real) -> Real;
+
+    /// Speed of a circular orbit through `pos` around `center` in this potential alone.
+    fn circular_velocity(
+        &self,
+        pos: &Vector2<Real>,
+        center: &Vector2<Real>,
+        gravity: Real,
+    ) -> Real {
+        let offset = pos - center;
+        (-self.acceleration(pos, gravity).dot(&offset))
+            .max(0.0)
+            .sqrt()
+    }
+}
+
+/// A softened point mass, e.g. a black hole that should stay put.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PointMass {
+    pub center: Vector2<Real>,
+    pub mass: Real,
+    /// Plummer softening length, like `SimulationConfig::softening`
+    pub softening: Real,
+}
+
+impl ExternalPotential for PointMass {
+    fn acceleration(&self, pos: &Vector2<Real>, gravity: Real) -> Vector2<Real> {
+        let offset = pos - self.center;
+        let dist_sq = offset.norm_squared() + self.softening * self.softening;
+        if dist_sq == 0.0 {
+            return Vector2::zeros();
+        }
+        -offset * (gravity * self.mass / (dist_sq * dist_sq.sqrt()))
+    }
+
+    fn potential(&self, pos: &Vector2<Real>, gravity: Real) -> Real {
+        let dist_sq = (pos - self.center).norm_squared() + self.softening * self.softening;
+        -gravity * self.mass / dist_sq.sqrt()
+    }
+}
+
+/// Cored isothermal sphere, the density falls with `r⁻²` outside of the core, so circular
+/// orbits there all have about the same speed. The potential grows without bound, so it is
+/// zero on the edge of the core instead of at infinity.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Isothermal {
+    pub center: Vector2<Real>,
+    /// speed of circular orbits far outside of the core, which doesn't depend on `gravity`
+    pub velocity: Real,
+    pub core_radius: Real,
+}
+
+impl ExternalPotential for Isothermal {
+    fn acceleration(&self, pos: &Vector2<Real>, _: Real) -> Vector2<Real> {
+        let offset = pos - self.center;
+        let dist_sq = offset.norm_squared() + self.core_radius * self.core_radius;
+        if dist_sq == 0.0 {
+            return Vector2::zeros();
+        }
+        -offset * (self.velocity * self.velocity / dist_sq)
+    }
+
+    fn potential(&self, pos: &Vector2<Real>, _: Real) -> Real {
+        let dist_sq = (pos - self.center).norm_squared() + self.core_radius * self.core_radius;
+        let core_sq = (self.core_radius * self.core_radius).max(Real::MIN_POSITIVE);
+        0.5 * self.velocity * self.velocity * (dist_sq / core_sq).ln()
+    }
+}
+
+/// Navarro-Frenk-White profile of cold dark matter halos, the density falls with `r⁻¹`
+/// inside and `r⁻³` far outside of `scale_radius`. Circular speeds peak at about 2.16 scale
+/// radii and fall off slowly beyond.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Nfw {
+    pub center: Vector2<Real>,
+    /// characteristic mass `4π ρ₀ r_s³`, the mass within `r` is
+    /// `mass * (ln(1 + x) - x / (1 + x))` with `x = r / scale_radius`
+    pub mass: Real,
+    pub scale_radius: Real,
+}
+
+impl Nfw {
+    /// Mass within `radius` of the center.
+    pub fn enclosed_mass(&self, radius: Real) -> Real {
+        let x = radius / self.scale_radius;
+        self.mass * (x.ln_1p() - x / (1.0 + x))
+    }
+}
+
+impl ExternalPotential for Nfw {
+    fn acceleration(&self, pos: &Vector2<Real>, gravity: Real) -> Vector2<Real> {
+        let offset = pos - self.center;
+        let dist = offset.norm();
+        if dist == 0.0 {
+            return Vector2::zeros();
+        }
+        -offset * (gravity * self.enclosed_mass(dist) / (dist * dist * dist))
+    }
+
+    fn potential(&self, pos: &Vector2<Real>, gravity: Real) -> Real {
+        let dist = (pos - self.center).norm();
+        if dist == 0.0 {
+            return -gravity * self.mass / self.scale_radius;
+        }
+        -gravity * self.mass * (dist / self.scale_radius).ln_1p() / dist
+    }
+}
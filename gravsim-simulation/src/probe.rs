@@ -0,0 +1,150 @@
+use crate::pipeline::Stage;
+use crate::potential::ExternalPotential;
+use crate::tree::FlatTree;
+use crate::{DilationZone, ForceStages, MassData, Real, Simulation, SimulationConfig, StarId};
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use nalgebra::Vector2;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A massless test particle, e.g. a spacecraft, moving through the gravitational field of
+/// the stars without pulling on them. Probes are neither stars nor inserted into the tree,
+/// `Stage::Probes` moves them with fourth order Runge-Kutta against the stars as they are
+/// after `Stage::Integrate`, so they follow close flybys far more accurately than stars.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Probe {
+    pub pos: Vector2<Real>,
+    pub vel: Vector2<Real>,
+    /// positions at the end of the last updates, oldest first, up to `TRAJECTORY_LENGTH`
+    pub trajectory: VecDeque<Vector2<Real>>,
+}
+
+impl Probe {
+    /// Runge-Kutta steps per update.
+    pub const SUBSTEPS: u32 = 16;
+    pub const TRAJECTORY_LENGTH: usize = 4096;
+
+    pub fn new(pos: Vector2<Real>, vel: Vector2<Real>) -> Self {
+        Self {
+            pos,
+            vel,
+            trajectory: VecDeque::from([pos]),
+        }
+    }
+
+    /// Moves the probe by `dt` through the field given by the `acceleration` at a position,
+    /// and appends its new position to the trajectory.
+    pub fn advance(&mut self, dt: Real, acceleration: impl Fn(&Vector2<Real>) -> Vector2<Real>) {
+        let h = dt / Self::SUBSTEPS as Real;
+        let half = 0.5 * h;
+        for _ in 0..Self::SUBSTEPS {
+            let (pos, vel) = (self.pos, self.vel);
+            let k1 = (vel, acceleration(&pos));
+            let k2 = (vel + k1.1 * half, acceleration(&(pos + k1.0 * half)));
+            let k3 = (vel + k2.1 * half, acceleration(&(pos + k2.0 * half)));
+            let k4 = (vel + k3.1 * h, acceleration(&(pos + k3.0 * h)));
+            self.pos += (k1.0 + (k2.0 + k3.0) * 2.0 + k4.0) * (h / 6.0);
+            self.vel += (k1.1 + (k2.1 + k3.1) * 2.0 + k4.1) * (h / 6.0);
+        }
+
+        if self.trajectory.len() == Self::TRAJECTORY_LENGTH {
+            self.trajectory.pop_front();
+        }
+        self.trajectory.push_back(self.pos);
+    }
+}
+
+/// The field of the stars frozen at one moment, as a massless body feels it: their gravity,
+/// without the near field, and the external potentials. Probes move through it, and it
+/// predicts orbits without touching the simulation, see `Simulation::predict_orbit`.
+pub struct Field<'a> {
+    tree: FlatTree,
+    potentials: &'a [Arc<dyn ExternalPotential>],
+    config: SimulationConfig,
+    forces: ForceStages,
+}
+
+impl Field<'_> {
+    /// Acceleration of a massless body at `pos`.
+    pub fn acceleration(&self, pos: &Vector2<Real>) -> Vector2<Real> {
+        let gravity = match self.forces.gravity {
+            true => self.tree.force_on(
+                &MassData {
+                    position: *pos,
+                    mass: 1.0,
+                },
+                &self.config,
+            ),
+            false => Vector2::zeros(),
+        };
+        let external: Vector2<Real> = self
+            .potentials
+            .iter()
+            .filter(|_| self.forces.external)
+            .map(|potential| potential.acceleration(pos, self.config.gravity))
+            .sum();
+        gravity + external
+    }
+}
+
+impl Simulation {
+    /// The current field of the stars in the domain, with the forces of the pipeline.
+    /// `excluded` leaves a star out, e.g. to predict its own orbit.
+    pub fn field(&self, excluded: Option<StarId>) -> Field<'_> {
+        let forces = ForceStages {
+            gravity: self.pipeline.contains(&Stage::Gravity),
+            external: self.pipeline.contains(&Stage::ExternalForces),
+        };
+        self.frozen_field(&self.config, forces, excluded)
+    }
+
+    pub(crate) fn frozen_field(
+        &self,
+        config: &SimulationConfig,
+        forces: ForceStages,
+        excluded: Option<StarId>,
+    ) -> Field<'_> {
+        let config = SimulationConfig {
+            near_field: None,
+            ..*config
+        };
+        let mut tree = FlatTree::bounding(&config.domain, 2 * self.stars.len());
+        self.stars
+            .iter()
+            .enumerate()
+            .filter(|&(id, star)| Some(id) != excluded && config.domain.contains(star.pos()))
+            .for_each(|(_, star)| tree.insert(&star.mass_point));
+        tree.summarize();
+        Field {
+            tree,
+            potentials: &self.potentials,
+            config,
+            forces,
+        }
+    }
+
+    /// Positions of a massless body starting at `pos` with `vel` at the end of each of the
+    /// next `updates`, moved like a probe through the current field, which stays frozen.
+    /// Starts with `pos`. `excluded` leaves a star out of the field, e.g. the one at `pos`.
+    pub fn predict_orbit(
+        &self,
+        pos: Vector2<Real>,
+        vel: Vector2<Real>,
+        updates: usize,
+        excluded: Option<StarId>,
+    ) -> Vec<Vector2<Real>> {
+        let field = self.field(excluded);
+        let mut probe = Probe::new(pos, vel);
+        let mut orbit = Vec::with_capacity(updates + 1);
+        orbit.push(pos);
+        for _ in 0..updates {
+            let dt = self.config.dt * DilationZone::time_scale(&self.dilation_zones, &probe.pos);
+            probe.advance(dt, |pos| field.acceleration(pos));
+            orbit.push(probe.pos);
+        }
+        orbit
+    }
+}
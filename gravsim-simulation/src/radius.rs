@@ -0,0 +1,86 @@
+use crate::{consts, Real, Star};
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+
+/// Radius of a star of a given mass, which collisions and renderers use to size stars. Set
+/// with `Simulation::set_mass_radius_relation`, `ConstantDensity` by default. Closures
+/// taking the mass and returning the radius are relations as well.
+pub trait MassRadiusRelation: Send + Sync {
+    fn radius(&self, mass: Real) -> Real;
+}
+
+impl<F> MassRadiusRelation for F
+where
+    F: Fn(Real) -> Real + Send + Sync,
+{
+    fn radius(&self, mass: Real) -> Real {
+        self(mass)
+    }
+}
+
+/// Spheres of the same density, the radius grows with the cube root of the mass.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ConstantDensity {
+    pub density: Real,
+}
+
+impl ConstantDensity {
+    pub const DEFAULT: Self = Self {
+        density: Star::DENSITY,
+    };
+}
+
+impl Default for ConstantDensity {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl MassRadiusRelation for ConstantDensity {
+    fn radius(&self, mass: Real) -> Real {
+        (0.75 * mass / (self.density * consts::PI)).cbrt()
+    }
+}
+
+/// Empirical fit of main sequence stars, `R ~ M^0.8` below and `R ~ M^0.57` above one solar
+/// mass. `solar_mass` and `solar_radius` are the mass and radius of the sun in simulation
+/// units.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MainSequence {
+    pub solar_mass: Real,
+    pub solar_radius: Real,
+}
+
+impl MassRadiusRelation for MainSequence {
+    fn radius(&self, mass: Real) -> Real {
+        let mass = mass / self.solar_mass;
+        let exponent = if mass < 1.0 { 0.8 } else { 0.57 };
+        self.solar_radius * mass.max(0.0).powf(exponent)
+    }
+}
+
+/// White dwarfs, which shrink as they get heavier, `R ~ M^(-1/3)`, and collapse towards
+/// the Chandrasekhar mass of 1.44 solar masses (Nauenberg's approximation). `solar_radius`
+/// is the radius of a white dwarf of one solar mass, both in simulation units.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct WhiteDwarf {
+    pub solar_mass: Real,
+    pub solar_radius: Real,
+}
+
+impl WhiteDwarf {
+    pub const CHANDRASEKHAR_MASS: Real = 1.44;
+}
+
+impl MassRadiusRelation for WhiteDwarf {
+    fn radius(&self, mass: Real) -> Real {
+        let mass = mass / self.solar_mass;
+        if mass <= 0.0 || mass >= Self::CHANDRASEKHAR_MASS {
+            return 0.0;
+        }
+        // normalized so a white dwarf of one solar mass has `solar_radius`
+        let collapse =
+            |mass: Real| (1.0 - (mass / Self::CHANDRASEKHAR_MASS).powf(4.0 / 3.0)).sqrt();
+        self.solar_radius * mass.cbrt().recip() * collapse(mass) / collapse(1.0)
+    }
+}
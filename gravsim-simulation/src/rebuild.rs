@@ -0,0 +1,123 @@
+use crate::tree::{self, FlatTree};
+use crate::{is_direct, BodyKind, MassData, Real, SimulationConfig, Star, StarId};
+use alloc::vec::Vec;
+use core::slice;
+use nalgebra::Vector2;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Spreads the tree build over several updates, see `SimulationConfig::incremental_rebuild`.
+/// Forces are evaluated with the last complete tree meanwhile, so the far field lags
+/// behind the stars by a few updates, but no single update pays for a full build.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct IncrementalRebuild {
+    /// stars inserted into the next tree per update
+    pub stars_per_update: usize,
+    /// updates a tree may be used for, once it is this stale the next one is finished at once
+    pub max_staleness: u64,
+}
+
+/// A tree built from the stars of earlier updates.
+#[derive(Clone, Debug)]
+pub struct StaleTree {
+    pub tree: FlatTree,
+    /// step at which the build started, no star in the tree is older
+    pub step: u64,
+    /// mass points of the stars as they were inserted, by id, `None` if they weren't
+    inserted: Vec<Option<MassData>>,
+}
+
+impl StaleTree {
+    fn new(config: &SimulationConfig, capacity: usize, step: u64) -> Self {
+        Self {
+            tree: FlatTree::bounding(&config.domain, 2 * capacity),
+            step,
+            inserted: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Inserts a star like `Simulation::update` does, skipping stars outside of the domain,
+    /// dominant ones and black holes.
+    fn insert(&mut self, id: StarId, star: &Star, kinds: &[BodyKind], config: &SimulationConfig) {
+        if self.inserted.len() <= id {
+            self.inserted.resize(id + 1, None);
+        }
+        if config.domain.contains(star.pos()) && !is_direct(id, star, kinds, config) {
+            self.tree.insert(&star.mass_point);
+            self.inserted[id] = Some(star.mass_point);
+        }
+    }
+
+    /// The force of the outdated copy of star `id` in the tree on `obj`, the star at its
+    /// current position. Subtracting it from the tree force removes the self interaction,
+    /// which a fresh tree skips because both positions are the same.
+    pub fn self_force(
+        &self,
+        id: StarId,
+        obj: &MassData,
+        config: &SimulationConfig,
+    ) -> Vector2<Real> {
+        match self.inserted.get(id) {
+            Some(Some(own)) => tree::direct_force_on(obj, slice::from_ref(own), config),
+            _ => Vector2::zeros(),
+        }
+    }
+}
+
+/// The tree forces are evaluated with and the one being built, see `IncrementalRebuild`.
+#[derive(Clone, Debug, Default)]
+pub struct TreeRebuild {
+    current: Option<StaleTree>,
+    pending: Option<StaleTree>,
+    /// next star to insert into `pending`
+    next: StarId,
+}
+
+impl TreeRebuild {
+    /// Inserts the next `stars_per_update` stars into the pending tree, and replaces the
+    /// current tree once all stars are in. Without a current tree, or once it is too stale,
+    /// the pending tree is finished at once.
+    pub fn advance(
+        &mut self,
+        stars: &[Star],
+        kinds: &[BodyKind],
+        config: &SimulationConfig,
+        settings: &IncrementalRebuild,
+        step: u64,
+    ) {
+        let budget = match self.staleness(step) {
+            Some(staleness) if staleness < settings.max_staleness => settings.stars_per_update,
+            _ => usize::MAX,
+        };
+
+        let pending = self
+            .pending
+            .get_or_insert_with(|| StaleTree::new(config, stars.len(), step));
+        let end = self.next.saturating_add(budget).min(stars.len());
+        for (id, star) in stars.iter().enumerate().take(end).skip(self.next) {
+            pending.insert(id, star, kinds, config);
+        }
+        self.next = end;
+
+        if end == stars.len() {
+            if let Some(mut pending) = self.pending.take() {
+                pending.tree.summarize();
+                self.current = Some(pending);
+            }
+            self.next = 0;
+        }
+    }
+
+    /// The complete tree to evaluate forces with, if there is one.
+    pub fn current(&self) -> Option<&StaleTree> {
+        self.current.as_ref()
+    }
+
+    /// Updates since the build of the current tree started.
+    pub fn staleness(&self, step: u64) -> Option<u64> {
+        self.current
+            .as_ref()
+            .map(|current| step.saturating_sub(current.step))
+    }
+}
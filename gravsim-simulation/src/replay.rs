@@ -0,0 +1,285 @@
+use crate::backend::SimulationBackend;
+use crate::diagnostics::Diagnostics;
+use crate::{Real, SimulationConfig, Star, StarId};
+use alloc::vec::Vec;
+use bincode::Options;
+use nalgebra::Vector2;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+/// Components of a star in a recording, each stored as the bits of a `Real`.
+const WORDS: usize = 5;
+
+/// Written once at the start of a recording.
+#[derive(Serialize, Deserialize)]
+struct Header {
+    magic: [u8; 4],
+    /// whether `Real` is `f64`, recordings only play back with the same precision
+    f64: bool,
+    /// of the recorded simulation, for the diagnostics of the replay
+    gravity: Real,
+    softening: Real,
+}
+
+impl Header {
+    const MAGIC: [u8; 4] = *b"GSRP";
+
+    fn new(config: &SimulationConfig) -> Self {
+        Self {
+            magic: Self::MAGIC,
+            f64: cfg!(feature = "f64"),
+            gravity: config.gravity,
+            softening: config.softening,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Frame {
+    step: u64,
+    time: f64,
+    /// whether `words` are xor'ed with those of the previous frame
+    delta: bool,
+    words: Vec<u64>,
+}
+
+/// Writes the stars of a simulation frame after frame, to be played back with `Replay`
+/// without simulating them again, e.g. to share a run or to find the first frame two runs
+/// differ in. Recordings are lossless. With `delta`, every frame only stores how the bits
+/// of each star changed since the previous one, which are mostly small numbers and take up
+/// little space, as integers are written with a variable length.
+pub struct Recorder<W: Write> {
+    writer: W,
+    delta: bool,
+    /// words of the previous frame
+    previous: Vec<u64>,
+    frames: u64,
+}
+
+impl Recorder<BufWriter<File>> {
+    pub fn create(
+        path: impl AsRef<Path>,
+        config: &SimulationConfig,
+        delta: bool,
+    ) -> io::Result<Self> {
+        Self::new(BufWriter::new(File::create(path)?), config, delta)
+    }
+}
+
+impl<W: Write> Recorder<W> {
+    /// Starts a recording of a simulation with `config`.
+    pub fn new(mut writer: W, config: &SimulationConfig, delta: bool) -> io::Result<Self> {
+        options()
+            .serialize_into(&mut writer, &Header::new(config))
+            .map_err(invalid_data)?;
+        Ok(Self {
+            writer,
+            delta,
+            previous: Vec::new(),
+            frames: 0,
+        })
+    }
+
+    /// Appends a frame with the state of `stars` at `step` and `time`. A frame with a
+    /// different number of stars than the previous one is stored in full.
+    pub fn record(&mut self, step: u64, time: f64, stars: &[Star]) -> io::Result<()> {
+        let words: Vec<u64> = stars.iter().flat_map(words_of).collect();
+        let delta = self.delta && words.len() == self.previous.len();
+        let frame = Frame {
+            step,
+            time,
+            delta,
+            words: match delta {
+                true => words
+                    .iter()
+                    .zip(&self.previous)
+                    .map(|(a, b)| a ^ b)
+                    .collect(),
+                false => words.clone(),
+            },
+        };
+        options()
+            .serialize_into(&mut self.writer, &frame)
+            .map_err(invalid_data)?;
+        self.previous = words;
+        self.frames += 1;
+        Ok(())
+    }
+
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    /// Flushes the frames written so far, so they can be read while recording.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    /// Flushes the frames written so far, returning the writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Plays back a recording of `Recorder`, one frame per `step`. Frames are read as they are
+/// needed, so recordings don't have to fit into memory. Once the last frame is reached, the
+/// stars stay as they are. Edits of the stars are ignored, as they would be undone by the
+/// next frame anyway.
+pub struct Replay<R: Read> {
+    reader: R,
+    header: Header,
+    words: Vec<u64>,
+    stars: Vec<Star>,
+    /// step and time of the current frame as they were recorded
+    pub step: u64,
+    pub time: f64,
+    /// frames played so far, including the current one
+    pub frames: u64,
+    pub finished: bool,
+    /// why the replay ended early, set by `step`
+    pub error: Option<io::Error>,
+}
+
+impl Replay<BufReader<File>> {
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read> Replay<R> {
+    /// Reads the header and the first frame, a recording without frames has no stars.
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let header: Header = options()
+            .deserialize_from(&mut reader)
+            .map_err(invalid_data)?;
+        if header.magic != Header::MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a gravsim recording",
+            ));
+        }
+        if header.f64 != cfg!(feature = "f64") {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "the recording was made with a different floating point precision",
+            ));
+        }
+
+        let mut replay = Self {
+            reader,
+            header,
+            words: Vec::new(),
+            stars: Vec::new(),
+            step: 0,
+            time: 0.0,
+            frames: 0,
+            finished: false,
+            error: None,
+        };
+        replay.advance()?;
+        Ok(replay)
+    }
+
+    /// Moves on to the next frame. Returns false at the end of the recording, which isn't
+    /// an error, even if the last frame was cut off.
+    pub fn advance(&mut self) -> io::Result<bool> {
+        if self.finished {
+            return Ok(false);
+        }
+        let frame: Frame = match options().deserialize_from(&mut self.reader) {
+            Ok(frame) => frame,
+            Err(e) => match *e {
+                bincode::ErrorKind::Io(e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                    self.finished = true;
+                    return Ok(false);
+                }
+                e => return Err(invalid_data(Box::new(e))),
+            },
+        };
+
+        if !frame.words.len().is_multiple_of(WORDS)
+            || (frame.delta && frame.words.len() != self.words.len())
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "a frame doesn't match the previous one",
+            ));
+        }
+        match frame.delta {
+            true => self
+                .words
+                .iter_mut()
+                .zip(frame.words)
+                .for_each(|(word, delta)| *word ^= delta),
+            false => self.words = frame.words,
+        }
+        self.stars = self.words.chunks_exact(WORDS).map(star_of).collect();
+        self.step = frame.step;
+        self.time = frame.time;
+        self.frames += 1;
+        Ok(true)
+    }
+
+    pub fn stars(&self) -> &[Star] {
+        &self.stars
+    }
+}
+
+impl<R: Read> SimulationBackend for Replay<R> {
+    /// Shows the next frame, a broken recording ends the replay, see `error`.
+    fn step(&mut self) {
+        if let Err(e) = self.advance() {
+            self.error = Some(e);
+            self.finished = true;
+        }
+    }
+
+    fn snapshot(&self) -> &[Star] {
+        &self.stars
+    }
+
+    /// Ignored, returns an id without a star.
+    fn add_star(&mut self, _star: Star) -> StarId {
+        self.stars.len()
+    }
+
+    fn remove_star(&mut self, _id: StarId) -> Option<Star> {
+        None
+    }
+
+    fn insert_star(&mut self, _id: StarId, _star: Star) {}
+
+    fn set_velocity(&mut self, _id: StarId, _vel: Vector2<Real>) -> bool {
+        false
+    }
+
+    fn diagnostics(&self) -> Diagnostics {
+        Diagnostics::with_softening(&self.stars, self.header.gravity, self.header.softening)
+    }
+
+    fn finished(&self) -> bool {
+        self.finished
+    }
+}
+
+fn words_of(star: &Star) -> [u64; WORDS] {
+    let pos = star.pos();
+    [pos.x, pos.y, star.vel.x, star.vel.y, star.mass()].map(|value| value.to_bits() as u64)
+}
+
+fn star_of(words: &[u64]) -> Star {
+    let [x, y, vx, vy, mass] = [0, 1, 2, 3, 4].map(|i| Real::from_bits(words[i] as _));
+    Star::new(Vector2::new(x, y), Vector2::new(vx, vy), mass)
+}
+
+/// Little endian with variable length integers, so small deltas take up few bytes.
+fn options() -> impl Options {
+    bincode::DefaultOptions::new()
+}
+
+fn invalid_data(error: bincode::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
@@ -0,0 +1,143 @@
+use crate::tree::FlatTree;
+use crate::{is_direct, BodyKind, MassData, Real, SimulationConfig, Star, StarId};
+use alloc::vec::Vec;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Keeps the tree from one force evaluation to the next and only moves the stars whose mass
+/// point changed, see `SimulationConfig::reuse_tree`. Forces are the same as with a fresh
+/// tree, but stars leaving their cells leave empty nodes behind, which the traversal has
+/// to skip, so the tree is built from scratch again once too many of them pile up.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TreeReuse {
+    /// fraction of the nodes that may be empty before the tree is built from scratch
+    pub max_empty_fraction: Real,
+}
+
+impl Default for TreeReuse {
+    fn default() -> Self {
+        Self {
+            max_empty_fraction: 0.25,
+        }
+    }
+}
+
+/// A tree updated in place with `FlatTree::remove` and `FlatTree::insert`, see `TreeReuse`.
+#[derive(Clone, Debug, Default)]
+pub struct ReusedTree {
+    tree: Option<FlatTree>,
+    /// mass points of the stars as they are in the tree, by id, `None` if they aren't
+    inserted: Vec<Option<MassData>>,
+    /// whether too many nodes were empty after the last update
+    degraded: bool,
+}
+
+impl ReusedTree {
+    /// Brings the tree up to date with `stars` of `kinds`, inserted like `Simulation::update`
+    /// does, skipping stars outside of the domain, dominant ones and black holes. Stars that changed are
+    /// removed and inserted again, then the centers of mass are refit. The tree is built
+    /// from scratch the first time, after the domain changed and once it degraded.
+    /// Returns whether it was built from scratch.
+    pub fn update(
+        &mut self,
+        stars: &[Star],
+        kinds: &[BodyKind],
+        config: &SimulationConfig,
+        settings: &TreeReuse,
+    ) -> bool {
+        let body = |id: StarId| {
+            let star = stars.get(id)?;
+            let inserted =
+                config.domain.contains(star.pos()) && !is_direct(id, star, kinds, config);
+            inserted.then_some(star.mass_point)
+        };
+        let (pos, scale) = config.domain.square();
+
+        let rebuilt = match &mut self.tree {
+            Some(tree)
+                if !self.degraded && *tree.root().pos() == pos && tree.root().scale() == scale =>
+            {
+                let count = self.inserted.len().max(stars.len());
+                self.inserted.resize(count, None);
+                for (id, inserted) in self.inserted.iter_mut().enumerate() {
+                    let new = body(id);
+                    if same(inserted, &new) {
+                        continue;
+                    }
+                    if let Some(old) = inserted {
+                        tree.remove(old);
+                    }
+                    if let Some(new) = &new {
+                        tree.insert(new);
+                    }
+                    *inserted = new;
+                }
+                false
+            }
+            _ => {
+                let mut tree = FlatTree::bounding(&config.domain, 2 * stars.len());
+                self.inserted = (0..stars.len()).map(body).collect();
+                self.inserted
+                    .iter()
+                    .flatten()
+                    .for_each(|obj| tree.insert(obj));
+                self.tree = Some(tree);
+                true
+            }
+        };
+
+        if let Some(tree) = &mut self.tree {
+            tree.summarize();
+            let nodes = tree.nodes();
+            let empty = nodes
+                .iter()
+                .filter(|node| node.center_of_mass().mass == 0.0)
+                .count();
+            self.degraded = empty as Real > settings.max_empty_fraction * nodes.len() as Real;
+        }
+        rebuilt
+    }
+
+    /// The tree as of the last `update`.
+    pub fn tree(&self) -> Option<&FlatTree> {
+        self.tree.as_ref()
+    }
+
+    /// Takes the star `id` out of the tree, the stars after it move down an id.
+    pub(crate) fn remove(&mut self, id: StarId) {
+        if id >= self.inserted.len() {
+            return;
+        }
+        if let (Some(tree), Some(old)) = (&mut self.tree, self.inserted.remove(id)) {
+            tree.remove(&old);
+        }
+    }
+
+    /// Makes room for a star at `id`, which is inserted into the tree by the next `update`.
+    pub(crate) fn insert(&mut self, id: StarId) {
+        if id < self.inserted.len() {
+            self.inserted.insert(id, None);
+        }
+    }
+
+    /// Follows the stars to their new ids after `Simulation::reorder_stars`, the tree
+    /// itself doesn't change.
+    pub(crate) fn reorder(&mut self, order: &[StarId]) {
+        if self.inserted.is_empty() {
+            return;
+        }
+        self.inserted = order
+            .iter()
+            .map(|&id| self.inserted.get(id).copied().flatten())
+            .collect();
+    }
+}
+
+fn same(a: &Option<MassData>, b: &Option<MassData>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => a.position == b.position && a.mass == b.mass,
+        (None, None) => true,
+        _ => false,
+    }
+}
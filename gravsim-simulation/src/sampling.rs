@@ -0,0 +1,121 @@
+use crate::Real;
+#[cfg(all(feature = "rand", feature = "std"))]
+use alloc::vec::Vec;
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+#[cfg(all(feature = "rand", feature = "std"))]
+use rand::seq::SliceRandom;
+#[cfg(all(feature = "rand", feature = "std"))]
+use rand::Rng;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// How a generated galaxy places its stars, see `Galaxy::with_sampling_seeded`. Independent
+/// random positions clump and leave gaps, which shows at a few hundred stars; the other
+/// methods spread the stars evenly over the disc while keeping them irregular.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum Sampling {
+    /// independent uniform random positions
+    #[default]
+    Random,
+    /// Latin hypercube sampling, every star in its own annulus of equal area and its own
+    /// sector of equal angle, at a random spot within both
+    Stratified,
+    /// the two dimensional Sobol sequence, shifted by a random offset
+    Sobol,
+    /// random positions keeping a minimum distance to each other that scales with the mean
+    /// distance between the stars, on top of any minimum separation of the galaxy
+    PoissonDisk,
+}
+
+impl Sampling {
+    /// Minimum distance between `count` stars on a disc of `radius` for `PoissonDisk`,
+    /// about half the closest packing random placement can reach.
+    pub fn poisson_disk_separation(&self, count: usize, radius: Real) -> Real {
+        match self {
+            Sampling::PoissonDisk => 0.75 * radius / (count.max(1) as Real).sqrt(),
+            _ => 0.0,
+        }
+    }
+}
+
+/// Points in the unit square drawn with a `Sampling`, which a galaxy maps onto its disc.
+/// `Random` draws the same points from the same rng as before there were other methods.
+#[cfg(all(feature = "rand", feature = "std"))]
+pub(crate) struct UnitSquare {
+    sampling: Sampling,
+    /// points drawn so far
+    index: usize,
+    /// the Cranley-Patterson rotation of the Sobol points
+    shift: [Real; 2],
+    /// stratum of every point along each dimension, for `Stratified`
+    strata: [Vec<usize>; 2],
+}
+
+#[cfg(all(feature = "rand", feature = "std"))]
+impl UnitSquare {
+    /// Prepares drawing `count` points. More can be drawn, e.g. when some are rejected,
+    /// beyond `count` stratified points are independent random ones.
+    pub fn new(sampling: Sampling, count: usize, rng: &mut impl Rng) -> Self {
+        let mut square = Self {
+            sampling,
+            index: 0,
+            shift: [0.0; 2],
+            strata: [Vec::new(), Vec::new()],
+        };
+        match sampling {
+            Sampling::Sobol => square.shift = [rng.gen(), rng.gen()],
+            Sampling::Stratified => {
+                for strata in &mut square.strata {
+                    *strata = (0..count).collect();
+                    strata.shuffle(rng);
+                }
+            }
+            Sampling::Random | Sampling::PoissonDisk => {}
+        }
+        square
+    }
+
+    pub fn sample(&mut self, rng: &mut impl Rng) -> [Real; 2] {
+        let index = self.index;
+        self.index += 1;
+        match self.sampling {
+            Sampling::Sobol => {
+                // the first point is the corner, which maps to the center of the disc
+                let point = sobol(index as u32 + 1);
+                [0, 1].map(|i| (point[i] + self.shift[i]).fract())
+            }
+            Sampling::Stratified if index < self.strata[0].len() => {
+                let count = self.strata[0].len() as Real;
+                [0, 1].map(|i| (self.strata[i][index] as Real + rng.gen::<Real>()) / count)
+            }
+            _ => [rng.gen(), rng.gen()],
+        }
+    }
+}
+
+/// The point at `index` of the two dimensional Sobol sequence: the van der Corput sequence in
+/// base 2, and the dimension of the primitive polynomial `x + 1`, whose direction numbers
+/// follow `v_k = v_(k-1) ^ (v_(k-1) >> 1)`.
+#[cfg(all(feature = "rand", feature = "std"))]
+fn sobol(index: u32) -> [Real; 2] {
+    let (mut x, mut y) = (0u32, 0u32);
+    let (mut v, mut w) = (1u32 << 31, 1u32 << 31);
+    let mut bits = index;
+    while bits != 0 {
+        if bits & 1 == 1 {
+            x ^= v;
+            y ^= w;
+        }
+        bits >>= 1;
+        v >>= 1;
+        w ^= w >> 1;
+    }
+    let scale = 1.0 / (1u64 << 32) as f64;
+    [(x as f64 * scale) as Real, (y as f64 * scale) as Real]
+}
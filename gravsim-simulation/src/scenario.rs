@@ -0,0 +1,452 @@
+use crate::collision::{CollisionModel, Species};
+use crate::color::ColorPolicy;
+use crate::group::{DisruptionMonitor, Group, PericenterMonitor};
+use crate::output::OutputPolicy;
+use crate::pipeline::Stage;
+use crate::probe::Probe;
+use crate::sampling::Sampling;
+use crate::schedule::{Event, Schedule};
+use crate::{
+    BodyKind, Galaxy, MassDistribution, Real, Simulation, SimulationConfig, SpiralArms, Star,
+};
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use nalgebra::Vector2;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Initial conditions of a simulation, read from a toml or json file, e.g.
+///
+/// ```toml
+/// # same stars in every run, leave out for random ones
+/// seed = 42
+///
+/// # stages of every update, collisions are resolved before integrating here
+/// pipeline = ["build_tree", "gravity", "collisions", "integrate", "boundaries"]
+///
+/// # run before every update, or inline as `script = { source = "..." }`, see `Script`
+/// script = { path = "ramp_gravity.rhai" }
+///
+/// [config]
+/// theta = 0.7
+/// # stars redden and fade towards the end of their lifetime
+/// evolution = { lifetime = 1e5, mass = 1000.0 }
+///
+/// [[galaxies]]
+/// stars = 5000
+/// radius = 10000.0
+/// black_hole = true
+/// position = [-20000.0, 0.0]
+/// velocity = [0.5, 0.0]
+/// colors = { radial = [[1.0, 0.9, 0.6], [0.4, 0.5, 1.0]] }
+/// arms = { count = 2, pitch = 0.3, contrast = 0.6 }
+/// sampling = "sobol"
+///
+/// [[stars]]
+/// position = [0.0, 15000.0]
+/// velocity = [0.0, 0.0]
+/// mass = 1000.0
+///
+/// [[probes]]
+/// position = [-30000.0, 12000.0]
+/// velocity = [1.2, -0.3]
+///
+/// [[events]]
+/// time = 2000.0
+/// action = { add_star = { position = [-30000.0, 0.0], velocity = [1.5, 0.0], mass = 1e4 } }
+///
+/// [[events]]
+/// time = 2500.0
+/// action = { set_dt = 0.5 }
+///
+/// [disruption]
+/// fraction = 0.3
+///
+/// [pericenters]
+/// window = 300.0
+///
+/// [output]
+/// every_time = 500.0
+/// events = ["merger", "pericenter", "near_pericenter"]
+///
+/// [collisions]
+/// default = { outcome = "bounce", restitution = 0.5 }
+/// rules = [{ species = [0, 1], outcome = "fragment", debris_fraction = 0.2, fragments = 6, ejection = 0.3, min_mass = 50.0 }]
+/// ```
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Scenario {
+    pub config: SimulationConfig,
+    pub galaxies: Vec<GalaxySpec>,
+    /// two galaxies on a collision course, added after `galaxies`
+    pub collision: Option<Collision>,
+    /// single stars, added after all galaxies
+    pub stars: Vec<StarSpec>,
+    /// test particles moving through the field of the stars, see `Probe`
+    pub probes: Vec<ProbeSpec>,
+    /// events in simulated time, see `Schedule`
+    pub events: Vec<Event>,
+    /// if set, the galaxies are watched for tidal disruptions
+    pub disruption: Option<DisruptionSpec>,
+    /// if set, closest approaches of the galaxies are detected
+    pub pericenters: Option<PericenterSpec>,
+    /// outcomes of collisions by species, if `config.merge_collisions` is set
+    pub collisions: CollisionModel,
+    /// replaces the stages of every update, e.g. to resolve collisions before integrating
+    pub pipeline: Option<Vec<Stage>>,
+    /// if set, the galaxies are generated from it, so runs of the scenario repeat exactly
+    pub seed: Option<u64>,
+    /// when frontends write snapshots or replay frames, if set, see `OutputPolicy`
+    pub output: Option<OutputPolicy>,
+    /// run by frontends before every update, if set, see `Script`
+    pub script: Option<ScriptSpec>,
+}
+
+/// A script of a scenario, either in its own file or inline.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum ScriptSpec {
+    /// relative to the scenario file if read with `Scenario::from_path`
+    Path(PathBuf),
+    Source(String),
+}
+
+impl ScriptSpec {
+    /// The source of the script, read from its file if needed.
+    pub fn source(&self) -> Result<String, String> {
+        match self {
+            ScriptSpec::Path(path) => std::fs::read_to_string(path)
+                .map_err(|e| format!("failed to read {}: {}", path.display(), e)),
+            ScriptSpec::Source(source) => Ok(source.clone()),
+        }
+    }
+}
+
+/// Parameters of a `DisruptionMonitor` watching every galaxy of a scenario.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DisruptionSpec {
+    /// share of the bound mass a galaxy has to lose
+    pub fraction: f64,
+    /// simulated time in which it has to be lost
+    pub window: f64,
+}
+
+impl Default for DisruptionSpec {
+    fn default() -> Self {
+        Self {
+            fraction: 0.2,
+            window: 500.0,
+        }
+    }
+}
+
+/// Parameters of a `PericenterMonitor` watching every pair of galaxies of a scenario.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PericenterSpec {
+    /// simulated time before and after a passage that counts as near it
+    pub window: f64,
+}
+
+impl Default for PericenterSpec {
+    fn default() -> Self {
+        Self { window: 500.0 }
+    }
+}
+
+/// A disc generated with `Galaxy::with_sampling_seeded`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GalaxySpec {
+    pub stars: usize,
+    pub radius: Real,
+    pub position: Vector2<Real>,
+    pub velocity: Vector2<Real>,
+    pub center_mass: Real,
+    pub mass_distribution: MassDistribution,
+    /// minimum distance between stars, 0 allows coincident stars
+    pub min_separation: Real,
+    /// spiral arms, none by default
+    pub arms: SpiralArms,
+    /// how the stars are placed on the disc, independently at random by default
+    pub sampling: Sampling,
+    pub colors: ColorPolicy,
+    /// species of the center and all stars, see `CollisionModel`
+    pub species: Species,
+    /// whether the center is a `BodyKind::BlackHole`
+    pub black_hole: bool,
+}
+
+impl Default for GalaxySpec {
+    fn default() -> Self {
+        Self {
+            stars: Simulation::N_STARS,
+            radius: 10_000.0,
+            position: Vector2::zeros(),
+            velocity: Vector2::zeros(),
+            center_mass: 1e1,
+            mass_distribution: Scenario::MASS_DISTRIBUTION,
+            min_separation: 0.0,
+            arms: SpiralArms::default(),
+            sampling: Sampling::default(),
+            colors: ColorPolicy::default(),
+            species: 0,
+            black_hole: false,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StarSpec {
+    pub position: Vector2<Real>,
+    pub velocity: Vector2<Real>,
+    pub mass: Real,
+    #[serde(default = "white")]
+    pub color: [f32; 3],
+    #[serde(default)]
+    pub species: Species,
+    #[serde(default)]
+    pub kind: BodyKind,
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProbeSpec {
+    pub position: Vector2<Real>,
+    pub velocity: Vector2<Real>,
+}
+
+fn white() -> [f32; 3] {
+    [1.0; 3]
+}
+
+impl Scenario {
+    /// Masses of galaxy stars if a galaxy doesn't specify them.
+    pub const MASS_DISTRIBUTION: MassDistribution = MassDistribution::new(100.0, 15000.0);
+
+    /// Reads a scenario, as json if the extension is `.json` and as toml otherwise.
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path)
+            .map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+        let scenario = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => Self::from_json(&source),
+            _ => Self::from_toml(&source),
+        };
+        let mut scenario: Self =
+            scenario.map_err(|e| format!("invalid scenario {}: {}", path.display(), e))?;
+        if let (Some(ScriptSpec::Path(script)), Some(dir)) = (&mut scenario.script, path.parent()) {
+            *script = dir.join(&*script);
+        }
+        Ok(scenario)
+    }
+
+    /// Reads a scenario from toml. Script paths are left as they are.
+    pub fn from_toml(source: &str) -> Result<Self, String> {
+        toml::from_str(source).map_err(|e| e.to_string())
+    }
+
+    /// Reads a scenario from json. Script paths are left as they are.
+    pub fn from_json(source: &str) -> Result<Self, String> {
+        serde_json::from_str(source).map_err(|e| e.to_string())
+    }
+
+    /// Just the given collision, with the default config.
+    pub fn collision(collision: Collision) -> Self {
+        Self {
+            collision: Some(collision),
+            ..Self::default()
+        }
+    }
+
+    pub fn stars(&self) -> Vec<Star> {
+        self.generate().0
+    }
+
+    /// All stars and their colors.
+    pub fn generate(&self) -> (Vec<Star>, Vec<[f32; 3]>) {
+        let collision = self.collision.as_ref().map(Collision::galaxies);
+        let (mut stars, mut colors) = (Vec::new(), Vec::new());
+        let galaxies = self.galaxies.iter().chain(collision.iter().flatten());
+        for (i, spec) in galaxies.enumerate() {
+            let center = Star::new(spec.position, spec.velocity, spec.center_mass);
+            let seed = match self.seed {
+                Some(seed) => seed.wrapping_add(i as u64),
+                None => rand::random(),
+            };
+            let galaxy = Galaxy::with_sampling_seeded(
+                center,
+                spec.stars,
+                spec.radius,
+                &spec.mass_distribution,
+                spec.min_separation,
+                &spec.arms,
+                spec.sampling,
+                seed,
+            );
+            colors.extend(galaxy.colors(&spec.colors));
+            stars.extend(galaxy.into_stars());
+        }
+        for star in &self.stars {
+            stars.push(Star::new(star.position, star.velocity, star.mass));
+            colors.push(star.color);
+        }
+        (stars, colors)
+    }
+
+    pub fn schedule(&self) -> Schedule {
+        Schedule::new(self.events.iter().cloned())
+    }
+
+    /// One group per galaxy, in the order of `generate`. Galaxies of the collision are
+    /// named `primary` and `secondary`.
+    pub fn groups(&self) -> Vec<Group> {
+        let collision = self.collision.as_ref().map(Collision::galaxies);
+        let names = (0..self.galaxies.len())
+            .map(|i| format!("galaxy {}", i))
+            .chain(["primary".to_string(), "secondary".to_string()]);
+
+        let mut start = 0;
+        self.galaxies
+            .iter()
+            .chain(collision.iter().flatten())
+            .zip(names)
+            .map(|(spec, name)| {
+                // the center and its stars
+                let stars = (start..start + spec.stars + 1).collect();
+                start += spec.stars + 1;
+                Group { name, stars }
+            })
+            .collect()
+    }
+
+    /// Species of all stars, in the order of `generate`.
+    pub fn species(&self) -> Vec<Species> {
+        let collision = self.collision.as_ref().map(Collision::galaxies);
+        let galaxies = self.galaxies.iter().chain(collision.iter().flatten());
+        galaxies
+            .flat_map(|spec| core::iter::repeat_n(spec.species, spec.stars + 1))
+            .chain(self.stars.iter().map(|star| star.species))
+            .collect()
+    }
+
+    /// Kinds of all stars, in the order of `generate`.
+    pub fn kinds(&self) -> Vec<BodyKind> {
+        let collision = self.collision.as_ref().map(Collision::galaxies);
+        let galaxies = self.galaxies.iter().chain(collision.iter().flatten());
+        let center = |spec: &GalaxySpec| match spec.black_hole {
+            true => BodyKind::BlackHole,
+            false => BodyKind::Star,
+        };
+        galaxies
+            .flat_map(|spec| {
+                core::iter::once(center(spec))
+                    .chain(core::iter::repeat_n(BodyKind::Star, spec.stars))
+            })
+            .chain(self.stars.iter().map(|star| star.kind))
+            .collect()
+    }
+
+    pub fn probes(&self) -> Vec<Probe> {
+        self.probes
+            .iter()
+            .map(|spec| Probe::new(spec.position, spec.velocity))
+            .collect()
+    }
+
+    pub fn disruption_monitor(&self) -> Option<DisruptionMonitor> {
+        self.disruption
+            .map(|spec| DisruptionMonitor::new(self.groups(), spec.fraction, spec.window))
+    }
+
+    pub fn pericenter_monitor(&self) -> Option<PericenterMonitor> {
+        self.pericenters
+            .map(|spec| PericenterMonitor::new(self.groups(), spec.window))
+    }
+
+    pub fn to_simulation(&self) -> Simulation {
+        self.to_simulation_with(self.stars())
+    }
+
+    /// The simulation of the scenario with `stars` generated before, e.g. by `generate`.
+    pub fn to_simulation_with(&self, stars: Vec<Star>) -> Simulation {
+        let mut simulation = Simulation::with_config(stars, self.config);
+        simulation.schedule = self.schedule();
+        simulation.disruption_monitor = self.disruption_monitor();
+        simulation.pericenter_monitor = self.pericenter_monitor();
+        simulation.species = self.species();
+        simulation.kinds = self.kinds();
+        simulation.collision_model = self.collisions.clone();
+        simulation.probes = self.probes();
+        if let Some(pipeline) = &self.pipeline {
+            simulation.pipeline = pipeline.clone();
+        }
+        simulation
+    }
+}
+
+/// The classic merging galaxies setup: a primary and a lighter secondary galaxy approaching
+/// each other along the x axis, placed and moving around their common center of mass.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Collision {
+    /// stars of the primary galaxy
+    pub stars: usize,
+    /// radius of the primary galaxy
+    pub radius: Real,
+    /// mass of the primary over the mass of the secondary, at least 1
+    pub mass_ratio: Real,
+    /// initial distance of the centers along the x axis
+    pub separation: Real,
+    /// offset of the centers along the y axis, 0 is a head on collision
+    pub impact_parameter: Real,
+    /// initial speed of the secondary relative to the primary
+    pub relative_velocity: Real,
+}
+
+impl Default for Collision {
+    fn default() -> Self {
+        Self {
+            stars: Simulation::N_STARS,
+            radius: 8_000.0,
+            mass_ratio: 3.0,
+            separation: 30_000.0,
+            impact_parameter: 6_000.0,
+            relative_velocity: 0.8,
+        }
+    }
+}
+
+impl Collision {
+    /// The primary in warm and the secondary in cool colors. Galaxy masses are dominated by their stars, so the
+    /// secondary has `mass_ratio` times fewer stars on a disc of the same density.
+    pub fn galaxies(&self) -> [GalaxySpec; 2] {
+        let ratio = self.mass_ratio.max(1.0);
+        // fractions of the total mass
+        let (primary, secondary) = (ratio / (1.0 + ratio), 1.0 / (1.0 + ratio));
+        let offset = Vector2::new(self.separation, self.impact_parameter);
+        let velocity = Vector2::new(-self.relative_velocity, 0.0);
+
+        [
+            GalaxySpec {
+                stars: self.stars,
+                radius: self.radius,
+                position: -offset * secondary,
+                velocity: -velocity * secondary,
+                colors: ColorPolicy::Uniform([1.0, 0.85, 0.6]),
+                ..GalaxySpec::default()
+            },
+            GalaxySpec {
+                stars: (self.stars as Real / ratio) as usize,
+                radius: self.radius / ratio.sqrt(),
+                position: offset * primary,
+                velocity: velocity * primary,
+                colors: ColorPolicy::Uniform([0.6, 0.75, 1.0]),
+                ..GalaxySpec::default()
+            },
+        ]
+    }
+}
@@ -0,0 +1,125 @@
+use crate::{Real, Simulation, Star};
+use alloc::string::String;
+use alloc::vec::Vec;
+use nalgebra::Vector2;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Something that happens at a given simulated time, see `Schedule`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(deny_unknown_fields)
+)]
+pub struct Event {
+    /// simulated time, the sum of the time steps of all updates
+    pub time: f64,
+    pub action: Action,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum Action {
+    /// adds a star, e.g. a perturber
+    AddStar {
+        position: Vector2<Real>,
+        velocity: Vector2<Real>,
+        mass: Real,
+    },
+    SetDt(Real),
+    SetGravity(Real),
+    SetTheta(Real),
+    /// doesn't change the simulation, frontends react to it, e.g. by taking a screenshot
+    Marker(String),
+}
+
+/// Events ordered by simulated time. `Simulation::update` shortens the update before an
+/// event so it lands exactly on the event time, which keeps events independent of the
+/// step count and robust to changes of `dt`.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Schedule {
+    /// sorted by descending time, so the next event is last
+    pending: Vec<Event>,
+}
+
+impl Schedule {
+    pub fn new(events: impl IntoIterator<Item = Event>) -> Self {
+        let mut schedule = Self::default();
+        events.into_iter().for_each(|event| schedule.add(event));
+        schedule
+    }
+
+    /// Adds an event, events at the same time fire in the order they were added.
+    pub fn add(&mut self, event: Event) {
+        let index = self
+            .pending
+            .partition_point(|pending| pending.time > event.time);
+        self.pending.insert(index, event);
+    }
+
+    /// Time of the next event.
+    pub fn next_time(&self) -> Option<f64> {
+        self.pending.last().map(|event| event.time)
+    }
+
+    pub fn pending(&self) -> impl Iterator<Item = &Event> {
+        self.pending.iter().rev()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Removes all events due at `time`, in the order they fire.
+    pub fn take_due(&mut self, time: f64) -> Vec<Event> {
+        let index = self.pending.partition_point(|event| event.time > time);
+        let mut due = self.pending.split_off(index);
+        due.reverse();
+        due
+    }
+}
+
+impl Action {
+    pub fn apply(&self, simulation: &mut Simulation) {
+        match self {
+            Action::AddStar {
+                position,
+                velocity,
+                mass,
+            } => simulation
+                .stars
+                .push(Star::new(*position, *velocity, *mass)),
+            Action::SetDt(dt) => simulation.config.dt = *dt,
+            Action::SetGravity(gravity) => simulation.config.gravity = *gravity,
+            Action::SetTheta(theta) => simulation.config.theta = *theta,
+            Action::Marker(_) => {}
+        }
+    }
+}
+
+impl Simulation {
+    /// Applies all events of `schedule` due at the current `time`, recording them in `fired`.
+    pub(crate) fn fire_due_events(&mut self) {
+        for event in self.schedule.take_due(self.time) {
+            event.action.apply(self);
+            self.fired.push(event);
+        }
+    }
+
+    /// The time step of the next update, shortened so it doesn't step past the next event.
+    /// Also returns the time of the event if it lands on one.
+    pub(crate) fn scheduled_dt(&self, dt: Real) -> (Real, Option<f64>) {
+        match self.schedule.next_time() {
+            Some(next) if dt > 0.0 && next - self.time <= dt as f64 => {
+                ((next - self.time) as Real, Some(next))
+            }
+            _ => (dt, None),
+        }
+    }
+}
@@ -0,0 +1,209 @@
+use crate::schedule::{Action, Event};
+use crate::{Real, Simulation, Star, StarId};
+use nalgebra::Vector2;
+use rhai::module_resolvers::DummyModuleResolver;
+use rhai::{Array, Dynamic, Engine, EvalAltResult, Scope, AST, FLOAT, INT};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// The simulation as seen by scripts. It is moved in for the duration of a run.
+type Handle = Rc<RefCell<Simulation>>;
+
+/// A small Rhai script that is run before every update, which allows time dependent
+/// experiment protocols, e.g. injecting a perturber at some step or slowly ramping up gravity.
+///
+/// The simulation is available as `sim`:
+/// ```text
+/// if sim.step == 500 {
+///     sim.add_star(-2000.0, 0.0, 0.5, 0.0, 1e4);
+/// }
+/// sim.gravity = 1e-4 * min(1.0, sim.step / 1000.0);
+/// ```
+///
+/// Events can also be scheduled in simulated time, see `Schedule`:
+/// ```text
+/// if sim.step == 0 {
+///     sim.at_add_star(2000.0, -2000.0, 0.0, 0.5, 0.0, 1e4);
+///     sim.at_set_dt(2500.0, 0.5);
+///     sim.at_marker(3000.0, "screenshot");
+/// }
+/// ```
+pub struct Script {
+    engine: Engine,
+    ast: AST,
+}
+
+impl Script {
+    /// Upper bound of operations per run, so broken scripts can't hang the simulation.
+    pub const MAX_OPERATIONS: u64 = 1_000_000;
+
+    pub fn new(source: &str) -> Result<Self, String> {
+        let engine = Self::engine();
+        let ast = engine.compile(source).map_err(|e| e.to_string())?;
+        Ok(Self { engine, ast })
+    }
+
+    /// Runs the script once on `simulation`.
+    pub fn run(&self, simulation: &mut Simulation) -> Result<(), String> {
+        let handle = Rc::new(RefCell::new(std::mem::replace(
+            simulation,
+            Simulation::new([]),
+        )));
+
+        let mut scope = Scope::new();
+        scope.push("sim", handle.clone());
+        // errors may hold the handle, e.g. after `throw sim;`
+        let result = self
+            .engine
+            .run_ast_with_scope(&mut scope, &self.ast)
+            .map_err(|e| e.to_string());
+        drop(scope);
+
+        // nothing else should outlive the scope, but a copy is taken rather than panicking
+        *simulation = match Rc::try_unwrap(handle) {
+            Ok(handle) => handle.into_inner(),
+            Err(handle) => handle.borrow().clone(),
+        };
+        result
+    }
+
+    /// An engine that can only access the simulation, not the file system.
+    fn engine() -> Engine {
+        let mut engine = Engine::new();
+        engine
+            .set_module_resolver(DummyModuleResolver::new())
+            .disable_symbol("eval")
+            .set_max_operations(Self::MAX_OPERATIONS)
+            .set_max_call_levels(32)
+            .set_max_expr_depths(64, 32)
+            .set_max_string_size(1 << 16)
+            .set_max_array_size(1 << 16)
+            .set_max_map_size(1 << 16);
+
+        engine
+            .register_type_with_name::<Handle>("Simulation")
+            .register_get("step", |sim: &mut Handle| sim.borrow().step as INT)
+            .register_get("time", |sim: &mut Handle| sim.borrow().time as FLOAT)
+            .register_get("star_count", |sim: &mut Handle| {
+                sim.borrow().stars.len() as INT
+            })
+            .register_get_set(
+                "theta",
+                |sim: &mut Handle| sim.borrow().config.theta as FLOAT,
+                |sim: &mut Handle, theta: FLOAT| sim.borrow_mut().config.theta = theta as Real,
+            )
+            .register_get_set(
+                "dt",
+                |sim: &mut Handle| sim.borrow().config.dt as FLOAT,
+                |sim: &mut Handle, dt: FLOAT| sim.borrow_mut().config.dt = dt as Real,
+            )
+            .register_get_set(
+                "gravity",
+                |sim: &mut Handle| sim.borrow().config.gravity as FLOAT,
+                |sim: &mut Handle, gravity: FLOAT| {
+                    sim.borrow_mut().config.gravity = gravity as Real
+                },
+            )
+            .register_fn(
+                "add_star",
+                |sim: &mut Handle, x: FLOAT, y: FLOAT, vx: FLOAT, vy: FLOAT, mass: FLOAT| {
+                    let mut sim = sim.borrow_mut();
+                    sim.stars.push(Star::new(
+                        Vector2::new(x as Real, y as Real),
+                        Vector2::new(vx as Real, vy as Real),
+                        mass as Real,
+                    ));
+                    (sim.stars.len() - 1) as INT
+                },
+            )
+            .register_fn("position", |sim: &mut Handle, id: INT| {
+                with_star(sim, id, |star| vector(star.pos()))
+            })
+            .register_fn("velocity", |sim: &mut Handle, id: INT| {
+                with_star(sim, id, |star| vector(&star.vel))
+            })
+            .register_fn("mass", |sim: &mut Handle, id: INT| {
+                with_star(sim, id, |star| star.mass() as FLOAT)
+            })
+            .register_fn(
+                "set_position",
+                |sim: &mut Handle, id: INT, x: FLOAT, y: FLOAT| {
+                    with_star(sim, id, |star| {
+                        star.mass_point.position = Vector2::new(x as Real, y as Real)
+                    })
+                },
+            )
+            .register_fn(
+                "set_velocity",
+                |sim: &mut Handle, id: INT, x: FLOAT, y: FLOAT| {
+                    with_star(sim, id, |star| {
+                        star.vel = Vector2::new(x as Real, y as Real)
+                    })
+                },
+            )
+            .register_fn("set_mass", |sim: &mut Handle, id: INT, mass: FLOAT| {
+                with_star(sim, id, |star| star.mass_point.mass = mass as Real)
+            })
+            .register_fn(
+                "at_add_star",
+                |sim: &mut Handle,
+                 time: FLOAT,
+                 x: FLOAT,
+                 y: FLOAT,
+                 vx: FLOAT,
+                 vy: FLOAT,
+                 mass: FLOAT| {
+                    let action = Action::AddStar {
+                        position: Vector2::new(x as Real, y as Real),
+                        velocity: Vector2::new(vx as Real, vy as Real),
+                        mass: mass as Real,
+                    };
+                    schedule(sim, time, action)
+                },
+            )
+            .register_fn("at_set_dt", |sim: &mut Handle, time: FLOAT, dt: FLOAT| {
+                schedule(sim, time, Action::SetDt(dt as Real))
+            })
+            .register_fn(
+                "at_set_gravity",
+                |sim: &mut Handle, time: FLOAT, gravity: FLOAT| {
+                    schedule(sim, time, Action::SetGravity(gravity as Real))
+                },
+            )
+            .register_fn(
+                "at_set_theta",
+                |sim: &mut Handle, time: FLOAT, theta: FLOAT| {
+                    schedule(sim, time, Action::SetTheta(theta as Real))
+                },
+            )
+            .register_fn("at_marker", |sim: &mut Handle, time: FLOAT, name: &str| {
+                schedule(sim, time, Action::Marker(name.to_string()))
+            });
+
+        engine
+    }
+}
+
+fn with_star<T>(
+    sim: &Handle,
+    id: INT,
+    f: impl FnOnce(&mut Star) -> T,
+) -> Result<T, Box<EvalAltResult>> {
+    let mut sim = sim.borrow_mut();
+    StarId::try_from(id)
+        .ok()
+        .and_then(|id| sim.stars.get_mut(id))
+        .map(f)
+        .ok_or_else(|| format!("no star with id {}", id).into())
+}
+
+fn schedule(sim: &Handle, time: FLOAT, action: Action) {
+    sim.borrow_mut().schedule.add(Event {
+        time: time as f64,
+        action,
+    });
+}
+
+fn vector(v: &Vector2<Real>) -> Array {
+    vec![Dynamic::from(v.x as FLOAT), Dynamic::from(v.y as FLOAT)]
+}
@@ -0,0 +1,291 @@
+use crate::collision::{CollisionModel, Species};
+use crate::evolution::Evolution;
+use crate::pipeline::Stage;
+use crate::probe::Probe;
+use crate::rebuild::IncrementalRebuild;
+use crate::reuse::TreeReuse;
+use crate::schedule::Schedule;
+#[cfg(feature = "rand")]
+use crate::thermal::ThermalNoise;
+use crate::tree::Aabb;
+use crate::{BodyKind, DilationZone, Real, Simulation, SimulationConfig, Star};
+use alloc::format;
+use alloc::vec::Vec;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+/// The full state of a simulation, so long runs can be checkpointed and continued.
+/// Force terms, custom stages of the pipeline and the integrator can't be serialized and
+/// have to be set up again.
+///
+/// Files start with `MAGIC` and the `VERSION` of their layout. Files of earlier versions are
+/// migrated when read, the fields they lack get their defaults.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub stars: Vec<Star>,
+    pub config: SimulationConfig,
+    pub step: u64,
+    pub time: f64,
+    /// events that haven't fired yet
+    pub schedule: Schedule,
+    pub dilation_zones: Vec<DilationZone>,
+    /// per star rgb colors of a renderer, indexed by `StarId`, empty if there are none
+    pub colors: Vec<[f32; 3]>,
+    /// see `Simulation::kinds`
+    pub kinds: Vec<BodyKind>,
+    /// see `Simulation::spins`
+    pub spins: Vec<Real>,
+    /// see `Simulation::ages`
+    pub ages: Vec<f64>,
+    /// see `Simulation::species`
+    pub species: Vec<Species>,
+    #[serde(with = "collisions")]
+    pub collision_model: CollisionModel,
+    pub probes: Vec<Probe>,
+    /// `Simulation::pipeline` without its custom stages
+    pub pipeline: Vec<Stage>,
+}
+
+impl Snapshot {
+    pub const MAGIC: [u8; 4] = *b"GSSN";
+    /// 1 added everything after `colors`, files of version 0 have neither magic nor version
+    pub const VERSION: u32 = 1;
+
+    pub fn of(simulation: &Simulation) -> Self {
+        Self {
+            stars: simulation.stars.clone(),
+            config: simulation.config,
+            step: simulation.step,
+            time: simulation.time,
+            schedule: simulation.schedule.clone(),
+            dilation_zones: simulation.dilation_zones.clone(),
+            colors: Vec::new(),
+            kinds: simulation.kinds.clone(),
+            spins: simulation.spins.clone(),
+            ages: simulation.ages.clone(),
+            species: simulation.species.clone(),
+            collision_model: simulation.collision_model.clone(),
+            probes: simulation.probes.clone(),
+            pipeline: simulation
+                .pipeline
+                .iter()
+                .filter(|stage| !matches!(stage, Stage::Custom(_)))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    pub fn to_simulation(&self) -> Simulation {
+        let mut simulation = Simulation::with_config(self.stars.iter().copied(), self.config);
+        simulation.step = self.step;
+        simulation.time = self.time;
+        simulation.schedule = self.schedule.clone();
+        simulation.dilation_zones = self.dilation_zones.clone();
+        simulation.kinds = self.kinds.clone();
+        simulation.spins = self.spins.clone();
+        simulation.ages = self.ages.clone();
+        simulation.species = self.species.clone();
+        simulation.collision_model = self.collision_model.clone();
+        simulation.probes = self.probes.clone();
+        simulation.pipeline = self.pipeline.clone();
+        simulation
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(&Self::MAGIC)?;
+        bincode::serialize_into(&mut writer, &(Self::VERSION, self)).map_err(invalid_data)?;
+        writer.flush()
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::read(BufReader::new(File::open(path)?))
+    }
+
+    /// Reads a snapshot written by `save` from `reader`, of any version up to `VERSION`.
+    pub fn read(mut reader: impl Read) -> io::Result<Self> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if magic != Self::MAGIC {
+            // the start of the stars of a version 0 file
+            let unversioned: Unversioned =
+                bincode::deserialize_from(magic.chain(reader)).map_err(invalid_data)?;
+            return Ok(unversioned.into());
+        }
+
+        let version: u32 = bincode::deserialize_from(&mut reader).map_err(invalid_data)?;
+        if version != Self::VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("snapshots of version {} can't be read", version),
+            ));
+        }
+        bincode::deserialize_from(reader).map_err(invalid_data)
+    }
+}
+
+impl Simulation {
+    /// Writes a `Snapshot` of this simulation to `path`.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        Snapshot::of(self).save(path)
+    }
+
+    /// Reads a simulation written by `save`.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Snapshot::load(path).map(|snapshot| snapshot.to_simulation())
+    }
+}
+
+/// A snapshot of version 0, from before snapshots had a version.
+#[derive(Deserialize)]
+struct Unversioned {
+    stars: Vec<Star>,
+    config: UnversionedConfig,
+    step: u64,
+    time: f64,
+    schedule: Schedule,
+    dilation_zones: Vec<DilationZone>,
+    colors: Vec<[f32; 3]>,
+}
+
+/// `SimulationConfig` of version 0, which had no `solver`.
+#[derive(Deserialize)]
+struct UnversionedConfig {
+    theta: Real,
+    gravity: Real,
+    softening: Real,
+    domain: Aabb,
+    dt: Real,
+    dominant_mass: Option<Real>,
+    merge_collisions: bool,
+    near_field: Option<Real>,
+    incremental_rebuild: Option<IncrementalRebuild>,
+    reuse_tree: Option<TreeReuse>,
+    sort_every: Option<u64>,
+    #[cfg(feature = "rand")]
+    thermal_noise: Option<ThermalNoise>,
+    evolution: Option<Evolution>,
+}
+
+impl From<Unversioned> for Snapshot {
+    fn from(snapshot: Unversioned) -> Self {
+        let config = snapshot.config;
+        Self {
+            stars: snapshot.stars,
+            config: SimulationConfig {
+                theta: config.theta,
+                gravity: config.gravity,
+                softening: config.softening,
+                domain: config.domain,
+                dt: config.dt,
+                dominant_mass: config.dominant_mass,
+                merge_collisions: config.merge_collisions,
+                near_field: config.near_field,
+                incremental_rebuild: config.incremental_rebuild,
+                reuse_tree: config.reuse_tree,
+                sort_every: config.sort_every,
+                #[cfg(feature = "rand")]
+                thermal_noise: config.thermal_noise,
+                evolution: config.evolution,
+                ..SimulationConfig::default()
+            },
+            step: snapshot.step,
+            time: snapshot.time,
+            schedule: snapshot.schedule,
+            dilation_zones: snapshot.dilation_zones,
+            colors: snapshot.colors,
+            kinds: Vec::new(),
+            spins: Vec::new(),
+            ages: Vec::new(),
+            species: Vec::new(),
+            collision_model: CollisionModel::default(),
+            probes: Vec::new(),
+            pipeline: Stage::default_pipeline(),
+        }
+    }
+}
+
+fn invalid_data(error: bincode::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+/// `CollisionModel` in a form bincode can read, which doesn't support the internally tagged
+/// `Outcome` of scenario files.
+mod collisions {
+    use crate::collision::{CollisionModel, Outcome, Rule, Species};
+    use crate::Real;
+    use alloc::vec::Vec;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    enum StoredOutcome {
+        Merge,
+        Bounce(Real),
+        Fragment(Real, u32, Real, Real),
+    }
+
+    type StoredModel = (StoredOutcome, Vec<([Species; 2], StoredOutcome)>);
+
+    impl From<Outcome> for StoredOutcome {
+        fn from(outcome: Outcome) -> Self {
+            match outcome {
+                Outcome::Merge => StoredOutcome::Merge,
+                Outcome::Bounce { restitution } => StoredOutcome::Bounce(restitution),
+                Outcome::Fragment {
+                    debris_fraction,
+                    fragments,
+                    ejection,
+                    min_mass,
+                } => StoredOutcome::Fragment(debris_fraction, fragments, ejection, min_mass),
+            }
+        }
+    }
+
+    impl From<StoredOutcome> for Outcome {
+        fn from(outcome: StoredOutcome) -> Self {
+            match outcome {
+                StoredOutcome::Merge => Outcome::Merge,
+                StoredOutcome::Bounce(restitution) => Outcome::Bounce { restitution },
+                StoredOutcome::Fragment(debris_fraction, fragments, ejection, min_mass) => {
+                    Outcome::Fragment {
+                        debris_fraction,
+                        fragments,
+                        ejection,
+                        min_mass,
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn serialize<S: Serializer>(
+        model: &CollisionModel,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let rules = model
+            .rules
+            .iter()
+            .map(|rule| (rule.species, rule.outcome.into()))
+            .collect();
+        let stored: StoredModel = (model.default.into(), rules);
+        stored.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<CollisionModel, D::Error> {
+        let (default, rules) = StoredModel::deserialize(deserializer)?;
+        Ok(CollisionModel {
+            default: default.into(),
+            rules: rules
+                .into_iter()
+                .map(|(species, outcome)| Rule {
+                    species,
+                    outcome: outcome.into(),
+                })
+                .collect(),
+        })
+    }
+}
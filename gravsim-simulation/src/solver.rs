@@ -0,0 +1,123 @@
+use crate::fmm::Fmm;
+use crate::tree::{direct_force_on, FlatTree};
+use crate::{MassData, Real, SimulationConfig};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::str::FromStr;
+use nalgebra::Vector2;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Computes the gravitational forces of a set of bodies on each other. `Simulation::update`
+/// walks its own tree unless a solver is selected with `SimulationConfig::solver` or
+/// `Simulation::set_force_solver`, so other methods, e.g. `BruteForce`, `fmm::Fmm` or a
+/// particle-mesh grid, plug in without touching the integrator.
+pub trait ForceSolver: Send + Sync {
+    /// Returns the force on each of `bodies` by all others, in order, with the opening
+    /// angle and gravitational constant of `config`. Bodies outside of `config.domain`
+    /// neither pull nor are pulled.
+    fn forces(&self, bodies: &[MassData], config: &SimulationConfig) -> Vec<Vector2<Real>>;
+}
+
+/// The built-in solvers, selected by `SimulationConfig::solver`, e.g. `solver = "brute_force"`
+/// in the config of a scenario or `--solver=brute_force` on the command line.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Solver {
+    /// the tree of the update, with `near_field`, `incremental_rebuild` and `reuse_tree`
+    #[default]
+    Tree,
+    BruteForce,
+    Fmm,
+}
+
+impl Solver {
+    /// The solver replacing the tree of the update, if any.
+    pub fn force_solver(&self) -> Option<&'static dyn ForceSolver> {
+        match self {
+            Solver::Tree => None,
+            Solver::BruteForce => Some(&BruteForce),
+            Solver::Fmm => Some(&Fmm),
+        }
+    }
+}
+
+impl FromStr for Solver {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Ok(match name {
+            "tree" => Solver::Tree,
+            "brute_force" => Solver::BruteForce,
+            "fmm" => Solver::Fmm,
+            _ => return Err(format!("unknown solver: {}", name)),
+        })
+    }
+}
+
+/// The Barnes-Hut tree walk of `Simulation::update` as a solver: every body walks a
+/// `FlatTree` of all bodies on its own, O(n log n) in total.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct BarnesHut;
+
+impl ForceSolver for BarnesHut {
+    fn forces(&self, bodies: &[MassData], config: &SimulationConfig) -> Vec<Vector2<Real>> {
+        let tree = tree_of(bodies, config);
+
+        let mut forces = vec![Vector2::zeros(); bodies.len()];
+        let fill = |(out, body): (&mut Vector2<Real>, &MassData)| {
+            if config.domain.contains(&body.position) {
+                *out = tree.force_on(body, config);
+            }
+        };
+        #[cfg(feature = "rayon")]
+        forces.par_iter_mut().zip(bodies).for_each(fill);
+        #[cfg(not(feature = "rayon"))]
+        forces.iter_mut().zip(bodies).for_each(fill);
+        forces
+    }
+}
+
+/// Every body pulled by every other one, O(n²), exact up to the softening. Only usable for a
+/// few thousand bodies, but a baseline for the other solvers and for small simulations that
+/// must not depend on `theta`.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct BruteForce;
+
+impl ForceSolver for BruteForce {
+    fn forces(&self, bodies: &[MassData], config: &SimulationConfig) -> Vec<Vector2<Real>> {
+        let sources: Vec<_> = bodies
+            .iter()
+            .filter(|body| config.domain.contains(&body.position))
+            .copied()
+            .collect();
+
+        let mut forces = vec![Vector2::zeros(); bodies.len()];
+        let fill = |(out, body): (&mut Vector2<Real>, &MassData)| {
+            if config.domain.contains(&body.position) {
+                *out = direct_force_on(body, &sources, config);
+            }
+        };
+        #[cfg(feature = "rayon")]
+        forces.par_iter_mut().zip(bodies).for_each(fill);
+        #[cfg(not(feature = "rayon"))]
+        forces.iter_mut().zip(bodies).for_each(fill);
+        forces
+    }
+}
+
+/// A summarized tree of the bodies in `config.domain`.
+pub(crate) fn tree_of(bodies: &[MassData], config: &SimulationConfig) -> FlatTree {
+    let mut tree = FlatTree::bounding(&config.domain, 2 * bodies.len());
+    bodies
+        .iter()
+        .filter(|body| config.domain.contains(&body.position))
+        .for_each(|body| tree.insert(body));
+    tree.summarize();
+    tree
+}
@@ -0,0 +1,64 @@
+use crate::{Real, Star};
+use alloc::vec::Vec;
+use nalgebra::Vector2;
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+use rand::{Rng, SeedableRng};
+use rand_xorshift::XorShiftRng;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Small random velocity kicks given to every star in each update, like the encounters of
+/// an unresolved background population. Useful to study how fast clusters evaporate, or to
+/// keep demos from settling down, see `SimulationConfig::thermal_noise`. The kicks of an
+/// update only depend on the seed, the step and the star ids, so seeded runs repeat exactly
+/// and continue the same way from a snapshot.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ThermalNoise {
+    /// kinetic energy a star gains per unit of time on average, whatever its mass. The
+    /// components of a kick are normal distributed with variance `temperature * dt / mass`.
+    pub temperature: Real,
+    pub seed: u64,
+}
+
+impl ThermalNoise {
+    pub fn new(temperature: Real, seed: u64) -> Self {
+        Self { temperature, seed }
+    }
+
+    /// With a random seed, so runs don't repeat.
+    #[cfg(feature = "std")]
+    pub fn unseeded(temperature: Real) -> Self {
+        Self::new(temperature, rand::random())
+    }
+
+    /// The kick of each of `stars` in update `step`, `dt` are the time steps of the stars.
+    /// Stars without mass or time step aren't kicked.
+    pub fn kicks(&self, stars: &[Star], dt: &[Real], step: u64) -> Vec<Vector2<Real>> {
+        // consecutive steps get unrelated streams, `seed_from_u64` scrambles the seed
+        let mut rng =
+            XorShiftRng::seed_from_u64(self.seed ^ step.wrapping_mul(0x9e37_79b9_7f4a_7c15));
+        stars
+            .iter()
+            .zip(dt)
+            .map(|(star, &dt)| {
+                // drawn for every star, so the kick of a star only depends on its id
+                let normal = standard_normal(&mut rng);
+                let variance = self.temperature * dt / star.mass();
+                match variance > 0.0 && variance.is_finite() {
+                    true => normal * variance.sqrt(),
+                    false => Vector2::zeros(),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Two independent standard normal samples, with the Box-Muller transform.
+fn standard_normal(rng: &mut XorShiftRng) -> Vector2<Real> {
+    // in (0, 1], so the logarithm is finite
+    let uniform = 1.0 - rng.gen::<Real>();
+    let angle = rng.gen::<Real>() * crate::consts::TAU;
+    Vector2::new(angle.cos(), angle.sin()) * (-2.0 * uniform.ln()).sqrt()
+}
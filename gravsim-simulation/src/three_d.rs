@@ -0,0 +1,133 @@
+use crate::diagnostics::Diagnostics;
+use crate::octree::Octree;
+use crate::radius::{ConstantDensity, MassRadiusRelation};
+use crate::{Real, Simulation, Star};
+use alloc::vec::Vec;
+use bytemuck::{Pod, Zeroable};
+use nalgebra::Vector3;
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// 3d counterpart of `Star`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct Star3 {
+    pub mass_point: MassData3,
+    pub vel: Vector3<Real>,
+}
+
+impl Star3 {
+    pub fn new(pos: Vector3<Real>, vel: Vector3<Real>, mass: Real) -> Self {
+        Self {
+            mass_point: MassData3 {
+                position: pos,
+                mass,
+            },
+            vel,
+        }
+    }
+
+    /// Lifts a 2d star into the xy plane at height `z`.
+    pub fn from_2d(star: &Star, z: Real) -> Self {
+        Self::new(star.pos().push(z), star.vel.push(0.0), star.mass())
+    }
+
+    /// Radius with the default relation, like `Star::radius`.
+    pub fn radius(&self) -> Real {
+        ConstantDensity::DEFAULT.radius(self.mass())
+    }
+
+    pub fn mass(&self) -> Real {
+        self.mass_point.mass
+    }
+
+    pub fn pos(&self) -> &Vector3<Real> {
+        &self.mass_point.position
+    }
+}
+
+/// 3d counterpart of `MassData`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MassData3 {
+    pub position: Vector3<Real>,
+    pub mass: Real,
+}
+
+/// 3d counterpart of `Simulation`, using an octree. The simulated region is a cube
+/// with the same edge length as the square of the 2d simulation.
+#[derive(Clone)]
+pub struct Simulation3 {
+    pub stars: Vec<Star3>,
+    pub theta: Real,
+    pub gravity: Real,
+    /// see `SimulationConfig::softening`
+    pub softening: Real,
+    pub step: u64,
+}
+
+impl Simulation3 {
+    pub const SCALE: Real = Simulation::SCALE;
+    pub const THETA: Real = Simulation::THETA;
+    pub const GRAVITY: Real = Simulation::GRAVITY;
+    pub const SOFTENING: Real = Simulation::SOFTENING;
+
+    pub fn new<I>(stars: I) -> Self
+    where
+        I: IntoIterator<Item = Star3>,
+    {
+        Self {
+            stars: stars.into_iter().collect(),
+            theta: Self::THETA,
+            gravity: Self::GRAVITY,
+            softening: Self::SOFTENING,
+            step: 0,
+        }
+    }
+
+    pub fn update(&mut self) {
+        let mut tree = Octree::new_root(-Vector3::repeat(Self::SCALE / 2.0), Self::SCALE);
+        for star in &self.stars {
+            if tree.contains(star.pos()) {
+                tree.insert(&star.mass_point);
+            }
+        }
+        tree.summarize();
+
+        // the tree calculates forces with the default gravitational constant
+        let gravity = self.gravity / Self::GRAVITY;
+        let (theta, softening) = (self.theta, self.softening);
+        let step = |star: &mut Star3| {
+            let force = tree.force_on(&star.mass_point, theta, softening);
+            star.vel += force * gravity / star.mass();
+            star.mass_point.position += star.vel;
+        };
+
+        #[cfg(feature = "rayon")]
+        self.stars
+            .par_iter_mut()
+            .filter(|star| tree.contains(star.pos()))
+            .for_each(step);
+        #[cfg(not(feature = "rayon"))]
+        self.stars
+            .iter_mut()
+            .filter(|star| tree.contains(star.pos()))
+            .for_each(step);
+
+        self.stars
+            .iter_mut()
+            .filter(|star| !tree.contains(star.pos()))
+            .for_each(|star| star.mass_point.position = Vector3::from_element(Real::NAN));
+
+        self.step += 1;
+    }
+
+    pub fn diagnostics(&self) -> Diagnostics {
+        Diagnostics::new_3d(&self.stars, self.gravity, self.softening)
+    }
+}
@@ -0,0 +1,63 @@
+use crate::rebuild::TreeRebuild;
+use crate::{Real, Simulation};
+use nalgebra::{Matrix2, Vector2};
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+
+impl Simulation {
+    /// Moves the whole system by `offset`: stars, probes with their trajectories and
+    /// dilation zones. The domain and the potentials of `add_potential` stay where they are,
+    /// so stars moved out of the domain are removed by the next update.
+    pub fn translate(&mut self, offset: Vector2<Real>) {
+        self.transform_positions(|pos| pos + offset);
+        // the pending tree was built from the old positions
+        self.rebuild = TreeRebuild::default();
+    }
+
+    /// Rotates the whole system counterclockwise by `theta` radians around the origin,
+    /// positions and velocities alike. Like `translate`, the domain and potentials stay,
+    /// and spins don't change.
+    pub fn rotate(&mut self, theta: Real) {
+        let (sin, cos) = theta.sin_cos();
+        let rotation = Matrix2::new(cos, -sin, sin, cos);
+        self.transform_positions(|pos| rotation * pos);
+        for star in &mut self.stars {
+            star.vel = rotation * star.vel;
+        }
+        for probe in &mut self.probes {
+            probe.vel = rotation * probe.vel;
+        }
+        if let Some(estimate) = &mut self.error_estimate {
+            estimate.rotate(&rotation);
+        }
+        self.rebuild = TreeRebuild::default();
+    }
+
+    /// Adds `velocity` to every star and probe, e.g. the negated velocity of the center of
+    /// mass to stop a system from drifting. Gravity doesn't change, so neither do the orbits
+    /// relative to each other.
+    pub fn boost(&mut self, velocity: Vector2<Real>) {
+        for star in &mut self.stars {
+            star.vel += velocity;
+        }
+        for probe in &mut self.probes {
+            probe.vel += velocity;
+        }
+    }
+
+    fn transform_positions(&mut self, transform: impl Fn(Vector2<Real>) -> Vector2<Real>) {
+        for star in &mut self.stars {
+            star.mass_point.position = transform(star.mass_point.position);
+        }
+        for probe in &mut self.probes {
+            probe.pos = transform(probe.pos);
+            probe
+                .trajectory
+                .iter_mut()
+                .for_each(|pos| *pos = transform(*pos));
+        }
+        for zone in &mut self.dilation_zones {
+            zone.center = transform(zone.center);
+        }
+    }
+}
@@ -1,15 +1,59 @@
-use crate::MassData;
-use crate::Simulation;
+use crate::kernel;
+use crate::{MassData, Real, SimulationConfig};
+use alloc::boxed::Box;
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use bytemuck::Zeroable;
 use nalgebra::Vector2;
 use num_enum::TryFromPrimitive;
-use std::collections::VecDeque;
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+/// An axis aligned rectangle, e.g. the domain of a simulation.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Aabb {
+    pub min: Vector2<Real>,
+    pub max: Vector2<Real>,
+}
+
+impl Aabb {
+    /// A rectangle of the given width and height centered on the origin.
+    pub fn centered(size: Vector2<Real>) -> Self {
+        Self {
+            min: -size / 2.0,
+            max: size / 2.0,
+        }
+    }
+
+    pub fn size(&self) -> Vector2<Real> {
+        self.max - self.min
+    }
+
+    /// Lower inclusive, upper exclusive like a tree cell.
+    pub fn contains(&self, pos: &Vector2<Real>) -> bool {
+        (0..2).all(|i| pos[i] >= self.min[i] && pos[i] < self.max[i])
+    }
+
+    /// Position and scale of the smallest square cell sharing the center of this rectangle
+    /// that contains it, trees are built on it.
+    pub fn square(&self) -> (Vector2<Real>, Real) {
+        let scale = self.size().max();
+        (
+            (self.min + self.max) / 2.0 - Vector2::repeat(scale / 2.0),
+            scale,
+        )
+    }
+}
 
 /// represents one quadrant of a node.
 /// The corresponding u8 value is the index of the quadrant in the child list.
 /// The bits of this value represent its coordinates with the constants
-/// `Octant::X` and `Octant::Y` as bitmasks.
+/// `Quadrant::X` and `Quadrant::Y` as bitmasks.
 #[repr(u8)]
-#[derive(Copy, Clone, Eq, PartialEq, TryFromPrimitive)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, TryFromPrimitive)]
 pub enum Quadrant {
     NorthWest = 0b00,
     NorthEast = 0b01,
@@ -23,28 +67,87 @@ impl Quadrant {
 
     /// Returns the offset a child in this quadrant has to its parent node.
     /// This value has to be scaled by half of the scale of its parent node.
-    pub fn offset(&self) -> Vector2<f32> {
+    pub fn offset(&self) -> Vector2<Real> {
         let bits = *self as u8;
         Vector2::new(
-            (bits & Self::X > 0) as u8 as f32,
-            (bits & Self::Y > 0) as u8 as f32,
+            (bits & Self::X > 0) as u8 as Real,
+            (bits & Self::Y > 0) as u8 as Real,
         )
     }
 
     /// Returns the quadrant a point of the given offset to its
     /// parent node (which has the given scale) will fall into.
-    pub fn from_offset(offset: &Vector2<f32>, scale: f32) -> Self {
-        let bits_x = (offset.x > 0.5 * scale) as u8 * Self::X;
-        let bits_y = (offset.y > 0.5 * scale) as u8 * Self::Y;
+    /// Like `Node::contains`, cells are half open, so a point exactly on the
+    /// center line belongs to the upper quadrant.
+    pub fn from_offset(offset: &Vector2<Real>, scale: Real) -> Self {
+        let bits_x = (offset.x >= 0.5 * scale) as u8 * Self::X;
+        let bits_y = (offset.y >= 0.5 * scale) as u8 * Self::Y;
 
         (bits_x | bits_y).try_into().unwrap()
     }
 }
 
+/// Counts how many nodes were accepted (approximated by their center of mass)
+/// or opened during force calculation, indexed by tree depth.
+#[derive(Clone, Debug, Default)]
+pub struct TraversalStats {
+    pub accepted: Vec<u64>,
+    pub opened: Vec<u64>,
+}
+
+impl TraversalStats {
+    pub fn record(&mut self, depth: usize, accepted: bool) {
+        if self.accepted.len() <= depth {
+            self.accepted.resize(depth + 1, 0);
+            self.opened.resize(depth + 1, 0);
+        }
+
+        if accepted {
+            self.accepted[depth] += 1;
+        } else {
+            self.opened[depth] += 1;
+        }
+    }
+
+    pub fn merge(mut self, other: Self) -> Self {
+        for (depth, (&accepted, &opened)) in other.accepted.iter().zip(&other.opened).enumerate() {
+            if self.accepted.len() <= depth {
+                self.accepted.resize(depth + 1, 0);
+                self.opened.resize(depth + 1, 0);
+            }
+            self.accepted[depth] += accepted;
+            self.opened[depth] += opened;
+        }
+        self
+    }
+
+    pub fn total_accepted(&self) -> u64 {
+        self.accepted.iter().sum()
+    }
+
+    pub fn total_opened(&self) -> u64 {
+        self.opened.iter().sum()
+    }
+
+    /// Fraction of visited nodes that were accepted, or `None` if nothing was visited.
+    pub fn acceptance_ratio(&self) -> Option<Real> {
+        Self::ratio(self.total_accepted(), self.total_opened())
+    }
+
+    pub fn acceptance_ratio_at(&self, depth: usize) -> Option<Real> {
+        Self::ratio(*self.accepted.get(depth)?, *self.opened.get(depth)?)
+    }
+
+    fn ratio(accepted: u64, opened: u64) -> Option<Real> {
+        let visited = accepted + opened;
+        (visited > 0).then(|| accepted as Real / visited as Real)
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Node {
-    pos: Vector2<f32>,
-    scale: f32,
+    pos: Vector2<Real>,
+    scale: Real,
 
     center_of_mass: MassData,
     children: [Option<Box<Node>>; 4],
@@ -52,7 +155,13 @@ pub struct Node {
 }
 
 impl Node {
-    pub fn new_root(pos: Vector2<f32>, scale: f32) -> Self {
+    /// A root covering `domain`, padded to a square.
+    pub fn bounding(domain: &Aabb) -> Self {
+        let (pos, scale) = domain.square();
+        Self::new_root(pos, scale)
+    }
+
+    pub fn new_root(pos: Vector2<Real>, scale: Real) -> Self {
         Self {
             pos,
             scale,
@@ -75,6 +184,8 @@ impl Node {
         })
     }
 
+    /// Places `obj` in the tree. Inner nodes don't have a valid center of mass
+    /// until `summarize` is called once all objects are inserted.
     pub fn insert(&mut self, obj: &MassData) {
         if self.center_of_mass.mass == 0.0 {
             // if this is the root node, don't subdivide
@@ -84,6 +195,12 @@ impl Node {
             return;
         }
 
+        if self.is_leaf() && !self.can_subdivide() {
+            // coincident (or nearly so) bodies would subdivide forever, merge them instead.
+            self.center_of_mass = merged(&self.pos, self.scale, &self.center_of_mass, obj);
+            return;
+        }
+
         if self.is_leaf() {
             // if this is a leaf, the center of mass is the star that was previously inserted.
             // this star has to be reinserted into the child nodes.
@@ -93,12 +210,6 @@ impl Node {
             self.insert_into(quadrant, &self.center_of_mass.clone())
         }
 
-        // update center of mass
-        self.center_of_mass.position = (self.center_of_mass.position * self.center_of_mass.mass
-            + obj.position * obj.mass)
-            / (self.center_of_mass.mass + obj.mass);
-        self.center_of_mass.mass += obj.mass;
-
         let offset = obj.position - self.pos;
         let quadrant = Quadrant::from_offset(&offset, self.scale);
 
@@ -110,48 +221,540 @@ impl Node {
         if let Some(child) = &mut self.children[quadrant as usize] {
             // if there already exists a child in this quadrant,
             // insert into that node to subdivide eventually.
-            child.insert(obj);
+            let obj = child.repaired(obj);
+            child.insert(&obj);
         } else {
             // if there isn't already a child node of that quadrant, create it / subdivide.
-            self.children[quadrant as usize] = Some(Node::new_child(self, quadrant, *obj));
+            let mut child = Node::new_child(self, quadrant, *obj);
+            child.center_of_mass = child.repaired(obj);
+            self.children[quadrant as usize] = Some(child);
+        }
+    }
+
+    fn can_subdivide(&self) -> bool {
+        can_subdivide(&self.pos, self.scale)
+    }
+
+    /// Computes masses and centers of mass of all inner nodes in a single bottom up pass.
+    /// Sums are accumulated in f64, with `rayon` the first few levels are processed in parallel.
+    /// Children are always summed in the same order, so results don't depend on threads.
+    pub fn summarize(&mut self) {
+        self.summarize_at(0);
+    }
+
+    /// Returns the mass and the mass weighted position sum of this subtree.
+    #[cfg_attr(not(feature = "rayon"), allow(clippy::only_used_in_recursion))]
+    fn summarize_at(&mut self, depth: usize) -> (f64, Vector2<f64>) {
+        if self.is_leaf() {
+            let mass = self.center_of_mass.mass as f64;
+            return (mass, self.center_of_mass.position.cast() * mass);
         }
+
+        let sum = |a: (f64, Vector2<f64>), b: (f64, Vector2<f64>)| (a.0 + b.0, a.1 + b.1);
+        #[cfg(feature = "rayon")]
+        {
+            const PARALLEL_DEPTH: usize = 4;
+            if depth < PARALLEL_DEPTH {
+                let summaries: Vec<_> = self
+                    .children
+                    .as_mut_slice()
+                    .par_iter_mut()
+                    .map(|child| child.as_mut().map(|child| child.summarize_at(depth + 1)))
+                    .collect();
+                let (mass, weighted_position) = summaries
+                    .into_iter()
+                    .flatten()
+                    .fold((0.0, Vector2::zeros()), sum);
+                return self.set_summary(mass, weighted_position);
+            }
+        }
+
+        let (mass, weighted_position) = self
+            .children
+            .iter_mut()
+            .flatten()
+            .map(|child| child.summarize_at(depth + 1))
+            .fold((0.0, Vector2::zeros()), sum);
+        self.set_summary(mass, weighted_position)
     }
 
-    pub fn force_on(&self, obj: &MassData) -> Vector2<f32> {
-        const EPSILON: f32 = 0.05;
+    fn set_summary(&mut self, mass: f64, weighted_position: Vector2<f64>) -> (f64, Vector2<f64>) {
+        self.center_of_mass = MassData {
+            position: (weighted_position / mass).cast(),
+            mass: mass as Real,
+        };
+        (mass, weighted_position)
+    }
+
+    fn repaired(&self, obj: &MassData) -> MassData {
+        repaired(&self.pos, self.scale, obj)
+    }
+
+    /// Clamps `pos` into the half open cell `[pos, pos + scale)` of this node.
+    pub fn clamp(&self, pos: &Vector2<Real>) -> Vector2<Real> {
+        clamp(&self.pos, self.scale, pos)
+    }
+
+    /// Calculates the force on `obj`, approximating nodes whose size to distance ratio
+    /// is below `config.theta`.
+    pub fn force_on(&self, obj: &MassData, config: &SimulationConfig) -> Vector2<Real> {
+        self.traverse(obj, config, None)
+    }
+
+    /// Same as `force_on`, but records which nodes were accepted or opened into `stats`.
+    pub fn force_on_with_stats(
+        &self,
+        obj: &MassData,
+        config: &SimulationConfig,
+        stats: &mut TraversalStats,
+    ) -> Vector2<Real> {
+        self.traverse(obj, config, Some(stats))
+    }
 
+    fn traverse(
+        &self,
+        obj: &MassData,
+        config: &SimulationConfig,
+        mut stats: Option<&mut TraversalStats>,
+    ) -> Vector2<Real> {
         // factor out G and obj.mass
         let mut force_part = Vector2::zeros();
 
         // bfs
-        let mut queue = VecDeque::from([self]);
-        while let Some(node) = queue.pop_front() {
+        let mut queue = VecDeque::from([(self, 0)]);
+        while let Some((node, depth)) = queue.pop_front() {
             let diff = node.center_of_mass.position - obj.position;
             let dist_sq = diff.norm_squared();
             if !dist_sq.is_normal() {
                 continue;
             }
 
-            let dist = (EPSILON + dist_sq).sqrt();
+            let dist = softened(dist_sq, config.softening);
             let q = node.scale / dist;
-            if q < Simulation::THETA || node.is_leaf() {
+            let accepted = q < config.theta || node.is_leaf();
+            if let Some(stats) = stats.as_deref_mut() {
+                stats.record(depth, accepted);
+            }
+
+            if accepted {
                 force_part += diff / dist.powi(3) * node.center_of_mass.mass;
             } else {
-                queue.extend(node.children.iter().filter_map(|child| child.as_deref()));
+                queue.extend(
+                    node.children
+                        .iter()
+                        .filter_map(|child| child.as_deref())
+                        .map(|child| (child, depth + 1)),
+                );
             }
         }
 
-        Simulation::GRAVITY * obj.mass * force_part
+        config.gravity * obj.mass * force_part
     }
 
     pub fn is_leaf(&self) -> bool {
         self.leaf
     }
 
-    pub fn contains(&self, pos: &Vector2<f32>) -> bool {
-        self.pos
-            .iter()
-            .zip(pos.iter())
-            .all(|(&a, &b)| b >= a && b < a + self.scale)
+    pub fn pos(&self) -> &Vector2<Real> {
+        &self.pos
+    }
+
+    pub fn scale(&self) -> Real {
+        self.scale
+    }
+
+    pub fn center_of_mass(&self) -> &MassData {
+        &self.center_of_mass
+    }
+
+    pub fn children(&self) -> impl Iterator<Item = &Node> {
+        self.children.iter().filter_map(|child| child.as_deref())
+    }
+
+    pub fn contains(&self, pos: &Vector2<Real>) -> bool {
+        contains(&self.pos, self.scale, pos)
+    }
+}
+
+/// Index of a node in `FlatTree::nodes`.
+pub type NodeIndex = u32;
+
+/// A node of a `FlatTree`, referring to its children by index.
+#[derive(Clone, Debug)]
+pub struct FlatNode {
+    pos: Vector2<Real>,
+    scale: Real,
+
+    center_of_mass: MassData,
+    /// indices of the children in each quadrant, 0 (the root) if there is none
+    children: [NodeIndex; 4],
+}
+
+impl FlatNode {
+    fn new(pos: Vector2<Real>, scale: Real, center_of_mass: MassData) -> Self {
+        Self {
+            pos,
+            scale,
+            center_of_mass,
+            children: [0; 4],
+        }
+    }
+
+    pub fn is_leaf(&self) -> bool {
+        self.children == [0; 4]
+    }
+
+    pub fn pos(&self) -> &Vector2<Real> {
+        &self.pos
+    }
+
+    pub fn scale(&self) -> Real {
+        self.scale
+    }
+
+    pub fn center_of_mass(&self) -> &MassData {
+        &self.center_of_mass
+    }
+
+    pub fn children(&self) -> impl Iterator<Item = NodeIndex> + '_ {
+        self.children.iter().copied().filter(|&child| child != 0)
+    }
+
+    pub fn contains(&self, pos: &Vector2<Real>) -> bool {
+        contains(&self.pos, self.scale, pos)
+    }
+}
+
+/// The same quad tree as `Node`, but with all nodes stored in a single `Vec`, which avoids
+/// an allocation per node and keeps the tree compact in memory.
+/// Children are always created after their parent, so their indices are larger.
+#[derive(Clone, Debug)]
+pub struct FlatTree {
+    nodes: Vec<FlatNode>,
+}
+
+impl FlatTree {
+    pub fn new_root(pos: Vector2<Real>, scale: Real) -> Self {
+        Self::with_capacity(pos, scale, 1)
+    }
+
+    /// See `Node::bounding`.
+    pub fn bounding(domain: &Aabb, capacity: usize) -> Self {
+        let (pos, scale) = domain.square();
+        Self::with_capacity(pos, scale, capacity)
+    }
+
+    /// Reserves space for `capacity` nodes, about twice the number of bodies is typical.
+    pub fn with_capacity(pos: Vector2<Real>, scale: Real, capacity: usize) -> Self {
+        let mut nodes = Vec::with_capacity(capacity);
+        nodes.push(FlatNode::new(pos, scale, MassData::zeroed()));
+        Self { nodes }
+    }
+
+    /// Places `obj` in the tree, see `Node::insert`.
+    pub fn insert(&mut self, obj: &MassData) {
+        let mut index = 0;
+        let mut obj = *obj;
+        loop {
+            let node = &mut self.nodes[index];
+            // inner nodes are only empty once all bodies were removed from below them
+            if node.is_leaf() && node.center_of_mass.mass == 0.0 {
+                node.center_of_mass = obj;
+                return;
+            } else if obj.mass == 0.0 {
+                return;
+            }
+
+            if node.is_leaf() {
+                if !can_subdivide(&node.pos, node.scale) {
+                    node.center_of_mass = merged(&node.pos, node.scale, &node.center_of_mass, &obj);
+                    return;
+                }
+
+                // move the body previously stored in this leaf to a child
+                let previous = node.center_of_mass;
+                let quadrant = Quadrant::from_offset(&(previous.position - node.pos), node.scale);
+                self.add_child(index, quadrant, &previous);
+            }
+
+            let node = &self.nodes[index];
+            let quadrant = Quadrant::from_offset(&(obj.position - node.pos), node.scale);
+            match node.children[quadrant as usize] {
+                0 => {
+                    self.add_child(index, quadrant, &obj);
+                    return;
+                }
+                child => {
+                    let child = &self.nodes[child as usize];
+                    obj = repaired(&child.pos, child.scale, &obj);
+                    index = node.children[quadrant as usize] as usize;
+                }
+            }
+        }
+    }
+
+    /// Takes `obj` out of the leaf it was inserted into, which is left empty unless bodies
+    /// were merged into it. Like after `insert`, the centers of mass of the nodes above are
+    /// stale until the next `summarize`. Returns whether there was a leaf to remove it from.
+    pub fn remove(&mut self, obj: &MassData) -> bool {
+        let Some(leaf) = self.leaf_containing(&obj.position) else {
+            return false;
+        };
+
+        let node = &mut self.nodes[leaf as usize];
+        let total = node.center_of_mass;
+        let mass = total.mass - obj.mass;
+        if mass <= total.mass * 8.0 * Real::EPSILON {
+            node.center_of_mass.mass = 0.0;
+        } else {
+            let position = (total.position * total.mass - obj.position * obj.mass) / mass;
+            node.center_of_mass = MassData {
+                position: clamp(&node.pos, node.scale, &position),
+                mass,
+            };
+        }
+        true
+    }
+
+    fn add_child(&mut self, parent: usize, quadrant: Quadrant, obj: &MassData) {
+        let parent_node = &self.nodes[parent];
+        let pos = parent_node.pos + quadrant.offset() * parent_node.scale * 0.5;
+        let scale = parent_node.scale * 0.5;
+
+        let child = self.nodes.len() as NodeIndex;
+        self.nodes
+            .push(FlatNode::new(pos, scale, repaired(&pos, scale, obj)));
+        self.nodes[parent].children[quadrant as usize] = child;
+    }
+
+    /// Computes masses and centers of mass of all inner nodes in a single pass over the nodes,
+    /// from the back so children are always summarized before their parents.
+    pub fn summarize(&mut self) {
+        let mut sums: Vec<(f64, Vector2<f64>)> = Vec::with_capacity(self.nodes.len());
+        sums.resize(self.nodes.len(), (0.0, Vector2::zeros()));
+
+        for index in (0..self.nodes.len()).rev() {
+            let node = &mut self.nodes[index];
+            if node.is_leaf() {
+                let mass = node.center_of_mass.mass as f64;
+                sums[index] = (mass, node.center_of_mass.position.cast() * mass);
+                continue;
+            }
+
+            let (mass, weighted_position) = node
+                .children()
+                .map(|child| sums[child as usize])
+                .fold((0.0, Vector2::zeros()), |a, b| (a.0 + b.0, a.1 + b.1));
+            // everything below was removed, keep the position finite
+            if mass == 0.0 {
+                node.center_of_mass.mass = 0.0;
+                sums[index] = (0.0, Vector2::zeros());
+                continue;
+            }
+            node.center_of_mass = MassData {
+                position: (weighted_position / mass).cast(),
+                mass: mass as Real,
+            };
+            sums[index] = (mass, weighted_position);
+        }
+    }
+
+    /// Calculates the force on `obj`, approximating nodes whose size to distance ratio
+    /// is below `config.theta`.
+    pub fn force_on(&self, obj: &MassData, config: &SimulationConfig) -> Vector2<Real> {
+        self.traverse(obj, config, None)
+    }
+
+    /// Same as `force_on`, but records which nodes were accepted or opened into `stats`.
+    pub fn force_on_with_stats(
+        &self,
+        obj: &MassData,
+        config: &SimulationConfig,
+        stats: &mut TraversalStats,
+    ) -> Vector2<Real> {
+        self.traverse(obj, config, Some(stats))
+    }
+
+    fn traverse(
+        &self,
+        obj: &MassData,
+        config: &SimulationConfig,
+        mut stats: Option<&mut TraversalStats>,
+    ) -> Vector2<Real> {
+        let mut force_part = Vector2::zeros();
+
+        let mut queue = VecDeque::from([(0, 0)]);
+        while let Some((index, depth)) = queue.pop_front() {
+            let node = &self.nodes[index as usize];
+            let diff = node.center_of_mass.position - obj.position;
+            let dist_sq = diff.norm_squared();
+            if !dist_sq.is_normal() {
+                continue;
+            }
+
+            // stars closer than the near field radius are left to `near_field::accelerations`,
+            // so nodes reaching into it are always opened
+            let near = config.near_field.is_some_and(|radius| {
+                let closest = match node.is_leaf() {
+                    true => node.center_of_mass.position,
+                    false => clamp(&node.pos, node.scale, &obj.position),
+                };
+                (closest - obj.position).norm_squared() < radius * radius
+            });
+            if near && node.is_leaf() {
+                continue;
+            }
+
+            let dist = softened(dist_sq, config.softening);
+            let accepted = !near && node.scale / dist < config.theta || node.is_leaf();
+            if let Some(stats) = stats.as_deref_mut() {
+                stats.record(depth, accepted);
+            }
+
+            if accepted {
+                force_part += diff / dist.powi(3) * node.center_of_mass.mass;
+            } else {
+                queue.extend(node.children().map(|child| (child, depth + 1)));
+            }
+        }
+
+        config.gravity * obj.mass * force_part
+    }
+
+    /// Gravitational potential at `pos`, approximated and softened like `force_on`, but
+    /// ignoring the near field. Bodies right at `pos` are skipped.
+    pub fn potential_at(&self, pos: &Vector2<Real>, config: &SimulationConfig) -> Real {
+        let mut potential = 0.0;
+
+        let mut queue = VecDeque::from([0]);
+        while let Some(index) = queue.pop_front() {
+            let node = &self.nodes[index as usize];
+            let dist_sq = (node.center_of_mass.position - pos).norm_squared();
+            if !dist_sq.is_normal() {
+                continue;
+            }
+
+            let dist = softened(dist_sq, config.softening);
+            if node.scale / dist < config.theta || node.is_leaf() {
+                potential -= node.center_of_mass.mass / dist;
+            } else {
+                queue.extend(node.children());
+            }
+        }
+
+        config.gravity * potential
+    }
+
+    /// The leaf whose cell contains `pos`, descending like `insert`, or `None` if `pos` is
+    /// outside of the tree or falls into a quadrant without a child.
+    pub fn leaf_containing(&self, pos: &Vector2<Real>) -> Option<NodeIndex> {
+        if !self.contains(pos) {
+            return None;
+        }
+
+        let mut index = 0;
+        let mut pos = *pos;
+        loop {
+            let node = &self.nodes[index as usize];
+            if node.is_leaf() {
+                return Some(index);
+            }
+
+            let quadrant = Quadrant::from_offset(&(pos - node.pos), node.scale);
+            index = match node.children[quadrant as usize] {
+                0 => return None,
+                child => child,
+            };
+            let child = &self.nodes[index as usize];
+            pos = clamp(&child.pos, child.scale, &pos);
+        }
+    }
+
+    pub fn root(&self) -> &FlatNode {
+        &self.nodes[0]
+    }
+
+    pub fn node(&self, index: NodeIndex) -> &FlatNode {
+        &self.nodes[index as usize]
+    }
+
+    pub fn nodes(&self) -> &[FlatNode] {
+        &self.nodes
+    }
+
+    pub fn contains(&self, pos: &Vector2<Real>) -> bool {
+        self.root().contains(pos)
+    }
+}
+
+/// Sums the exact force of all `sources` on `obj`, skipping sources at its position.
+/// Used for the few masses that are too heavy to be grouped with others in a tree.
+pub fn direct_force_on(
+    obj: &MassData,
+    sources: &[MassData],
+    config: &SimulationConfig,
+) -> Vector2<Real> {
+    let force_part = kernel::force_part(&obj.position, sources, config.softening);
+    config.gravity * obj.mass * force_part
+}
+
+/// The distance of bodies `dist_sq` apart squared, with Plummer softening.
+pub fn softened(dist_sq: Real, softening: Real) -> Real {
+    (softening * softening + dist_sq).sqrt()
+}
+
+/// Whether halving the cell still produces children of nonzero size in `Real`.
+fn can_subdivide(cell: &Vector2<Real>, scale: Real) -> bool {
+    (0..2).all(|i| {
+        let center = cell[i] + scale * 0.5;
+        center > cell[i] && center < cell[i] + scale
+    })
+}
+
+fn contains(cell: &Vector2<Real>, scale: Real, pos: &Vector2<Real>) -> bool {
+    cell.iter()
+        .zip(pos.iter())
+        .all(|(&a, &b)| b >= a && b < a + scale)
+}
+
+/// Clamps `pos` into the half open cell `[cell, cell + scale)`.
+fn clamp(cell: &Vector2<Real>, scale: Real, pos: &Vector2<Real>) -> Vector2<Real> {
+    // largest float strictly below the upper bound of the cell
+    fn below(x: Real) -> Real {
+        match x {
+            x if x > 0.0 => Real::from_bits(x.to_bits() - 1),
+            x if x < 0.0 => Real::from_bits(x.to_bits() + 1),
+            _ => -Real::from_bits(1),
+        }
+    }
+
+    Vector2::from_fn(|i, _| pos[i].clamp(cell[i], below(cell[i] + scale)))
+}
+
+/// Returns `obj` with its position clamped into the cell.
+/// The position of a child is computed by repeated halving, so `Real` rounding can
+/// leave points that were assigned to it by `Quadrant::from_offset` just outside of it.
+fn repaired(cell: &Vector2<Real>, scale: Real, obj: &MassData) -> MassData {
+    let mut obj = *obj;
+    if !contains(cell, scale, &obj.position) {
+        obj.position = clamp(cell, scale, &obj.position);
+    }
+    debug_assert!(
+        contains(cell, scale, &obj.position),
+        "{:?} is outside of cell at {:?} with scale {}",
+        obj.position,
+        cell,
+        scale
+    );
+    obj
+}
+
+/// Merges two bodies in a cell that is too small to be subdivided.
+fn merged(cell: &Vector2<Real>, scale: Real, a: &MassData, b: &MassData) -> MassData {
+    let mass = a.mass + b.mass;
+    let position = (a.position * a.mass + b.position * b.mass) / mass;
+    MassData {
+        position: clamp(cell, scale, &position),
+        mass,
     }
 }
@@ -0,0 +1,154 @@
+use crate::direct::DirectSum;
+use crate::tree::FlatTree;
+use crate::{MassData, Simulation, SimulationConfig, Star};
+use alloc::vec::Vec;
+use core::fmt;
+
+/// Consistency checks of the tree built for a simulation state, a first diagnostic step
+/// when the physics look off. Stars that left the domain are ignored.
+#[derive(Clone, Debug)]
+pub struct Validation {
+    /// stars in the domain, all of them are inserted into the tree
+    pub stars: usize,
+    pub nodes: usize,
+    /// relative difference between the mass of the root and the summed mass of the stars
+    pub mass_error: f64,
+    /// inner nodes whose mass differs from the summed mass of their children
+    pub unclosed_nodes: usize,
+    /// nodes whose center of mass lies outside of their cell
+    pub uncontained_nodes: usize,
+    /// relative errors of the tree force against the direct sum on a sample of stars
+    pub force_errors: Vec<f64>,
+}
+
+impl Validation {
+    /// Relative tolerance of the mass checks, sums are accumulated in f64 but stored in `Real`.
+    pub const MASS_TOLERANCE: f64 = 1e-4;
+
+    /// Builds the tree of `stars` like `Simulation::update` does and checks it. The force
+    /// is compared to the O(n) direct sum on `samples` stars spread evenly over `stars`.
+    /// The near field is left to the tree, so the forces of both methods are comparable.
+    pub fn new(stars: &[Star], config: &SimulationConfig, samples: usize) -> Self {
+        let config = SimulationConfig {
+            near_field: None,
+            ..*config
+        };
+        let bodies: Vec<MassData> = stars
+            .iter()
+            .filter(|star| config.domain.contains(star.pos()))
+            .map(|star| star.mass_point)
+            .collect();
+
+        let mut tree = FlatTree::bounding(&config.domain, 2 * bodies.len());
+        bodies.iter().for_each(|body| tree.insert(body));
+        tree.summarize();
+
+        let total_mass: f64 = bodies.iter().map(|body| body.mass as f64).sum();
+        let root_mass = tree.root().center_of_mass().mass as f64;
+
+        let unclosed_nodes = tree
+            .nodes()
+            .iter()
+            .filter(|node| !node.is_leaf())
+            .filter(|node| {
+                let mass = node.center_of_mass().mass as f64;
+                let children: f64 = node
+                    .children()
+                    .map(|child| tree.node(child).center_of_mass().mass as f64)
+                    .sum();
+                (mass - children).abs() > Self::MASS_TOLERANCE * children
+            })
+            .count();
+        let uncontained_nodes = tree
+            .nodes()
+            .iter()
+            .filter(|node| node.center_of_mass().mass > 0.0)
+            .filter(|node| !node.contains(&node.center_of_mass().position))
+            .count();
+
+        let stride = (bodies.len() / samples.max(1)).max(1);
+        let direct = DirectSum::new(bodies);
+        let force_errors = direct
+            .bodies()
+            .iter()
+            .step_by(stride)
+            .take(samples)
+            .filter_map(|body| {
+                let exact = direct.force_on(body, &config);
+                let approximated = tree.force_on(body, &config);
+                let norm = exact.norm() as f64;
+                (norm > 0.0).then(|| (approximated - exact).norm() as f64 / norm)
+            })
+            .collect();
+
+        Self {
+            stars: direct.bodies().len(),
+            nodes: tree.nodes().len(),
+            mass_error: match total_mass > 0.0 {
+                true => (root_mass - total_mass).abs() / total_mass,
+                false => 0.0,
+            },
+            unclosed_nodes,
+            uncontained_nodes,
+            force_errors,
+        }
+    }
+
+    pub fn max_force_error(&self) -> Option<f64> {
+        self.force_errors.iter().copied().reduce(f64::max)
+    }
+
+    pub fn mean_force_error(&self) -> Option<f64> {
+        let count = self.force_errors.len();
+        (count > 0).then(|| self.force_errors.iter().sum::<f64>() / count as f64)
+    }
+
+    /// Whether the structural checks passed. Force errors depend on `theta`, so they are
+    /// only reported.
+    pub fn is_consistent(&self) -> bool {
+        self.mass_error <= Self::MASS_TOLERANCE
+            && self.unclosed_nodes == 0
+            && self.uncontained_nodes == 0
+    }
+}
+
+impl fmt::Display for Validation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let verdict = |ok: bool| if ok { "ok" } else { "FAILED" };
+        writeln!(
+            f,
+            "{} stars in the domain, {} nodes",
+            self.stars, self.nodes
+        )?;
+        writeln!(
+            f,
+            "mass closure: {} (root off by {:.2e}, {} inner nodes off)",
+            verdict(self.mass_error <= Self::MASS_TOLERANCE && self.unclosed_nodes == 0),
+            self.mass_error,
+            self.unclosed_nodes
+        )?;
+        writeln!(
+            f,
+            "containment: {} ({} centers of mass outside of their cell)",
+            verdict(self.uncontained_nodes == 0),
+            self.uncontained_nodes
+        )?;
+        match (self.max_force_error(), self.mean_force_error()) {
+            (Some(max), Some(mean)) => write!(
+                f,
+                "force vs direct on {} stars: mean relative error {:.2e}, max {:.2e}",
+                self.force_errors.len(),
+                mean,
+                max
+            ),
+            _ => write!(f, "force vs direct: no stars to sample"),
+        }
+    }
+}
+
+impl Simulation {
+    /// Validates the tree of the current state, see `Validation::new`.
+    pub fn validate(&self, samples: usize) -> Validation {
+        Validation::new(&self.stars, &self.config, samples)
+    }
+}
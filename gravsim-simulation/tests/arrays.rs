@@ -0,0 +1,20 @@
+//! Uses the public API without naming any nalgebra type.
+
+use gravsim_simulation::{MassData, Simulation, Star};
+
+#[test]
+fn simulation_works_with_plain_arrays() {
+    let mut simulation = Simulation::new([
+        Star::from_arrays([-10.0, 0.0], [0.0, 0.5], 1e3),
+        Star::from_arrays([10.0, 0.0], [0.0, -0.5], 1e3),
+    ]);
+    assert_eq!(simulation.stars[0].pos_array(), [-10.0, 0.0]);
+    assert_eq!(simulation.stars[1].vel_array(), [0.0, -0.5]);
+
+    simulation.update();
+    let [x, _] = simulation.stars[0].pos_array();
+    assert!(x > -10.0);
+
+    let obj = MassData::from_array([1.0, 2.0], 3.0);
+    assert_eq!(obj.position_array(), [1.0, 2.0]);
+}
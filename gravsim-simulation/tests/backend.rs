@@ -0,0 +1,76 @@
+use gravsim_simulation::backend::SimulationBackend;
+use gravsim_simulation::reuse::TreeReuse;
+use gravsim_simulation::{BodyKind, Real, Simulation, SimulationConfig, Star};
+
+#[test]
+fn simulation_as_backend() {
+    let mut backend: Box<dyn SimulationBackend> = Box::new(Simulation::new([]));
+
+    let a = backend.add_star(Star::from_arrays([-5.0, 0.0], [0.0; 2], 1e3));
+    let b = backend.add_star(Star::from_arrays([5.0, 0.0], [0.0; 2], 1e3));
+    let c = backend.add_star(Star::from_arrays([0.0, 50.0], [0.0; 2], 1.0));
+    assert_eq!((a, b, c), (0, 1, 2));
+
+    backend.step();
+    assert!(backend.snapshot()[a].pos().x > -5.0);
+    assert!(backend.diagnostics().potential_energy < 0.0);
+
+    assert_eq!(backend.remove_star(b).map(|star| star.mass()), Some(1e3));
+    assert!(backend.remove_star(2).is_none());
+    assert_eq!(backend.snapshot()[1].mass(), 1.0);
+
+    backend.insert_star(1, Star::from_arrays([5.0, 0.0], [0.0; 2], 1e3));
+    assert_eq!(backend.snapshot()[1].mass(), 1e3);
+    assert!(backend.set_velocity(2, [0.0, 1.0].into()));
+    assert!(!backend.set_velocity(3, [0.0, 1.0].into()));
+    assert_eq!(backend.snapshot()[2].vel_array(), [0.0, 1.0]);
+    assert_eq!(backend.as_simulation().map(|sim| sim.step), Some(1));
+}
+
+#[test]
+fn removing_a_star_in_front_of_a_black_hole_keeps_it_one() {
+    let stars: Vec<_> = (0..50)
+        .map(|i| {
+            let pos = [
+                (i % 10) as Real * 30.0 - 150.0,
+                (i / 10) as Real * 30.0 - 75.0,
+            ];
+            Star::from_arrays(pos, [0.0; 2], 10.0 + i as Real)
+        })
+        .collect();
+    let config = SimulationConfig {
+        reuse_tree: Some(TreeReuse::default()),
+        ..SimulationConfig::default()
+    };
+    let mut simulation = Simulation::with_config(stars, config);
+    simulation.kinds = vec![BodyKind::Star; 10];
+    simulation.kinds.push(BodyKind::BlackHole);
+    simulation.species = (0..50).collect();
+    simulation.spins = (0..50).map(|i| i as Real).collect();
+    simulation.ages = (0..50).map(|i| i as f64).collect();
+    simulation.update();
+
+    simulation.remove_star(3);
+    assert_eq!(simulation.kind(9), BodyKind::BlackHole);
+    assert_eq!(simulation.kind(10), BodyKind::Star);
+    assert_eq!(simulation.black_holes().collect::<Vec<_>>(), [9]);
+    assert_eq!((simulation.species[3], simulation.species.len()), (4, 49));
+    assert_eq!((simulation.spins[3], simulation.spins.len()), (4.0, 49));
+    assert_eq!((simulation.ages[3], simulation.ages.len()), (4.0, 49));
+
+    // the reused tree lost the removed star as well
+    let mut fresh = Simulation::with_config(simulation.stars.clone(), simulation.config);
+    fresh.config.reuse_tree = None;
+    fresh.kinds = simulation.kinds.clone();
+    simulation.update();
+    fresh.update();
+    for (star, fresh) in simulation.stars.iter().zip(&fresh.stars) {
+        assert!((star.vel - fresh.vel).norm() <= 1e-4 * fresh.vel.norm());
+    }
+
+    simulation.insert_star(3, Star::from_arrays([0.0; 2], [0.0; 2], 1.0));
+    assert_eq!(simulation.kind(10), BodyKind::BlackHole);
+    assert_eq!(simulation.kind(3), BodyKind::Star);
+    assert_eq!(simulation.species[..5], [0, 1, 2, 0, 4]);
+    assert_eq!(simulation.spins.len(), 50);
+}
@@ -0,0 +1,64 @@
+use gravsim_simulation::{BodyKind, Real, Simulation, SimulationConfig, Star};
+use nalgebra::Vector2;
+
+#[test]
+fn black_holes_are_pulled_by_every_star_exactly() {
+    let mut stars = vec![Star::new(Vector2::new(3.0, -2.0), Vector2::zeros(), 1e6)];
+    for i in 0..400 {
+        let (x, y) = ((i % 20) as Real, (i / 20) as Real);
+        let pos = Vector2::new(x * 37.0 - 200.0, y * 23.0 + 40.0 * (x * 0.3).sin());
+        stars.push(Star::new(
+            pos,
+            Vector2::zeros(),
+            1.0 + (i % 7) as Real * 50.0,
+        ));
+    }
+    let config = SimulationConfig {
+        // a tree this coarse is far off
+        theta: 2.0,
+        ..SimulationConfig::default()
+    };
+    let mut simulation = Simulation::with_config(stars.clone(), config);
+    simulation.kinds = vec![BodyKind::BlackHole];
+
+    simulation.update();
+
+    let center = stars[0].pos().cast::<f64>();
+    let softening = config.softening as f64;
+    let expected: Vector2<f64> = stars[1..]
+        .iter()
+        .map(|star| {
+            let offset = star.pos().cast::<f64>() - center;
+            let dist = (offset.norm_squared() + softening * softening).sqrt();
+            offset * (config.gravity as f64 * star.mass() as f64 / dist.powi(3))
+        })
+        .sum();
+    let acceleration = simulation.stars[0].vel.cast::<f64>() / config.dt as f64;
+    let error = (acceleration - expected).norm() / expected.norm();
+    assert!(error < 1e-4, "{} {}", acceleration, expected);
+}
+
+#[test]
+fn stars_swallowing_black_holes_become_black_holes() {
+    let stars = [
+        Star::new(Vector2::new(0.0, 0.0), Vector2::new(0.0, 1.0), 300.0),
+        Star::new(Vector2::new(0.1, 0.0), Vector2::new(0.0, -1.0), 100.0),
+        Star::new(Vector2::new(50.0, 0.0), Vector2::new(0.0, 1.0), 100.0),
+    ];
+    let mut simulation = Simulation::with_config(
+        stars,
+        SimulationConfig {
+            gravity: 0.0,
+            merge_collisions: true,
+            ..SimulationConfig::default()
+        },
+    );
+    simulation.kinds = vec![BodyKind::Star, BodyKind::BlackHole];
+
+    simulation.update();
+
+    assert_eq!(simulation.merges.len(), 1);
+    assert_eq!(simulation.kind(0), BodyKind::BlackHole);
+    assert_eq!(simulation.kind(2), BodyKind::Star);
+    assert_eq!(simulation.black_holes().collect::<Vec<_>>(), [0]);
+}
@@ -0,0 +1,27 @@
+use gravsim_simulation::scenario::{Collision, Scenario};
+use gravsim_simulation::Real;
+use nalgebra::Vector2;
+
+#[test]
+fn collisions_start_at_rest_in_the_center_of_mass_frame() {
+    let collision = Collision {
+        stars: 3000,
+        mass_ratio: 2.0,
+        separation: 12_000.0,
+        impact_parameter: 3_000.0,
+        ..Collision::default()
+    };
+    let [primary, secondary] = collision.galaxies();
+    assert_eq!(secondary.stars, 1500);
+    assert!(
+        (secondary.position - primary.position - Vector2::new(12_000.0, 3_000.0)).norm() < 1e-2
+    );
+    assert!((secondary.velocity - primary.velocity).x < 0.0);
+
+    let stars = Scenario::collision(collision).stars();
+    assert_eq!(stars.len(), 3000 + 1500 + 2);
+    let mass: Real = stars.iter().map(|star| star.mass()).sum();
+    let momentum: Vector2<Real> = stars.iter().map(|star| star.vel * star.mass()).sum();
+    // only zero up to the random star masses
+    assert!(momentum.norm() / mass < 0.1 * collision.relative_velocity);
+}
@@ -0,0 +1,152 @@
+use gravsim_simulation::collision::{merge_overlapping, merge_swept, Merge};
+use gravsim_simulation::radius::ConstantDensity;
+use gravsim_simulation::{Simulation, SimulationConfig, Star};
+use nalgebra::Vector2;
+
+#[test]
+fn overlapping_stars_merge_conserving_mass_and_momentum() {
+    let mut stars = [
+        Star::new(Vector2::new(0.0, 0.0), Vector2::new(1.0, 0.0), 300.0),
+        Star::new(Vector2::new(0.1, 0.0), Vector2::new(0.0, 2.0), 100.0),
+        Star::new(Vector2::new(50.0, 0.0), Vector2::zeros(), 100.0),
+    ];
+
+    let merges = merge_overlapping(&mut stars, &ConstantDensity::DEFAULT);
+
+    assert_eq!(
+        merges,
+        [Merge {
+            into: 0,
+            from: 1,
+            fraction: 0.25,
+            spin: 15.0
+        }]
+    );
+    assert_eq!(stars[0].mass(), 400.0);
+    assert_eq!(stars[0].vel * 400.0, Vector2::new(300.0, 200.0));
+    assert!((stars[0].pos().x - 0.025).abs() < 1e-6);
+    assert_eq!(stars[1].mass(), 0.0);
+    assert!(stars[1].pos().x.is_nan());
+    assert_eq!(stars[2].mass(), 100.0);
+}
+
+#[test]
+fn merges_turn_orbital_angular_momentum_into_spin() {
+    let stars = [
+        Star::new(Vector2::new(0.0, 0.0), Vector2::new(0.0, 1.0), 100.0),
+        Star::new(Vector2::new(0.1, 0.0), Vector2::new(0.0, -1.0), 100.0),
+        Star::new(Vector2::new(50.0, 0.0), Vector2::new(0.0, 1.0), 100.0),
+    ];
+    let mut simulation = Simulation::with_config(
+        stars,
+        SimulationConfig {
+            gravity: 0.0,
+            merge_collisions: true,
+            ..SimulationConfig::default()
+        },
+    );
+    let before = simulation.diagnostics();
+
+    simulation.update();
+
+    let after = simulation.diagnostics();
+    assert_eq!(simulation.merges.len(), 1);
+    assert!(
+        (simulation.spins[0] + 10.0).abs() < 1e-4,
+        "{}",
+        simulation.spins[0]
+    );
+    assert_eq!(simulation.spins[1], 0.0);
+    assert_eq!(before.spin, 0.0);
+    assert!((after.spin + 10.0).abs() < 1e-4);
+    let drift = after.total_angular_momentum() - before.total_angular_momentum();
+    assert!(
+        drift.abs() < 1e-3 * before.angular_momentum.abs(),
+        "{}",
+        drift
+    );
+}
+
+#[test]
+fn collisions_are_opt_in() {
+    let stars = [
+        Star::new(Vector2::new(0.0, 0.0), Vector2::zeros(), 100.0),
+        Star::new(Vector2::new(0.1, 0.0), Vector2::zeros(), 100.0),
+    ];
+    // without gravity, so the stars don't fly past each other in one step
+    let config = SimulationConfig {
+        gravity: 0.0,
+        ..SimulationConfig::default()
+    };
+    let mut passing = Simulation::with_config(stars, config);
+    let mut merging = Simulation::with_config(
+        stars,
+        SimulationConfig {
+            merge_collisions: true,
+            ..config
+        },
+    );
+
+    passing.update();
+    merging.update();
+
+    assert!(passing.merges.is_empty());
+    assert_eq!(merging.merges.len(), 1);
+    assert_eq!(merging.stars[0].mass(), 200.0);
+}
+
+#[test]
+fn fast_stars_merge_when_their_paths_cross() {
+    let previous = [
+        Vector2::new(-10.0, 0.0),
+        Vector2::new(10.0, 0.2),
+        Vector2::new(-10.0, 5.0),
+        Vector2::new(10.0, 5.0),
+    ];
+    // the first pair tunnels through each other, the second one passes at a distance
+    let mut stars = [
+        Star::new(Vector2::new(10.0, 0.0), Vector2::new(20.0, 0.0), 300.0),
+        Star::new(Vector2::new(-10.0, 0.2), Vector2::new(-20.0, 0.0), 100.0),
+        Star::new(Vector2::new(10.0, 5.0), Vector2::new(20.0, 0.0), 100.0),
+        Star::new(Vector2::new(-10.0, 8.0), Vector2::new(-20.0, 3.0), 100.0),
+    ];
+
+    let merges = merge_swept(&mut stars, &previous, &ConstantDensity::DEFAULT);
+
+    assert_eq!(
+        merges,
+        [Merge {
+            into: 0,
+            from: 1,
+            fraction: 0.25,
+            spin: 600.0
+        }]
+    );
+    assert_eq!(stars[0].mass(), 400.0);
+    assert_eq!(stars[0].vel * 400.0, Vector2::new(4000.0, 0.0));
+    assert!(stars[1].pos().x.is_nan());
+    assert_eq!(stars[2].mass(), 100.0);
+    assert_eq!(stars[3].mass(), 100.0);
+}
+
+#[test]
+fn updates_catch_stars_tunneling_through_each_other() {
+    let stars = [
+        Star::new(Vector2::new(-10.0, 0.0), Vector2::new(15.0, 0.0), 100.0),
+        Star::new(Vector2::new(10.0, 0.0), Vector2::new(-15.0, 0.0), 100.0),
+    ];
+    let mut simulation = Simulation::with_config(
+        stars,
+        SimulationConfig {
+            gravity: 0.0,
+            merge_collisions: true,
+            ..SimulationConfig::default()
+        },
+    );
+
+    simulation.update();
+
+    assert_eq!(simulation.merges.len(), 1);
+    assert_eq!(simulation.stars[0].mass(), 200.0);
+    assert_eq!(simulation.stars[0].vel, Vector2::zeros());
+}
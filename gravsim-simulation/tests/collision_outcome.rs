@@ -0,0 +1,227 @@
+use gravsim_simulation::collision::{collide, CollisionModel, Debris, Outcome, Rule};
+use gravsim_simulation::radius::ConstantDensity;
+use gravsim_simulation::{Real, Simulation, SimulationConfig, Star};
+use nalgebra::Vector2;
+
+fn positions(stars: &[Star]) -> Vec<Vector2<Real>> {
+    stars.iter().map(|star| *star.pos()).collect()
+}
+
+fn everything(outcome: Outcome) -> CollisionModel {
+    CollisionModel {
+        default: outcome,
+        rules: Vec::new(),
+    }
+}
+
+fn head_on() -> Vec<Star> {
+    vec![
+        Star::new(Vector2::new(0.0, 0.0), Vector2::new(1.0, 0.0), 100.0),
+        Star::new(Vector2::new(0.1, 0.0), Vector2::new(-1.0, 0.0), 100.0),
+    ]
+}
+
+fn assert_close(a: Vector2<Real>, b: Vector2<Real>) {
+    assert!((a - b).norm() < 1e-4, "{} != {}", a, b);
+}
+
+const FRAGMENT: Outcome = Outcome::Fragment {
+    debris_fraction: 0.5,
+    fragments: 4,
+    ejection: 0.5,
+    min_mass: 0.0,
+};
+
+#[test]
+fn elastic_bounces_reverse_the_approach() {
+    let mut stars = head_on();
+    let previous = positions(&stars);
+
+    let collisions = collide(
+        &mut stars,
+        &previous,
+        &[],
+        &everything(Outcome::Bounce { restitution: 1.0 }),
+        &ConstantDensity::DEFAULT,
+    );
+
+    assert_eq!(collisions.bounces, [[0, 1]]);
+    assert!(collisions.merges.is_empty());
+    assert_close(stars[0].vel, Vector2::new(-1.0, 0.0));
+    assert_close(stars[1].vel, Vector2::new(1.0, 0.0));
+    // pushed apart around the center of mass
+    let distance = stars[1].pos().x - stars[0].pos().x;
+    assert!((distance - stars[0].radius() - stars[1].radius()).abs() < 1e-5);
+    assert!((stars[0].pos().x + stars[1].pos().x - 0.1).abs() < 1e-5);
+}
+
+#[test]
+fn inelastic_bounces_stop_the_approach() {
+    let mut stars = head_on();
+    let previous = positions(&stars);
+
+    collide(
+        &mut stars,
+        &previous,
+        &[],
+        &everything(Outcome::Bounce { restitution: 0.0 }),
+        &ConstantDensity::DEFAULT,
+    );
+
+    assert_close(stars[0].vel, Vector2::zeros());
+    assert_close(stars[1].vel, Vector2::zeros());
+}
+
+#[test]
+fn fragments_conserve_mass_momentum_and_center_of_mass() {
+    let mut stars = vec![
+        Star::new(Vector2::new(0.0, 0.0), Vector2::zeros(), 1000.0),
+        Star::new(Vector2::new(0.1, 0.0), Vector2::new(-2.0, 0.0), 100.0),
+    ];
+    let previous = positions(&stars);
+
+    let collisions = collide(
+        &mut stars,
+        &previous,
+        &[],
+        &everything(FRAGMENT),
+        &ConstantDensity::DEFAULT,
+    );
+
+    assert_eq!(collisions.merges.len(), 1);
+    assert_eq!(
+        collisions.debris,
+        (2..6).map(|id| Debris { id, from: 1 }).collect::<Vec<_>>()
+    );
+    assert_eq!(stars.len(), 6);
+    assert!((stars[0].mass() - 1050.0).abs() < 1e-3);
+
+    let alive: Vec<_> = stars.iter().filter(|star| star.mass() > 0.0).collect();
+    let mass: Real = alive.iter().map(|star| star.mass()).sum();
+    let momentum: Vector2<Real> = alive.iter().map(|star| star.vel * star.mass()).sum();
+    let center: Vector2<Real> = alive.iter().map(|star| star.pos() * star.mass()).sum();
+    assert!((mass - 1100.0).abs() < 1e-3);
+    assert!((momentum - Vector2::new(-200.0, 0.0)).norm() < 1e-2);
+    assert!((center - Vector2::new(10.0, 0.0)).norm() < 1e-2);
+
+    // the debris doesn't collide again right away
+    let previous = positions(&stars);
+    let again = collide(
+        &mut stars,
+        &previous,
+        &[],
+        &everything(FRAGMENT),
+        &ConstantDensity::DEFAULT,
+    );
+    assert_eq!(again, Default::default());
+}
+
+#[test]
+fn fragments_conserve_angular_momentum() {
+    // off center, so the impact has angular momentum
+    let stars = [
+        Star::new(Vector2::new(0.0, 0.0), Vector2::zeros(), 1000.0),
+        Star::new(Vector2::new(0.1, 0.5), Vector2::new(-2.0, 0.0), 100.0),
+    ];
+    let mut simulation = Simulation::with_config(
+        stars,
+        SimulationConfig {
+            gravity: 0.0,
+            merge_collisions: true,
+            ..SimulationConfig::default()
+        },
+    );
+    simulation.collision_model = everything(FRAGMENT);
+    let before = simulation.diagnostics().total_angular_momentum();
+
+    simulation.update();
+
+    let after = simulation.diagnostics();
+    assert_eq!(simulation.debris.len(), 4);
+    assert!(after.spin != 0.0);
+    assert!(
+        (after.total_angular_momentum() - before).abs() < 1e-3 * before.abs(),
+        "{} != {}",
+        after.total_angular_momentum(),
+        before
+    );
+}
+
+#[test]
+fn light_fragments_merge_instead() {
+    let mut stars = head_on();
+    let previous = positions(&stars);
+    let outcome = Outcome::Fragment {
+        debris_fraction: 0.5,
+        fragments: 4,
+        ejection: 0.5,
+        min_mass: 20.0,
+    };
+
+    let collisions = collide(
+        &mut stars,
+        &previous,
+        &[],
+        &everything(outcome),
+        &ConstantDensity::DEFAULT,
+    );
+
+    assert!(collisions.debris.is_empty());
+    assert_eq!(stars.len(), 2);
+    assert_eq!(stars[0].mass(), 200.0);
+}
+
+#[test]
+fn rules_select_the_outcome_by_species() {
+    let mut stars = head_on();
+    stars.extend(
+        head_on()
+            .iter()
+            .map(|star| Star::new(star.pos() + Vector2::new(0.0, 50.0), star.vel, star.mass())),
+    );
+    let previous = positions(&stars);
+    let model = CollisionModel {
+        default: Outcome::Merge,
+        rules: vec![Rule {
+            species: [1, 0],
+            outcome: Outcome::Bounce { restitution: 1.0 },
+        }],
+    };
+
+    // the last star has no entry, and is of species 0
+    let collisions = collide(
+        &mut stars,
+        &previous,
+        &[0, 1, 0],
+        &model,
+        &ConstantDensity::DEFAULT,
+    );
+
+    assert_eq!(collisions.bounces, [[0, 1]]);
+    assert_eq!(collisions.merges.len(), 1);
+    assert_eq!(stars[2].mass(), 200.0);
+}
+
+#[test]
+fn debris_inherits_the_species() {
+    let stars = [
+        Star::new(Vector2::new(0.0, 0.0), Vector2::zeros(), 1000.0),
+        Star::new(Vector2::new(0.1, 0.0), Vector2::new(-2.0, 0.0), 100.0),
+    ];
+    let mut simulation = Simulation::with_config(
+        stars,
+        SimulationConfig {
+            gravity: 0.0,
+            merge_collisions: true,
+            ..SimulationConfig::default()
+        },
+    );
+    simulation.species = vec![0, 3];
+    simulation.collision_model = everything(FRAGMENT);
+
+    simulation.update();
+
+    assert_eq!(simulation.debris.len(), 4);
+    assert_eq!(simulation.stars.len(), 6);
+    assert_eq!(simulation.species, [0, 3, 3, 3, 3, 3]);
+}
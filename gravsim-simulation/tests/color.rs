@@ -0,0 +1,32 @@
+use gravsim_simulation::color::ColorPolicy;
+use gravsim_simulation::Star;
+use nalgebra::Vector2;
+
+fn stars() -> Vec<Star> {
+    vec![
+        Star::new(Vector2::zeros(), Vector2::zeros(), 10.0),
+        Star::new(Vector2::new(5.0, 0.0), Vector2::zeros(), 100.0),
+        Star::new(Vector2::new(0.0, 10.0), Vector2::zeros(), 1.0),
+    ]
+}
+
+#[test]
+fn gradients_span_the_galaxy() {
+    let (black, white) = ([0.0; 3], [1.0; 3]);
+
+    let by_mass = ColorPolicy::ByMass(black, white).colors(&stars());
+    assert_eq!(by_mass, [[0.5; 3], white, black]);
+
+    let radial = ColorPolicy::Radial(black, white).colors(&stars());
+    assert_eq!(radial, [black, [0.5; 3], white]);
+}
+
+#[test]
+fn palettes_are_stable() {
+    let palette = ColorPolicy::Palette(vec![[1.0, 0.0, 0.0], [0.0, 0.0, 1.0]]);
+    let colors = palette.colors(&stars());
+    assert_eq!(colors, palette.colors(&stars()));
+    assert!(colors
+        .iter()
+        .all(|color| *color == [1.0, 0.0, 0.0] || *color == [0.0, 0.0, 1.0]));
+}
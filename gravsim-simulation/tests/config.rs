@@ -0,0 +1,78 @@
+use gravsim_simulation::tree::Aabb;
+use gravsim_simulation::{Simulation, SimulationConfig, Star};
+use nalgebra::Vector2;
+
+#[test]
+fn time_step_and_domain_come_from_the_config() {
+    let config = SimulationConfig {
+        gravity: 0.0,
+        domain: Aabb::centered(Vector2::repeat(100.0)),
+        dt: 0.5,
+        ..SimulationConfig::default()
+    };
+    let star = |x| Star::new(Vector2::new(x, 0.0), Vector2::new(0.0, 2.0), 1.0);
+    let mut simulation = Simulation::with_config([star(0.0), star(60.0)], config);
+
+    simulation.update();
+
+    assert_eq!(*simulation.stars[0].pos(), Vector2::new(0.0, 1.0));
+    assert!(simulation.stars[1].pos().x.is_nan());
+}
+
+#[test]
+fn gravity_scales_accelerations() {
+    let stars = [
+        Star::new(Vector2::new(-10.0, 0.0), Vector2::zeros(), 1e3),
+        Star::new(Vector2::new(10.0, 0.0), Vector2::zeros(), 1e3),
+    ];
+    let mut weak = Simulation::new(stars);
+    let mut strong = Simulation::with_config(
+        stars,
+        SimulationConfig {
+            gravity: 4.0 * Simulation::GRAVITY,
+            ..SimulationConfig::default()
+        },
+    );
+
+    weak.update();
+    strong.update();
+
+    let ratio = strong.stars[0].vel.x / weak.stars[0].vel.x;
+    assert!((ratio - 4.0).abs() < 1e-4);
+}
+
+#[test]
+fn update_by_overrides_the_time_step() {
+    let star = Star::new(Vector2::zeros(), Vector2::new(0.0, 2.0), 1.0);
+    let mut simulation = Simulation::new([star]);
+
+    simulation.update_by(0.25);
+
+    assert_eq!(*simulation.stars[0].pos(), Vector2::new(0.0, 0.5));
+    assert_eq!(simulation.time, 0.25);
+    assert_eq!(simulation.config.dt, 1.0);
+}
+
+#[test]
+fn frame_pacing_doesnt_change_the_physics() {
+    let stars = [
+        Star::new(Vector2::new(-10.0, 0.0), Vector2::zeros(), 1e5),
+        Star::new(Vector2::new(10.0, 0.0), Vector2::zeros(), 1e5),
+    ];
+    let mut coarse = Simulation::new(stars);
+    let mut fine = Simulation::new(stars);
+
+    for _ in 0..20 {
+        coarse.update_by(0.5);
+        fine.update_by(0.25);
+        fine.update_by(0.25);
+    }
+
+    assert_eq!(coarse.time, fine.time);
+    let coarse_distance = coarse.stars[1].pos().x - coarse.stars[0].pos().x;
+    let fine_distance = fine.stars[1].pos().x - fine.stars[0].pos().x;
+    assert!(coarse_distance < 20.0);
+    let difference = (coarse_distance - fine_distance).abs();
+    // first order, both step sizes stay within a few percent
+    assert!(difference < 0.05 * (20.0 - fine_distance), "{}", difference);
+}
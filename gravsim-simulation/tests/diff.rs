@@ -0,0 +1,62 @@
+use gravsim_simulation::diff::Diff;
+use gravsim_simulation::{Real, Simulation, Star};
+use nalgebra::Vector2;
+
+fn ring(count: usize) -> Vec<Star> {
+    (0..count)
+        .map(|i| {
+            let angle = i as Real * 0.3;
+            let pos = Vector2::new(angle.cos(), angle.sin()) * (20.0 + i as Real);
+            Star::new(pos, Vector2::new(-pos.y, pos.x) * 1e-3, 1.0)
+        })
+        .collect()
+}
+
+#[test]
+fn identical_runs_have_no_differences() {
+    let mut a = Simulation::new(ring(50));
+    let mut b = Simulation::new(ring(50));
+    a.update();
+    b.update();
+
+    let diff = a.diff(&b);
+    assert!(diff.is_identical(), "{}", diff);
+    assert_eq!(diff.deltas.len(), 50);
+    assert_eq!(diff.max_position_delta(), Some(0.0));
+    assert_eq!(diff.rms_velocity_delta(), Some(0.0));
+}
+
+#[test]
+fn differences_are_reported_per_star() {
+    let a = ring(10);
+    let mut b = a.clone();
+    b[3].mass_point.position.x += 3.0;
+    b[3].mass_point.position.y += 4.0;
+    b[7].vel.x += 0.5;
+
+    let diff = Diff::new(&a, &b);
+    assert!(!diff.is_identical());
+    assert_eq!(diff.differing(1e-6).count(), 2);
+
+    let largest = diff.largest(2);
+    assert_eq!(largest[0].id, 3);
+    assert!((largest[0].position - 5.0).abs() < 1e-4);
+    assert!((diff.max_velocity_delta().unwrap() - 0.5).abs() < 1e-4);
+    assert!((diff.rms_position_delta().unwrap() - (25.0f64 / 10.0).sqrt()).abs() < 1e-4);
+}
+
+#[test]
+fn removed_and_extra_stars_exist_in_one_state_only() {
+    let a = ring(10);
+    let mut b = ring(12);
+    // removed like a star leaving the domain
+    b[4].mass_point.position = Vector2::from_element(Real::NAN);
+    b[4].mass_point.mass = 0.0;
+
+    let diff = Diff::new(&a, &b);
+    assert_eq!(diff.only_in_a, vec![4]);
+    assert_eq!(diff.only_in_b, vec![10, 11]);
+    assert_eq!(diff.deltas.len(), 9);
+    assert!(diff.differing(0.0).next().is_none());
+    assert!(!diff.is_identical());
+}
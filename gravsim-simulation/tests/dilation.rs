@@ -0,0 +1,27 @@
+use gravsim_simulation::{DilationZone, Simulation, Star};
+use nalgebra::Vector2;
+
+#[test]
+fn stars_in_dilation_zones_move_slower() {
+    let star = |x| Star::new(Vector2::new(x, 0.0), Vector2::new(0.0, 1.0), 1e-3);
+    let mut simulation = Simulation::new([star(-100.0), star(100.0), star(300.0)]);
+    simulation.dilation_zones = vec![
+        DilationZone {
+            center: Vector2::new(100.0, 0.0),
+            radius: 10.0,
+            time_scale: 0.25,
+        },
+        DilationZone {
+            center: Vector2::new(300.0, 0.0),
+            radius: 10.0,
+            time_scale: 0.0,
+        },
+    ];
+
+    simulation.update();
+
+    let moved: Vec<_> = simulation.stars.iter().map(|star| star.pos().y).collect();
+    assert!((moved[0] - 1.0).abs() < 1e-3);
+    assert!((moved[1] - 0.25).abs() < 1e-3);
+    assert_eq!(moved[2], 0.0);
+}
@@ -0,0 +1,183 @@
+use gravsim_simulation::direct::DirectSum;
+use gravsim_simulation::tree::{FlatTree, Node};
+use gravsim_simulation::{MassData, Real, Simulation, SimulationConfig};
+use nalgebra::Vector2;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rand_xorshift::XorShiftRng;
+
+const ROOT: Real = -1000.0;
+const SCALE: Real = 2000.0;
+
+fn uniform(rng: &mut XorShiftRng, count: usize) -> Vec<MassData> {
+    (0..count)
+        .map(|_| MassData {
+            position: Vector2::from_fn(|_, _| rng.gen_range(-900.0..900.0)),
+            mass: rng.gen_range(1.0..10.0),
+        })
+        .collect()
+}
+
+/// A few dense clumps of different sizes, where centers of mass matter most.
+fn clustered(rng: &mut XorShiftRng, count: usize) -> Vec<MassData> {
+    let clumps: Vec<(Vector2<Real>, Real)> = (0..5)
+        .map(|_| {
+            let center = Vector2::from_fn(|_, _| rng.gen_range(-600.0..600.0));
+            (center, rng.gen_range(10.0..150.0))
+        })
+        .collect();
+    (0..count)
+        .map(|i| {
+            let (center, radius) = clumps[i % clumps.len()];
+            // sums of uniforms bunch up towards the center of the clump
+            let offset = Vector2::from_fn(|_, _| {
+                (0..3).map(|_| rng.gen_range(-1.0..1.0)).sum::<Real>() / 3.0
+            });
+            MassData {
+                position: center + offset * radius,
+                mass: rng.gen_range(1.0..100.0),
+            }
+        })
+        .collect()
+}
+
+fn config(theta: Real) -> SimulationConfig {
+    SimulationConfig {
+        theta,
+        near_field: None,
+        ..SimulationConfig::default()
+    }
+}
+
+fn node(bodies: &[MassData]) -> Node {
+    let mut tree = Node::new_root(Vector2::repeat(ROOT), SCALE);
+    bodies.iter().for_each(|body| tree.insert(body));
+    tree.summarize();
+    tree
+}
+
+fn flat(bodies: &[MassData]) -> FlatTree {
+    let mut tree = FlatTree::with_capacity(Vector2::repeat(ROOT), SCALE, 2 * bodies.len());
+    bodies.iter().for_each(|body| tree.insert(body));
+    tree.summarize();
+    tree
+}
+
+/// Mean and max relative error of `force` against the direct sum, on every tenth body.
+fn errors(direct: &DirectSum, force: impl Fn(&MassData) -> Vector2<Real>) -> (f64, f64) {
+    let config = config(0.0);
+    let errors: Vec<f64> = direct
+        .bodies()
+        .iter()
+        .step_by(10)
+        .map(|body| {
+            let exact = direct.force_on(body, &config);
+            (force(body) - exact).norm() as f64 / exact.norm() as f64
+        })
+        .collect();
+    let mean = errors.iter().sum::<f64>() / errors.len() as f64;
+    (mean, errors.into_iter().fold(0.0, f64::max))
+}
+
+/// Mean error a monopole tree may have at `theta`, which shrinks roughly with its square.
+/// Towards `theta` 1 cells get as large as their distance and the error grows faster, by how
+/// much depends on the bodies, which `f32` and `f64` draw differently from the same seeds.
+fn tolerance(theta: Real) -> f64 {
+    let theta = (theta as f64).powi(2);
+    0.1 * theta * (1.0 + theta)
+}
+
+#[test]
+fn direct_sum_is_symmetric() {
+    let mut rng = XorShiftRng::seed_from_u64(0xd1ec7);
+    let direct = DirectSum::new(uniform(&mut rng, 500));
+    let forces = direct.forces(&config(Simulation::THETA));
+
+    // every pair contributes equal and opposite forces
+    let total: Vector2<f64> = forces.iter().map(|force| force.cast::<f64>()).sum();
+    let largest = forces.iter().map(|force| force.norm()).fold(0.0, Real::max);
+    assert!((total.norm() as Real) < largest * 1e-3, "{}", total);
+}
+
+#[test]
+fn trees_without_approximation_match_the_direct_sum() {
+    let mut rng = XorShiftRng::seed_from_u64(0x7e7a0);
+    let bodies = uniform(&mut rng, 2000);
+    let direct = DirectSum::new(bodies.clone());
+    let config = config(0.0);
+
+    let node = node(&bodies);
+    let flat = flat(&bodies);
+    for (name, (mean, max)) in [
+        ("node", errors(&direct, |body| node.force_on(body, &config))),
+        ("flat", errors(&direct, |body| flat.force_on(body, &config))),
+    ] {
+        assert!(mean < 1e-4, "{}: mean error {:.2e}", name, mean);
+        assert!(max < 1e-2, "{}: max error {:.2e}", name, max);
+    }
+}
+
+#[test]
+fn tree_error_shrinks_with_theta() {
+    for (name, bodies) in [
+        (
+            "uniform",
+            uniform(&mut XorShiftRng::seed_from_u64(0x7e7a1), 2000),
+        ),
+        (
+            "clustered",
+            clustered(&mut XorShiftRng::seed_from_u64(0x7e7a2), 2000),
+        ),
+    ] {
+        let direct = DirectSum::new(bodies.clone());
+        let node = node(&bodies);
+        let flat = flat(&bodies);
+
+        let mut previous = 0.0;
+        for theta in [0.25, 0.5, 1.0] {
+            let config = config(theta);
+            let (node_mean, _) = errors(&direct, |body| node.force_on(body, &config));
+            let (flat_mean, _) = errors(&direct, |body| flat.force_on(body, &config));
+
+            for mean in [node_mean, flat_mean] {
+                assert!(
+                    mean < tolerance(theta),
+                    "{} at theta {}: mean error {:.2e}",
+                    name,
+                    theta,
+                    mean
+                );
+            }
+            assert!(
+                node_mean >= previous,
+                "{} at theta {}: mean error {:.2e} below the one at a smaller theta",
+                name,
+                theta,
+                node_mean
+            );
+            previous = node_mean;
+        }
+    }
+}
+
+#[test]
+fn insertion_order_does_not_change_the_accuracy() {
+    let mut rng = XorShiftRng::seed_from_u64(0x0bde7);
+    let mut bodies = clustered(&mut rng, 2000);
+    let direct = DirectSum::new(bodies.clone());
+    let config = config(Simulation::THETA);
+
+    let ordered_tree = flat(&bodies);
+    let (ordered, _) = errors(&direct, |body| ordered_tree.force_on(body, &config));
+    bodies.shuffle(&mut rng);
+    let shuffled_tree = flat(&bodies);
+    let (shuffled, _) = errors(&direct, |body| shuffled_tree.force_on(body, &config));
+
+    assert!(shuffled < tolerance(Simulation::THETA), "{:.2e}", shuffled);
+    assert!(
+        (ordered - shuffled).abs() < 1e-4,
+        "{:.2e} vs {:.2e}",
+        ordered,
+        shuffled
+    );
+}
@@ -0,0 +1,32 @@
+use gravsim_simulation::tree::{Aabb, Node};
+use gravsim_simulation::{MassData, Simulation, SimulationConfig, Star};
+use nalgebra::Vector2;
+
+#[test]
+fn trees_pad_rectangles_to_squares() {
+    let domain = Aabb {
+        min: Vector2::new(0.0, -10.0),
+        max: Vector2::new(100.0, 10.0),
+    };
+    assert_eq!(domain.square(), (Vector2::new(0.0, -50.0), 100.0));
+
+    let mut tree = Node::bounding(&domain);
+    tree.insert(&MassData::from_array([99.0, 9.0], 1.0));
+    tree.summarize();
+    assert_eq!(tree.center_of_mass().position, Vector2::new(99.0, 9.0));
+}
+
+#[test]
+fn stars_outside_of_rectangular_domains_are_removed() {
+    let config = SimulationConfig {
+        domain: Aabb::centered(Vector2::new(1000.0, 10.0)),
+        ..SimulationConfig::default()
+    };
+    let star = |x, y| Star::new(Vector2::new(x, y), Vector2::zeros(), 1.0);
+    let mut simulation = Simulation::with_config([star(-450.0, 0.0), star(0.0, 20.0)], config);
+
+    simulation.update();
+
+    assert!(simulation.stars[0].pos().x.is_finite());
+    assert!(simulation.stars[1].pos().x.is_nan());
+}
@@ -0,0 +1,53 @@
+use gravsim_simulation::{Real, Simulation, SimulationConfig, Star};
+use nalgebra::Vector2;
+use rand::{Rng, SeedableRng};
+use rand_xorshift::XorShiftRng;
+
+/// Velocities after one update from rest, i.e. the accelerations.
+fn accelerations(stars: &[Star], config: SimulationConfig) -> Vec<Vector2<Real>> {
+    let mut simulation = Simulation::with_config(stars.iter().copied(), config);
+    simulation.update();
+    simulation.stars.iter().map(|star| star.vel).collect()
+}
+
+#[test]
+fn dominant_masses_are_evaluated_directly() {
+    let mut rng = XorShiftRng::seed_from_u64(3);
+    let center = Star::new(Vector2::zeros(), Vector2::zeros(), 1e6);
+    let stars: Vec<_> = [center]
+        .into_iter()
+        .chain((0..500).map(|_| {
+            let pos = Vector2::from_fn(|_, _| rng.gen_range(-200.0..200.0));
+            Star::new(pos, Vector2::zeros(), rng.gen_range(1.0..500.0))
+        }))
+        .collect();
+
+    let coarse = SimulationConfig {
+        theta: 1.0,
+        ..SimulationConfig::default()
+    };
+    let exact = accelerations(
+        &stars,
+        SimulationConfig {
+            theta: 0.0,
+            ..coarse
+        },
+    );
+    let error = |accelerations: Vec<Vector2<Real>>| -> Real {
+        accelerations
+            .iter()
+            .zip(&exact)
+            .map(|(a, b)| (a - b).norm() / b.norm())
+            .sum()
+    };
+
+    let tree_error = error(accelerations(&stars, coarse));
+    let direct_error = error(accelerations(
+        &stars,
+        SimulationConfig {
+            dominant_mass: Some(1e5),
+            ..coarse
+        },
+    ));
+    assert!(direct_error < tree_error);
+}
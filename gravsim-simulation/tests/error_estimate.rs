@@ -0,0 +1,22 @@
+use gravsim_simulation::diagnostics::ErrorEstimate;
+use gravsim_simulation::{Simulation, Star};
+use nalgebra::Vector2;
+
+#[test]
+fn close_encounters_accumulate_more_error() {
+    // a wide orbit around a heavy center, and a star plunging right through it
+    let mut simulation = Simulation::new([
+        Star::new(Vector2::zeros(), Vector2::zeros(), 1e5),
+        Star::new(Vector2::new(500.0, 0.0), Vector2::new(0.0, 0.14), 1.0),
+        Star::new(Vector2::new(-20.0, 0.5), Vector2::new(1.0, 0.0), 1.0),
+    ]);
+    simulation.error_estimate = Some(ErrorEstimate::default());
+
+    for _ in 0..40 {
+        simulation.update();
+    }
+
+    let errors = &simulation.error_estimate.as_ref().unwrap().accumulated;
+    assert_eq!(errors.len(), 3);
+    assert!(errors[2] > 100.0 * errors[1], "{:?}", errors);
+}
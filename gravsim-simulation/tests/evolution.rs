@@ -0,0 +1,58 @@
+use gravsim_simulation::evolution::Evolution;
+use gravsim_simulation::{Simulation, SimulationConfig, Star};
+use nalgebra::Vector2;
+
+fn evolving() -> Simulation {
+    let stars = [
+        Star::new(Vector2::new(-5000.0, 0.0), Vector2::zeros(), 4000.0),
+        Star::new(Vector2::new(5000.0, 0.0), Vector2::zeros(), 1000.0),
+    ];
+    let config = SimulationConfig {
+        evolution: Some(Evolution {
+            lifetime: 100.0,
+            mass: 1000.0,
+            exponent: 2.5,
+        }),
+        ..SimulationConfig::default()
+    };
+    Simulation::with_config(stars, config)
+}
+
+#[test]
+fn heavy_stars_burn_out_first() {
+    let mut simulation = evolving();
+    let evolution = simulation.config.evolution.unwrap();
+    // 100 * 4^-2.5
+    assert!((evolution.lifetime(4000.0) - 3.125).abs() < 1e-9);
+
+    let mut expired = Vec::new();
+    for _ in 0..10 {
+        simulation.update();
+        expired.extend(simulation.expired.iter().map(|&id| (simulation.step, id)));
+    }
+
+    assert_eq!(expired, vec![(4, 0)]);
+    assert_eq!(simulation.ages, vec![10.0, 10.0]);
+}
+
+#[test]
+fn stars_redden_before_they_fade() {
+    let evolution = Evolution::default();
+    let lifetime = evolution.lifetime(1000.0);
+
+    assert_eq!(evolution.tint(1000.0, lifetime * 0.5), [1.0; 3]);
+    let giant = evolution.tint(1000.0, lifetime * 0.95);
+    assert!(giant[0] > 1.0 && giant[2] < 1.0, "{:?}", giant);
+    let remnant = evolution.tint(1000.0, lifetime);
+    assert!(remnant.iter().all(|&tint| tint < 1.0), "{:?}", remnant);
+}
+
+#[test]
+fn stars_do_not_age_without_evolution() {
+    let mut simulation = evolving();
+    simulation.config.evolution = None;
+
+    simulation.update();
+
+    assert!(simulation.ages.is_empty());
+}
@@ -0,0 +1,146 @@
+use gravsim_simulation::direct::DirectSum;
+use gravsim_simulation::fmm::Fmm;
+use gravsim_simulation::solver::{BarnesHut, ForceSolver};
+use gravsim_simulation::tree::FlatTree;
+use gravsim_simulation::{MassData, Real, Simulation, SimulationConfig, Star};
+use nalgebra::Vector2;
+use rand::{Rng, SeedableRng};
+use rand_xorshift::XorShiftRng;
+
+fn uniform(rng: &mut XorShiftRng, count: usize) -> Vec<MassData> {
+    (0..count)
+        .map(|_| MassData {
+            position: Vector2::from_fn(|_, _| rng.gen_range(-900.0..900.0)),
+            mass: rng.gen_range(1.0..10.0),
+        })
+        .collect()
+}
+
+/// A few dense clumps, so cells of very different sizes interact.
+fn clustered(rng: &mut XorShiftRng, count: usize) -> Vec<MassData> {
+    let clumps: Vec<(Vector2<Real>, Real)> = (0..5)
+        .map(|_| {
+            let center = Vector2::from_fn(|_, _| rng.gen_range(-600.0..600.0));
+            (center, rng.gen_range(10.0..150.0))
+        })
+        .collect();
+    (0..count)
+        .map(|i| {
+            let (center, radius) = clumps[i % clumps.len()];
+            let offset = Vector2::from_fn(|_, _| rng.gen_range(-1.0..1.0));
+            MassData {
+                position: center + offset * radius,
+                mass: rng.gen_range(1.0..100.0),
+            }
+        })
+        .collect()
+}
+
+fn config(theta: Real) -> SimulationConfig {
+    SimulationConfig {
+        theta,
+        near_field: None,
+        ..SimulationConfig::default()
+    }
+}
+
+/// Mean and max relative error of `forces` against the direct sum.
+fn errors(bodies: &[MassData], forces: &[Vector2<Real>]) -> (f64, f64) {
+    let direct = DirectSum::new(bodies.to_vec());
+    let exact = direct.forces(&config(0.0));
+    let errors: Vec<f64> = forces
+        .iter()
+        .zip(&exact)
+        .map(|(force, exact)| (force - exact).norm() as f64 / exact.norm() as f64)
+        .collect();
+    let mean = errors.iter().sum::<f64>() / errors.len() as f64;
+    (mean, errors.into_iter().fold(0.0, f64::max))
+}
+
+#[test]
+fn fmm_without_approximation_matches_the_direct_sum() {
+    let bodies = uniform(&mut XorShiftRng::seed_from_u64(0xf3300), 1000);
+
+    let (mean, max) = errors(&bodies, &Fmm.forces(&bodies, &config(0.0)));
+
+    assert!(mean < 1e-4, "mean error {:.2e}", mean);
+    assert!(max < 1e-2, "max error {:.2e}", max);
+}
+
+#[test]
+fn fmm_error_is_below_the_tree_tolerance() {
+    for (name, bodies) in [
+        (
+            "uniform",
+            uniform(&mut XorShiftRng::seed_from_u64(0xf3301), 2000),
+        ),
+        (
+            "clustered",
+            clustered(&mut XorShiftRng::seed_from_u64(0xf3302), 2000),
+        ),
+    ] {
+        for theta in [0.25, Simulation::THETA] {
+            let (mean, _) = errors(&bodies, &Fmm.forces(&bodies, &config(theta)));
+            // the tolerance of the monopole trees in tests/direct.rs
+            assert!(
+                mean < 0.1 * (theta as f64).powi(2),
+                "{} at theta {}: mean error {:.2e}",
+                name,
+                theta,
+                mean
+            );
+        }
+    }
+}
+
+#[test]
+fn barnes_hut_solver_walks_the_tree() {
+    let mut bodies = uniform(&mut XorShiftRng::seed_from_u64(0xf3303), 500);
+    let config = config(Simulation::THETA);
+    // outside of the domain
+    bodies.push(MassData {
+        position: Vector2::repeat(Simulation::SCALE),
+        mass: 1.0,
+    });
+
+    let forces = BarnesHut.forces(&bodies, &config);
+
+    let mut tree = FlatTree::bounding(&config.domain, 2 * bodies.len());
+    bodies[..500].iter().for_each(|body| tree.insert(body));
+    tree.summarize();
+    for (body, force) in bodies[..500].iter().zip(&forces) {
+        assert_eq!(*force, tree.force_on(body, &config));
+    }
+    assert_eq!(forces[500], Vector2::zeros());
+}
+
+#[test]
+fn simulations_with_fmm_follow_the_tree_walk() {
+    let bodies = clustered(&mut XorShiftRng::seed_from_u64(0xf3304), 1000);
+    let stars = bodies
+        .iter()
+        .map(|body| Star::new(body.position, Vector2::zeros(), body.mass));
+    let mut tree = Simulation::new(stars.clone());
+    let mut fmm = Simulation::new(stars);
+    fmm.set_force_solver(Some(Box::new(Fmm)));
+
+    tree.update();
+    fmm.update();
+
+    // starting at rest with a time step of 1, the momenta are the forces of the update
+    let momenta = |simulation: &Simulation| -> Vec<_> {
+        simulation
+            .stars
+            .iter()
+            .map(|star| star.vel * star.mass())
+            .collect()
+    };
+    let (fmm_mean, _) = errors(&bodies, &momenta(&fmm));
+    let (tree_mean, _) = errors(&bodies, &momenta(&tree));
+    assert!(
+        fmm_mean <= tree_mean,
+        "{:.2e} vs {:.2e} of the tree",
+        fmm_mean,
+        tree_mean
+    );
+}
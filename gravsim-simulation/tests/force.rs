@@ -0,0 +1,50 @@
+use gravsim_simulation::force::ForceTerm;
+use gravsim_simulation::tree::FlatTree;
+use gravsim_simulation::{Real, Simulation, Star};
+use nalgebra::Vector2;
+
+struct Drag(Real);
+
+impl ForceTerm for Drag {
+    fn acceleration(&self, star: &Star, _: &FlatTree) -> Vector2<Real> {
+        -star.vel * self.0
+    }
+}
+
+/// Pulls every star towards the center of mass of the whole tree.
+struct Anchor;
+
+impl ForceTerm for Anchor {
+    fn acceleration(&self, star: &Star, tree: &FlatTree) -> Vector2<Real> {
+        (tree.root().center_of_mass().position - star.pos()) * 1e-3
+    }
+}
+
+#[test]
+fn drag_slows_stars_down() {
+    let mut simulation =
+        Simulation::new([Star::new(Vector2::zeros(), Vector2::new(1.0, 0.0), 1.0)]);
+    simulation.add_force(Box::new(Drag(0.5)));
+
+    simulation.update();
+    assert_eq!(simulation.stars[0].vel, Vector2::new(0.5, 0.0));
+    assert_eq!(*simulation.stars[0].pos(), Vector2::new(0.5, 0.0));
+
+    // clones keep their forces
+    let mut clone = simulation.clone();
+    clone.update();
+    assert_eq!(clone.stars[0].vel, Vector2::new(0.25, 0.0));
+}
+
+#[test]
+fn forces_can_query_the_tree() {
+    let mut simulation = Simulation::new([
+        Star::new(Vector2::new(-100.0, 0.0), Vector2::zeros(), 1e-6),
+        Star::new(Vector2::new(100.0, 0.0), Vector2::zeros(), 1e-6),
+    ]);
+    simulation.add_force(Box::new(Anchor));
+
+    simulation.update();
+    assert!(simulation.stars[0].vel.x > 0.09);
+    assert!(simulation.stars[1].vel.x < -0.09);
+}
@@ -0,0 +1,193 @@
+//! Runs built-in setups and the shipped `scenarios` for a fixed number of steps and compares
+//! summary statistics against the golden values in `tests/golden`.
+//! Run with `GRAVSIM_BLESS=1` to regenerate the golden values after intended physics changes.
+//! Golden values are for single precision, the test is skipped with the `f64` feature.
+
+#![cfg(not(feature = "f64"))]
+
+use gravsim_simulation::diagnostics::Diagnostics;
+use gravsim_simulation::scenario::Scenario;
+use gravsim_simulation::{MassData, Simulation, Star};
+use nalgebra::Vector2;
+use rand::{Rng, SeedableRng};
+use rand_xorshift::XorShiftRng;
+use std::path::PathBuf;
+
+const STEPS: usize = 20;
+const RELATIVE_TOLERANCE: f64 = 1e-4;
+/// Shells of equally many stars around the center of mass, by distance.
+const SHELLS: usize = 4;
+
+/// 1000 uniformly distributed stars at rest, collapsing.
+fn uniform_1k() -> Simulation {
+    let objs: Vec<MassData> =
+        bincode::deserialize(include_bytes!("../benches/test_data/stars_1k.bin")).unwrap();
+
+    Simulation::new(
+        objs.iter()
+            .map(|obj| Star::new(obj.position, Vector2::zeros(), obj.mass)),
+    )
+}
+
+/// A disc of stars on circular orbits around a light center. The stars hold most of the mass,
+/// so the Lagrangian radii follow its mass profile.
+fn disc() -> Simulation {
+    let mut rng = XorShiftRng::seed_from_u64(0xd15c);
+    let center = Star::new(Vector2::zeros(), Vector2::zeros(), 100.0);
+
+    // angle, distance and mass, from the center outwards
+    let mut bodies: Vec<(f32, f32, f32)> = (0..500)
+        .map(|_| {
+            let a = rng.gen::<f32>() * std::f32::consts::TAU;
+            let d = 50.0 + rng.gen::<f32>().sqrt() * 500.0;
+            (a, d, 1.0 + rng.gen::<f32>() * 10.0)
+        })
+        .collect();
+    bodies.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+    // orbits are circular for the mass inside of them
+    let mut enclosed = center.mass();
+    let stars = bodies.into_iter().map(|(a, d, mass)| {
+        enclosed += mass;
+        let direction = Vector2::new(a.cos(), a.sin());
+        let velocity = (Simulation::GRAVITY * enclosed / d).sqrt();
+        Star::new(
+            direction * d,
+            Vector2::new(-direction.y, direction.x) * velocity,
+            mass,
+        )
+    });
+    Simulation::new([center].into_iter().chain(stars))
+}
+
+/// A scenario of `scenarios`, seeded so its stars are the same in every run.
+fn scenario(name: &str) -> Simulation {
+    let path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "..", "scenarios", name]
+        .iter()
+        .collect::<PathBuf>()
+        .with_extension("toml");
+    let mut scenario = Scenario::from_path(path).unwrap();
+    scenario.seed = Some(0);
+    scenario.to_simulation()
+}
+
+/// Velocity dispersion in each of the `SHELLS`, from the innermost outwards.
+fn velocity_dispersions(simulation: &Simulation, center: Vector2<f64>) -> Vec<f64> {
+    let mut stars: Vec<(f64, Vector2<f64>)> = simulation
+        .stars
+        .iter()
+        .map(|star| {
+            let position = star.pos().cast::<f64>();
+            ((position - center).norm(), star.vel.cast::<f64>())
+        })
+        .collect();
+    stars.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let shell = stars.len().div_ceil(SHELLS).max(1);
+    stars
+        .chunks(shell)
+        .map(|shell| {
+            let count = shell.len() as f64;
+            let mean = shell.iter().map(|(_, vel)| vel).sum::<Vector2<f64>>() / count;
+            let variance = shell
+                .iter()
+                .map(|(_, vel)| (vel - mean).norm_squared())
+                .sum::<f64>()
+                / count;
+            variance.sqrt()
+        })
+        .collect()
+}
+
+fn summary(simulation: &Simulation) -> Vec<(String, f64)> {
+    let diagnostics = simulation.diagnostics();
+
+    let mut values = vec![
+        ("kinetic_energy".to_string(), diagnostics.kinetic_energy),
+        ("potential_energy".to_string(), diagnostics.potential_energy),
+        ("center_of_mass_x".to_string(), diagnostics.center_of_mass.x),
+        ("center_of_mass_y".to_string(), diagnostics.center_of_mass.y),
+    ];
+    for (fraction, radius) in Diagnostics::LAGRANGIAN_FRACTIONS
+        .iter()
+        .zip(&diagnostics.lagrangian_radii)
+    {
+        values.push((format!("lagrangian_radius_{}", fraction), *radius));
+    }
+    let dispersions = velocity_dispersions(simulation, diagnostics.center_of_mass);
+    for (shell, dispersion) in dispersions.into_iter().enumerate() {
+        values.push((format!("velocity_dispersion_{}", shell), dispersion));
+    }
+    for (id, probe) in simulation.probes.iter().enumerate() {
+        values.push((format!("probe_{}_x", id), probe.pos.x as f64));
+        values.push((format!("probe_{}_y", id), probe.pos.y as f64));
+    }
+    values
+}
+
+fn check(name: &str, mut simulation: Simulation) {
+    for _ in 0..STEPS {
+        simulation.update();
+    }
+    let actual = summary(&simulation);
+
+    let path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "tests", "golden", name]
+        .iter()
+        .collect::<PathBuf>()
+        .with_extension("txt");
+
+    if std::env::var_os("GRAVSIM_BLESS").is_some() {
+        let contents: String = actual
+            .iter()
+            .map(|(key, value)| format!("{} {:e}\n", key, value))
+            .collect();
+        std::fs::write(&path, contents).unwrap();
+        return;
+    }
+
+    let golden = std::fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("failed to read {:?}: {}", path, e));
+    let golden: Vec<(&str, f64)> = golden
+        .lines()
+        .filter_map(|line| line.split_once(' '))
+        .map(|(key, value)| (key, value.parse().unwrap()))
+        .collect();
+
+    assert_eq!(golden.len(), actual.len(), "{}: statistics changed", name);
+    for ((golden_key, expected), (key, value)) in golden.iter().zip(&actual) {
+        assert_eq!(golden_key, key);
+        assert!(
+            (value - expected).abs() <= RELATIVE_TOLERANCE * expected.abs(),
+            "{}: {} is {}, expected {}",
+            name,
+            key,
+            value,
+            expected
+        );
+    }
+}
+
+#[test]
+fn golden_uniform_1k() {
+    check("uniform_1k", uniform_1k());
+}
+
+#[test]
+fn golden_disc() {
+    check("disc", disc());
+}
+
+#[test]
+fn golden_two_galaxies() {
+    check("two_galaxies", scenario("two_galaxies"));
+}
+
+#[test]
+fn golden_collision() {
+    check("collision", scenario("collision"));
+}
+
+#[test]
+fn golden_gravity_assist() {
+    check("gravity_assist", scenario("gravity_assist"));
+}
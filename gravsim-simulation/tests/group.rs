@@ -0,0 +1,137 @@
+use gravsim_simulation::group::{DisruptionMonitor, Group, PericenterMonitor};
+use gravsim_simulation::schedule::Action;
+use gravsim_simulation::{Real, Simulation, Star};
+use nalgebra::Vector2;
+
+/// A center of `mass` with light stars on circular orbits around it.
+fn cluster(center: Vector2<Real>, stars: usize, mass: Real) -> Vec<Star> {
+    let mut cluster = vec![Star::new(center, Vector2::zeros(), mass)];
+    cluster.extend((0..stars).map(|i| {
+        let angle = i as Real / stars as Real * std::f32::consts::TAU as Real;
+        let offset = Vector2::new(angle.cos(), angle.sin()) * 100.0;
+        let speed = (Simulation::GRAVITY * mass / 100.0).sqrt();
+        Star::new(
+            center + offset,
+            Vector2::new(-offset.y, offset.x) / 100.0 * speed,
+            1.0,
+        )
+    }));
+    cluster
+}
+
+fn group(stars: usize) -> Group {
+    Group {
+        name: "cluster".to_string(),
+        stars: (0..stars).collect(),
+    }
+}
+
+#[test]
+fn orbiting_stars_are_bound() {
+    let stars = cluster(Vector2::new(50.0, 0.0), 20, 1e4);
+    let state = group(stars.len()).bound_state(&stars, Simulation::GRAVITY);
+    assert_eq!(state.mass, 1e4 + 20.0);
+    assert!((state.center_of_mass - Vector2::new(50.0, 0.0)).norm() < 1e-3);
+}
+
+#[test]
+fn losing_bound_mass_is_reported_once() {
+    // as heavy as its stars, so losing stars matters
+    let mut stars = cluster(Vector2::zeros(), 20, 20.0);
+    let mut monitor = DisruptionMonitor::new(vec![group(stars.len())], 0.2, 10.0);
+    assert!(monitor.record(&stars, Simulation::GRAVITY, 0.0).is_empty());
+
+    // kick out 5 pairs of opposite stars, which keeps the center of mass at rest
+    let (first, second) = stars.split_at_mut(11);
+    for star in first[1..6].iter_mut().chain(&mut second[..5]) {
+        star.vel *= 100.0;
+    }
+    let disruptions = monitor.record(&stars, Simulation::GRAVITY, 1.0);
+    assert_eq!(disruptions.len(), 1);
+    assert_eq!(disruptions[0].group, 0);
+    assert!((disruptions[0].lost_fraction - 0.25).abs() < 1e-6);
+
+    assert!(monitor.record(&stars, Simulation::GRAVITY, 2.0).is_empty());
+}
+
+fn single(name: &str, id: usize) -> Group {
+    Group {
+        name: name.to_string(),
+        stars: vec![id],
+    }
+}
+
+#[test]
+fn closest_approaches_are_reported_after_they_passed() {
+    let mut monitor = PericenterMonitor::new(vec![single("a", 0), single("b", 1)], 3.0);
+    let at = |distance: Real| {
+        [
+            Star::new(Vector2::zeros(), Vector2::zeros(), 1.0),
+            Star::new(Vector2::new(distance, 0.0), Vector2::zeros(), 1.0),
+        ]
+    };
+
+    // closing in at 20 per update is near once the passage is 3 updates away
+    let mut near = Vec::new();
+    for (time, distance) in [(0.0, 100.0), (1.0, 80.0), (2.0, 60.0), (3.0, 50.0)] {
+        assert!(monitor
+            .record(&at(distance), Simulation::GRAVITY, time)
+            .is_empty());
+        near.push(monitor.is_near(time));
+    }
+    assert_eq!(near, [false, false, true, true]);
+
+    let pericenters = monitor.record(&at(55.0), Simulation::GRAVITY, 4.0);
+    assert_eq!(pericenters.len(), 1);
+    assert_eq!(pericenters[0].groups, [0, 1]);
+    assert_eq!(pericenters[0].time, 3.0);
+    assert_eq!(pericenters[0].distance, 50.0);
+    assert_eq!(pericenters[0].position, Vector2::new(25.0, 0.0));
+
+    // near until the window after the passage
+    assert!(monitor
+        .record(&at(70.0), Simulation::GRAVITY, 6.0)
+        .is_empty());
+    assert!(monitor.is_near(6.0));
+    assert!(monitor
+        .record(&at(90.0), Simulation::GRAVITY, 7.0)
+        .is_empty());
+    assert!(!monitor.is_near(7.0));
+}
+
+#[test]
+fn passages_of_orbiting_groups_fire_markers() {
+    // an eccentric binary just past apocenter
+    let mut simulation = Simulation::new([
+        Star::new(Vector2::new(-500.0, 0.0), Vector2::new(0.03, -0.1), 1e6),
+        Star::new(Vector2::new(500.0, 0.0), Vector2::new(-0.03, 0.1), 1e6),
+    ]);
+    simulation.pericenter_monitor = Some(PericenterMonitor::new(
+        vec![single("primary", 0), single("secondary", 1)],
+        100.0,
+    ));
+
+    let mut closest = (Real::INFINITY, 0.0);
+    let mut passages = Vec::new();
+    // about half an orbit
+    for _ in 0..4000 {
+        simulation.update();
+        let distance = (simulation.stars[1].pos() - simulation.stars[0].pos()).norm();
+        if distance < closest.0 {
+            closest = (distance, simulation.time);
+        }
+        passages.extend(
+            simulation
+                .pericenters
+                .iter()
+                .map(|pericenter| pericenter.time),
+        );
+        if !simulation.pericenters.is_empty() {
+            assert!(simulation.fired.iter().any(|event| matches!(
+                &event.action,
+                Action::Marker(name) if name == "pericenter of primary and secondary"
+            )));
+        }
+    }
+    assert_eq!(passages, [closest.1]);
+}
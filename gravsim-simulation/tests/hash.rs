@@ -0,0 +1,45 @@
+use gravsim_simulation::{Real, Simulation, Star};
+use nalgebra::Vector2;
+
+fn simulation() -> Simulation {
+    Simulation::new((0..100).map(|i| {
+        let a = i as Real * 0.1;
+        Star::new(
+            Vector2::new(a.cos(), a.sin()) * (10.0 + i as Real),
+            Vector2::new(-a.sin(), a.cos()),
+            1.0 + i as Real,
+        )
+    }))
+}
+
+#[test]
+fn equal_states_hash_equally() {
+    let mut a = simulation();
+    let mut b = simulation();
+    assert_eq!(a.state_hash(), b.state_hash());
+
+    for _ in 0..10 {
+        a.update();
+        b.update();
+    }
+    assert_eq!(a.state_hash(), b.state_hash());
+}
+
+#[test]
+fn hash_detects_changes() {
+    let a = simulation();
+    let mut b = simulation();
+    b.stars[42].vel.x += 1e-3;
+
+    assert_ne!(a.state_hash(), b.state_hash());
+}
+
+#[test]
+fn quantized_hash_tolerates_rounding() {
+    let a = simulation();
+    let mut b = simulation();
+    b.stars[42].mass_point.position.x += 1e-6;
+
+    assert_ne!(a.state_hash(), b.state_hash());
+    assert_eq!(a.state_hash_quantized(1e-2), b.state_hash_quantized(1e-2));
+}
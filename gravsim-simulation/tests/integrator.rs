@@ -0,0 +1,47 @@
+use gravsim_simulation::integrator::{Euler, Integrator, Leapfrog, RungeKutta4, Verlet};
+use gravsim_simulation::{Simulation, Star};
+use nalgebra::Vector2;
+
+/// Relative energy drift of an eccentric orbit around a heavy star after many orbits.
+fn energy_drift(integrator: impl Integrator + 'static) -> f64 {
+    let mut simulation = Simulation::new([
+        Star::new(Vector2::zeros(), Vector2::zeros(), 1e4),
+        Star::new(Vector2::new(20.0, 0.0), Vector2::new(0.0, 0.1), 1e-3),
+    ]);
+    simulation.set_integrator(Box::new(integrator));
+
+    let initial = simulation.diagnostics().total_energy();
+    for _ in 0..5000 {
+        simulation.update();
+    }
+    ((simulation.diagnostics().total_energy() - initial) / initial).abs()
+}
+
+#[test]
+fn higher_order_integrators_conserve_energy_better() {
+    let euler = energy_drift(Euler);
+    for (name, drift) in [
+        ("leapfrog", energy_drift(Leapfrog)),
+        ("verlet", energy_drift(Verlet)),
+        ("rk4", energy_drift(RungeKutta4)),
+    ] {
+        assert!(drift * 10.0 < euler, "{name}: {drift} vs euler: {euler}");
+    }
+}
+
+#[test]
+fn euler_is_the_default() {
+    let stars = [
+        Star::new(Vector2::new(-50.0, 0.0), Vector2::new(0.0, 0.2), 1e3),
+        Star::new(Vector2::new(50.0, 0.0), Vector2::new(0.0, -0.2), 1e3),
+    ];
+    let mut default = Simulation::new(stars);
+    let mut euler = Simulation::new(stars);
+    euler.set_integrator(Box::new(Euler));
+
+    for _ in 0..10 {
+        default.update();
+        euler.update();
+    }
+    assert_eq!(default.state_hash(), euler.state_hash());
+}
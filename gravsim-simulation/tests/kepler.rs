@@ -0,0 +1,46 @@
+use gravsim_simulation::kepler::Orbit;
+use gravsim_simulation::{Real, Simulation, Star};
+use nalgebra::Vector2;
+
+/// Two stars of mass 1 at distance `r`, moving with relative speed `v` perpendicular to it.
+fn pair(r: Real, v: Real) -> (Star, Star) {
+    (
+        Star::new(Vector2::zeros(), Vector2::zeros(), 1.0),
+        Star::new(Vector2::new(r, 0.0), Vector2::new(0.0, v), 1.0),
+    )
+}
+
+#[test]
+fn circular_orbits_have_no_eccentricity() {
+    let gravity = Simulation::GRAVITY;
+    let r = 10.0;
+    // circular speed of the relative orbit
+    let v = (gravity * 2.0 / r).sqrt();
+    let (a, b) = pair(r, v);
+
+    let orbit = Orbit::of(&a, &b, gravity).unwrap();
+    assert!(orbit.is_bound());
+    assert!(orbit.eccentricity < 1e-3, "{:?}", orbit);
+    assert!((orbit.semi_major_axis - r as f64).abs() < 1e-2);
+    assert!((orbit.pericenter - r as f64).abs() < 1e-2);
+    let period = 2.0 * std::f64::consts::PI * r as f64 / v as f64;
+    assert!((orbit.period.unwrap() / period - 1.0).abs() < 1e-3);
+}
+
+#[test]
+fn fast_pairs_are_unbound() {
+    let gravity = Simulation::GRAVITY;
+    let r = 10.0;
+    let escape = (2.0 * gravity * 2.0 / r).sqrt();
+    let (a, b) = pair(r, escape * 1.5);
+
+    let orbit = Orbit::of(&a, &b, gravity).unwrap();
+    assert!(!orbit.is_bound());
+    assert!(orbit.eccentricity > 1.0);
+    assert!(orbit.semi_major_axis < 0.0);
+    assert_eq!(orbit.apocenter(), None);
+    // launched perpendicular, so this is the closest approach
+    assert!((orbit.pericenter - r as f64).abs() < 1e-2);
+
+    assert_eq!(Orbit::of(&a, &a, gravity), None);
+}
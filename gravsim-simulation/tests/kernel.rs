@@ -0,0 +1,83 @@
+use gravsim_simulation::kernel::{self, InstructionSet};
+use gravsim_simulation::{MassData, Real, Simulation};
+use nalgebra::Vector2;
+use rand::{Rng, SeedableRng};
+use rand_xorshift::XorShiftRng;
+
+fn sources(rng: &mut XorShiftRng, count: usize) -> Vec<MassData> {
+    (0..count)
+        .map(|_| MassData {
+            position: Vector2::from_fn(|_, _| rng.gen_range(-900.0..900.0)),
+            mass: rng.gen_range(1.0..10.0),
+        })
+        .collect()
+}
+
+#[test]
+fn instruction_sets_agree_bit_for_bit() {
+    let mut rng = XorShiftRng::seed_from_u64(0x4b31);
+    // counts around the lane width, to cover the remainder
+    for count in [0, 1, 15, 16, 17, 100, 1003] {
+        let sources = sources(&mut rng, count);
+        let pos = Vector2::from_fn(|_, _| rng.gen_range(-900.0..900.0));
+        let scalar = InstructionSet::Scalar.force_part(&pos, &sources, Simulation::SOFTENING);
+
+        for set in InstructionSet::ALL
+            .into_iter()
+            .filter(|set| set.is_supported())
+        {
+            let force = set.force_part(&pos, &sources, Simulation::SOFTENING);
+            assert_eq!(
+                force.x.to_bits(),
+                scalar.x.to_bits(),
+                "{:?}, {}",
+                set,
+                count
+            );
+            assert_eq!(
+                force.y.to_bits(),
+                scalar.y.to_bits(),
+                "{:?}, {}",
+                set,
+                count
+            );
+        }
+    }
+}
+
+#[test]
+fn matches_the_serial_sum() {
+    let mut rng = XorShiftRng::seed_from_u64(0x4b32);
+    let sources = sources(&mut rng, 1000);
+    let pos = Vector2::new(12.0, -34.0);
+
+    let mut serial = Vector2::<f64>::zeros();
+    for source in &sources {
+        let diff = (source.position - pos).cast::<f64>();
+        let dist = (diff.norm_squared() + (Simulation::SOFTENING as f64).powi(2)).sqrt();
+        serial += diff * source.mass as f64 / dist.powi(3);
+    }
+
+    let force = kernel::force_part(&pos, &sources, Simulation::SOFTENING).cast::<f64>();
+    let error = (force - serial).norm() / serial.norm();
+    assert!(error < 1e-5, "relative error {}", error);
+}
+
+#[test]
+fn sources_at_the_position_are_skipped() {
+    let pos = Vector2::new(3.0, 4.0);
+    let sources = [
+        MassData {
+            position: pos,
+            mass: 100.0,
+        },
+        MassData {
+            position: Vector2::zeros(),
+            mass: 1.0,
+        },
+    ];
+
+    let force = kernel::force_part(&pos, &sources, 0.0);
+    let expected: Vector2<Real> = -pos / 125.0;
+    assert!((force - expected).norm() < 1e-6, "{}", force);
+}
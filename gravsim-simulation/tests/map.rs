@@ -0,0 +1,79 @@
+use gravsim_simulation::fits::{self, Units};
+use gravsim_simulation::map::{Grid, Map, Quantity};
+use gravsim_simulation::{Real, Simulation, SimulationConfig, Star};
+use nalgebra::Vector2;
+
+fn grid() -> Grid {
+    Grid {
+        min: Vector2::new(-100.0, -50.0),
+        pixel_size: 10.0,
+        width: 20,
+        height: 10,
+    }
+}
+
+#[test]
+fn surface_density_keeps_the_mass_in_the_grid() {
+    let stars = [
+        Star::new(Vector2::new(-95.0, -45.0), Vector2::zeros(), 100.0),
+        Star::new(Vector2::new(55.0, 25.0), Vector2::zeros(), 300.0),
+        Star::new(Vector2::new(59.0, 21.0), Vector2::zeros(), 100.0),
+        // outside of the grid
+        Star::new(Vector2::new(500.0, 0.0), Vector2::zeros(), 1000.0),
+    ];
+
+    let map = Map::surface_density(&stars, grid());
+
+    assert_eq!(map.quantity, Quantity::SurfaceDensity);
+    assert_eq!(map.value(0, 0), 1.0);
+    assert_eq!(map.value(15, 7), 4.0);
+    let mass: f64 = map.values.iter().sum::<f64>() * 100.0;
+    assert_eq!(mass, 500.0);
+}
+
+#[test]
+fn potential_of_a_single_star() {
+    let star = Star::new(Vector2::new(0.0, 0.0), Vector2::zeros(), 1e4);
+    let config = SimulationConfig::default();
+
+    let map = Map::potential(&[star], &config, grid());
+
+    let center = map.grid.center(3, 2);
+    let dist_sq = center.norm_squared();
+    let expected =
+        -Simulation::GRAVITY * star.mass() / (Simulation::SOFTENING.powi(2) + dist_sq).sqrt();
+    assert!(((map.value(3, 2) as Real - expected) / expected).abs() < 1e-5);
+    assert!(map.value(10, 5) < map.value(0, 0));
+}
+
+#[test]
+fn fits_files_have_a_valid_layout() {
+    let star = Star::new(Vector2::new(-95.0, -45.0), Vector2::zeros(), 100.0);
+    let map = Map::surface_density(&[star], grid());
+    let units = Units {
+        length: 0.001,
+        length_unit: "kpc".to_string(),
+        mass: 2.0,
+        mass_unit: "solMass".to_string(),
+        ..Units::default()
+    };
+
+    let bytes = fits::encode(&map, &units, 123.0);
+
+    assert_eq!(bytes.len() % 2880, 0);
+    let cards: Vec<_> = bytes[..2880]
+        .chunks(80)
+        .map(|card| std::str::from_utf8(card).unwrap())
+        .collect();
+    let has_card = |start: &str| cards.iter().any(|card| card.starts_with(start));
+    assert!(cards[0].starts_with("SIMPLE  =                    T"));
+    assert!(has_card("NAXIS1  =                   20"));
+    assert!(has_card("BUNIT   = 'solMass kpc-2'"));
+    assert!(has_card("CUNIT1  = 'kpc     '"));
+    assert!(cards.iter().any(|card| card.trim_end() == "END"));
+
+    // the first pixel, in solMass / kpc² and big endian
+    let first = f32::from_be_bytes(bytes[2880..2884].try_into().unwrap());
+    assert!((first - 2e6).abs() < 1.0, "{}", first);
+    assert_eq!(bytes.len(), 2880 + 2880);
+}
@@ -0,0 +1,80 @@
+use gravsim_simulation::morton;
+use gravsim_simulation::tree::Aabb;
+use gravsim_simulation::{Real, Simulation, SimulationConfig, Star, StarId};
+use nalgebra::Vector2;
+use rand::{Rng, SeedableRng};
+use rand_xorshift::XorShiftRng;
+
+fn stars(rng: &mut XorShiftRng, count: usize) -> Vec<Star> {
+    (0..count)
+        .map(|_| {
+            Star::new(
+                Vector2::from_fn(|_, _| rng.gen_range(-900.0..900.0)),
+                Vector2::from_fn(|_, _| rng.gen_range(-1.0..1.0)),
+                rng.gen_range(1.0..10.0),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn codes_follow_the_quadrants() {
+    let domain = Aabb::centered(Vector2::repeat(100.0));
+    let code = |x: Real, y: Real| morton::code(&domain, &Vector2::new(x, y)).unwrap();
+
+    assert_eq!(code(-50.0, -50.0), 0);
+    assert!(code(-25.0, -25.0) < code(25.0, -25.0));
+    assert!(code(25.0, -25.0) < code(-25.0, 25.0));
+    assert!(code(-25.0, 25.0) < code(25.0, 25.0));
+    // within a quadrant, its own quadrants come first
+    assert!(code(-1.0, -1.0) < code(1.0, -49.0));
+    assert_eq!(morton::code(&domain, &Vector2::new(50.0, 0.0)), None);
+}
+
+#[test]
+fn removed_stars_are_sorted_last() {
+    let mut simulation = Simulation::new(stars(&mut XorShiftRng::seed_from_u64(0x3047), 100));
+    simulation.stars[10].mass_point.position = Vector2::from_element(Real::NAN);
+    simulation.stars[20].mass_point.position = Vector2::repeat(Simulation::SCALE);
+
+    let order = simulation.sort_stars();
+
+    assert_eq!(order[98..], [10, 20]);
+    let codes: Vec<_> = simulation.stars[..98]
+        .iter()
+        .map(|star| morton::code(&simulation.config.domain, star.pos()).unwrap())
+        .collect();
+    assert!(codes.windows(2).all(|pair| pair[0] <= pair[1]));
+}
+
+#[test]
+fn sorting_keeps_the_stars_and_their_ids_in_sync() {
+    let initial = stars(&mut XorShiftRng::seed_from_u64(0x3048), 500);
+    let config = SimulationConfig {
+        sort_every: Some(3),
+        ..SimulationConfig::default()
+    };
+    let mut sorted = Simulation::with_config(initial.iter().copied(), config);
+    sorted.species = (0..initial.len()).map(|id| (id % 3) as u16).collect();
+    let mut plain = Simulation::new(initial.iter().copied());
+
+    // the original id of the star at each id of `sorted`
+    let mut original: Vec<StarId> = (0..initial.len()).collect();
+    for step in 0..10 {
+        sorted.update();
+        plain.update();
+
+        assert_eq!(sorted.sorted.is_some(), step % 3 == 0, "step {}", step);
+        if let Some(order) = &sorted.sorted {
+            original = order.iter().map(|&id| original[id]).collect();
+        }
+    }
+
+    for (id, star) in sorted.stars.iter().enumerate() {
+        let reference = &plain.stars[original[id]];
+        let distance = (star.pos() - reference.pos()).norm();
+        assert!(distance < 1e-2, "star {} is {} off", id, distance);
+        assert_eq!(star.mass(), reference.mass());
+        assert_eq!(sorted.species[id], (original[id] % 3) as u16);
+    }
+}
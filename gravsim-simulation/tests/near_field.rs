@@ -0,0 +1,66 @@
+use gravsim_simulation::{near_field, Real, Simulation, SimulationConfig, Star};
+use nalgebra::Vector2;
+use rand::{Rng, SeedableRng};
+use rand_xorshift::XorShiftRng;
+
+fn stars(seed: u64, n: usize) -> Vec<Star> {
+    let mut rng = XorShiftRng::seed_from_u64(seed);
+    (0..n)
+        .map(|_| {
+            let pos = Vector2::from_fn(|_, _| rng.gen_range(-200.0..200.0));
+            Star::new(pos, Vector2::zeros(), rng.gen_range(1.0..500.0))
+        })
+        .collect()
+}
+
+/// Velocities after one update from rest, i.e. the accelerations.
+fn accelerations(stars: &[Star], config: SimulationConfig) -> Vec<Vector2<Real>> {
+    let mut simulation = Simulation::with_config(stars.iter().copied(), config);
+    simulation.update();
+    simulation.stars.iter().map(|star| star.vel).collect()
+}
+
+#[test]
+fn near_field_matches_the_tree() {
+    let stars = stars(5, 500);
+    let exact = SimulationConfig {
+        theta: 0.0,
+        ..SimulationConfig::default()
+    };
+    let tree = accelerations(&stars, exact);
+    let split = accelerations(
+        &stars,
+        SimulationConfig {
+            near_field: Some(40.0),
+            ..exact
+        },
+    );
+
+    for (a, b) in split.iter().zip(&tree) {
+        assert!((a - b).norm() <= 1e-3 * b.norm(), "{} != {}", a, b);
+    }
+}
+
+#[test]
+fn near_field_conserves_momentum() {
+    let stars = stars(6, 300);
+    let bodies: Vec<_> = (0..stars.len()).collect();
+    let config = SimulationConfig {
+        gravity: 1.0,
+        ..SimulationConfig::default()
+    };
+    let accelerations = near_field::accelerations(&stars, &bodies, 60.0, &config);
+
+    let momentum: Vector2<Real> = stars
+        .iter()
+        .zip(&accelerations)
+        .map(|(star, acceleration)| acceleration * star.mass())
+        .sum();
+    let scale: Real = stars
+        .iter()
+        .zip(&accelerations)
+        .map(|(star, acceleration)| acceleration.norm() * star.mass())
+        .sum();
+    assert!(momentum.norm() <= 1e-4 * scale);
+    assert!(accelerations.iter().any(|a| a.norm() > 0.0));
+}
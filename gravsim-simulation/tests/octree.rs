@@ -0,0 +1,66 @@
+use gravsim_simulation::octree::{Octant, Octree};
+use gravsim_simulation::three_d::{MassData3, Simulation3, Star3};
+use gravsim_simulation::{Real, Simulation, Star};
+use nalgebra::{Vector2, Vector3};
+
+#[test]
+fn octant_offsets_round_trip() {
+    for bits in 0..8u8 {
+        let octant = Octant::try_from(bits).unwrap();
+        let offset = (octant.offset() * 2.0).add_scalar(0.5);
+        assert_eq!(Octant::from_offset(&offset, 4.0), octant);
+    }
+}
+
+#[test]
+fn octree_sums_mass() {
+    let mut tree = Octree::new_root(Vector3::repeat(-8.0), 16.0);
+    for i in 0..100 {
+        let t = i as Real * 0.37;
+        tree.insert(&MassData3 {
+            position: Vector3::new(t.sin(), t.cos(), t.sin() * t.cos()) * 7.0,
+            mass: 1.0,
+        });
+    }
+    tree.insert(&MassData3 {
+        position: Vector3::zeros(),
+        mass: 1.0,
+    });
+    tree.insert(&MassData3 {
+        position: Vector3::zeros(),
+        mass: 1.0,
+    });
+    tree.summarize();
+
+    assert_eq!(tree.center_of_mass().mass, 102.0);
+}
+
+#[test]
+fn flat_3d_matches_2d() {
+    let stars = [
+        Star::new(Vector2::new(-20.0, 3.0), Vector2::new(0.0, 0.3), 1e3),
+        Star::new(Vector2::new(15.0, -2.0), Vector2::new(0.0, -0.2), 2e3),
+        Star::new(Vector2::new(1.0, 30.0), Vector2::new(-0.1, 0.0), 5e2),
+    ];
+    let mut simulation = Simulation::new(stars);
+    let mut simulation_3d = Simulation3::new(stars.iter().map(|star| Star3::from_2d(star, 0.0)));
+
+    for _ in 0..10 {
+        simulation.update();
+        simulation_3d.update();
+    }
+
+    for (star, star_3d) in simulation.stars.iter().zip(&simulation_3d.stars) {
+        assert!((star.pos() - star_3d.pos().xy()).norm() < 1e-3);
+        assert_eq!(star_3d.pos().z, 0.0);
+    }
+
+    let (diagnostics, diagnostics_3d) = (simulation.diagnostics(), simulation_3d.diagnostics());
+    assert!((diagnostics.total_energy() - diagnostics_3d.total_energy()).abs() < 1e-6);
+}
+
+#[test]
+fn lifted_stars_keep_their_radius() {
+    let star = Star::new(Vector2::new(1.0, 2.0), Vector2::zeros(), 1000.0);
+    assert_eq!(Star3::from_2d(&star, 5.0).radius(), star.radius());
+}
@@ -0,0 +1,133 @@
+use gravsim_simulation::integrator::Leapfrog;
+use gravsim_simulation::output::{Cadence, Logarithmic, OutputPolicy, Trigger};
+use gravsim_simulation::schedule::{Action, Event, Schedule};
+use gravsim_simulation::{Real, Simulation, Star};
+use nalgebra::Vector2;
+
+fn one_star() -> Simulation {
+    Simulation::new([Star::new(Vector2::zeros(), Vector2::zeros(), 1.0)])
+}
+
+/// Steps after which `policy` writes, within the first `updates` updates of `simulation`.
+fn written(mut simulation: Simulation, policy: OutputPolicy, updates: usize) -> Vec<u64> {
+    let mut cadence = Cadence::new(policy, &simulation);
+    let mut steps = Vec::new();
+    for _ in 0..updates {
+        simulation.update();
+        if cadence.due(&simulation) {
+            steps.push(simulation.step);
+        }
+    }
+    steps
+}
+
+#[test]
+fn fixed_intervals_count_steps_or_simulated_time() {
+    let steps = written(one_star(), OutputPolicy::every_steps(7), 30);
+    assert_eq!(steps, [7, 14, 21, 28]);
+
+    let mut simulation = one_star();
+    simulation.config.dt = 0.1;
+    let policy = OutputPolicy {
+        every_time: Some(1.0),
+        ..OutputPolicy::default()
+    };
+    assert_eq!(written(simulation, policy, 45), [10, 20, 30, 40]);
+}
+
+#[test]
+fn logarithmic_outputs_get_sparser() {
+    let policy = OutputPolicy {
+        logarithmic: Some(Logarithmic {
+            start: 1.0,
+            per_decade: 2,
+        }),
+        ..OutputPolicy::default()
+    };
+    // at 1, 3.16, 10, 31.6, 100, 316 and 1000
+    assert_eq!(
+        written(one_star(), policy, 1000),
+        [1, 4, 10, 32, 100, 317, 1000]
+    );
+}
+
+#[test]
+fn continued_runs_write_the_same_steps() {
+    let policy = OutputPolicy {
+        every_time: Some(5.0),
+        logarithmic: Some(Logarithmic {
+            start: 2.0,
+            per_decade: 3,
+        }),
+        ..OutputPolicy::default()
+    };
+    let whole = written(one_star(), policy.clone(), 200);
+
+    let mut simulation = one_star();
+    for _ in 0..73 {
+        simulation.update();
+    }
+    let rest = written(simulation, policy, 127);
+    assert_eq!(
+        rest,
+        whole[whole.iter().position(|&step| step > 73).unwrap()..]
+    );
+}
+
+#[test]
+fn markers_trigger_outputs() {
+    let mut simulation = one_star();
+    simulation.schedule = Schedule::new([
+        Event {
+            time: 12.0,
+            action: Action::Marker("flyby".into()),
+        },
+        Event {
+            time: 20.0,
+            action: Action::Marker("other".into()),
+        },
+    ]);
+    let policy = OutputPolicy {
+        events: vec![Trigger::Marker("flyby".into())],
+        ..OutputPolicy::default()
+    };
+    assert_eq!(written(simulation, policy, 30), [12]);
+}
+
+#[test]
+fn pericenter_passages_of_the_heaviest_stars_trigger_outputs() {
+    // an eccentric binary just past apocenter, and a light star far away
+    let mut simulation = Simulation::new([
+        Star::new(Vector2::new(-5000.0, 8000.0), Vector2::zeros(), 1.0),
+        Star::new(Vector2::new(-500.0, 0.0), Vector2::new(0.03, -0.1), 1e6),
+        Star::new(Vector2::new(500.0, 0.0), Vector2::new(-0.03, 0.1), 1e6),
+    ]);
+    simulation.set_integrator(Box::new(Leapfrog));
+    let mut cadence = Cadence::new(
+        OutputPolicy {
+            events: vec![Trigger::Pericenter],
+            ..OutputPolicy::default()
+        },
+        &simulation,
+    );
+
+    let mut separations: Vec<Real> = Vec::new();
+    let mut steps = Vec::new();
+    // about half an orbit
+    for _ in 0..4000 {
+        simulation.update();
+        separations.push((simulation.stars[2].pos() - simulation.stars[1].pos()).norm());
+        if cadence.due(&simulation) {
+            steps.push(simulation.step);
+        }
+    }
+
+    // right after the closest approach
+    let closest = separations
+        .iter()
+        .enumerate()
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(i, _)| i as u64 + 1)
+        .unwrap();
+    assert_eq!(steps, [closest + 1]);
+}
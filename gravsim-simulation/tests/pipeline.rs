@@ -0,0 +1,75 @@
+use gravsim_simulation::pipeline::Stage;
+use gravsim_simulation::{Simulation, SimulationConfig, Star};
+use nalgebra::Vector2;
+use std::sync::Arc;
+
+fn without_gravity(stars: impl IntoIterator<Item = Star>) -> Simulation {
+    Simulation::with_config(
+        stars,
+        SimulationConfig {
+            gravity: 0.0,
+            merge_collisions: true,
+            ..SimulationConfig::default()
+        },
+    )
+}
+
+#[test]
+fn removing_gravity_leaves_stars_coasting() {
+    let stars = [
+        Star::new(Vector2::new(-10.0, 0.0), Vector2::new(0.0, 1.0), 1e6),
+        Star::new(Vector2::new(10.0, 0.0), Vector2::new(0.0, -1.0), 1e6),
+    ];
+    let mut simulation = Simulation::new(stars);
+    simulation.pipeline.retain(|stage| *stage != Stage::Gravity);
+
+    simulation.update();
+
+    assert_eq!(simulation.stars[0].vel, Vector2::new(0.0, 1.0));
+    assert_eq!(*simulation.stars[1].pos(), Vector2::new(10.0, -1.0));
+}
+
+#[test]
+fn collisions_can_run_before_integrating() {
+    // overlapping at the start, apart after the step
+    let stars = [
+        Star::new(Vector2::new(0.0, 0.0), Vector2::new(-0.3, 0.0), 100.0),
+        Star::new(Vector2::new(0.5, 0.0), Vector2::new(0.3, 0.0), 100.0),
+    ];
+    let mut after = without_gravity(stars);
+    let mut before = without_gravity(stars);
+    let integrate = before
+        .pipeline
+        .iter()
+        .position(|stage| *stage == Stage::Integrate)
+        .unwrap();
+    before.pipeline.retain(|stage| *stage != Stage::Collisions);
+    before.pipeline.insert(integrate, Stage::Collisions);
+
+    after.update();
+    before.update();
+
+    assert!(after.merges.is_empty());
+    assert_eq!(before.merges.len(), 1);
+    assert_eq!(*before.stars[0].pos(), Vector2::new(0.25, 0.0));
+}
+
+#[test]
+fn custom_stages_run_in_order() {
+    let star = Star::new(Vector2::zeros(), Vector2::new(1.0, 0.0), 1.0);
+    let mut simulation = without_gravity([star]);
+    let stop = Stage::Custom(Arc::new(|simulation: &mut Simulation| {
+        simulation.stars[0].vel = Vector2::new(0.0, 2.0)
+    }));
+    simulation.pipeline.insert(0, stop.clone());
+
+    simulation.update();
+
+    // stopped before moving
+    assert_eq!(*simulation.stars[0].pos(), Vector2::new(0.0, 2.0));
+    assert_eq!(simulation.pipeline[0], stop);
+    assert_eq!(
+        simulation.pipeline.len(),
+        Stage::default_pipeline().len() + 1
+    );
+}
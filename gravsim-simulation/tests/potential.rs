@@ -0,0 +1,186 @@
+use gravsim_simulation::integrator::Leapfrog;
+use gravsim_simulation::pipeline::Stage;
+use gravsim_simulation::potential::{ExternalPotential, Isothermal, Nfw, PointMass};
+use gravsim_simulation::{Galaxy, MassDistribution, Real, Simulation, Star};
+use nalgebra::Vector2;
+
+const GRAVITY: Real = Simulation::GRAVITY;
+
+fn potentials() -> Vec<(&'static str, Box<dyn ExternalPotential>)> {
+    let center = Vector2::new(150.0, -40.0);
+    vec![
+        (
+            "point mass",
+            Box::new(PointMass {
+                center,
+                mass: 1e8,
+                softening: 50.0,
+            }),
+        ),
+        (
+            "isothermal",
+            Box::new(Isothermal {
+                center,
+                velocity: 3.0,
+                core_radius: 200.0,
+            }),
+        ),
+        (
+            "nfw",
+            Box::new(Nfw {
+                center,
+                mass: 1e9,
+                scale_radius: 1000.0,
+            }),
+        ),
+    ]
+}
+
+#[test]
+fn accelerations_are_the_gradient_of_the_potentials() {
+    for (name, potential) in potentials() {
+        for pos in [Vector2::new(400.0, 30.0), Vector2::new(-2500.0, 4000.0)] {
+            let h = 1.0;
+            let slope = |axis: Vector2<Real>| {
+                let ahead = potential.potential(&(pos + axis * h), GRAVITY);
+                let behind = potential.potential(&(pos - axis * h), GRAVITY);
+                (ahead - behind) / (2.0 * h)
+            };
+            let gradient = Vector2::new(slope(Vector2::x()), slope(Vector2::y()));
+            let acceleration = potential.acceleration(&pos, GRAVITY);
+            assert!(
+                (acceleration + gradient).norm() < 1e-2 * acceleration.norm(),
+                "{} at {}: {} {}",
+                name,
+                pos,
+                acceleration,
+                gradient
+            );
+        }
+    }
+}
+
+#[test]
+fn isothermal_rotation_curves_are_flat() {
+    let halo = Isothermal {
+        center: Vector2::zeros(),
+        velocity: 3.0,
+        core_radius: 200.0,
+    };
+    for radius in [2000.0, 5000.0, 20000.0] {
+        let pos = Vector2::new(0.6, 0.8) * radius;
+        let speed = halo.circular_velocity(&pos, &halo.center, GRAVITY);
+        assert!((speed - 3.0).abs() < 0.03, "{} at {}", speed, radius);
+    }
+}
+
+#[test]
+fn nfw_halos_enclose_their_mass_slowly() {
+    let halo = Nfw {
+        center: Vector2::zeros(),
+        mass: 1e9,
+        scale_radius: 1000.0,
+    };
+    let mass = |x: Real| halo.enclosed_mass(x * halo.scale_radius);
+    assert_eq!(mass(0.0), 0.0);
+    assert!((mass(1.0) / 1e9 - 0.1931).abs() < 1e-3);
+    assert!(mass(10.0) > mass(1.0) && mass(100.0) > mass(10.0));
+
+    // circular speeds peak at about 2.16 scale radii
+    let speed = |x: Real| {
+        let pos = Vector2::new(x * halo.scale_radius, 0.0);
+        halo.circular_velocity(&pos, &halo.center, GRAVITY)
+    };
+    assert!(speed(2.16) > speed(1.5) && speed(2.16) > speed(3.0));
+}
+
+#[test]
+fn a_star_orbits_a_halo_without_halo_particles() {
+    let halo = Nfw {
+        center: Vector2::zeros(),
+        mass: 1e9,
+        scale_radius: 1000.0,
+    };
+    let pos = Vector2::new(2000.0, 0.0);
+    let speed = halo.circular_velocity(&pos, &halo.center, GRAVITY);
+    let mut simulation = Simulation::new([Star::new(pos, Vector2::new(0.0, speed), 1.0)]);
+    simulation.set_integrator(Box::new(Leapfrog));
+    simulation.add_potential(Box::new(halo));
+
+    let energy = |simulation: &Simulation| {
+        let star = &simulation.stars[0];
+        0.5 * star.vel.norm_squared() as f64 + simulation.external_potential_energy()
+    };
+    let initial = energy(&simulation);
+    for _ in 0..3000 {
+        simulation.update();
+        let radius = simulation.stars[0].pos().norm();
+        assert!((radius - 2000.0).abs() < 20.0, "{}", radius);
+    }
+    // more than a full orbit
+    assert!(simulation.stars[0].pos().y > 0.0);
+    assert!((energy(&simulation) - initial).abs() < 1e-3 * initial.abs());
+
+    // clones keep their potentials
+    let mut clone = simulation.clone();
+    clone.update();
+    simulation.update();
+    assert_eq!(clone.stars[0].vel, simulation.stars[0].vel);
+}
+
+#[test]
+fn potentials_are_external_forces() {
+    let mut simulation =
+        Simulation::new([Star::new(Vector2::new(1000.0, 0.0), Vector2::zeros(), 1.0)]);
+    simulation.add_potential(Box::new(PointMass {
+        center: Vector2::zeros(),
+        mass: 1e6,
+        softening: 0.0,
+    }));
+    simulation
+        .pipeline
+        .retain(|stage| !matches!(stage, Stage::ExternalForces));
+
+    simulation.update();
+    assert_eq!(simulation.stars[0].vel, Vector2::zeros());
+}
+
+#[test]
+fn galaxies_in_a_halo_have_flat_rotation_curves() {
+    let halo = Isothermal {
+        center: Vector2::zeros(),
+        velocity: 3.0,
+        core_radius: 200.0,
+    };
+    let center = Star::new(Vector2::zeros(), Vector2::zeros(), 1e3);
+    let distribution = MassDistribution::new(100.0, 15000.0);
+    let galaxy = Galaxy::new_seeded(center, 500, 4000.0, &distribution, 3).with_halo(&halo);
+
+    for star in &galaxy.stars()[1..] {
+        if star.pos().norm() > 1000.0 {
+            assert!((star.vel.norm() - 3.0).abs() < 0.1, "{}", star.vel.norm());
+        }
+    }
+
+    let mean_radius = |stars: &[Star]| {
+        stars[1..]
+            .iter()
+            .map(|star| star.pos().norm())
+            .sum::<Real>()
+            / (stars.len() - 1) as Real
+    };
+    let mut simulation = Simulation::new(galaxy.stars().to_vec());
+    simulation.set_integrator(Box::new(Leapfrog));
+    simulation.add_potential(Box::new(halo));
+    let initial = mean_radius(&simulation.stars);
+    for _ in 0..200 {
+        simulation.update();
+    }
+    let radius = mean_radius(&simulation.stars);
+    assert!(
+        (radius - initial).abs() < 0.03 * initial,
+        "{} {}",
+        radius,
+        initial
+    );
+}
@@ -0,0 +1,109 @@
+use gravsim_simulation::pipeline::Stage;
+use gravsim_simulation::probe::Probe;
+use gravsim_simulation::{Real, Simulation, Star};
+use nalgebra::Vector2;
+
+#[test]
+fn probes_do_not_pull_on_stars() {
+    let star = Star::new(Vector2::zeros(), Vector2::zeros(), 1e6);
+    let mut simulation = Simulation::new([star]);
+    simulation
+        .probes
+        .push(Probe::new(Vector2::new(100.0, 0.0), Vector2::zeros()));
+
+    simulation.update();
+
+    assert_eq!(*simulation.stars[0].pos(), *star.pos());
+    assert_eq!(simulation.stars[0].vel, Vector2::zeros());
+    let probe = &simulation.probes[0];
+    assert!(probe.vel.x < 0.0 && probe.vel.y == 0.0, "{}", probe.vel);
+    assert_eq!(probe.trajectory.len(), 2);
+    assert_eq!(probe.trajectory.back(), Some(&probe.pos));
+}
+
+#[test]
+fn probes_stay_on_circular_orbits() {
+    let star = Star::new(Vector2::zeros(), Vector2::zeros(), 1e6);
+    let mut simulation = Simulation::new([star]);
+
+    // the circular speed of the softened potential
+    let radius: Real = 100.0;
+    let attraction = Simulation::GRAVITY * star.mass() * radius
+        / (Simulation::SOFTENING.powi(2) + radius * radius).powf(1.5);
+    let speed = (attraction * radius).sqrt();
+    simulation.probes.push(Probe::new(
+        Vector2::new(radius, 0.0),
+        Vector2::new(0.0, speed),
+    ));
+
+    let period = 2.0 * std::f64::consts::PI * radius as f64 / speed as f64;
+    while simulation.time < period {
+        simulation.update();
+        let distance = simulation.probes[0].pos.norm();
+        assert!((distance - radius).abs() < 0.1, "{}", distance);
+    }
+
+    // the last update overshoots the period by less than a step
+    let probe = &simulation.probes[0];
+    let overshoot = (simulation.time - period) as Real;
+    let expected = Vector2::new(radius, 0.0) + Vector2::new(0.0, speed) * overshoot;
+    assert!((probe.pos - expected).norm() < 1.0, "{}", probe.pos);
+}
+
+#[test]
+fn probes_coast_without_gravity() {
+    let star = Star::new(Vector2::zeros(), Vector2::zeros(), 1e6);
+    let mut simulation = Simulation::new([star]);
+    simulation.pipeline.retain(|stage| *stage != Stage::Gravity);
+    simulation
+        .probes
+        .push(Probe::new(Vector2::new(100.0, 0.0), Vector2::new(0.0, 1.0)));
+
+    simulation.update();
+
+    assert_eq!(simulation.probes[0].vel, Vector2::new(0.0, 1.0));
+    assert!((simulation.probes[0].pos - Vector2::new(100.0, 1.0)).norm() < 1e-4);
+}
+
+#[test]
+fn trajectories_keep_the_latest_positions() {
+    let mut probe = Probe::new(Vector2::zeros(), Vector2::new(1.0, 0.0));
+    for _ in 0..Probe::TRAJECTORY_LENGTH + 10 {
+        probe.advance(1.0, |_| Vector2::zeros());
+    }
+
+    assert_eq!(probe.trajectory.len(), Probe::TRAJECTORY_LENGTH);
+    assert_eq!(probe.trajectory.back(), Some(&probe.pos));
+    assert_eq!(probe.trajectory[0].x, 11.0);
+}
+
+#[test]
+fn predicted_orbits_match_probes() {
+    let star = Star::new(Vector2::zeros(), Vector2::zeros(), 1e6);
+    let (pos, vel) = (Vector2::new(100.0, 0.0), Vector2::new(0.0, 0.5));
+    let mut simulation = Simulation::new([star]);
+
+    let orbit = simulation.predict_orbit(pos, vel, 20, None);
+
+    assert_eq!(orbit.len(), 21);
+    assert_eq!(orbit[0], pos);
+    simulation.probes.push(Probe::new(pos, vel));
+    for _ in 0..20 {
+        simulation.update();
+    }
+    // the star stays put, so the frozen field is the one the probe moves through
+    assert!((orbit[20] - simulation.probes[0].pos).norm() < 1e-3);
+}
+
+#[test]
+fn predicted_orbits_can_leave_a_star_out() {
+    let star = Star::new(Vector2::new(100.0, 0.0), Vector2::zeros(), 1e6);
+    let other = Star::new(Vector2::new(-100.0, 0.0), Vector2::zeros(), 1.0);
+    let simulation = Simulation::new([star, other]);
+
+    let orbit = simulation.predict_orbit(*star.pos(), Vector2::new(0.0, 1.0), 10, Some(0));
+
+    // only the light star pulls
+    let drift = orbit[10] - Vector2::new(100.0, 10.0);
+    assert!(drift.norm() < 1e-3, "{}", drift);
+}
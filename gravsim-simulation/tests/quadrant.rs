@@ -0,0 +1,95 @@
+use gravsim_simulation::tree::{Node, Quadrant};
+use gravsim_simulation::{MassData, Real};
+use nalgebra::Vector2;
+use rand::{Rng, SeedableRng};
+use rand_xorshift::XorShiftRng;
+
+const QUADRANTS: [Quadrant; 4] = [
+    Quadrant::NorthWest,
+    Quadrant::NorthEast,
+    Quadrant::SouthWest,
+    Quadrant::SouthEast,
+];
+
+fn child(parent: &Node, quadrant: Quadrant) -> Box<Node> {
+    let mass_data = MassData {
+        position: Vector2::zeros(),
+        mass: 1.0,
+    };
+    Node::new_child(parent, quadrant, mass_data)
+}
+
+#[test]
+fn offset_bits() {
+    assert_eq!(Quadrant::NorthWest.offset(), Vector2::new(0.0, 0.0));
+    assert_eq!(Quadrant::NorthEast.offset(), Vector2::new(1.0, 0.0));
+    assert_eq!(Quadrant::SouthWest.offset(), Vector2::new(0.0, 1.0));
+    assert_eq!(Quadrant::SouthEast.offset(), Vector2::new(1.0, 1.0));
+}
+
+#[test]
+fn offset_roundtrip() {
+    for scale in [1.0, 3.0, 1000.0, 50000.0] {
+        for quadrant in QUADRANTS {
+            // lower corner and center of the quadrant
+            let corner = quadrant.offset() * scale * 0.5;
+            let center = corner + Vector2::repeat(scale * 0.25);
+
+            assert_eq!(Quadrant::from_offset(&corner, scale), quadrant);
+            assert_eq!(Quadrant::from_offset(&center, scale), quadrant);
+        }
+    }
+}
+
+#[test]
+fn boundary_belongs_to_upper_quadrant() {
+    let scale = 1000.0;
+    let half = 0.5 * scale;
+
+    let cases = [
+        (Vector2::new(half, 0.0), Quadrant::NorthEast),
+        (Vector2::new(0.0, half), Quadrant::SouthWest),
+        (Vector2::new(half, half), Quadrant::SouthEast),
+    ];
+    for (offset, expected) in cases {
+        assert_eq!(Quadrant::from_offset(&offset, scale), expected);
+    }
+}
+
+#[test]
+fn boundary_consistent_with_contains() {
+    let root = Node::new_root(Vector2::repeat(-500.0), 1000.0);
+
+    // points on the center lines of the root
+    for point in [
+        Vector2::new(0.0, -250.0),
+        Vector2::new(-250.0, 0.0),
+        Vector2::new(0.0, 0.0),
+        Vector2::new(0.0, 250.0),
+    ] {
+        let quadrant = Quadrant::from_offset(&(point - Vector2::repeat(-500.0)), 1000.0);
+        assert!(child(&root, quadrant).contains(&point));
+    }
+}
+
+#[test]
+fn random_offsets_fall_into_their_child() {
+    let mut rng = XorShiftRng::seed_from_u64(0x5eed);
+    let root_pos = Vector2::repeat(-500.0);
+    let root = Node::new_root(root_pos, 1000.0);
+
+    for _ in 0..100_000 {
+        let point = root_pos + Vector2::from_fn(|_, _| rng.gen::<Real>() * 1000.0);
+        assert!(root.contains(&point));
+
+        let quadrant = Quadrant::from_offset(&(point - root_pos), 1000.0);
+        assert!(child(&root, quadrant).contains(&point), "{:?}", point);
+
+        // exactly one child contains the point
+        let containing = QUADRANTS
+            .iter()
+            .filter(|&&q| child(&root, q).contains(&point))
+            .count();
+        assert_eq!(containing, 1, "{:?}", point);
+    }
+}
@@ -0,0 +1,62 @@
+use gravsim_simulation::radius::{ConstantDensity, MainSequence, MassRadiusRelation, WhiteDwarf};
+use gravsim_simulation::{Real, Simulation, SimulationConfig, Star};
+use nalgebra::Vector2;
+
+#[test]
+fn default_relation_is_the_star_radius() {
+    let star = Star::new(Vector2::zeros(), Vector2::zeros(), 1000.0);
+    let simulation = Simulation::new([star]);
+
+    assert_eq!(simulation.radius(&star), star.radius());
+    assert_eq!(ConstantDensity::DEFAULT.radius(1000.0), star.radius());
+}
+
+#[test]
+fn collisions_use_the_relation_of_the_simulation() {
+    let stars = [
+        Star::new(Vector2::new(0.0, 0.0), Vector2::zeros(), 100.0),
+        Star::new(Vector2::new(5.0, 0.0), Vector2::zeros(), 100.0),
+    ];
+    let config = SimulationConfig {
+        gravity: 0.0,
+        merge_collisions: true,
+        ..SimulationConfig::default()
+    };
+    let mut small = Simulation::with_config(stars, config);
+    let mut large = Simulation::with_config(stars, config);
+    large.set_mass_radius_relation(Box::new(|mass: Real| mass / 25.0));
+
+    small.update();
+    large.update();
+
+    assert!(small.merges.is_empty());
+    assert_eq!(large.merges.len(), 1);
+    assert_eq!(large.radius(&large.stars[0]), 8.0);
+}
+
+#[test]
+fn main_sequence_is_continuous_at_one_solar_mass() {
+    let sun = MainSequence {
+        solar_mass: 1000.0,
+        solar_radius: 2.0,
+    };
+
+    assert_eq!(sun.radius(1000.0), 2.0);
+    assert!((sun.radius(999.0) - sun.radius(1001.0)).abs() < 1e-2);
+    assert!(sun.radius(500.0) < sun.radius(1000.0));
+    assert!(sun.radius(5000.0) > sun.radius(1000.0));
+}
+
+#[test]
+fn white_dwarfs_shrink_with_mass() {
+    let dwarf = WhiteDwarf {
+        solar_mass: 1000.0,
+        solar_radius: 0.5,
+    };
+
+    assert!((dwarf.radius(1000.0) - 0.5).abs() < 1e-5);
+    assert!(dwarf.radius(600.0) > dwarf.radius(1000.0));
+    assert!(dwarf.radius(1400.0) < dwarf.radius(1000.0));
+    assert_eq!(dwarf.radius(1500.0), 0.0);
+    assert_eq!(dwarf.radius(0.0), 0.0);
+}
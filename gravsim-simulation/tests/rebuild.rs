@@ -0,0 +1,73 @@
+use gravsim_simulation::rebuild::IncrementalRebuild;
+use gravsim_simulation::{Real, Simulation, SimulationConfig, Star};
+use nalgebra::Vector2;
+
+fn ring(count: usize) -> Vec<Star> {
+    (0..count)
+        .map(|i| {
+            let angle = i as Real * 0.2;
+            let pos = Vector2::new(angle.cos(), angle.sin()) * (50.0 + i as Real);
+            Star::new(pos, Vector2::new(-pos.y, pos.x) * 1e-3, 10.0)
+        })
+        .collect()
+}
+
+fn incremental(stars_per_update: usize, max_staleness: u64) -> SimulationConfig {
+    SimulationConfig {
+        incremental_rebuild: Some(IncrementalRebuild {
+            stars_per_update,
+            max_staleness,
+        }),
+        ..SimulationConfig::default()
+    }
+}
+
+#[test]
+fn first_update_builds_the_whole_tree() {
+    let mut fresh = Simulation::new(ring(100));
+    let mut incremental = Simulation::with_config(ring(100), incremental(10, 20));
+    fresh.update();
+    incremental.update();
+
+    assert_eq!(incremental.tree_staleness, 0);
+    assert_eq!(fresh.state_hash(), incremental.state_hash());
+}
+
+#[test]
+fn tree_is_replaced_once_all_stars_are_inserted() {
+    let mut simulation = Simulation::with_config(ring(100), incremental(25, 20));
+    let staleness: Vec<_> = (0..9)
+        .map(|_| {
+            simulation.update();
+            simulation.tree_staleness
+        })
+        .collect();
+
+    // built at once, then over four updates starting at steps 1 and 5
+    assert_eq!(staleness, [0, 1, 2, 3, 3, 4, 5, 6, 3]);
+}
+
+#[test]
+fn staleness_is_bounded() {
+    let mut simulation = Simulation::with_config(ring(100), incremental(0, 3));
+    for _ in 0..20 {
+        simulation.update();
+        assert!(
+            simulation.tree_staleness <= 3,
+            "{}",
+            simulation.tree_staleness
+        );
+    }
+}
+
+#[test]
+fn stars_are_not_attracted_by_their_outdated_copy() {
+    let star = Star::new(Vector2::zeros(), Vector2::new(10.0, 0.0), 1000.0);
+    let mut simulation = Simulation::with_config([star], incremental(0, 100));
+    for _ in 0..10 {
+        simulation.update();
+    }
+
+    assert_eq!(simulation.tree_staleness, 9);
+    assert_eq!(simulation.stars[0].vel, Vector2::new(10.0, 0.0));
+}
@@ -0,0 +1,121 @@
+use gravsim_simulation::backend::SimulationBackend;
+use gravsim_simulation::replay::{Recorder, Replay};
+use gravsim_simulation::{Real, Simulation, SimulationConfig, Star};
+use nalgebra::Vector2;
+use rand::{Rng, SeedableRng};
+use rand_xorshift::XorShiftRng;
+
+fn simulation(count: usize) -> Simulation {
+    let mut rng = XorShiftRng::seed_from_u64(0x5e71);
+    Simulation::new((0..count).map(|_| {
+        Star::new(
+            Vector2::from_fn(|_, _| rng.gen_range(-900.0..900.0)),
+            Vector2::from_fn(|_, _| rng.gen_range(-1.0..1.0)),
+            rng.gen_range(1.0..10.0),
+        )
+    }))
+}
+
+/// Records `frames` updates, returning the recording and the stars of every frame.
+fn record(simulation: &mut Simulation, frames: usize, delta: bool) -> (Vec<u8>, Vec<Vec<Star>>) {
+    let mut recorder = Recorder::new(Vec::new(), &simulation.config, delta).unwrap();
+    let mut expected = Vec::new();
+    for _ in 0..frames {
+        recorder
+            .record(simulation.step, simulation.time, &simulation.stars)
+            .unwrap();
+        expected.push(simulation.stars.clone());
+        simulation.update();
+    }
+    assert_eq!(recorder.frames(), frames as u64);
+    (recorder.finish().unwrap(), expected)
+}
+
+fn bits(stars: &[Star]) -> Vec<[u64; 5]> {
+    stars
+        .iter()
+        .map(|star| {
+            [
+                star.pos().x,
+                star.pos().y,
+                star.vel.x,
+                star.vel.y,
+                star.mass(),
+            ]
+            .map(|value| value.to_bits() as u64)
+        })
+        .collect()
+}
+
+#[test]
+fn replays_match_the_recorded_frames_exactly() {
+    for delta in [false, true] {
+        let mut simulation = simulation(200);
+        // removed stars have NaN positions
+        simulation.stars[7].mass_point.position = Vector2::from_element(Real::NAN);
+        let (recording, expected) = record(&mut simulation, 10, delta);
+
+        let mut replay = Replay::new(recording.as_slice()).unwrap();
+        for (frame, stars) in expected.iter().enumerate() {
+            assert_eq!(replay.step, frame as u64);
+            assert_eq!(bits(replay.snapshot()), bits(stars), "frame {}", frame);
+            replay.step();
+        }
+        assert!(replay.finished);
+        assert!(replay.error.is_none());
+        assert_eq!(bits(replay.snapshot()), bits(expected.last().unwrap()));
+    }
+}
+
+#[test]
+fn deltas_are_smaller() {
+    let (full, _) = record(&mut simulation(1000), 10, false);
+    let (delta, _) = record(&mut simulation(1000), 10, true);
+
+    assert!(
+        delta.len() < full.len(),
+        "{} >= {}",
+        delta.len(),
+        full.len()
+    );
+}
+
+#[test]
+fn frames_can_change_the_star_count() {
+    let mut simulation = simulation(50);
+    let config = SimulationConfig::default();
+    let mut recorder = Recorder::new(Vec::new(), &config, true).unwrap();
+    recorder.record(0, 0.0, &simulation.stars).unwrap();
+    simulation.stars.truncate(30);
+    recorder.record(1, 1.0, &simulation.stars).unwrap();
+    simulation.stars[0].vel.x += 1.0;
+    recorder.record(2, 2.0, &simulation.stars).unwrap();
+    let recording = recorder.finish().unwrap();
+
+    let mut replay = Replay::new(recording.as_slice()).unwrap();
+    assert_eq!(replay.snapshot().len(), 50);
+    replay.step();
+    assert_eq!(replay.snapshot().len(), 30);
+    replay.step();
+    assert_eq!(bits(replay.snapshot()), bits(&simulation.stars));
+    assert_eq!(replay.time, 2.0);
+}
+
+#[test]
+fn truncated_recordings_end_without_an_error() {
+    let (mut recording, expected) = record(&mut simulation(100), 3, true);
+    recording.truncate(recording.len() - 10);
+
+    let mut replay = Replay::new(recording.as_slice()).unwrap();
+    replay.step();
+    replay.step();
+
+    assert!(replay.finished);
+    assert!(replay.error.is_none());
+    assert_eq!(bits(replay.snapshot()), bits(&expected[1]));
+}
+
+#[test]
+fn other_files_are_rejected() {
+    assert!(Replay::new(&b"not a recording at all"[..]).is_err());
+}
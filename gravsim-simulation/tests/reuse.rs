@@ -0,0 +1,124 @@
+use gravsim_simulation::integrator::Verlet;
+use gravsim_simulation::reuse::{ReusedTree, TreeReuse};
+use gravsim_simulation::tree::FlatTree;
+use gravsim_simulation::{Real, Simulation, SimulationConfig, Star};
+use nalgebra::Vector2;
+use rand::{Rng, SeedableRng};
+use rand_xorshift::XorShiftRng;
+
+fn stars(rng: &mut XorShiftRng, count: usize) -> Vec<Star> {
+    (0..count)
+        .map(|_| {
+            Star::new(
+                Vector2::from_fn(|_, _| rng.gen_range(-900.0..900.0)),
+                Vector2::from_fn(|_, _| rng.gen_range(-1.0..1.0)),
+                rng.gen_range(1.0..10.0),
+            )
+        })
+        .collect()
+}
+
+fn fresh(stars: &[Star], config: &SimulationConfig) -> FlatTree {
+    let mut tree = FlatTree::bounding(&config.domain, 2 * stars.len());
+    stars
+        .iter()
+        .filter(|star| config.domain.contains(star.pos()))
+        .for_each(|star| tree.insert(&star.mass_point));
+    tree.summarize();
+    tree
+}
+
+#[test]
+fn reused_tree_gives_the_forces_of_a_fresh_tree() {
+    let mut rng = XorShiftRng::seed_from_u64(0x7e05);
+    let mut stars = stars(&mut rng, 1000);
+    let config = SimulationConfig::default();
+    let settings = TreeReuse {
+        max_empty_fraction: 1.0,
+    };
+    let mut reused = ReusedTree::default();
+    assert!(reused.update(&stars, &[], &config, &settings));
+
+    for (id, star) in stars.iter_mut().enumerate() {
+        match id % 10 {
+            0 => star.mass_point.position = Vector2::from_element(Real::NAN),
+            1..=3 => star.mass_point.position *= -0.5,
+            4..=6 => star.mass_point.position += star.vel,
+            _ => {}
+        }
+    }
+    stars.extend(self::stars(&mut rng, 100));
+    assert!(!reused.update(&stars, &[], &config, &settings));
+
+    let tree = reused.tree().unwrap();
+    let fresh = fresh(&stars, &config);
+    let (mass, expected) = (tree.root().center_of_mass(), fresh.root().center_of_mass());
+    assert!((mass.mass - expected.mass).abs() < 1e-2 * expected.mass);
+    assert!((mass.position - expected.position).norm() < 1e-2);
+    for star in stars
+        .iter()
+        .filter(|star| config.domain.contains(star.pos()))
+    {
+        let force = tree.force_on(&star.mass_point, &config);
+        let expected = fresh.force_on(&star.mass_point, &config);
+        assert!(
+            (force - expected).norm() <= 1e-3 * expected.norm(),
+            "{:?} vs {:?}",
+            force,
+            expected
+        );
+    }
+}
+
+#[test]
+fn degraded_tree_is_built_from_scratch() {
+    let mut stars = stars(&mut XorShiftRng::seed_from_u64(0x7e06), 500);
+    let config = SimulationConfig::default();
+    let settings = TreeReuse::default();
+    let mut reused = ReusedTree::default();
+    assert!(reused.update(&stars, &[], &config, &settings));
+
+    // small steps stay within the tolerance
+    for step in 0..5 {
+        stars.iter_mut().for_each(|star| {
+            let vel = star.vel;
+            star.mass_point.position += vel;
+        });
+        assert!(
+            !reused.update(&stars, &[], &config, &settings),
+            "step {}",
+            step
+        );
+    }
+
+    // everything moving into one quadrant leaves most of the tree empty
+    stars
+        .iter_mut()
+        .for_each(|star| star.mass_point.position = star.pos().abs() * 0.5);
+    assert!(!reused.update(&stars, &[], &config, &settings));
+    assert!(reused.update(&stars, &[], &config, &settings));
+}
+
+#[test]
+fn simulations_reusing_the_tree_follow_fresh_builds() {
+    let initial = stars(&mut XorShiftRng::seed_from_u64(0x7e07), 500);
+    let config = SimulationConfig {
+        reuse_tree: Some(TreeReuse::default()),
+        ..SimulationConfig::default()
+    };
+    let mut reused = Simulation::with_config(initial.iter().copied(), config);
+    let mut plain = Simulation::new(initial.iter().copied());
+    // two force evaluations at different positions per update
+    reused.set_integrator(Box::new(Verlet));
+    plain.set_integrator(Box::new(Verlet));
+
+    for _ in 0..10 {
+        reused.update();
+        plain.update();
+    }
+
+    for (star, reference) in reused.stars.iter().zip(&plain.stars) {
+        let distance = (star.pos() - reference.pos()).norm();
+        assert!(distance < 1e-2, "{} off", distance);
+    }
+}
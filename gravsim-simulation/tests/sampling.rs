@@ -0,0 +1,78 @@
+use gravsim_simulation::sampling::Sampling;
+use gravsim_simulation::{Galaxy, MassDistribution, Real, SpiralArms, Star};
+use nalgebra::Vector2;
+
+const RADIUS: Real = 1000.0;
+const STARS: usize = 512;
+
+/// Positions of the stars of a galaxy at the origin drawn with `sampling`.
+fn positions(sampling: Sampling) -> Vec<Vector2<Real>> {
+    let center = Star::new(Vector2::zeros(), Vector2::zeros(), 1e3);
+    let galaxy = Galaxy::with_sampling_seeded(
+        center,
+        STARS,
+        RADIUS,
+        &MassDistribution::new(100.0, 15000.0),
+        0.0,
+        &SpiralArms::default(),
+        sampling,
+        5,
+    );
+    galaxy.stars()[1..].iter().map(|star| *star.pos()).collect()
+}
+
+/// Stars in each of 8 annuli of equal area times 8 sectors.
+fn cell_counts(positions: &[Vector2<Real>]) -> Vec<usize> {
+    let mut counts = vec![0; 64];
+    for pos in positions {
+        let annulus = ((pos.norm() / RADIUS).powi(2) * 8.0) as usize;
+        let angle = pos.x.atan2(pos.y).rem_euclid(std::f64::consts::TAU as Real);
+        let sector = (angle / std::f64::consts::TAU as Real * 8.0) as usize;
+        counts[annulus.min(7) * 8 + sector.min(7)] += 1;
+    }
+    counts
+}
+
+fn variance(counts: &[usize]) -> f64 {
+    let mean = counts.iter().sum::<usize>() as f64 / counts.len() as f64;
+    counts
+        .iter()
+        .map(|&count| (count as f64 - mean).powi(2))
+        .sum::<f64>()
+        / counts.len() as f64
+}
+
+#[test]
+fn sobol_galaxies_have_less_shot_noise() {
+    let random = variance(&cell_counts(&positions(Sampling::Random)));
+    let sobol = variance(&cell_counts(&positions(Sampling::Sobol)));
+
+    // independent positions scatter about as much as the mean of 8 per cell
+    assert!(sobol < 0.3 * random, "{} {}", sobol, random);
+}
+
+#[test]
+fn stratified_galaxies_fill_every_annulus_and_sector_equally() {
+    let counts = cell_counts(&positions(Sampling::Stratified));
+
+    for i in 0..8 {
+        let annulus: usize = counts[i * 8..(i + 1) * 8].iter().sum();
+        let sector: usize = (0..8).map(|j| counts[j * 8 + i]).sum();
+        // give or take a star right on a border, which rounding may move across it
+        for count in [annulus, sector] {
+            assert!(count.abs_diff(STARS / 8) <= 1, "{:?}", counts);
+        }
+    }
+}
+
+#[test]
+fn poisson_disk_galaxies_keep_their_distance() {
+    let positions = positions(Sampling::PoissonDisk);
+    let separation = Sampling::PoissonDisk.poisson_disk_separation(STARS, RADIUS);
+
+    for (i, a) in positions.iter().enumerate() {
+        for b in &positions[i + 1..] {
+            assert!((a - b).norm() >= separation * 0.999);
+        }
+    }
+}
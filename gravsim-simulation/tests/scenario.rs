@@ -0,0 +1,167 @@
+use gravsim_simulation::output::{OutputPolicy, Trigger};
+use gravsim_simulation::pipeline::Stage;
+use gravsim_simulation::scenario::{Scenario, ScriptSpec};
+use nalgebra::Vector2;
+
+fn read(name: &str, source: &str) -> Scenario {
+    let path = std::env::temp_dir().join(name);
+    std::fs::write(&path, source).unwrap();
+    let scenario = Scenario::from_path(&path);
+    std::fs::remove_file(path).unwrap();
+    scenario.unwrap()
+}
+
+#[test]
+fn toml_scenarios_place_galaxies_and_stars() {
+    let scenario = read(
+        "gravsim-scenario-test.toml",
+        r#"
+        [config]
+        theta = 0.7
+
+        [[galaxies]]
+        stars = 100
+        position = [1000.0, 0.0]
+
+        [[stars]]
+        position = [0.0, 5.0]
+        velocity = [1.0, 0.0]
+        mass = 2.0
+        "#,
+    );
+    let simulation = scenario.to_simulation();
+
+    assert_eq!(simulation.config.theta, 0.7);
+    // the galaxy center, its stars and the single star
+    assert_eq!(simulation.stars.len(), 102);
+    assert_eq!(*simulation.stars[0].pos(), Vector2::new(1000.0, 0.0));
+    assert_eq!(*simulation.stars[101].pos(), Vector2::new(0.0, 5.0));
+}
+
+#[test]
+fn json_scenarios_are_read_by_extension() {
+    let scenario = read(
+        "gravsim-scenario-test.json",
+        r#"{ "stars": [{ "position": [1.0, 2.0], "velocity": [0.0, 0.0], "mass": 3.0 }] }"#,
+    );
+    assert_eq!(scenario.stars().len(), 1);
+}
+
+#[test]
+fn unknown_keys_are_rejected() {
+    let path = std::env::temp_dir().join("gravsim-scenario-typo.toml");
+    std::fs::write(&path, "[[galaxies]]\nstar = 100\n").unwrap();
+    assert!(Scenario::from_path(&path).is_err());
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn scenarios_replace_the_pipeline() {
+    let scenario = read(
+        "gravsim-scenario-pipeline.toml",
+        r#"
+        pipeline = ["collisions", "build_tree", "gravity", "integrate"]
+        "#,
+    );
+    let simulation = scenario.to_simulation();
+
+    assert_eq!(
+        simulation.pipeline,
+        [
+            Stage::Collisions,
+            Stage::BuildTree,
+            Stage::Gravity,
+            Stage::Integrate
+        ]
+    );
+}
+
+#[test]
+fn scenarios_launch_probes() {
+    let scenario = read(
+        "gravsim-scenario-probes.toml",
+        r#"
+        [[probes]]
+        position = [100.0, 0.0]
+        velocity = [0.0, 1.5]
+        "#,
+    );
+    let simulation = scenario.to_simulation();
+
+    assert_eq!(simulation.probes.len(), 1);
+    assert_eq!(simulation.probes[0].pos, Vector2::new(100.0, 0.0));
+    assert_eq!(simulation.probes[0].vel, Vector2::new(0.0, 1.5));
+}
+
+#[test]
+fn scenarios_configure_the_output() {
+    let scenario = read(
+        "gravsim-output-test.toml",
+        r#"
+        [output]
+        every_time = 500.0
+        events = ["merger", "pericenter", { marker = "flyby" }]
+        "#,
+    );
+    assert_eq!(
+        scenario.output,
+        Some(OutputPolicy {
+            every_time: Some(500.0),
+            events: vec![
+                Trigger::Merger,
+                Trigger::Pericenter,
+                Trigger::Marker("flyby".into())
+            ],
+            ..OutputPolicy::default()
+        })
+    );
+}
+
+#[test]
+fn scenarios_watch_pairs_of_galaxies_for_pericenters() {
+    let scenario = read(
+        "gravsim-pericenter-test.toml",
+        r#"
+        [[galaxies]]
+        stars = 20
+        position = [-5000.0, 0.0]
+
+        [[galaxies]]
+        stars = 10
+        position = [5000.0, 0.0]
+
+        [pericenters]
+        window = 300.0
+
+        [output]
+        events = ["near_pericenter"]
+        "#,
+    );
+    let simulation = scenario.to_simulation();
+
+    let monitor = simulation.pericenter_monitor.unwrap();
+    assert_eq!(monitor.window, 300.0);
+    assert_eq!(monitor.groups[0].stars, (0..21).collect::<Vec<_>>());
+    assert_eq!(monitor.groups[1].stars, (21..32).collect::<Vec<_>>());
+    assert_eq!(scenario.output.unwrap().events, [Trigger::NearPericenter]);
+}
+
+#[test]
+fn script_paths_are_relative_to_the_scenario() {
+    let script = std::env::temp_dir().join("gravsim-scenario-script.rhai");
+    std::fs::write(&script, "sim.gravity *= 2.0;").unwrap();
+    let scenario = read(
+        "gravsim-scenario-script.toml",
+        "script = { path = \"gravsim-scenario-script.rhai\" }\n",
+    );
+    std::fs::remove_file(&script).unwrap();
+
+    assert!(matches!(&scenario.script, Some(ScriptSpec::Path(path)) if *path == script));
+
+    let scenario = read(
+        "gravsim-scenario-inline-script.toml",
+        "script = { source = \"sim.theta = 0.5;\" }\n",
+    );
+    let source = scenario.script.map(|script| script.source());
+    assert_eq!(source, Some(Ok("sim.theta = 0.5;".to_string())));
+}
@@ -0,0 +1,73 @@
+use gravsim_simulation::schedule::{Action, Event, Schedule};
+use gravsim_simulation::{Simulation, Star};
+use nalgebra::Vector2;
+
+fn one_star() -> Simulation {
+    Simulation::new([Star::new(Vector2::zeros(), Vector2::zeros(), 1.0)])
+}
+
+#[test]
+fn events_fire_at_their_simulated_time() {
+    let mut simulation = one_star();
+    simulation.config.dt = 0.4;
+    simulation.schedule = Schedule::new([
+        Event {
+            time: 2.0,
+            action: Action::Marker("second".into()),
+        },
+        Event {
+            time: 1.0,
+            action: Action::SetDt(0.3),
+        },
+        Event {
+            time: 2.0,
+            action: Action::AddStar {
+                position: Vector2::new(5.0, 0.0),
+                velocity: Vector2::zeros(),
+                mass: 2.0,
+            },
+        },
+    ]);
+
+    // 0.4, 0.8, then shortened to land on 1.0
+    let mut fired_at = Vec::new();
+    while !simulation.schedule.is_empty() {
+        simulation.update();
+        for event in &simulation.fired {
+            fired_at.push((simulation.time, event.action.clone()));
+        }
+    }
+
+    assert_eq!(fired_at.len(), 3);
+    assert_eq!(fired_at[0].0, 1.0);
+    assert_eq!(fired_at[0].1, Action::SetDt(0.3));
+    // events at the same time fire in the order they were added
+    assert_eq!(fired_at[1].0, 2.0);
+    assert_eq!(fired_at[1].1, Action::Marker("second".into()));
+    assert_eq!(fired_at[2].0, 2.0);
+
+    assert_eq!(simulation.config.dt, 0.3);
+    assert_eq!(simulation.stars.len(), 2);
+    // 3 updates to 1.0, 4 more to 2.0 at the new time step
+    assert_eq!(simulation.step, 7);
+}
+
+#[test]
+fn events_are_independent_of_the_time_step() {
+    let time_of_event = |dt| {
+        let mut simulation = one_star();
+        simulation.config.dt = dt;
+        simulation.schedule.add(Event {
+            time: 10.0,
+            action: Action::SetGravity(0.0),
+        });
+        while simulation.fired.is_empty() {
+            simulation.update();
+        }
+        simulation.time
+    };
+
+    assert_eq!(time_of_event(1.0), 10.0);
+    assert_eq!(time_of_event(0.3), 10.0);
+    assert_eq!(time_of_event(3.0), 10.0);
+}
@@ -0,0 +1,83 @@
+use gravsim_simulation::script::Script;
+use gravsim_simulation::{Simulation, Star};
+use nalgebra::Vector2;
+
+fn two_stars() -> Simulation {
+    Simulation::new([
+        Star::new(Vector2::new(-10.0, 0.0), Vector2::zeros(), 1.0),
+        Star::new(Vector2::new(10.0, 0.0), Vector2::zeros(), 1.0),
+    ])
+}
+
+#[test]
+fn script_injects_star_at_step() {
+    let script = Script::new(
+        r#"
+        if sim.step == 3 {
+            let id = sim.add_star(0.0, 100.0, 1.0, 0.0, 5.0);
+            sim.set_velocity(id, 0.0, -1.0);
+        }
+        sim.gravity = 1e-4 * (sim.step + 1);
+        "#,
+    )
+    .unwrap();
+
+    let mut simulation = two_stars();
+    for _ in 0..5 {
+        script.run(&mut simulation).unwrap();
+        simulation.update();
+    }
+
+    assert_eq!(simulation.stars.len(), 3);
+    assert_eq!(simulation.stars[2].mass(), 5.0);
+    assert!(simulation.stars[2].vel.y < 0.0);
+    assert!((simulation.config.gravity - 5e-4).abs() < 1e-9);
+}
+
+#[test]
+fn script_errors_leave_simulation_intact() {
+    let mut simulation = two_stars();
+
+    let script = Script::new("sim.set_mass(7, 1.0);").unwrap();
+    assert!(script.run(&mut simulation).is_err());
+
+    let script = Script::new("loop {}").unwrap();
+    assert!(script.run(&mut simulation).is_err());
+
+    let script = Script::new("sim = 5;").unwrap();
+    assert!(script.run(&mut simulation).is_ok());
+
+    let script = Script::new("throw sim;").unwrap();
+    assert!(script.run(&mut simulation).is_err());
+
+    assert!(Script::new("import \"file\" as f;")
+        .and_then(|script| script.run(&mut simulation))
+        .is_err());
+
+    assert_eq!(simulation.stars.len(), 2);
+}
+
+#[test]
+fn scripts_schedule_events_in_simulated_time() {
+    let script = Script::new(
+        r#"
+        if sim.step == 0 {
+            sim.dt = 0.5;
+            sim.at_add_star(1.25, 0.0, 100.0, 0.0, 0.0, 5.0);
+            sim.at_marker(1.25, "perturber");
+        }
+        "#,
+    )
+    .unwrap();
+
+    let mut simulation = two_stars();
+    while simulation.stars.len() == 2 {
+        script.run(&mut simulation).unwrap();
+        simulation.update();
+    }
+
+    assert_eq!(simulation.time, 1.25);
+    assert_eq!(simulation.step, 3);
+    assert_eq!(simulation.fired.len(), 2);
+    assert_eq!(simulation.config.dt, 0.5);
+}
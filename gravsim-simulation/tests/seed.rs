@@ -0,0 +1,88 @@
+use gravsim_simulation::scenario::{GalaxySpec, Scenario};
+use gravsim_simulation::{Galaxy, MassDistribution, SimulationConfig, Star};
+use nalgebra::Vector2;
+
+fn bits(stars: &[Star]) -> Vec<[u64; 5]> {
+    stars
+        .iter()
+        .map(|star| {
+            [
+                star.pos().x,
+                star.pos().y,
+                star.vel.x,
+                star.vel.y,
+                star.mass(),
+            ]
+            .map(|value| value.to_bits() as u64)
+        })
+        .collect()
+}
+
+fn scenario(seed: u64) -> Scenario {
+    let galaxy = |x| GalaxySpec {
+        stars: 400,
+        radius: 2000.0,
+        position: Vector2::new(x, 0.0),
+        ..GalaxySpec::default()
+    };
+    Scenario {
+        config: SimulationConfig {
+            near_field: Some(100.0),
+            ..SimulationConfig::default()
+        },
+        galaxies: vec![galaxy(-2500.0), galaxy(2500.0)],
+        seed: Some(seed),
+        ..Scenario::default()
+    }
+}
+
+/// The stars of `scenario` after some updates, on a thread pool with `threads` threads.
+fn run(scenario: &Scenario, threads: usize) -> Vec<Star> {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .unwrap();
+    pool.install(|| {
+        let mut simulation = scenario.to_simulation();
+        for _ in 0..20 {
+            simulation.update();
+        }
+        simulation.stars
+    })
+}
+
+#[test]
+fn seeded_galaxies_repeat() {
+    let center = Star::new(Vector2::zeros(), Vector2::zeros(), 1e1);
+    let distribution = MassDistribution::new(100.0, 15000.0);
+    let galaxy = |seed| Galaxy::new_seeded(center, 1000, 100.0, &distribution, seed);
+
+    assert_eq!(bits(galaxy(1).stars()), bits(galaxy(1).stars()));
+    assert_ne!(bits(galaxy(1).stars()), bits(galaxy(2).stars()));
+}
+
+#[test]
+fn seeded_scenarios_give_distinct_galaxies() {
+    let stars = scenario(3).stars();
+    assert_eq!(bits(&stars), bits(&scenario(3).stars()));
+
+    // the first star of each galaxy, relative to its center
+    let first = stars[1].pos() - stars[0].pos();
+    let other = stars[402].pos() - stars[401].pos();
+    assert!((first - other).norm() > 1.0);
+}
+
+#[test]
+fn seeded_runs_repeat_exactly_on_any_number_of_threads() {
+    let reference = bits(&run(&scenario(7), 1));
+
+    for threads in [1, 2, 5] {
+        assert_eq!(
+            bits(&run(&scenario(7), threads)),
+            reference,
+            "{} threads",
+            threads
+        );
+    }
+    assert_ne!(bits(&run(&scenario(8), 2)), reference);
+}
@@ -0,0 +1,22 @@
+use gravsim_simulation::{Galaxy, MassDistribution, Star};
+use nalgebra::Vector2;
+
+#[test]
+fn generated_stars_keep_the_minimum_separation() {
+    let center = Star::new(Vector2::new(100.0, -50.0), Vector2::zeros(), 1e1);
+    let galaxy = Galaxy::with_min_separation(
+        center,
+        5_000,
+        100.0,
+        &MassDistribution::new(100.0, 15000.0),
+        0.5,
+    );
+
+    let stars = galaxy.stars();
+    assert_eq!(stars.len(), 5_001);
+    for (i, a) in stars.iter().enumerate() {
+        for b in &stars[i + 1..] {
+            assert!((a.pos() - b.pos()).norm() >= 0.5 - 1e-3);
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use gravsim_simulation::collision::{CollisionModel, Outcome, Rule};
+use gravsim_simulation::pipeline::Stage;
+use gravsim_simulation::probe::Probe;
+use gravsim_simulation::snapshot::Snapshot;
+use gravsim_simulation::thermal::ThermalNoise;
+use gravsim_simulation::{BodyKind, Real, Simulation, Star};
+use nalgebra::Vector2;
+
+#[test]
+fn loaded_snapshot_continues_identically() {
+    let stars = (0..100).map(|i| {
+        let angle = i as Real * 0.1;
+        let pos = Vector2::new(angle.cos(), angle.sin()) * (10.0 + i as Real);
+        Star::new(pos, Vector2::new(-pos.y, pos.x) * 1e-3, 1.0)
+    });
+    let mut simulation = Simulation::new(stars);
+    simulation.config.theta = 0.7;
+    simulation.config.thermal_noise = Some(ThermalNoise::new(1e-4, 0x5eed));
+    simulation.kinds = vec![BodyKind::Star, BodyKind::BlackHole];
+    simulation.update();
+
+    let path = std::env::temp_dir().join("gravsim-snapshot-test.bin");
+    let mut snapshot = Snapshot::of(&simulation);
+    snapshot.colors = vec![[0.5, 0.25, 1.0]; simulation.stars.len()];
+    snapshot.save(&path).unwrap();
+    let loaded = Snapshot::load(&path).unwrap();
+    let mut restored = loaded.to_simulation();
+    std::fs::remove_file(path).unwrap();
+
+    assert_eq!(loaded.colors, snapshot.colors);
+    assert_eq!(restored.config.theta, 0.7);
+    assert_eq!(restored.step, 1);
+    assert_eq!(restored.kinds, simulation.kinds);
+    assert_eq!(
+        restored.config.thermal_noise,
+        simulation.config.thermal_noise
+    );
+
+    simulation.update();
+    restored.update();
+    for (a, b) in simulation.stars.iter().zip(&restored.stars) {
+        assert_eq!(a.pos(), b.pos());
+        assert_eq!(a.vel, b.vel);
+    }
+}
+
+#[test]
+fn spins_and_ages_survive_a_round_trip() {
+    let stars = (0..10).map(|i| {
+        let pos = Vector2::new(i as Real * 100.0, 0.0);
+        Star::new(pos, Vector2::zeros(), 1.0)
+    });
+    let mut simulation = Simulation::new(stars);
+    simulation.spins = (0..10).map(|i| i as Real * 0.5).collect();
+    simulation.ages = (0..10).map(|i| i as f64 * 1e3).collect();
+
+    let path = std::env::temp_dir().join("gravsim-snapshot-per-star-test.bin");
+    simulation.save(&path).unwrap();
+    let restored = Simulation::load(&path).unwrap();
+    std::fs::remove_file(path).unwrap();
+
+    assert_eq!(restored.spins, simulation.spins);
+    assert_eq!(restored.ages, simulation.ages);
+}
+
+#[test]
+fn collision_setup_probes_and_pipeline_survive_a_round_trip() {
+    let stars = (0..10).map(|i| {
+        let pos = Vector2::new(i as Real * 100.0, 0.0);
+        Star::new(pos, Vector2::zeros(), 1.0)
+    });
+    let mut simulation = Simulation::new(stars);
+    simulation.species = (0..10).map(|i| i % 3).collect();
+    simulation.collision_model = CollisionModel {
+        default: Outcome::Bounce { restitution: 0.5 },
+        rules: vec![Rule {
+            species: [1, 2],
+            outcome: Outcome::Fragment {
+                debris_fraction: 0.2,
+                fragments: 6,
+                ejection: 0.3,
+                min_mass: 50.0,
+            },
+        }],
+    };
+    simulation.probes = vec![Probe::new(Vector2::new(0.0, 50.0), Vector2::new(1e-3, 0.0))];
+    simulation.pipeline = vec![Stage::BuildTree, Stage::Gravity, Stage::Integrate];
+    simulation.update();
+
+    let path = std::env::temp_dir().join("gravsim-snapshot-physics-test.bin");
+    simulation.save(&path).unwrap();
+    let restored = Simulation::load(&path).unwrap();
+    std::fs::remove_file(path).unwrap();
+
+    assert_eq!(restored.species, simulation.species);
+    assert_eq!(restored.collision_model, simulation.collision_model);
+    assert_eq!(restored.probes, simulation.probes);
+    assert_eq!(restored.pipeline, simulation.pipeline);
+}
+
+/// `tests/snapshots/unversioned.snapshot` was written before snapshots had a version, with
+/// single precision.
+#[cfg(not(feature = "f64"))]
+#[test]
+fn unversioned_snapshots_are_migrated() {
+    let path = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/snapshots/unversioned.snapshot"
+    );
+    let snapshot = Snapshot::load(path).unwrap();
+    assert_eq!(snapshot.colors, vec![[1.0, 0.5, 0.25]; 3]);
+    assert_eq!(snapshot.pipeline, Stage::default_pipeline());
+
+    let simulation = snapshot.to_simulation();
+    assert_eq!(simulation.stars.len(), 3);
+    assert_eq!(*simulation.stars[2].pos(), Vector2::new(200.0, -50.0));
+    assert_eq!(simulation.stars[2].vel, Vector2::new(0.0, 1.0));
+    assert_eq!(simulation.stars[2].mass(), 12.0);
+    assert_eq!((simulation.step, simulation.time), (5, 2.5));
+    assert_eq!((simulation.config.theta, simulation.config.dt), (0.7, 0.5));
+    assert_eq!(simulation.schedule.next_time(), Some(10.0));
+    assert!(simulation.kinds.is_empty() && simulation.probes.is_empty());
+
+    // and are written with the current version
+    let resaved = std::env::temp_dir().join("gravsim-snapshot-migrated-test.bin");
+    snapshot.save(&resaved).unwrap();
+    let bytes = std::fs::read(&resaved).unwrap();
+    std::fs::remove_file(&resaved).unwrap();
+    assert_eq!(bytes[..4], Snapshot::MAGIC);
+}
@@ -0,0 +1,64 @@
+use gravsim_simulation::tree::FlatTree;
+use gravsim_simulation::{MassData, Real, Simulation, SimulationConfig, Star};
+use nalgebra::Vector2;
+
+fn softened(softening: Real) -> SimulationConfig {
+    SimulationConfig {
+        softening,
+        ..SimulationConfig::default()
+    }
+}
+
+#[test]
+fn pairs_pull_with_the_plummer_force() {
+    let config = softened(10.0);
+    let source = MassData {
+        position: Vector2::zeros(),
+        mass: 1e4,
+    };
+    let mut tree = FlatTree::bounding(&config.domain, 2);
+    tree.insert(&source);
+    tree.summarize();
+
+    let gravity = config.gravity * source.mass;
+    // at `softening / √2`
+    let maximum = 2.0 / (27.0 as Real).sqrt() * gravity / config.softening.powi(2);
+    for r in [0.1, 1.0, 7.0, 10.0, 100.0] {
+        let obj = MassData {
+            position: Vector2::new(r, 0.0),
+            mass: 1.0,
+        };
+        let force = tree.force_on(&obj, &config);
+        let expected = -gravity * r / (r * r + config.softening.powi(2)).powf(1.5);
+        assert!(
+            (force.x - expected).abs() <= 1e-5 * expected.abs(),
+            "at {}",
+            r
+        );
+        assert!(force.norm() <= maximum * 1.0001, "at {}", r);
+    }
+}
+
+#[test]
+fn softening_bounds_the_speed_of_close_encounters() {
+    let config = softened(50.0);
+    let mass = 1e6;
+    let separation = 500.0;
+    let mut simulation = Simulation::with_config(
+        [
+            Star::new(Vector2::new(-separation / 2.0, 0.0), Vector2::zeros(), mass),
+            Star::new(Vector2::new(separation / 2.0, 0.0), Vector2::zeros(), mass),
+        ],
+        config,
+    );
+
+    // both fall through each other, all of the softened potential turns into kinetic energy
+    let bound = (config.gravity * mass * (1.0 / config.softening - 1.0 / separation)).sqrt();
+    let mut fastest: Real = 0.0;
+    for _ in 0..2000 {
+        simulation.update();
+        fastest = fastest.max(simulation.stars[0].vel.norm());
+    }
+    assert!(fastest > 0.9 * bound, "{} of {}", fastest, bound);
+    assert!(fastest < 1.05 * bound, "{} of {}", fastest, bound);
+}
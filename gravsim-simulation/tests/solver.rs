@@ -0,0 +1,98 @@
+use gravsim_simulation::direct::DirectSum;
+use gravsim_simulation::solver::{BarnesHut, BruteForce, ForceSolver, Solver};
+use gravsim_simulation::{MassData, Real, Simulation, SimulationConfig, Star};
+use nalgebra::Vector2;
+
+fn bodies() -> Vec<MassData> {
+    (0..300)
+        .map(|i| {
+            let (x, y) = ((i % 20) as Real, (i / 20) as Real);
+            MassData {
+                position: Vector2::new(x * 41.0 - 400.0, y * 29.0 + 30.0 * (x * 0.7).sin()),
+                mass: 1.0 + (i % 9) as Real * 20.0,
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn brute_force_is_the_direct_sum() {
+    let bodies = bodies();
+    let config = SimulationConfig::default();
+
+    let forces = BruteForce.forces(&bodies, &config);
+
+    assert_eq!(forces, DirectSum::new(bodies).forces(&config));
+}
+
+#[test]
+fn bodies_outside_of_the_domain_are_left_out() {
+    let mut bodies = bodies();
+    let config = SimulationConfig::default();
+    let inside = BruteForce.forces(&bodies, &config);
+    bodies.push(MassData {
+        position: Vector2::repeat(Simulation::SCALE),
+        mass: 1e9,
+    });
+
+    let forces = BruteForce.forces(&bodies, &config);
+
+    assert_eq!(forces[..inside.len()], inside[..]);
+    assert_eq!(forces[inside.len()], Vector2::zeros());
+}
+
+#[test]
+fn simulations_can_swap_solvers() {
+    let stars: Vec<_> = bodies()
+        .iter()
+        .map(|body| Star::new(body.position, Vector2::zeros(), body.mass))
+        .collect();
+    let config = SimulationConfig {
+        theta: 0.3,
+        near_field: None,
+        ..SimulationConfig::default()
+    };
+    let mut exact = Simulation::with_config(stars.clone(), config);
+    exact.set_force_solver(Some(Box::new(BruteForce)));
+    let mut tree = Simulation::with_config(stars, config);
+    tree.set_force_solver(Some(Box::new(BarnesHut)));
+
+    exact.update();
+    tree.update();
+
+    // over all stars, as some are pulled about equally in all directions
+    let (error, total) = exact
+        .stars
+        .iter()
+        .zip(&tree.stars)
+        .map(|(exact, tree)| {
+            let (exact, tree) = (exact.vel.cast::<f64>(), tree.vel.cast::<f64>());
+            ((exact - tree).norm_squared(), exact.norm_squared())
+        })
+        .fold((0.0, 0.0), |(a, b), (c, d)| (a + c, b + d));
+    assert!((error / total).sqrt() < 0.01, "{}", (error / total).sqrt());
+}
+
+#[test]
+fn the_config_selects_brute_force() {
+    let stars: Vec<_> = bodies()
+        .iter()
+        .map(|body| Star::new(body.position, Vector2::zeros(), body.mass))
+        .collect();
+    let config = SimulationConfig {
+        solver: "brute_force".parse().unwrap(),
+        ..SimulationConfig::default()
+    };
+    assert_eq!(config.solver, Solver::BruteForce);
+
+    let mut selected = Simulation::with_config(stars.clone(), config);
+    let mut set = Simulation::with_config(stars, SimulationConfig::default());
+    set.set_force_solver(Some(Box::new(BruteForce)));
+
+    selected.update();
+    set.update();
+
+    for (selected, set) in selected.stars.iter().zip(&set.stars) {
+        assert_eq!(selected.vel, set.vel);
+    }
+}
@@ -0,0 +1,60 @@
+use gravsim_simulation::{Galaxy, MassDistribution, Simulation, SpiralArms, Star};
+use nalgebra::Vector2;
+
+const RADIUS: f32 = 1000.0;
+
+fn galaxy(arms: &SpiralArms) -> Galaxy {
+    let center = Star::new(Vector2::new(300.0, 200.0), Vector2::new(0.5, 0.0), 1e3);
+    let distribution = MassDistribution::new(100.0, 15000.0);
+    Galaxy::with_arms_seeded(center, 4000, RADIUS as _, &distribution, 0.0, arms, 9)
+}
+
+#[test]
+fn galaxies_without_arms_are_discs() {
+    let center = Star::new(Vector2::new(300.0, 200.0), Vector2::new(0.5, 0.0), 1e3);
+    let distribution = MassDistribution::new(100.0, 15000.0);
+    let disc = Galaxy::new_seeded(center, 4000, RADIUS as _, &distribution, 9);
+
+    for arms in [SpiralArms::default(), SpiralArms::new(3, 0.3, 0.0)] {
+        let stars = galaxy(&arms).into_stars();
+        assert_eq!(stars.len(), disc.stars().len());
+        for (star, expected) in stars.iter().zip(disc.stars()) {
+            assert_eq!(star.pos(), expected.pos());
+            assert_eq!(star.vel, expected.vel);
+        }
+    }
+}
+
+#[test]
+fn stars_crowd_into_the_arms() {
+    let arms = SpiralArms::new(2, 0.3, 0.8);
+    let galaxy = galaxy(&arms);
+    let center = galaxy.stars()[0];
+    let phases: Vec<_> = galaxy.stars()[1..]
+        .iter()
+        .map(|star| arms.phase(&(star.pos() - center.pos()), RADIUS as _))
+        .collect();
+
+    // without arms about half of the stars would be on the denser side
+    let in_arms = phases.iter().filter(|phase| phase.cos() > 0.0).count();
+    assert!(in_arms as f64 > 0.65 * phases.len() as f64, "{}", in_arms);
+}
+
+#[test]
+fn stars_are_slower_in_the_arms() {
+    let arms = SpiralArms::new(2, 0.3, 0.8);
+    let galaxy = galaxy(&arms);
+    let center = galaxy.stars()[0];
+
+    for star in &galaxy.stars()[1..] {
+        let offset = star.pos() - center.pos();
+        let phase = arms.phase(&offset, RADIUS as _);
+        let speed = (star.vel - center.vel).norm();
+        let circular = (Simulation::GRAVITY * center.mass() / offset.norm()).sqrt();
+        if phase.cos() > 0.9 {
+            assert!(speed < circular, "{} {}", speed, circular);
+        }
+        // the orbits stay bound
+        assert!(speed < 1.5 * circular);
+    }
+}
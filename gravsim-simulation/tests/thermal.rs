@@ -0,0 +1,79 @@
+use gravsim_simulation::thermal::ThermalNoise;
+use gravsim_simulation::{Real, Simulation, SimulationConfig, Star};
+use nalgebra::Vector2;
+
+fn grid(count: usize, mass: Real) -> Vec<Star> {
+    (0..count)
+        .map(|i| {
+            let pos = Vector2::new((i % 50) as Real, (i / 50) as Real) * 10.0;
+            Star::new(pos, Vector2::zeros(), mass)
+        })
+        .collect()
+}
+
+/// Just the kicks, without gravity between the stars.
+fn noisy(stars: Vec<Star>, noise: ThermalNoise) -> Simulation {
+    let config = SimulationConfig {
+        gravity: 0.0,
+        thermal_noise: Some(noise),
+        ..SimulationConfig::default()
+    };
+    Simulation::with_config(stars, config)
+}
+
+fn mean_kinetic_energy(stars: &[Star]) -> f64 {
+    let total: f64 = stars
+        .iter()
+        .map(|star| 0.5 * star.mass() as f64 * star.vel.norm_squared() as f64)
+        .sum();
+    total / stars.len() as f64
+}
+
+#[test]
+fn seeded_noise_repeats() {
+    let run = |seed: u64| {
+        let mut simulation = noisy(grid(100, 1.0), ThermalNoise::new(1e-3, seed));
+        for _ in 0..10 {
+            simulation.update();
+        }
+        simulation.state_hash()
+    };
+
+    assert_eq!(run(1), run(1));
+    assert_ne!(run(1), run(2));
+}
+
+#[test]
+fn stars_gain_the_temperature_whatever_their_mass() {
+    let temperature = 1e-3;
+    for mass in [1.0, 100.0] {
+        let mut simulation = noisy(grid(2000, mass), ThermalNoise::new(temperature, 0x7e));
+        simulation.config.dt = 0.5;
+        for _ in 0..20 {
+            simulation.update();
+        }
+
+        // a random walk, the energy grows linearly with time
+        let expected = temperature as f64 * 10.0;
+        let energy = mean_kinetic_energy(&simulation.stars);
+        assert!(
+            (energy / expected - 1.0).abs() < 0.1,
+            "mass {}: {} instead of {}",
+            mass,
+            energy,
+            expected
+        );
+    }
+}
+
+#[test]
+fn removed_stars_are_not_kicked() {
+    let mut simulation = noisy(grid(10, 1.0), ThermalNoise::new(1.0, 3));
+    simulation.stars[4].mass_point.position = Vector2::from_element(Real::NAN);
+    simulation.stars[4].mass_point.mass = 0.0;
+
+    simulation.update();
+
+    assert_eq!(simulation.stars[4].vel, Vector2::zeros());
+    assert!(simulation.stars[5].vel != Vector2::zeros());
+}
@@ -0,0 +1,117 @@
+use gravsim_simulation::diagnostics::Diagnostics;
+use gravsim_simulation::fmm::Fmm;
+use gravsim_simulation::rebuild::IncrementalRebuild;
+use gravsim_simulation::reuse::TreeReuse;
+use gravsim_simulation::thermal::ThermalNoise;
+use gravsim_simulation::{Galaxy, MassDistribution, Simulation, SimulationConfig, Star};
+use nalgebra::Vector2;
+
+fn stars() -> Vec<Star> {
+    let distribution = MassDistribution::new(100.0, 15000.0);
+    let galaxy = |x, seed| {
+        let center = Star::new(Vector2::new(x, 0.0), Vector2::zeros(), 1e1);
+        Galaxy::new_seeded(center, 500, 2000.0, &distribution, seed)
+            .stars()
+            .to_vec()
+    };
+    let mut stars = galaxy(-2500.0, 1);
+    stars.extend(galaxy(2500.0, 2));
+    stars
+}
+
+/// Sets up a simulation before it is run, shared by the threads of the pool.
+type Setup = Box<dyn Fn(&mut Simulation) + Send + Sync>;
+
+/// State hash and energy after some updates of a simulation set up by `setup`, on a thread
+/// pool with `threads` threads.
+fn run(setup: &(dyn Fn(&mut Simulation) + Sync), threads: usize) -> (u64, u64) {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .unwrap();
+    pool.install(|| {
+        let mut simulation = Simulation::new(stars());
+        setup(&mut simulation);
+        for _ in 0..10 {
+            simulation.update();
+        }
+        let diagnostics = Diagnostics::with_softening(
+            &simulation.stars,
+            simulation.config.gravity,
+            simulation.config.softening,
+        );
+        (
+            simulation.state_hash(),
+            diagnostics.total_energy().to_bits(),
+        )
+    })
+}
+
+fn configured(config: SimulationConfig) -> impl Fn(&mut Simulation) + Send + Sync {
+    move |simulation| simulation.config = config
+}
+
+#[test]
+fn every_update_path_gives_the_same_bits_on_any_number_of_threads() {
+    let default = SimulationConfig::default();
+    let setups: Vec<(&str, Setup)> = vec![
+        ("default", Box::new(configured(default))),
+        (
+            "near field",
+            Box::new(configured(SimulationConfig {
+                near_field: Some(100.0),
+                ..default
+            })),
+        ),
+        (
+            "reused tree",
+            Box::new(configured(SimulationConfig {
+                reuse_tree: Some(TreeReuse::default()),
+                ..default
+            })),
+        ),
+        (
+            "incremental rebuild",
+            Box::new(configured(SimulationConfig {
+                incremental_rebuild: Some(IncrementalRebuild {
+                    stars_per_update: 300,
+                    max_staleness: 4,
+                }),
+                ..default
+            })),
+        ),
+        (
+            "thermal noise",
+            Box::new(configured(SimulationConfig {
+                thermal_noise: Some(ThermalNoise::new(1.0, 5)),
+                ..default
+            })),
+        ),
+        (
+            "collisions",
+            Box::new(configured(SimulationConfig {
+                merge_collisions: true,
+                ..default
+            })),
+        ),
+        (
+            "fmm",
+            Box::new(|simulation: &mut Simulation| {
+                simulation.set_force_solver(Some(Box::new(Fmm)))
+            }),
+        ),
+    ];
+
+    for (name, setup) in &setups {
+        let reference = run(setup.as_ref(), 1);
+        for threads in [2, 3, 8] {
+            assert_eq!(
+                run(setup.as_ref(), threads),
+                reference,
+                "{} on {} threads",
+                name,
+                threads
+            );
+        }
+    }
+}
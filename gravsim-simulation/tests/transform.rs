@@ -0,0 +1,84 @@
+use gravsim_simulation::probe::Probe;
+use gravsim_simulation::{DilationZone, Real, Simulation, Star};
+use nalgebra::Vector2;
+
+fn binary() -> Simulation {
+    Simulation::new([
+        Star::new(Vector2::new(-50.0, 0.0), Vector2::new(0.0, -0.2), 1e5),
+        Star::new(Vector2::new(50.0, 0.0), Vector2::new(0.0, 0.2), 1e5),
+    ])
+}
+
+fn close(a: Vector2<Real>, b: Vector2<Real>) -> bool {
+    (a - b).norm() < 1e-3 * (1.0 + b.norm())
+}
+
+#[test]
+fn transforms_move_everything_along() {
+    let mut simulation = binary();
+    simulation
+        .probes
+        .push(Probe::new(Vector2::new(0.0, 200.0), Vector2::new(1.0, 0.0)));
+    simulation.dilation_zones.push(DilationZone {
+        center: Vector2::new(50.0, 0.0),
+        radius: 10.0,
+        time_scale: 0.5,
+    });
+
+    simulation.translate(Vector2::new(10.0, 20.0));
+    assert_eq!(*simulation.stars[0].pos(), Vector2::new(-40.0, 20.0));
+    assert_eq!(simulation.probes[0].pos, Vector2::new(10.0, 220.0));
+    assert_eq!(
+        simulation.dilation_zones[0].center,
+        Vector2::new(60.0, 20.0)
+    );
+
+    simulation.rotate(core::f64::consts::FRAC_PI_2 as Real);
+    assert!(close(
+        *simulation.stars[0].pos(),
+        Vector2::new(-20.0, -40.0)
+    ));
+    assert!(close(simulation.stars[0].vel, Vector2::new(0.2, 0.0)));
+    assert!(close(simulation.probes[0].pos, Vector2::new(-220.0, 10.0)));
+    assert!(close(simulation.probes[0].vel, Vector2::new(0.0, 1.0)));
+    assert!(close(
+        simulation.dilation_zones[0].center,
+        Vector2::new(-20.0, 60.0)
+    ));
+
+    simulation.boost(Vector2::new(-0.5, 2.0));
+    assert!(close(simulation.stars[0].vel, Vector2::new(-0.3, 2.0)));
+    assert!(close(simulation.stars[1].vel, Vector2::new(-0.7, 2.0)));
+    assert!(close(simulation.probes[0].vel, Vector2::new(-0.5, 3.0)));
+}
+
+#[test]
+fn orbits_dont_depend_on_the_frame() {
+    let mut reference = binary();
+    let mut moved = binary();
+    let theta: Real = 0.7;
+    let boost = Vector2::new(0.25, -0.125);
+    moved.translate(Vector2::new(3000.0, -1200.0));
+    moved.rotate(theta);
+    moved.boost(boost);
+
+    for _ in 0..500 {
+        reference.update();
+        moved.update();
+    }
+
+    let (sin, cos) = theta.sin_cos();
+    let rotate = |v: Vector2<Real>| Vector2::new(cos * v.x - sin * v.y, sin * v.x + cos * v.y);
+    let separation =
+        |simulation: &Simulation| simulation.stars[1].pos() - simulation.stars[0].pos();
+    let relative_vel = |simulation: &Simulation| simulation.stars[1].vel - simulation.stars[0].vel;
+    assert!(close(separation(&moved), rotate(separation(&reference))));
+    assert!(close(
+        relative_vel(&moved),
+        rotate(relative_vel(&reference))
+    ));
+    assert!(close(
+        moved.stars[0].vel,
+        rotate(reference.stars[0].vel) + boost
+    ));
+}
@@ -0,0 +1,164 @@
+use gravsim_simulation::tree::{FlatTree, Node};
+use gravsim_simulation::{MassData, Real, SimulationConfig};
+use nalgebra::Vector2;
+use rand::{Rng, SeedableRng};
+use rand_xorshift::XorShiftRng;
+
+/// Asserts that every leaf contains the body stored in it.
+fn assert_leaves_contain_bodies(node: &Node) -> usize {
+    if node.is_leaf() {
+        let position = node.center_of_mass().position;
+        assert!(
+            node.contains(&position),
+            "{:?} outside of leaf at {:?} with scale {}",
+            position,
+            node.pos(),
+            node.scale()
+        );
+        return 1;
+    }
+
+    node.children().map(assert_leaves_contain_bodies).sum()
+}
+
+#[test]
+fn bodies_near_cell_edges_stay_in_their_cells() {
+    let mut rng = XorShiftRng::seed_from_u64(0xce11);
+
+    // an awkward root so halving the scale doesn't produce exact cell boundaries
+    let root_pos = Vector2::new(-0.1, -7.3);
+    let scale = 13.7;
+    let mut tree = Node::new_root(root_pos, scale);
+
+    let mut inserted = 0;
+    for _ in 0..20_000 {
+        // snap to a fine grid of cell edges and wiggle by a few ulps
+        let cells = 1 << rng.gen_range(1..12);
+        let position = root_pos
+            + Vector2::from_fn(|_, _| {
+                let edge = rng.gen_range(0..cells) as Real / cells as Real * scale;
+                edge * (1.0 + rng.gen_range(-4..=4) as Real * Real::EPSILON)
+            });
+
+        if tree.contains(&position) {
+            tree.insert(&MassData {
+                position: position + Vector2::from_fn(|_, _| rng.gen::<Real>() * 1e-3),
+                mass: 1.0,
+            });
+            inserted += 1;
+        }
+    }
+
+    tree.summarize();
+
+    assert!(inserted > 0);
+    assert_eq!(assert_leaves_contain_bodies(&tree), inserted);
+    assert_eq!(tree.center_of_mass().mass, inserted as Real);
+}
+
+#[test]
+fn clamp_moves_points_into_cell() {
+    let node = Node::new_root(Vector2::new(-1.0, 2.0), 3.0);
+
+    for point in [
+        Vector2::new(-5.0, 0.0),
+        Vector2::new(2.0, 5.0),
+        Vector2::new(-1.0, 2.0),
+        Vector2::new(1.9999999, 4.9999999),
+    ] {
+        assert!(node.contains(&node.clamp(&point)));
+    }
+
+    let inside = Vector2::new(0.5, 3.5);
+    assert_eq!(node.clamp(&inside), inside);
+}
+
+#[test]
+fn coincident_bodies_are_merged() {
+    let mut tree = Node::new_root(Vector2::new(-1.0, -1.0), 4.0);
+    let obj = MassData {
+        position: Vector2::new(1.0, 2.0),
+        mass: 1.0,
+    };
+    for _ in 0..3 {
+        tree.insert(&obj);
+    }
+    tree.insert(&MassData {
+        position: Vector2::new(1.0, 1.9999999),
+        mass: 1.0,
+    });
+
+    tree.summarize();
+
+    assert_eq!(tree.center_of_mass().mass, 4.0);
+    assert!(assert_leaves_contain_bodies(&tree) < 4);
+}
+
+#[test]
+fn flat_tree_matches_node() {
+    let mut rng = XorShiftRng::seed_from_u64(0xf1a7);
+    let objs: Vec<_> = (0..2000)
+        .map(|_| MassData {
+            position: Vector2::from_fn(|_, _| rng.gen::<Real>() * 1000.0 - 500.0),
+            mass: rng.gen_range(1.0..10.0),
+        })
+        .collect();
+
+    let mut node = Node::new_root(Vector2::repeat(-500.0), 1000.0);
+    let mut flat = FlatTree::new_root(Vector2::repeat(-500.0), 1000.0);
+    for obj in &objs {
+        node.insert(obj);
+        flat.insert(obj);
+    }
+    node.summarize();
+    flat.summarize();
+
+    assert_eq!(
+        flat.root().center_of_mass().mass,
+        node.center_of_mass().mass
+    );
+    let config = SimulationConfig::default();
+    for obj in &objs {
+        assert_eq!(flat.force_on(obj, &config), node.force_on(obj, &config));
+    }
+}
+
+#[test]
+fn removed_bodies_leave_the_summary() {
+    let mut rng = XorShiftRng::seed_from_u64(0xf1a8);
+    let objs: Vec<_> = (0..500)
+        .map(|_| MassData {
+            position: Vector2::from_fn(|_, _| rng.gen::<Real>() * 1000.0 - 500.0),
+            mass: rng.gen_range(1.0..10.0),
+        })
+        .collect();
+    let (kept, removed) = objs.split_at(300);
+
+    let mut tree = FlatTree::new_root(Vector2::repeat(-500.0), 1000.0);
+    objs.iter().for_each(|obj| tree.insert(obj));
+    tree.summarize();
+    assert!(removed.iter().all(|obj| tree.remove(obj)));
+    tree.summarize();
+
+    let mut fresh = FlatTree::new_root(Vector2::repeat(-500.0), 1000.0);
+    kept.iter().for_each(|obj| fresh.insert(obj));
+    fresh.summarize();
+    let (mass, center) = (tree.root().center_of_mass(), fresh.root().center_of_mass());
+    assert!((mass.mass - center.mass).abs() < 1e-3);
+    assert!((mass.position - center.position).norm() < 1e-3);
+    let config = SimulationConfig::default();
+    for obj in kept {
+        let (force, expected) = (tree.force_on(obj, &config), fresh.force_on(obj, &config));
+        assert!((force - expected).norm() <= 1e-3 * expected.norm());
+    }
+
+    // emptied leaves take new bodies again
+    removed.iter().for_each(|obj| tree.insert(obj));
+    tree.summarize();
+    let total: f64 = objs.iter().map(|obj| obj.mass as f64).sum();
+    assert!((tree.root().center_of_mass().mass as f64 - total).abs() < 1e-2);
+    assert!(!tree.remove(&MassData {
+        position: Vector2::repeat(600.0),
+        mass: 1.0,
+    }));
+}
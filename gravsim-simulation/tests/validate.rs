@@ -0,0 +1,42 @@
+use gravsim_simulation::validate::Validation;
+use gravsim_simulation::{Simulation, SimulationConfig, Star};
+use nalgebra::Vector2;
+use rand::{Rng, SeedableRng};
+use rand_xorshift::XorShiftRng;
+
+#[test]
+fn random_stars_build_a_consistent_tree() {
+    let mut rng = XorShiftRng::seed_from_u64(0x7a11d);
+    let stars: Vec<_> = (0..2_000)
+        .map(|_| {
+            let pos = Vector2::from_fn(|_, _| rng.gen_range(-1000.0..1000.0));
+            Star::new(pos, Vector2::zeros(), rng.gen_range(1.0..10.0))
+        })
+        .collect();
+
+    let validation = Simulation::new(stars).validate(50);
+    assert!(validation.is_consistent(), "{}", validation);
+    assert_eq!(validation.stars, 2_000);
+    assert_eq!(validation.force_errors.len(), 50);
+    assert!(
+        validation.max_force_error().unwrap() < 0.1,
+        "{}",
+        validation
+    );
+}
+
+#[test]
+fn stars_outside_of_the_domain_are_ignored() {
+    let config = SimulationConfig::default();
+    let stars = [
+        Star::new(Vector2::new(-10.0, 0.0), Vector2::zeros(), 1.0),
+        Star::new(Vector2::new(10.0, 0.0), Vector2::zeros(), 1.0),
+        Star::new(config.domain.max * 2.0, Vector2::zeros(), 1.0),
+    ];
+
+    let validation = Validation::new(&stars, &config, 10);
+    assert!(validation.is_consistent(), "{}", validation);
+    assert_eq!(validation.stars, 2);
+    // with two stars the tree is exact
+    assert!(validation.max_force_error().unwrap() < 1e-5);
+}